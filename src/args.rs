@@ -0,0 +1,300 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::net::SocketAddr;
+use std::path::{absolute, Path, PathBuf};
+
+use clap::{Parser, Subcommand};
+use once_cell::sync::OnceCell;
+
+/// Command line arguments passed to the downloader.
+#[derive(Parser, Debug, Clone, Default)]
+#[command(name = "e621_downloader", version, about)]
+pub struct Args {
+    /// The command to run instead of downloading.
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Runs with the config, login, and tags of the named profile (stored in `profiles/<NAME>`).
+    #[arg(long, value_name = "NAME", value_parser = parse_profile_name)]
+    profile: Option<String>,
+    /// Keeps the config, login, and tag files in the working directory, even when the platform config directory was
+    /// opted into (a `portable.txt` file beside the program does the same for the program's directory).
+    #[arg(long)]
+    portable: bool,
+    /// Loads the config from this file instead of `config.json` (the login is loaded from the same directory).
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Loads the tags from this file instead of `tags.txt`.
+    #[arg(long, value_name = "PATH")]
+    tags: Option<PathBuf>,
+    /// Also writes every log event to this file as newline-delimited JSON.
+    #[arg(long, value_name = "PATH")]
+    json_log: Option<PathBuf>,
+    /// Emits newline-delimited JSON progress events on stdout (for GUI wrappers).
+    #[arg(long)]
+    progress_json: bool,
+    /// Skips the confirmation prompt shown before downloading starts.
+    #[arg(short, long)]
+    yes: bool,
+    /// Shows the main menu before downloading.
+    #[arg(long)]
+    menu: bool,
+    /// Asks which groups from the tag file should be downloaded this run.
+    #[arg(long)]
+    select_groups: bool,
+    /// Lets you review the grabbed posts and deselect the ones you don't want before downloading.
+    #[arg(long)]
+    review: bool,
+    /// Shows a thumbnail of each post while reviewing, or of the post when a single one is grabbed (needs a terminal
+    /// with kitty, iTerm, or sixel graphics).
+    #[arg(long)]
+    preview: bool,
+    /// Serves a JSON API on a local socket (default `127.0.0.1:6210`) that starts, tracks, and cancels runs, instead of
+    /// downloading right away.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_SERVE_ADDRESS,
+        value_parser = parse_loopback_address,
+        conflicts_with_all = ["menu", "select_groups", "review"]
+    )]
+    serve: Option<SocketAddr>,
+    /// Streams progress events as server-sent events from `/events` on a local socket (default `127.0.0.1:6211`), for
+    /// dashboards following long runs.
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        num_args = 0..=1,
+        default_missing_value = DEFAULT_EVENT_STREAM_ADDRESS,
+        value_parser = parse_loopback_address
+    )]
+    event_stream: Option<SocketAddr>,
+}
+
+/// The address `--serve` listens on when no address is given.
+const DEFAULT_SERVE_ADDRESS: &str = "127.0.0.1:6210";
+
+/// The address `--event-stream` listens on when no address is given.
+const DEFAULT_EVENT_STREAM_ADDRESS: &str = "127.0.0.1:6211";
+
+/// Commands that replace the normal download run.
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Checks every entry in the tag file against the API and prints a report, without downloading anything.
+    Validate,
+    /// Checks a post against the blacklist and explains which blacklist lines flag it.
+    BlacklistTest {
+        /// The id of the post to check.
+        post_id: i64,
+    },
+    /// Writes the blacklist of your e621 account to a file, so it can be edited and imported back.
+    BlacklistExport {
+        /// The file to write the blacklist to.
+        path: PathBuf,
+    },
+    /// Replaces the blacklist of your e621 account with the blacklist in a file.
+    BlacklistImport {
+        /// The file to read the blacklist from.
+        path: PathBuf,
+    },
+    /// Grabs the posts of the tag file and saves them to a file instead of downloading them.
+    Grab {
+        /// The file to save the grabbed posts to.
+        #[arg(short, long, value_name = "PATH")]
+        output: PathBuf,
+    },
+    /// Downloads the posts saved by `grab`, instead of grabbing posts.
+    Download {
+        /// The file the grabbed posts were saved to.
+        #[arg(short, long, value_name = "PATH")]
+        input: PathBuf,
+    },
+    /// Lists the tags related to a tag with their post counts, and adds the ones you pick to the tag file.
+    Suggest {
+        /// The tag to find related tags for.
+        tag: String,
+    },
+    /// Lists the sets you created or maintain on e621 and downloads the ones you pick.
+    MySets,
+    /// Downloads the newest release for this platform and replaces the program with it.
+    SelfUpdate,
+    /// Bundles the log, config, tag file, and system information into a zip file to attach to an issue, with the API
+    /// key left out.
+    Diagnose {
+        /// The zip file to write.
+        #[arg(default_value = "e621_downloader_diagnostics.zip")]
+        path: PathBuf,
+    },
+    /// Adds the saved searches of your e621 account to the tag file as groups.
+    SavedSearches {
+        /// Downloads the saved searches right away instead of adding them to the tag file.
+        #[arg(long)]
+        run: bool,
+    },
+}
+
+/// Checks that a profile name is safe to use as a directory name.
+///
+/// # Arguments
+///
+/// * `name`: The name of the profile.
+///
+/// returns: Result<String, String>
+fn parse_profile_name(name: &str) -> Result<String, String> {
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+    {
+        return Err(String::from(
+            "profile names can only contain letters, numbers, `-`, and `_`",
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+/// Checks that an address to listen on is a loopback address, as nothing served by the downloader has authentication.
+///
+/// # Arguments
+///
+/// * `address`: The address to listen on.
+///
+/// returns: Result<SocketAddr, String>
+fn parse_loopback_address(address: &str) -> Result<SocketAddr, String> {
+    let address: SocketAddr = address.parse().map_err(|e| format!("{e}"))?;
+    if !address.ip().is_loopback() {
+        return Err(String::from(
+            "only loopback addresses (e.g `127.0.0.1:6210`) can be listened on",
+        ));
+    }
+
+    Ok(address)
+}
+
+static ARGS: OnceCell<Args> = OnceCell::new();
+
+impl Args {
+    /// The command to run instead of downloading, if any.
+    pub fn command(&self) -> Option<&Command> {
+        self.command.as_ref()
+    }
+
+    /// The name of the profile to run with, if any.
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// The path of the config file passed on the command line, if any.
+    pub fn config(&self) -> Option<&Path> {
+        self.config.as_deref()
+    }
+
+    /// The path of the tag file passed on the command line, if any.
+    pub fn tags(&self) -> Option<&Path> {
+        self.tags.as_deref()
+    }
+
+    /// Whether the config, login, and tag files should be kept in the working directory.
+    pub fn portable(&self) -> bool {
+        self.portable
+    }
+
+    /// The path of the json log passed on the command line, if any.
+    pub fn json_log(&self) -> Option<&Path> {
+        self.json_log.as_deref()
+    }
+
+    /// Whether progress events should be emitted as newline-delimited JSON on stdout.
+    pub fn progress_json(&self) -> bool {
+        self.progress_json
+    }
+
+    /// Whether the confirmation prompt before downloading should be skipped.
+    pub fn yes(&self) -> bool {
+        self.yes
+    }
+
+    /// Whether the main menu should be shown before downloading.
+    pub fn menu(&self) -> bool {
+        self.menu
+    }
+
+    /// Whether the user should be asked which groups to download this run.
+    pub fn select_groups(&self) -> bool {
+        self.select_groups
+    }
+
+    /// Whether the grabbed posts should be reviewed before downloading.
+    pub fn review(&self) -> bool {
+        self.review
+    }
+
+    /// Whether thumbnails should be shown while reviewing or confirming a single post.
+    pub fn preview(&self) -> bool {
+        self.preview
+    }
+
+    /// The address to serve the JSON API on, if `--serve` was passed.
+    pub fn serve(&self) -> Option<SocketAddr> {
+        self.serve
+    }
+
+    /// The address to stream progress events on, if `--event-stream` was passed.
+    pub fn event_stream(&self) -> Option<SocketAddr> {
+        self.event_stream
+    }
+
+    /// Gets the global instance of [Args], parsing the command line on first access.
+    pub fn get() -> &'static Self {
+        ARGS.get_or_init(|| Args::parse().resolve_paths())
+    }
+
+    /// Sets the global instance of [Args] instead of parsing the command line, for programs embedding the downloader
+    /// (e.g `Args::set(Args::parse_from(["e621_downloader", "--yes"]))`).
+    ///
+    /// This has to be called before anything reads the args, returns false if they were already set.
+    ///
+    /// # Arguments
+    ///
+    /// * `args`: The args to use.
+    ///
+    /// returns: bool
+    pub fn set(args: Args) -> bool {
+        ARGS.set(args.resolve_paths()).is_ok()
+    }
+
+    /// Makes the paths passed on the command line absolute, so they still point to the same files after entering a
+    /// profile directory.
+    fn resolve_paths(mut self) -> Self {
+        self.config = self.config.map(|e| absolute(&e).unwrap_or(e));
+        self.tags = self.tags.map(|e| absolute(&e).unwrap_or(e));
+        self.json_log = self.json_log.map(|e| absolute(&e).unwrap_or(e));
+        if let Some(
+            Command::BlacklistExport { path }
+            | Command::BlacklistImport { path }
+            | Command::Diagnose { path }
+            | Command::Grab { output: path }
+            | Command::Download { input: path },
+        ) = &mut self.command
+        {
+            *path = absolute(&*path).unwrap_or(path.clone());
+        }
+
+        self
+    }
+}
@@ -14,15 +14,60 @@
  * limitations under the License.
  */
 
-
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::fs::read_to_string;
+use std::path::{Path, PathBuf};
 
 use anyhow::Context;
 
+use crate::e621::error::E621Error;
 use crate::e621::io::parser::BaseParser;
+use crate::e621::io::report::BlacklistHit;
+use crate::e621::io::tag::tag_file_path;
+use crate::e621::io::user_cache::UserCache;
 use crate::e621::sender::entries::{PostEntry, UserEntry};
 use crate::e621::sender::RequestSender;
 
+/// Name of the local blacklist file, which is kept next to the tag file.
+pub const BLACKLIST_NAME: &str = "blacklist.txt";
+
+/// The path of the local blacklist file, next to the tag file.
+pub fn local_blacklist_path() -> PathBuf {
+    tag_file_path().with_file_name(BLACKLIST_NAME)
+}
+
+/// Reads the local blacklist file, if it exists.
+///
+/// returns: Result<Option<String>, E621Error> ([None] if there is no local blacklist)
+pub fn read_local_blacklist() -> Result<Option<String>, E621Error> {
+    let path = local_blacklist_path();
+    if !path.exists() {
+        trace!("No local blacklist at {}...", path.display());
+        return Ok(None);
+    }
+
+    trace!("Reading local blacklist {}...", path.display());
+    read_blacklist_file(&path).map(Some)
+}
+
+/// Reads a blacklist file, which uses the same syntax as the blacklist on e621, with one entry per line.
+///
+/// Lines starting with `#` are comments and are left out.
+///
+/// # Arguments
+///
+/// * `path`: The path of the blacklist file.
+///
+/// returns: Result<String, E621Error>
+pub fn read_blacklist_file(path: &Path) -> Result<String, E621Error> {
+    Ok(read_to_string(path)?
+        .lines()
+        .filter(|e| !e.trim_start().starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
 /// Root token which contains all the tokens of the blacklist.
 #[derive(Default, Debug)]
 struct RootToken {
@@ -33,16 +78,94 @@ struct RootToken {
 /// A line token that contains all collected [`TagToken`]s from a parsed line.
 #[derive(Debug, Default)]
 struct LineToken {
+    /// The line as it was written in the blacklist.
+    text: String,
     /// Total [TagToken] in the line.
     tags: Vec<TagToken>,
 }
 
 impl LineToken {
-    fn new(tags: Vec<TagToken>) -> Self {
-        LineToken { tags }
+    fn new(text: String, tags: Vec<TagToken>) -> Self {
+        LineToken { text, tags }
+    }
+
+    /// The first plain tag the line needs a post to have, if it has one.
+    ///
+    /// A post can only be flagged by the line when it has every tag that isn't negated, so posts without this tag can
+    /// skip the line entirely.
+    fn required_tag(&self) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|e| !e.negated && matches!(e.tag_type, TagType::None))
+            .map(|e| e.name.as_str())
     }
 }
 
+/// An index of the blacklist lines by the plain tag each line requires, so a post is only checked against the lines it
+/// could possibly be flagged by.
+#[derive(Default, Debug)]
+struct LineIndex {
+    /// The indices of the lines, keyed by the tag they require.
+    by_tag: HashMap<String, Vec<usize>>,
+    /// The indices of the lines that don't require a plain tag, which every post has to be checked against.
+    unindexed: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Indexes the blacklist lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines`: The lines to index.
+    ///
+    /// returns: LineIndex
+    fn new(lines: &[LineToken]) -> Self {
+        let mut index = LineIndex::default();
+        for (line_index, line) in lines.iter().enumerate() {
+            match line.required_tag() {
+                Some(tag) => index
+                    .by_tag
+                    .entry(tag.to_string())
+                    .or_default()
+                    .push(line_index),
+                None => index.unindexed.push(line_index),
+            }
+        }
+
+        index
+    }
+
+    /// Gets the indices of the lines a post could be flagged by, in the order they are in the blacklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_tags`: The tags of the post.
+    ///
+    /// returns: Vec<usize>
+    fn candidates(&self, post_tags: &HashSet<&str>) -> Vec<usize> {
+        let mut candidates = self.unindexed.clone();
+        for tag in post_tags {
+            if let Some(lines) = self.by_tag.get(*tag) {
+                candidates.extend(lines);
+            }
+        }
+
+        candidates.sort_unstable();
+        candidates
+    }
+}
+
+/// Collects the tags of a post into a set, so they only have to be gathered once per post.
+///
+/// # Arguments
+///
+/// * `post`: The post to collect the tags of.
+///
+/// returns: HashSet<&str>
+fn post_tag_set(post: &PostEntry) -> HashSet<&str> {
+    post.tags.iter().map(String::as_str).collect()
+}
+
 /// Enum that contains each possible option from `rating:` being in blacklist.
 #[derive(Debug, PartialEq)]
 enum Rating {
@@ -58,8 +181,8 @@ enum Rating {
 
 /// A enum that contains what type the [TagToken] is.
 ///
-/// The tag can be seen as four types: [Rating](TagType::Rating), [Id](TagType::Id), [User](TagType::User), and
-/// [None](TagType::None).
+/// The tag is either a plain tag ([None](TagType::None)), a [Wildcard](TagType::Wildcard) pattern, or one of the
+/// special `name:value` tags (e.g `rating:e`, `width:<1000`).
 #[derive(Debug)]
 enum TagType {
     /// A post rating type.
@@ -69,11 +192,61 @@ enum TagType {
     /// A user type.
     User(Option<String>),
     /// The blacklisted score
-    Score(Ordering, i32),
+    Score(Comparison),
+    /// The blacklisted file type (e.g `webm`).
+    FileType(String),
+    /// The blacklisted width.
+    Width(Comparison),
+    /// The blacklisted height.
+    Height(Comparison),
+    /// The blacklisted favorite count.
+    FavCount(Comparison),
+    /// The blacklisted pool id.
+    Pool(i64),
+    /// A tag with `*` wildcards (e.g `*_fluids`), matching any tag that fits the pattern.
+    Wildcard,
     /// No type.
     None,
 }
 
+/// A comparison against one of the post's numbers, written as `<5`, `<=5`, `>5`, `>=5`, `5` (or `=5`), or as an
+/// inclusive range like `0..50` (either side of the range can be left out).
+#[derive(Debug, PartialEq)]
+enum Comparison {
+    /// The number is lower than the value.
+    Less(i64),
+    /// The number is lower than or equal to the value.
+    LessOrEqual(i64),
+    /// The number is greater than the value.
+    Greater(i64),
+    /// The number is greater than or equal to the value.
+    GreaterOrEqual(i64),
+    /// The number is equal to the value.
+    Equal(i64),
+    /// The number is between the two values (inclusive).
+    Range(i64, i64),
+}
+
+impl Comparison {
+    /// Checks if the number passes the comparison.
+    ///
+    /// # Arguments
+    ///
+    /// * `number`: The number to check.
+    ///
+    /// returns: bool
+    fn matches(&self, number: i64) -> bool {
+        match *self {
+            Comparison::Less(value) => number < value,
+            Comparison::LessOrEqual(value) => number <= value,
+            Comparison::Greater(value) => number > value,
+            Comparison::GreaterOrEqual(value) => number >= value,
+            Comparison::Equal(value) => number == value,
+            Comparison::Range(min, max) => (min..=max).contains(&number),
+        }
+    }
+}
+
 /// Tag token that contains essential information about what is blacklisted.
 #[derive(Debug)]
 struct TagToken {
@@ -83,6 +256,8 @@ struct TagToken {
     tag_type: TagType,
     /// The tag (value for special tags)
     name: String,
+    /// The tag as it was written in the blacklist.
+    text: String,
 }
 
 impl Default for TagToken {
@@ -91,6 +266,7 @@ impl Default for TagToken {
             negated: false,
             tag_type: TagType::None,
             name: String::new(),
+            text: String::new(),
         }
     }
 }
@@ -120,7 +296,7 @@ impl BlacklistParser {
                 break;
             }
 
-            lines.push(self.parse_line());
+            lines.extend(self.parse_line());
         }
 
         trace!("Parsed blacklist...");
@@ -129,7 +305,20 @@ impl BlacklistParser {
     }
 
     /// Parses each tag and collects them into a [`LineToken`].
-    fn parse_line(&mut self) -> LineToken {
+    ///
+    /// A line with a special tag that can't be parsed is left out as a whole, since leaving out only the invalid tag
+    /// would make the line filter a different set of posts than the user wrote it for.
+    ///
+    /// returns: Option<LineToken> ([None] if the line was left out)
+    fn parse_line(&mut self) -> Option<LineToken> {
+        let text = self
+            .base_parser
+            .get_current_input()
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_string();
         let mut tags: Vec<TagToken> = Vec::new();
         loop {
             if self.base_parser.starts_with("\n") {
@@ -142,10 +331,22 @@ impl BlacklistParser {
                 break;
             }
 
-            tags.push(self.parse_tag());
+            match self.parse_tag() {
+                Ok(tag) => tags.push(tag),
+                Err(msg) => {
+                    self.base_parser.report_error(
+                        format!("{msg}, leaving out the blacklist line \"{text}\"").as_str(),
+                    );
+                    if self.base_parser.starts_with("\n") {
+                        self.base_parser.consume_char();
+                    }
+
+                    return None;
+                }
+            }
         }
 
-        LineToken::new(tags)
+        Some(LineToken::new(text, tags))
     }
 
     /// Checks if tag is negated.
@@ -154,8 +355,19 @@ impl BlacklistParser {
     }
 
     /// Parses tag and runs through basic identification before returning it as a [`TagToken`].
-    fn parse_tag(&mut self) -> TagToken {
-        let mut token = TagToken::default();
+    ///
+    /// returns: Result<TagToken, String> (the error describes the special tag that couldn't be parsed)
+    fn parse_tag(&mut self) -> Result<TagToken, String> {
+        let mut token = TagToken {
+            text: self
+                .base_parser
+                .get_current_input()
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            ..Default::default()
+        };
         if self.is_tag_negated() {
             assert_eq!(self.base_parser.consume_char(), '-');
             token.negated = true;
@@ -165,10 +377,12 @@ impl BlacklistParser {
 
         // This will be considered a special tag if it contains the syntax of one.
         if !self.base_parser.eof() && self.base_parser.next_char() == ':' {
-            self.parse_special_tag(&mut token);
+            self.parse_special_tag(&mut token)?;
+        } else if token.name.contains('*') {
+            token.tag_type = TagType::Wildcard;
         }
 
-        token
+        Ok(token)
     }
 
     /// Parses special tag and updates token with the appropriate type and value.
@@ -177,44 +391,52 @@ impl BlacklistParser {
     ///
     /// * `token`: The special [TagToken] to parse.
     ///
-    /// returns: ()
+    /// returns: Result<(), String>
     ///
     /// # Errors
     ///
-    /// An error can occur if 1) the `assert_eq` fails in its check or 2) if the [TagToken] name is not any of the matched
-    /// values.
-    fn parse_special_tag(&mut self, token: &mut TagToken) {
+    /// An error is returned if the [TagToken] name is not any of the matched values, or if its value can't be parsed.
+    fn parse_special_tag(&mut self, token: &mut TagToken) -> Result<(), String> {
         assert_eq!(self.base_parser.consume_char(), ':');
         match token.name.as_str() {
             "rating" => {
                 let rating_string = self.base_parser.consume_while(valid_rating);
                 token.tag_type = TagType::Rating(self.get_rating(&rating_string));
             }
-            "id" => {
-                token.tag_type = TagType::Id(Some(
-                    self.base_parser
-                        .consume_while(valid_id)
-                        .parse::<i64>()
-                        .unwrap_or_default(),
-                ));
-            }
+            "id" => match self.base_parser.consume_while(valid_id).parse::<i64>() {
+                Ok(id) => token.tag_type = TagType::Id(Some(id)),
+                Err(_) => return Err(invalid_value(&token.name)),
+            },
             "user" => {
                 token.tag_type = TagType::User(Some(self.base_parser.consume_while(valid_user)));
             }
-            "score" => {
-                let ordering = self.get_ordering();
-                let score = self.base_parser.consume_while(valid_score);
-                token.tag_type = TagType::Score(
-                    ordering,
-                    score.parse::<i32>().unwrap(),
-                );
+            "score" | "width" | "height" | "favcount" => {
+                let Some(comparison) = self.parse_comparison() else {
+                    return Err(invalid_value(&token.name));
+                };
+
+                token.tag_type = match token.name.as_str() {
+                    "score" => TagType::Score(comparison),
+                    "width" => TagType::Width(comparison),
+                    "height" => TagType::Height(comparison),
+                    _ => TagType::FavCount(comparison),
+                };
             }
-            _ => {
-                self.base_parser.report_error(
-                    format!("Unknown special tag identifier: {}", token.name).as_str(),
+            "type" => {
+                token.tag_type = TagType::FileType(
+                    self.base_parser
+                        .consume_while(valid_file_type)
+                        .to_lowercase(),
                 );
             }
+            "pool" => match self.base_parser.consume_while(valid_id).parse::<i64>() {
+                Ok(id) => token.tag_type = TagType::Pool(id),
+                Err(_) => return Err(invalid_value(&token.name)),
+            },
+            _ => return Err(format!("Unknown special tag identifier: {}", token.name)),
         };
+
+        Ok(())
     }
 
     /// Checks the value and create a new [Rating] from it.
@@ -233,17 +455,46 @@ impl BlacklistParser {
         }
     }
 
-    /// Gets the ordering of the score.
-    fn get_ordering(&mut self) -> Ordering {
-        let order = self.base_parser.consume_while(valid_ordering);
-        match order.as_str() {
-            "<" => Ordering::Less,
-            ">=" => Ordering::Greater, // This is greater than or equal, but ordering has no combination for that.
-            _ => Ordering::Equal // Defaults to equal (e.g nothing happens).
+    /// Parses the comparison of a number tag (e.g `<5`, `>=-10`, `0..50`).
+    ///
+    /// returns: Option<Comparison> ([None] if the comparison is invalid)
+    fn parse_comparison(&mut self) -> Option<Comparison> {
+        let operator = self.base_parser.consume_while(valid_ordering);
+        let value = self.base_parser.consume_while(valid_score);
+        if operator.is_empty() && self.base_parser.starts_with("..") {
+            self.base_parser.consume_while(|c| c == '.');
+            let max = self.base_parser.consume_while(valid_score);
+            return match (value.is_empty(), max.is_empty()) {
+                (true, true) => None,
+                (true, false) => Some(Comparison::LessOrEqual(max.parse().ok()?)),
+                (false, true) => Some(Comparison::GreaterOrEqual(value.parse().ok()?)),
+                (false, false) => Some(Comparison::Range(value.parse().ok()?, max.parse().ok()?)),
+            };
+        }
+
+        let value = value.parse().ok()?;
+        match operator.as_str() {
+            "<" => Some(Comparison::Less(value)),
+            "<=" => Some(Comparison::LessOrEqual(value)),
+            ">" => Some(Comparison::Greater(value)),
+            ">=" => Some(Comparison::GreaterOrEqual(value)),
+            "" | "=" => Some(Comparison::Equal(value)),
+            _ => None,
         }
     }
 }
 
+/// Describes a special tag with a value that couldn't be parsed.
+///
+/// # Arguments
+///
+/// * `name`: The name of the special tag.
+///
+/// returns: String
+fn invalid_value(name: &str) -> String {
+    format!("Invalid value for {name}")
+}
+
 /// Validates character for tag.
 ///
 /// # Arguments
@@ -308,7 +559,7 @@ fn valid_ordering(c: char) -> bool {
     matches!(c, '<' | '>' | '=')
 }
 
-/// Validates character for score.
+/// Validates character for score (or any other number, which can be negative).
 ///
 /// # Arguments
 ///
@@ -316,7 +567,18 @@ fn valid_ordering(c: char) -> bool {
 ///
 /// returns: bool
 fn valid_score(c: char) -> bool {
-    c.is_ascii_digit()
+    c.is_ascii_digit() || c == '-'
+}
+
+/// Validates character for file type.
+///
+/// # Arguments
+///
+/// * `c`: The character to check.
+///
+/// returns: bool
+fn valid_file_type(c: char) -> bool {
+    c.is_ascii_alphanumeric()
 }
 
 /// Validates character for id.
@@ -330,6 +592,37 @@ fn valid_id(c: char) -> bool {
     c.is_ascii_digit()
 }
 
+/// Checks if a tag matches a wildcard pattern, where each `*` matches any amount of characters.
+///
+/// # Arguments
+///
+/// * `pattern`: The pattern to match against.
+/// * `tag`: The tag to check.
+///
+/// returns: bool
+fn matches_wildcard(pattern: &str, tag: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = tag.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(last) = parts.pop() else {
+        // There was no wildcard in the pattern, so the tag has to match it exactly.
+        return rest.is_empty();
+    };
+
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+
+    rest.ends_with(last)
+}
+
 /// A worker that checks and flags post based on a tag predicate, typically from the user's blacklist.
 ///
 /// It works by comparing and removing any grabbed post that matches with all of the tags in a `LineToken`.
@@ -347,6 +640,8 @@ struct FlagWorker {
     negated_margin: i16,
     /// Whether the post is flagged or not
     flagged: bool,
+    /// The indices of the tags in the line that matched the post
+    matched: Vec<usize>,
 }
 
 impl FlagWorker {
@@ -358,7 +653,7 @@ impl FlagWorker {
     fn set_flag_margin(&mut self, tags: &[TagToken]) {
         for tag in tags {
             if tag.negated {
-                if let TagType::Score(_, _) = tag.tag_type {
+                if let TagType::Score(_) = tag.tag_type {
                     // This is done because e621's blacklist itself doesn't handle scores that are negated, at
                     // least from my testing.
                     continue;
@@ -378,7 +673,7 @@ impl FlagWorker {
     /// * `rating`: The blacklisted rating.
     /// * `post`: The post to check against.
     /// * `negated`: Whether the blacklisted rating is negated or not (this will determine if the rating whitelists the
-    /// post or adds towards removing it from the download pool).
+    ///   post or adds towards removing it from the download pool).
     fn flag_rating(&mut self, rating: &Rating, post: &PostEntry, negated: bool) {
         // A nice tuple hack to get around some massive nesting.
         match (rating, post.rating.as_str()) {
@@ -396,7 +691,7 @@ impl FlagWorker {
     /// * `id`: The blacklisted id to compare.
     /// * `post_id`: The post id to check against.
     /// * `negated`: Whether the blacklisted rating is negated or not (this will determine if the rating whitelists the
-    /// post or adds towards removing it from the download pool).
+    ///   post or adds towards removing it from the download pool).
     fn flag_id(&mut self, id: i64, post_id: i64, negated: bool) {
         if post_id == id {
             self.raise_flag(negated);
@@ -410,33 +705,38 @@ impl FlagWorker {
     /// * `user_id`: The blacklisted user id.
     /// * `uploader_id`: The user id to check against.
     /// * `negated`: Whether the blacklisted rating is negated or not (this will determine if the rating whitelists the
-    /// post or adds towards removing it from the download pool).
+    ///   post or adds towards removing it from the download pool).
     fn flag_user(&mut self, user_id: i64, uploader_id: i64, negated: bool) {
         if user_id == uploader_id {
             self.raise_flag(negated);
         }
     }
 
-    /// Flags post based on it's score.
+    /// Flags post based on one of its numbers (e.g score, width, favorite count).
     ///
     /// # Arguments
     ///
-    /// * `ordering`: The ordering of the score blacklisted (e.g <, >=).
-    /// * `score`: The score to check and blacklist.
-    /// * `post_score`: The post score to check against.
-    fn flag_score(&mut self, ordering: &Ordering, score: &i32, post_score: i64, negated: bool) {
-        match ordering {
-            Ordering::Less => {
-                if post_score < *score as i64 {
-                    self.raise_flag(negated);
-                }
-            }
-            Ordering::Greater => {
-                if post_score >= *score as i64 {
-                    self.raise_flag(negated);
-                }
-            }
-            _ => {}
+    /// * `comparison`: The blacklisted comparison.
+    /// * `post_value`: The post number to check against.
+    /// * `negated`: Whether the blacklisted number is negated or not (this will determine if the number whitelists the
+    ///   post or adds towards removing it from the download pool).
+    fn flag_number(&mut self, comparison: &Comparison, post_value: i64, negated: bool) {
+        if comparison.matches(post_value) {
+            self.raise_flag(negated);
+        }
+    }
+
+    /// Flags post if any of its tags matches the blacklisted wildcard pattern.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern`: The blacklisted pattern, where `*` matches any amount of characters.
+    /// * `post_tags`: The tags of the post to check against.
+    /// * `negated`: Whether the blacklisted pattern is negated or not (this will determine if the pattern whitelists
+    ///   the post or adds towards removing it from the download pool).
+    fn flag_wildcard(&mut self, pattern: &str, post_tags: &HashSet<&str>, negated: bool) {
+        if post_tags.iter().any(|e| matches_wildcard(pattern, e)) {
+            self.raise_flag(negated);
         }
     }
 
@@ -445,10 +745,16 @@ impl FlagWorker {
     /// # Arguments
     ///
     /// * `post`: The post to check.
+    /// * `post_tags`: The tags of the post (see [post_tag_set]).
     /// * `blacklist_line`: The blacklist tags to check the post against.
-    fn check_post(&mut self, post: &PostEntry, blacklist_line: &LineToken) {
-        let post_tags = post.tags.clone().combine_tags();
-        for tag in &blacklist_line.tags {
+    fn check_post(
+        &mut self,
+        post: &PostEntry,
+        post_tags: &HashSet<&str>,
+        blacklist_line: &LineToken,
+    ) {
+        for (index, tag) in blacklist_line.tags.iter().enumerate() {
+            let raised = self.flags + self.negated_flags;
             match &tag.tag_type {
                 TagType::Rating(rating) => {
                     self.flag_rating(rating, post, tag.negated);
@@ -468,15 +774,41 @@ impl FlagWorker {
                         .unwrap();
                     self.flag_user(user_id, post.uploader_id, tag.negated);
                 }
-                TagType::Score(ordering, score) => {
-                    self.flag_score(ordering, score, post.score.total, tag.negated);
+                TagType::Score(comparison) => {
+                    self.flag_number(comparison, post.score.total, tag.negated);
+                }
+                TagType::FileType(ext) => {
+                    if post.file.ext.eq_ignore_ascii_case(ext) {
+                        self.raise_flag(tag.negated);
+                    }
+                }
+                TagType::Width(comparison) => {
+                    self.flag_number(comparison, post.file.width, tag.negated);
+                }
+                TagType::Height(comparison) => {
+                    self.flag_number(comparison, post.file.height, tag.negated);
+                }
+                TagType::FavCount(comparison) => {
+                    self.flag_number(comparison, post.fav_count, tag.negated);
+                }
+                TagType::Pool(id) => {
+                    if post.pools.contains(id) {
+                        self.raise_flag(tag.negated);
+                    }
+                }
+                TagType::Wildcard => {
+                    self.flag_wildcard(&tag.name, post_tags, tag.negated);
                 }
                 TagType::None => {
-                    if post_tags.iter().any(|e| e == tag.name.as_str()) {
+                    if post_tags.contains(tag.name.as_str()) {
                         self.raise_flag(tag.negated);
                     }
                 }
             }
+
+            if self.flags + self.negated_flags > raised {
+                self.matched.push(index);
+            }
         }
 
         if self.is_negated_margin_met() {
@@ -517,20 +849,26 @@ impl FlagWorker {
 
 /// Blacklist that holds all of the blacklist entries.
 /// These entries will be looped through a parsed before being used for filtering posts that are blacklisted.
-pub(crate) struct Blacklist {
+pub struct Blacklist {
     /// The blacklist parser which parses the blacklist and tokenizes it.
     blacklist_parser: BlacklistParser,
     /// All of the blacklist tokens after being parsed.
     blacklist_tokens: RootToken,
+    /// The index of the parsed blacklist lines.
+    line_index: LineIndex,
+    /// The amount of posts each blacklist line has filtered, in the same order as the lines.
+    hits: Vec<u64>,
     /// Request sender used for getting user information.
     request_sender: RequestSender,
 }
 
 impl Blacklist {
-    pub(crate) fn new(request_sender: RequestSender) -> Self {
+    pub fn new(request_sender: RequestSender) -> Self {
         Blacklist {
             blacklist_parser: BlacklistParser::default(),
             blacklist_tokens: RootToken::default(),
+            line_index: LineIndex::default(),
+            hits: Vec::new(),
             request_sender,
         }
     }
@@ -542,14 +880,20 @@ impl Blacklist {
     /// * `user_blacklist`: The user blacklist to parse
     ///
     /// returns: &mut Blacklist
-    pub(crate) fn parse_blacklist(&mut self, user_blacklist: String) -> &mut Blacklist {
+    pub fn parse_blacklist(&mut self, user_blacklist: String) -> &mut Blacklist {
         self.blacklist_parser = BlacklistParser::new(user_blacklist);
         self.blacklist_tokens = self.blacklist_parser.parse_blacklist();
+        self.line_index = LineIndex::new(&self.blacklist_tokens.lines);
+        self.hits = vec![0; self.blacklist_tokens.lines.len()];
         self
     }
 
     /// Caches user id into the tag name for quicker access during the blacklist checks.
-    pub(crate) fn cache_users(&mut self) {
+    ///
+    /// The ids are looked up from the [UserCache] first, and only users that aren't cached (or were cached too long
+    /// ago) are looked up from the API.
+    pub fn cache_users(&mut self) -> Result<(), E621Error> {
+        let mut user_cache = UserCache::load();
         let tags: Vec<&mut TagToken> = self
             .blacklist_tokens
             .lines
@@ -558,49 +902,197 @@ impl Blacklist {
             .collect();
         for tag in tags {
             if let TagType::User(Some(username)) = &tag.tag_type {
-                let user: UserEntry = self
-                    .request_sender
-                    .get_entry_from_appended_id(username, "user");
-                tag.name = format!("{}", user.id);
+                let id = match user_cache.get(username) {
+                    Some(id) => {
+                        trace!("Using cached id {id} for user {username}...");
+                        id
+                    }
+                    None => {
+                        let user: UserEntry = self
+                            .request_sender
+                            .get_entry_from_appended_id(username, "user")?;
+                        user_cache.insert(username, user.id);
+                        user.id
+                    }
+                };
+                tag.name = format!("{id}");
             }
         }
+
+        if let Err(e) = user_cache.save() {
+            warn!("Unable to save the user cache: {e}");
+        }
+
+        Ok(())
     }
 
     /// Checks if the blacklist is empty.
-    pub(crate) fn is_empty(&self) -> bool {
+    pub fn is_empty(&self) -> bool {
         self.blacklist_tokens.lines.is_empty()
     }
 
+    /// The blacklist lines that have filtered posts so far, along with how many posts each of them filtered, sorted
+    /// from the most posts filtered to the least.
+    ///
+    /// returns: Vec<BlacklistHit>
+    pub fn hits(&self) -> Vec<BlacklistHit> {
+        let mut hits: Vec<BlacklistHit> = self
+            .blacklist_tokens
+            .lines
+            .iter()
+            .zip(&self.hits)
+            .filter(|(_, &posts)| posts > 0)
+            .map(|(line, &posts)| BlacklistHit {
+                line: line.text.clone(),
+                posts,
+            })
+            .collect();
+        hits.sort_by_key(|e| Reverse(e.posts));
+        hits
+    }
+
+    /// Explains why a post is blacklisted, describing each blacklist line that flags it along with the tags of the
+    /// line the post matched.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to explain.
+    ///
+    /// returns: Vec<String> (empty if the post isn't blacklisted)
+    pub fn explain_post(&self, post: &PostEntry) -> Vec<String> {
+        let post_tags = post_tag_set(post);
+        self.blacklist_tokens
+            .lines
+            .iter()
+            .filter_map(|line| {
+                let flag_worker = self.check_line(post, &post_tags, line);
+                if !flag_worker.is_flagged() {
+                    return None;
+                }
+
+                let matched: Vec<String> = flag_worker
+                    .matched
+                    .iter()
+                    .map(|&e| &line.tags[e])
+                    .filter(|e| !e.negated)
+                    .map(|e| format!("\"{}\"", e.text))
+                    .collect();
+                Some(format!(
+                    "\"{}\" (the post matches {})",
+                    line.text,
+                    matched.join(", ")
+                ))
+            })
+            .collect()
+    }
+
     /// Filters through a set of posts, only retaining posts that aren't blacklisted.
     ///
+    /// Each filtered post is counted towards the first blacklist line that flags it (see [Blacklist::hits]).
+    ///
     /// # Arguments
     ///
     /// * `posts`: Posts to filter through.
     ///
-    /// returns: u16
-    pub(crate) fn filter_posts(&self, posts: &mut Vec<PostEntry>) -> u16 {
-        let mut filtered: u16 = 0;
-        for blacklist_line in &self.blacklist_tokens.lines {
-            posts.retain(|e| {
-                let mut flag_worker = FlagWorker::default();
-                flag_worker.set_flag_margin(&blacklist_line.tags);
-                flag_worker.check_post(e, blacklist_line);
-                if flag_worker.is_flagged() {
-                    filtered += 1;
-                }
+    /// returns: usize
+    pub fn filter_posts(&mut self, posts: &mut Vec<PostEntry>) -> usize {
+        self.filter_posts_with_whitelist(posts, &[])
+    }
+
+    /// Filters through a set of posts, only retaining posts that aren't blacklisted or have a whitelisted tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: Posts to filter through.
+    /// * `whitelist`: Tags that exempt a post from the blacklist.
+    ///
+    /// returns: usize
+    pub fn filter_posts_with_whitelist(
+        &mut self,
+        posts: &mut Vec<PostEntry>,
+        whitelist: &[String],
+    ) -> usize {
+        self.filter_posts_with_lines(posts, whitelist).len()
+    }
+
+    /// Filters through a set of posts like [Blacklist::filter_posts_with_whitelist], returning the id of every
+    /// filtered post along with the blacklist line that flagged it.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: Posts to filter through.
+    /// * `whitelist`: Tags that exempt a post from the blacklist.
+    ///
+    /// returns: Vec<(i64, String)>
+    pub fn filter_posts_with_lines(
+        &mut self,
+        posts: &mut Vec<PostEntry>,
+        whitelist: &[String],
+    ) -> Vec<(i64, String)> {
+        let mut exempt: usize = 0;
+        let mut filtered_posts = Vec::new();
+        let mut hits = vec![0; self.blacklist_tokens.lines.len()];
+        posts.retain(|e| {
+            let post_tags = post_tag_set(e);
+            if whitelist.iter().any(|tag| post_tags.contains(tag.as_str())) {
+                exempt += 1;
+                return true;
+            }
+
+            let flagged_by = self
+                .line_index
+                .candidates(&post_tags)
+                .into_iter()
+                .find(|&line| {
+                    self.check_line(e, &post_tags, &self.blacklist_tokens.lines[line])
+                        .is_flagged()
+                });
+            if let Some(line) = flagged_by {
+                hits[line] += 1;
+                filtered_posts.push((e.id, self.blacklist_tokens.lines[line].text.clone()));
+            }
+
+            // This inverses the flag to make sure it retains what isn't flagged and disposes of
+            // what is flagged.
+            flagged_by.is_none()
+        });
+
+        for (total, line_hits) in self.hits.iter_mut().zip(hits) {
+            *total += line_hits;
+        }
 
-                // This inverses the flag to make sure it retains what isn't flagged and disposes of
-                // what is flagged.
-                !flag_worker.is_flagged()
-            });
+        if exempt > 0 {
+            trace!("{exempt} posts are exempt from the blacklist by the whitelist...");
         }
 
+        let filtered = filtered_posts.len();
         match filtered.cmp(&1) {
             Ordering::Less => trace!("No posts filtered..."),
             Ordering::Equal => trace!("Filtered {filtered} post with blacklist..."),
             Ordering::Greater => trace!("Filtered {filtered} posts with blacklist..."),
         }
 
-        filtered
+        filtered_posts
+    }
+
+    /// Checks a post against a single blacklist line.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to check.
+    /// * `post_tags`: The tags of the post (see [post_tag_set]).
+    /// * `line`: The blacklist line to check the post against.
+    ///
+    /// returns: FlagWorker
+    fn check_line(
+        &self,
+        post: &PostEntry,
+        post_tags: &HashSet<&str>,
+        line: &LineToken,
+    ) -> FlagWorker {
+        let mut flag_worker = FlagWorker::default();
+        flag_worker.set_flag_margin(&line.tags);
+        flag_worker.check_post(post, post_tags, line);
+        flag_worker
     }
 }
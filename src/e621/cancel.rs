@@ -0,0 +1,35 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Whether the current run was asked to stop.
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the current run to stop, it finishes the tag or post it's working on and then skips everything else.
+pub fn cancel() {
+    CANCELLED.store(true, Ordering::Relaxed);
+}
+
+/// Clears a previous cancellation so a new run can start.
+pub fn reset_cancel() {
+    CANCELLED.store(false, Ordering::Relaxed);
+}
+
+/// Whether the current run was asked to stop.
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::Relaxed)
+}
@@ -0,0 +1,271 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use reqwest::Url;
+
+/// A heading (e.g `h4. Appearance`, or `h4#appearance. Appearance` with an anchor).
+static HEADING: Lazy<Regex> = Lazy::new(|| Regex::new(r"^h([1-6])(?:#[\w-]+)?\.\s*(.*)$").unwrap());
+
+/// A list item, where the amount of `*` is how deep the item is nested.
+static LIST_ITEM: Lazy<Regex> = Lazy::new(|| Regex::new(r"^(\*+)\s+(.*)$").unwrap());
+
+/// A link to a wiki page (e.g `[[red_fox]]`, or `[[red_fox|foxes]]` with its own text).
+static WIKI_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\[\[([^\]|]+)(?:\|([^\]]*))?\]\]").unwrap());
+
+/// A link to a post search (e.g `{{fox rating:s}}`, or `{{fox|foxes}}` with its own text).
+static SEARCH_LINK: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\{\{([^}|]+)(?:\|([^}]*))?\}\}").unwrap());
+
+/// A link with its own text (e.g `"e621":https://e621.net`, `"e621":[https://e621.net]`, or `"Help":/help`).
+static TEXT_LINK: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r#""([^"]+)":(?:\[([^\]]+)\]|(https?://[^\s<>\[\]]+|/[^\s<>\[\]]*))"#).unwrap()
+});
+
+/// A reference to a post, pool, or set by its id (e.g `post #123`).
+static ID_LINK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?i)\b(post|pool|set) #(\d+)").unwrap());
+
+/// The start of a section, which can have a title (e.g `[section=Variants]` or `[section,expanded=Variants]`).
+static SECTION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)\[section(?:,expanded)?(?:=([^\]]*))?\]").unwrap());
+
+/// Formatting tags and what they are replaced with in markdown, formatting markdown has no syntax for is left out.
+const FORMATTING: [(&str, &str); 16] = [
+    ("[b]", "**"),
+    ("[/b]", "**"),
+    ("[i]", "*"),
+    ("[/i]", "*"),
+    ("[s]", "~~"),
+    ("[/s]", "~~"),
+    ("[u]", ""),
+    ("[/u]", ""),
+    ("[sup]", ""),
+    ("[/sup]", ""),
+    ("[sub]", ""),
+    ("[/sub]", ""),
+    ("[spoiler]", ""),
+    ("[/spoiler]", ""),
+    ("[/section]", ""),
+    ("[nodtext]", ""),
+];
+
+/// Converts text in e621's DText markup (e.g a wiki page) to markdown.
+///
+/// Headings, lists, quotes, code blocks, bold, italic, and strikethrough text are converted, and links (including
+/// wiki links, search links, and `post #123` references) point to the instance at the base url. Formatting markdown
+/// has no syntax for (e.g underlined text and spoilers) is left out, keeping the text.
+///
+/// # Arguments
+///
+/// * `dtext`: The text to convert.
+/// * `base_url`: The url of the instance links are relative to (e.g `https://e621.net`).
+///
+/// returns: String
+pub fn dtext_to_markdown(dtext: &str, base_url: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code = false;
+    let mut quote_depth: usize = 0;
+    for line in dtext.replace("\r\n", "\n").lines() {
+        if in_code {
+            match line.trim_end().strip_suffix("[/code]") {
+                Some(code) => {
+                    if !code.is_empty() {
+                        lines.push(code.to_string());
+                    }
+
+                    lines.push(String::from("```"));
+                    in_code = false;
+                }
+                None => lines.push(line.to_string()),
+            }
+
+            continue;
+        }
+
+        let mut line = line.trim();
+        if let Some(code) = line.strip_prefix("[code]") {
+            lines.push(String::from("```"));
+            match code.strip_suffix("[/code]") {
+                Some(code) => {
+                    lines.push(code.to_string());
+                    lines.push(String::from("```"));
+                }
+                None => {
+                    if !code.is_empty() {
+                        lines.push(code.to_string());
+                    }
+
+                    in_code = true;
+                }
+            }
+
+            continue;
+        }
+
+        while let Some(rest) = line.strip_prefix("[quote]") {
+            quote_depth += 1;
+            line = rest.trim_start();
+        }
+
+        let mut closed_quotes = 0;
+        while let Some(rest) = line.strip_suffix("[/quote]") {
+            closed_quotes += 1;
+            line = rest.trim_end();
+        }
+
+        let converted = format!(
+            "{}{}",
+            "> ".repeat(quote_depth),
+            convert_line(line, base_url)
+        );
+        lines.push(converted.trim_end().to_string());
+        quote_depth = quote_depth.saturating_sub(closed_quotes);
+    }
+
+    if in_code {
+        lines.push(String::from("```"));
+    }
+
+    lines.join("\n")
+}
+
+/// Converts a single line that isn't part of a code block, turning headings and list items into their markdown
+/// counterparts before converting the text of the line.
+///
+/// # Arguments
+///
+/// * `line`: The line to convert.
+/// * `base_url`: The url of the instance links are relative to.
+///
+/// returns: String
+fn convert_line(line: &str, base_url: &str) -> String {
+    if let Some(heading) = HEADING.captures(line) {
+        let level: usize = heading[1].parse().unwrap_or(1);
+        return format!(
+            "{} {}",
+            "#".repeat(level),
+            convert_inline(&heading[2], base_url)
+        );
+    }
+
+    if let Some(item) = LIST_ITEM.captures(line) {
+        return format!(
+            "{}- {}",
+            "  ".repeat(item[1].len() - 1),
+            convert_inline(&item[2], base_url)
+        );
+    }
+
+    convert_inline(line, base_url)
+}
+
+/// Converts the links and formatting within a line.
+///
+/// # Arguments
+///
+/// * `text`: The text to convert.
+/// * `base_url`: The url of the instance links are relative to.
+///
+/// returns: String
+fn convert_inline(text: &str, base_url: &str) -> String {
+    let text = WIKI_LINK.replace_all(text, |e: &Captures| {
+        let title = e[1].trim().to_lowercase().replace(' ', "_");
+        let label = e
+            .get(2)
+            .map_or_else(|| title.replace('_', " "), |e| e.as_str().to_string());
+        link(
+            &label,
+            base_url,
+            "/wiki_pages/show_or_new",
+            &[("title", &title)],
+        )
+    });
+    let text = SEARCH_LINK.replace_all(&text, |e: &Captures| {
+        let search = e[1].trim();
+        let label = e.get(2).map_or(search, |e| e.as_str());
+        link(label, base_url, "/posts", &[("tags", search)])
+    });
+    let text = TEXT_LINK.replace_all(&text, |e: &Captures| {
+        let url = e.get(2).or_else(|| e.get(3)).map_or("", |e| e.as_str());
+        if url.starts_with('/') {
+            format!("[{}]({base_url}{url})", &e[1])
+        } else {
+            format!("[{}]({url})", &e[1])
+        }
+    });
+    let text = ID_LINK.replace_all(&text, |e: &Captures| {
+        let path = match e[1].to_lowercase().as_str() {
+            "post" => "posts",
+            "pool" => "pools",
+            _ => "post_sets",
+        };
+        format!("[{}]({base_url}/{path}/{})", &e[0], &e[2])
+    });
+    let mut text = SECTION
+        .replace_all(&text, |e: &Captures| {
+            e.get(1)
+                .map(|e| format!("**{}**", e.as_str()))
+                .unwrap_or_default()
+        })
+        .to_string();
+    for (dtext, markdown) in FORMATTING {
+        text = replace_ignore_case(&text, dtext, markdown);
+    }
+
+    text
+}
+
+/// Creates a markdown link to a page of the instance.
+///
+/// # Arguments
+///
+/// * `label`: The text of the link.
+/// * `base_url`: The url of the instance.
+/// * `path`: The path of the page (e.g `/posts`).
+/// * `query`: The query parameters of the page.
+///
+/// returns: String
+fn link(label: &str, base_url: &str, path: &str, query: &[(&str, &str)]) -> String {
+    match Url::parse_with_params(&format!("{base_url}{path}"), query) {
+        Ok(url) => format!("[{label}]({url})"),
+        Err(_) => label.to_string(),
+    }
+}
+
+/// Replaces every occurrence of an ASCII pattern, ignoring the case of the text.
+///
+/// # Arguments
+///
+/// * `text`: The text to replace in.
+/// * `pattern`: The pattern to replace.
+/// * `replacement`: What to replace the pattern with.
+///
+/// returns: String
+fn replace_ignore_case(text: &str, pattern: &str, replacement: &str) -> String {
+    let lowercase = text.to_ascii_lowercase();
+    let mut result = String::with_capacity(text.len());
+    let mut last = 0;
+    for (index, _) in lowercase.match_indices(pattern) {
+        result.push_str(&text[last..index]);
+        result.push_str(replacement);
+        last = index + pattern.len();
+    }
+
+    result.push_str(&text[last..]);
+    result
+}
@@ -0,0 +1,298 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::error::Error;
+use std::fmt::Display;
+use std::io;
+use std::sync::{Mutex, PoisonError};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+/// An error that happened while talking to e621, or while saving what was downloaded from it.
+///
+/// Each error only affects the item (post, pool, set, or tag) being worked on, so callers can choose to skip the item
+/// and continue instead of exiting.
+///
+/// The message of each error already includes its cause, so it can be logged on its own.
+#[derive(thiserror::Error, Debug)]
+pub enum E621Error {
+    /// The request couldn't be sent, or its response couldn't be read.
+    #[error("The request failed: {0}")]
+    Network(reqwest::Error),
+    /// The host of the instance couldn't be resolved to an address.
+    #[error("Unable to resolve {host}: {error}")]
+    Resolve {
+        /// The host that was resolved.
+        host: String,
+        /// Why the host couldn't be resolved.
+        error: io::Error,
+    },
+    /// The server responded with an error status.
+    #[error("The server responded with status {status} for {url}")]
+    Status {
+        /// The url that was requested.
+        url: String,
+        /// The status of the response.
+        status: StatusCode,
+    },
+    /// The server rejected the login credentials.
+    #[error("The server rejected the login credentials (status {0})!")]
+    Auth(StatusCode),
+    /// The response body couldn't be deserialized.
+    #[error("Unable to parse the {name} response: {error}")]
+    Parse {
+        /// What the response is for.
+        name: String,
+        /// Why the response couldn't be deserialized.
+        error: serde_json::Error,
+    },
+    /// A file or directory couldn't be read or written.
+    #[error("Unable to access the file system: {0}")]
+    Io(io::Error),
+    /// The response was valid, but isn't shaped the way the API is expected to respond.
+    #[error("{0}")]
+    ApiSchema(String),
+    /// The search has more tags than the account can search at once.
+    #[error(
+        "The search has {tags} tags{}, but your account can only search {limit} tags at once",
+        counted_injected_tags(*.injected)
+    )]
+    TooManyTags {
+        /// The amount of tags in the search, including the tags the downloader added.
+        tags: usize,
+        /// The amount of tags the downloader added to the search (e.g `rating:s` for safe groups, or `id:>N` for
+        /// incremental groups).
+        injected: usize,
+        /// The most tags the account can search at once.
+        limit: usize,
+    },
+    /// A filter couldn't decide whether to grab a post, so the search was stopped.
+    #[error("The {name} filter failed: {error:#}")]
+    Filter {
+        /// The name of the filter.
+        name: String,
+        /// Why the filter failed.
+        error: anyhow::Error,
+    },
+    /// The file that was saved doesn't match the md5 the API reported for it.
+    #[error("The file saved from {url} has the md5 {actual}, but {expected} was expected")]
+    Checksum {
+        /// The url of the file.
+        url: String,
+        /// The md5 the API reported.
+        expected: String,
+        /// The md5 of the saved file.
+        actual: String,
+    },
+    /// The config couldn't be loaded, or has invalid options.
+    #[error("Unable to load the config: {0:#}")]
+    Config(anyhow::Error),
+    /// The tag file is missing, or was just created and has to be filled in before anything can be downloaded.
+    #[error("{0}")]
+    TagFile(String),
+}
+
+impl E621Error {
+    /// The error wrapped by this error, whose message is already part of this error's message.
+    fn wrapped(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            E621Error::Network(e) => Some(e),
+            E621Error::Io(e) => Some(e),
+            E621Error::Resolve { error, .. } => Some(error),
+            E621Error::Parse { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Notes how many of the tags of a search the downloader added, so the user knows why the search has more tags than
+/// they wrote.
+///
+/// # Arguments
+///
+/// * `injected`: The amount of tags the downloader added to the search.
+///
+/// returns: String
+fn counted_injected_tags(injected: usize) -> String {
+    match injected {
+        0 => String::new(),
+        _ => format!(" (counting {injected} added by the downloader, like `rating:s` for safe groups or `id:>N` for incremental groups)"),
+    }
+}
+
+impl From<reqwest::Error> for E621Error {
+    fn from(error: reqwest::Error) -> Self {
+        E621Error::Network(error)
+    }
+}
+
+impl From<io::Error> for E621Error {
+    fn from(error: io::Error) -> Self {
+        E621Error::Io(error)
+    }
+}
+
+/// The stage a run is in, saved with every skipped item so a report shows what the run was doing when it failed.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum RunStage {
+    /// Loading the config and login, and checking the login with e621.
+    #[default]
+    Startup,
+    /// Parsing the tag file and identifying its tags.
+    Parsing,
+    /// Grabbing the posts of every tag, pool, and set.
+    Grabbing,
+    /// Downloading the grabbed posts.
+    Downloading,
+}
+
+/// An item that was skipped because of an error, along with the error.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct SkippedItem {
+    /// A description of the skipped item (e.g `"braeburned"` or `post 1234`).
+    pub item: String,
+    /// Why the item was skipped.
+    pub reason: String,
+    /// The stage the run was in when the item was skipped.
+    pub stage: RunStage,
+    /// The errors that caused the reason, from the closest to the root cause.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub causes: Vec<String>,
+}
+
+/// The items (tags, pools, sets, posts, or tag file lines) skipped this run because of an error.
+static SKIPPED_ITEMS: Mutex<Vec<SkippedItem>> = Mutex::new(Vec::new());
+
+/// The stage the current run is in.
+static RUN_STAGE: Mutex<RunStage> = Mutex::new(RunStage::Startup);
+
+/// Sets the stage the current run is in.
+///
+/// # Arguments
+///
+/// * `stage`: The stage the run is entering.
+pub fn set_run_stage(stage: RunStage) {
+    *RUN_STAGE.lock().unwrap_or_else(PoisonError::into_inner) = stage;
+}
+
+/// The stage the current run is in.
+pub fn run_stage() -> RunStage {
+    *RUN_STAGE.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Gets the errors that caused an error, from the closest to the root cause, leaving out the errors whose message is
+/// already part of the message before them (e.g reqwest includes the cause in its messages).
+///
+/// # Arguments
+///
+/// * `error`: The error to get the causes of.
+///
+/// returns: Vec<String>
+pub fn error_causes(error: &(dyn Error + 'static)) -> Vec<String> {
+    let mut source = match error.downcast_ref::<E621Error>() {
+        Some(e) => e.wrapped().and_then(|e| e.source()),
+        None => error.source(),
+    };
+
+    let mut causes = Vec::new();
+    let mut previous = error.to_string();
+    while let Some(cause) = source {
+        let message = cause.to_string();
+        if !previous.contains(&message) {
+            causes.push(message.clone());
+        }
+
+        previous = message;
+        source = cause.source();
+    }
+
+    causes
+}
+
+/// Logs a warning for an item that's skipped because of an error, adding it to the skipped items so the run can list
+/// them at the end and exit with an error code.
+///
+/// # Arguments
+///
+/// * `item`: A description of the skipped item.
+/// * `reason`: Why the item was skipped.
+pub fn skip_item(item: &str, reason: &dyn Display) {
+    push_skipped_item(item, reason, Vec::new());
+}
+
+/// Like [skip_item], but also saves the errors that caused the error, so reports show the whole chain instead of only
+/// the outermost message.
+///
+/// # Arguments
+///
+/// * `item`: A description of the skipped item.
+/// * `error`: The error the item was skipped because of.
+pub fn skip_item_error(item: &str, error: &(dyn Error + 'static)) {
+    push_skipped_item(item, error, error_causes(error));
+}
+
+/// Logs and saves a skipped item.
+///
+/// # Arguments
+///
+/// * `item`: A description of the skipped item.
+/// * `reason`: Why the item was skipped.
+/// * `causes`: The errors that caused the reason.
+fn push_skipped_item(item: &str, reason: &dyn Display, causes: Vec<String>) {
+    warn!("Skipping {item}: {reason}");
+    for cause in &causes {
+        trace!("  Caused by: {cause}");
+    }
+
+    SKIPPED_ITEMS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(SkippedItem {
+            item: item.to_string(),
+            reason: reason.to_string(),
+            stage: run_stage(),
+            causes,
+        });
+}
+
+/// The amount of items skipped this run because of an error.
+pub fn skipped_items() -> usize {
+    SKIPPED_ITEMS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .len()
+}
+
+/// The items skipped because of an error, after the given amount of items were already skipped (e.g by an earlier
+/// run of the same process).
+///
+/// # Arguments
+///
+/// * `start`: The amount of skipped items to leave out, from [skipped_items].
+///
+/// returns: Vec<SkippedItem>
+pub fn skipped_items_since(start: usize) -> Vec<SkippedItem> {
+    SKIPPED_ITEMS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .skip(start)
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,106 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Mutex, PoisonError};
+
+use serde::Serialize;
+use serde_json::to_string;
+
+use crate::args::Args;
+
+/// The functions called with every emitted event, used by `--serve` and `--event-stream` to follow the run.
+static LISTENERS: Mutex<Vec<fn(&ProgressEvent)>> = Mutex::new(Vec::new());
+
+/// Adds a function to call with every emitted [ProgressEvent], whether `--progress-json` is passed or not.
+///
+/// # Arguments
+///
+/// * `listener`: The function to call with each event.
+pub fn add_listener(listener: fn(&ProgressEvent)) {
+    LISTENERS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(listener);
+}
+
+/// A machine-readable progress event, emitted as a single line of JSON on stdout when `--progress-json` is passed.
+///
+/// Each event is tagged with an `event` field containing its snake-cased name (e.g `"post_downloaded"`).
+#[derive(Serialize, Debug)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    /// Grabbing has started for a tag, pool, set, post, or favorites.
+    GrabStarted {
+        /// The tag (or id) being grabbed.
+        tag: &'a str,
+    },
+    /// A post was downloaded and saved.
+    PostDownloaded {
+        /// The collection the post belongs to.
+        collection: &'a str,
+        /// The file name the post was saved as.
+        file: &'a str,
+        /// The size of the file in bytes.
+        bytes: i64,
+        /// Whether the sample was downloaded because the original file wasn't available.
+        degraded: bool,
+    },
+    /// A post was skipped and not downloaded.
+    PostSkipped {
+        /// The collection the post belongs to.
+        collection: &'a str,
+        /// The file name the post would have been saved as.
+        file: &'a str,
+        /// Why the post was skipped.
+        reason: &'a str,
+    },
+    /// Grabbing has finished and the posts are about to be downloaded.
+    DownloadStarted {
+        /// The amount of posts to download.
+        posts: usize,
+        /// The total size of the posts in bytes.
+        bytes: u64,
+    },
+    /// The run has finished.
+    RunFinished {
+        /// The total amount of posts downloaded.
+        downloaded: u64,
+        /// The total amount of posts skipped.
+        skipped: u64,
+    },
+}
+
+impl ProgressEvent<'_> {
+    /// Passes the event to every listener, then writes it to stdout as a line of JSON if `--progress-json` is enabled.
+    pub fn emit(&self) {
+        let listeners = LISTENERS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for listener in listeners {
+            listener(self);
+        }
+
+        if !Args::get().progress_json() {
+            return;
+        }
+
+        match to_string(self) {
+            Ok(json) => println!("{json}"),
+            Err(e) => trace!("Unable to serialize progress event {self:?}: {e}"),
+        }
+    }
+}
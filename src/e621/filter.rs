@@ -0,0 +1,308 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread::spawn;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use serde_json::to_string;
+
+use crate::e621::io::tag::GroupOptions;
+use crate::e621::io::{config_directory, Config};
+use crate::e621::sender::entries::PostEntry;
+
+/// A filter deciding which searched posts get grabbed.
+///
+/// Filters run in [Grabber::search](crate::e621::grabber::Grabber) after the blacklist and before the posts are added
+/// to a collection, in the order they were added. Each filter only sees the posts every filter before it accepted. If a
+/// filter fails, the search is skipped instead of grabbing posts the filter didn't check.
+pub trait PostFilter {
+    /// The name of the filter, used in the log.
+    fn name(&self) -> &str;
+
+    /// Checks whether the post passes the filter and should be grabbed.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to check.
+    /// * `options`: The options of the group being grabbed.
+    ///
+    /// returns: Result<bool, Error>
+    fn accepts(&mut self, post: &PostEntry, options: &GroupOptions) -> Result<bool, Error>;
+}
+
+/// Creates the built-in filters, set up from the config.
+///
+/// returns: Vec<Box<dyn PostFilter>>
+pub fn default_filters() -> Vec<Box<dyn PostFilter>> {
+    let mut filters: Vec<Box<dyn PostFilter>> = vec![
+        Box::new(ExtensionFilter),
+        Box::new(ScoreFilter),
+        Box::new(SizeFilter),
+    ];
+    if let Some(path) = Config::get().filter_script() {
+        filters.push(Box::new(ScriptFilter::new(path)));
+    }
+
+    filters
+}
+
+/// Removes posts whose file type isn't allowed by the group options (or the config if the group doesn't set them).
+pub struct ExtensionFilter;
+
+impl PostFilter for ExtensionFilter {
+    fn name(&self) -> &str {
+        "file type"
+    }
+
+    fn accepts(&mut self, post: &PostEntry, options: &GroupOptions) -> Result<bool, Error> {
+        let config = Config::get();
+        let only = options
+            .only_file_types()
+            .unwrap_or(config.only_file_types());
+        let exclude = options
+            .exclude_file_types()
+            .unwrap_or(config.exclude_file_types());
+        let ext = post.file.ext.to_lowercase();
+        Ok((only.is_empty() || only.contains(&ext)) && !exclude.contains(&ext))
+    }
+}
+
+/// Removes posts below the minimum score or favorite count set by the group options (or the config if the group
+/// doesn't set them).
+pub struct ScoreFilter;
+
+impl PostFilter for ScoreFilter {
+    fn name(&self) -> &str {
+        "score"
+    }
+
+    fn accepts(&mut self, post: &PostEntry, options: &GroupOptions) -> Result<bool, Error> {
+        let config = Config::get();
+        Ok(options
+            .min_score()
+            .or(config.min_score())
+            .is_none_or(|min| post.score.total >= min)
+            && options
+                .min_fav_count()
+                .or(config.min_fav_count())
+                .is_none_or(|min| post.fav_count >= min))
+    }
+}
+
+/// Removes posts smaller than the minimum width or height set by the group options.
+pub struct SizeFilter;
+
+impl PostFilter for SizeFilter {
+    fn name(&self) -> &str {
+        "size"
+    }
+
+    fn accepts(&mut self, post: &PostEntry, options: &GroupOptions) -> Result<bool, Error> {
+        Ok(options.min_width().is_none_or(|min| post.file.width >= min)
+            && options
+                .min_height()
+                .is_none_or(|min| post.file.height >= min))
+    }
+}
+
+/// How long the filter script can take to answer for a post before it's treated as failed.
+const SCRIPT_ANSWER_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Asks an external program (the `filterScript` in the config) whether each post should be grabbed.
+///
+/// The program is started once and kept running for the whole run. Each post is written to its stdin as a line of
+/// JSON, and it answers each with a line of `accept` or `reject` on its stdout. If the program fails or doesn't answer
+/// within [SCRIPT_ANSWER_TIMEOUT], it's stopped and the search is skipped, and the program is started again for the
+/// next search.
+pub struct ScriptFilter {
+    /// The path of the program.
+    path: PathBuf,
+    /// The running program, started on the first post.
+    child: Option<Child>,
+    /// The lines the running program answered with, read on their own thread so answers can time out.
+    answers: Option<Receiver<Result<String, std::io::Error>>>,
+}
+
+impl ScriptFilter {
+    /// Creates a filter running the given program.
+    ///
+    /// A relative path is resolved against the config directory when the program is there, so the filter doesn't
+    /// depend on the directory the downloader is started from. Otherwise it's left as is, so programs on the `PATH`
+    /// can be used.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the program.
+    ///
+    /// returns: ScriptFilter
+    pub fn new(path: &str) -> Self {
+        let in_config_directory = config_directory().join(path);
+        ScriptFilter {
+            path: if in_config_directory.is_file() {
+                in_config_directory
+            } else {
+                PathBuf::from(path)
+            },
+            child: None,
+            answers: None,
+        }
+    }
+
+    /// Starts the program if it isn't running yet.
+    fn start(&mut self) -> Result<(), Error> {
+        if self.child.is_some() {
+            return Ok(());
+        }
+
+        trace!("Starting filter script {}...", self.path.display());
+        let mut child = Command::new(&self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .with_context(|| format!("Unable to start {}", self.path.display()))?;
+        if let Some(stdout) = child.stdout.take() {
+            let (sender, receiver) = channel();
+            spawn(move || {
+                for line in BufReader::new(stdout).lines() {
+                    if sender.send(line).is_err() {
+                        break;
+                    }
+                }
+            });
+            self.answers = Some(receiver);
+        }
+
+        self.child = Some(child);
+        Ok(())
+    }
+
+    /// Stops the program, so it's started again for the next post.
+    fn stop(&mut self) {
+        self.answers = None;
+        if let Some(mut child) = self.child.take() {
+            if let Err(e) = child.kill().and_then(|_| child.wait()) {
+                trace!("Unable to stop filter script {}: {e}", self.path.display());
+            }
+        }
+    }
+
+    /// Sends the post to the program and reads its answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to send.
+    ///
+    /// returns: Result<bool, Error>
+    fn ask(&mut self, post: &PostEntry) -> Result<bool, Error> {
+        self.start()?;
+        let (Some(stdin), Some(answers)) = (
+            self.child.as_mut().and_then(|e| e.stdin.as_mut()),
+            self.answers.as_ref(),
+        ) else {
+            bail!("the script has no stdin or stdout");
+        };
+
+        writeln!(stdin, "{}", to_string(post)?)?;
+        stdin.flush()?;
+
+        let answer = match answers.recv_timeout(SCRIPT_ANSWER_TIMEOUT) {
+            Ok(answer) => answer?,
+            Err(RecvTimeoutError::Timeout) => bail!(
+                "the script didn't answer within {} seconds",
+                SCRIPT_ANSWER_TIMEOUT.as_secs()
+            ),
+            Err(RecvTimeoutError::Disconnected) => bail!("the script exited before answering"),
+        };
+
+        match answer.trim() {
+            "accept" => Ok(true),
+            "reject" => Ok(false),
+            answer => bail!("the script answered {answer:?} instead of `accept` or `reject`"),
+        }
+    }
+}
+
+impl PostFilter for ScriptFilter {
+    fn name(&self) -> &str {
+        "filter script"
+    }
+
+    fn accepts(&mut self, post: &PostEntry, _options: &GroupOptions) -> Result<bool, Error> {
+        self.ask(post).inspect_err(|_| self.stop())
+    }
+}
+
+impl Drop for ScriptFilter {
+    /// Closes the program's stdin so it can finish up, and waits for it to exit.
+    fn drop(&mut self) {
+        if let Some(mut child) = self.child.take() {
+            drop(child.stdin.take());
+            if let Err(e) = child.wait() {
+                trace!(
+                    "Unable to wait for filter script {}: {e}",
+                    self.path.display()
+                );
+            }
+        }
+    }
+}
+
+/// A filter running a closure, so programs embedding the downloader can hook their own selection logic into the grabber
+/// without writing a type for it.
+pub struct FnFilter<F> {
+    /// The name of the filter.
+    name: String,
+    /// The closure deciding whether a post is accepted.
+    filter: F,
+}
+
+impl<F> FnFilter<F>
+where
+    F: FnMut(&PostEntry, &GroupOptions) -> bool,
+{
+    /// Creates a filter from a closure.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the filter, used in the log.
+    /// * `filter`: The closure deciding whether a post is accepted.
+    ///
+    /// returns: FnFilter<F>
+    pub fn new(name: &str, filter: F) -> Self {
+        FnFilter {
+            name: name.to_string(),
+            filter,
+        }
+    }
+}
+
+impl<F> PostFilter for FnFilter<F>
+where
+    F: FnMut(&PostEntry, &GroupOptions) -> bool,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn accepts(&mut self, post: &PostEntry, options: &GroupOptions) -> Result<bool, Error> {
+        Ok((self.filter)(post, options))
+    }
+}
@@ -15,49 +15,217 @@
  */
 
 use std::cell::RefCell;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{HashMap, HashSet};
+use std::iter::once;
 use std::rc::Rc;
+use std::time::{Duration, SystemTime};
 
+use indicatif::{ProgressBar, ProgressDrawTarget};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::args::Args;
 use crate::e621::blacklist::Blacklist;
-use crate::e621::io::tag::{Group, Tag, TagSearchType, TagType};
-use crate::e621::io::{emergency_exit, Config, Login};
-use crate::e621::sender::entries::{PoolEntry, PostEntry, SetEntry};
+use crate::e621::cancel::is_cancelled;
+use crate::e621::error::{skip_item_error, E621Error};
+use crate::e621::events::ProgressEvent;
+use crate::e621::filter::{default_filters, PostFilter};
+use crate::e621::io::audit::{AuditLog, AUDIT_NAME};
+use crate::e621::io::tag::{CollectionOrder, Group, GroupOptions, Tag, TagSearchType, TagType};
+use crate::e621::io::tag_state::{TagState, TAG_STATE_NAME};
+use crate::e621::io::{Config, Login};
+use crate::e621::sanitize::{truncate_bytes, truncate_chars};
+use crate::e621::sender::entries::{PoolEntry, PostEntry, SetEntry, DEFAULT_TAG_QUERY_LIMIT};
 use crate::e621::sender::RequestSender;
+use crate::e621::tui::{ProgressBarBuilder, ProgressStyleBuilder};
 
 /// A trait for implementing a conversion function for turning a type into a [Vec] of the same type
 ///
 /// This can be used as a simple wrapping function for flattening an internal array of the type.
-pub(crate) trait NewVec<T> {
+pub trait NewVec<T> {
     fn new_vec(value: T) -> Vec<Self>
     where
         Self: Sized;
 }
 
 /// A collection of values taken from a [PostEntry].
-pub(crate) struct GrabbedPost {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GrabbedPost {
+    /// The ID of the post.
+    id: i64,
+    /// The total score of the post.
+    score: i64,
+    /// All the tags of the post, only kept when the posts are reviewed before downloading.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// The url of the post's preview (thumbnail).
+    #[serde(rename = "previewUrl", default)]
+    preview_url: Option<String>,
     /// The url that leads to the file to download.
     url: String,
     /// The name of the file to download.
     name: String,
     /// The size of the file to download.
+    #[serde(rename = "fileSize")]
     file_size: i64,
+    /// The md5 of the file to download, if it's the original file (the API doesn't report the md5 of samples).
+    #[serde(default)]
+    md5: Option<String>,
+    /// When the post was uploaded, if the API reported a valid date.
+    #[serde(rename = "createdAt", default, with = "time::serde::rfc3339::option")]
+    created_at: Option<OffsetDateTime>,
+    /// Whether the sample is downloaded because the original file wasn't available.
+    #[serde(default)]
+    degraded: bool,
+    /// Whether the post has notes.
+    #[serde(rename = "hasNotes", default)]
+    has_notes: bool,
+    /// The amount of comments on the post.
+    #[serde(rename = "commentCount", default)]
+    comment_count: i64,
+    /// Whether the post is added to the user's favorites once it's downloaded.
+    #[serde(default)]
+    favorite: bool,
+    /// Whether the post is upvoted once it's downloaded.
+    #[serde(default)]
+    upvote: bool,
 }
 
 impl GrabbedPost {
+    /// Creates a new [GrabbedPost] from the post and the name to save it as.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post being grabbed.
+    /// * `url`: The url of the file to download.
+    /// * `name`: The name of the file to download.
+    ///
+    /// returns: GrabbedPost
+    fn new(post: &PostEntry, url: String, name: String) -> Self {
+        GrabbedPost {
+            id: post.id,
+            score: post.score.total,
+            tags: if Args::get().review() {
+                post.tags.clone().combine_tags()
+            } else {
+                Vec::new()
+            },
+            preview_url: post.preview.url.clone(),
+            md5: (post.file.url.as_ref() == Some(&url)).then(|| post.file.md5.clone()),
+            created_at: OffsetDateTime::parse(&post.created_at, &Rfc3339).ok(),
+            url,
+            name,
+            file_size: post.file.size,
+            degraded: post.file.url.is_none(),
+            has_notes: post.has_notes,
+            comment_count: post.comment_count,
+            favorite: false,
+            upvote: false,
+        }
+    }
+
+    /// The ID of the post.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// The total score of the post.
+    pub fn score(&self) -> i64 {
+        self.score
+    }
+
+    /// All the tags of the post, empty unless the posts are reviewed (see [Args::review]).
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// The url of the post's preview (thumbnail).
+    pub fn preview_url(&self) -> Option<&str> {
+        self.preview_url.as_deref()
+    }
+
     /// The url that leads to the file to download.
-    pub(crate) fn url(&self) -> &str {
+    pub fn url(&self) -> &str {
         &self.url
     }
 
     /// The name of the file to download.
-    pub(crate) fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
     /// The size of the file to download.
-    pub(crate) fn file_size(&self) -> i64 {
+    pub fn file_size(&self) -> i64 {
         self.file_size
     }
+
+    /// The md5 of the file to download, if it's the original file.
+    pub fn md5(&self) -> Option<&str> {
+        self.md5.as_deref()
+    }
+
+    /// When the post was uploaded, if the API reported a valid date.
+    pub fn created_at(&self) -> Option<SystemTime> {
+        self.created_at.map(SystemTime::from)
+    }
+
+    /// Whether the sample is downloaded because the original file wasn't available.
+    pub fn degraded(&self) -> bool {
+        self.degraded
+    }
+
+    /// Whether the post has notes.
+    pub fn has_notes(&self) -> bool {
+        self.has_notes
+    }
+
+    /// The amount of comments on the post.
+    pub fn comment_count(&self) -> i64 {
+        self.comment_count
+    }
+
+    /// Whether the post is added to the user's favorites once it's downloaded.
+    pub fn favorite(&self) -> bool {
+        self.favorite
+    }
+
+    /// Whether the post is upvoted once it's downloaded.
+    pub fn upvote(&self) -> bool {
+        self.upvote
+    }
+
+    /// Gets the url and file extension to download for the post based on the configured download quality.
+    ///
+    /// In `"sample"` quality, the sample is used when the post has one, otherwise the original file is used. The API
+    /// doesn't report the size of samples, so size estimates still use the size of the original file.
+    ///
+    /// If the original file has no url (which happens when the post is hidden from anonymous users), the sample is used
+    /// as a fallback. Posts without either url are removed before this point.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to get the url and extension of.
+    ///
+    /// returns: (String, String)
+    fn url_and_ext(post: &PostEntry) -> (String, String) {
+        if Config::get().download_quality() == "sample" && post.sample.has.unwrap_or(false) {
+            if let Some(url) = &post.sample.url {
+                let ext = url.rsplit('.').next().unwrap_or(&post.file.ext);
+                return (url.clone(), ext.to_string());
+            }
+        }
+
+        match &post.file.url {
+            Some(url) => (url.clone(), post.file.ext.clone()),
+            None => {
+                let url = post.sample.url.clone().unwrap();
+                let ext = url.rsplit('.').next().unwrap_or(&post.file.ext).to_string();
+                (url, ext)
+            }
+        }
+    }
 }
 
 impl NewVec<Vec<PostEntry>> for GrabbedPost {
@@ -102,11 +270,11 @@ impl From<(&PostEntry, &str, u16)> for GrabbedPost {
     ///
     /// returns: GrabbedPost
     fn from((post, name, current_page): (&PostEntry, &str, u16)) -> Self {
-        GrabbedPost {
-            url: post.file.url.clone().unwrap(),
-            name: format!("{} Page_{:05}.{}", name, current_page, post.file.ext),
-            file_size: post.file.size,
-        }
+        let (url, ext) = GrabbedPost::url_and_ext(post);
+        let page = format!(" Page_{:05}.{}", current_page, ext);
+        let max_bytes = Config::get().max_file_name_length();
+        let name = truncate_bytes(name, max_bytes.saturating_sub(page.len()));
+        GrabbedPost::new(post, url, format!("{name}{page}"))
     }
 }
 
@@ -119,43 +287,36 @@ impl From<(PostEntry, &str)> for GrabbedPost {
     ///
     /// returns: GrabbedPost
     fn from((post, name_convention): (PostEntry, &str)) -> Self {
+        let (url, ext) = GrabbedPost::url_and_ext(&post);
         match name_convention {
-            "md5" => GrabbedPost {
-                url: post.file.url.clone().unwrap(),
-                name: format!("{}.{}", post.file.md5, post.file.ext),
-                file_size: post.file.size,
-            },
-            "id" => GrabbedPost {
-                url: post.file.url.clone().unwrap(),
-                name: format!("{}.{}", post.id, post.file.ext),
-                file_size: post.file.size,
-            },
-            _ => {
-                emergency_exit("Incorrect naming convention!");
-                GrabbedPost {
-                    url: String::new(),
-                    name: String::new(),
-                    file_size: 0,
-                }
-            }
+            "id" => GrabbedPost::new(&post, url, format!("{}.{}", post.id, ext)),
+            // Other conventions are reset when the config is loaded, so md5 (the default) is used for them.
+            _ => GrabbedPost::new(&post, url, format!("{}.{}", post.file.md5, ext)),
         }
     }
 }
 
+/// The most characters a collection name is shortened to by [Shorten].
+const SHORT_NAME_LENGTH: usize = 25;
+
 /// A trait for the shorten function, it allows for generic types to be the parameter.
-pub(crate) trait Shorten<T> {
+pub trait Shorten<T> {
     /// Shortens a string by replacing a portion of it with a delimiter of type `T` and then returning the new string.
     fn shorten(&self, delimiter: T) -> String;
 }
 
 /// A set of posts with category and name.
-pub(crate) struct PostCollection {
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PostCollection {
     /// The name of the set.
     name: String,
     /// The category of the set.
     category: String,
     /// The posts in the set.
     posts: Vec<GrabbedPost>,
+    /// The type of the tag the set was searched with, if it was grabbed from a search.
+    #[serde(rename = "tagType", default)]
+    tag_type: Option<TagType>,
 }
 
 impl PostCollection {
@@ -168,32 +329,66 @@ impl PostCollection {
     /// * `posts`: Posts for collection.
     ///
     /// returns: PostCollection
-    pub(crate) fn new(name: &str, category: &str, posts: Vec<GrabbedPost>) -> Self {
+    pub fn new(name: &str, category: &str, posts: Vec<GrabbedPost>) -> Self {
         PostCollection {
             name: name.to_string(),
             category: category.to_string(),
             posts,
+            tag_type: None,
         }
     }
 
     /// The name of the set.
-    pub(crate) fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
     /// The category of the set.
-    pub(crate) fn category(&self) -> &str {
+    pub fn category(&self) -> &str {
         &self.category
     }
 
     /// The posts in the set.
-    pub(crate) fn posts(&self) -> &Vec<GrabbedPost> {
+    pub fn posts(&self) -> &Vec<GrabbedPost> {
         &self.posts
     }
+
+    /// The posts in the set, mutably.
+    pub fn posts_mut(&mut self) -> &mut Vec<GrabbedPost> {
+        &mut self.posts
+    }
+
+    /// The type of the tag the set was searched with, if it was grabbed from a search.
+    pub fn tag_type(&self) -> Option<&TagType> {
+        self.tag_type.as_ref()
+    }
+
+    /// Sets the type of the tag the set was searched with.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_type`: The type of the tag.
+    pub fn set_tag_type(&mut self, tag_type: TagType) {
+        self.tag_type = Some(tag_type);
+    }
+
+    /// Whether the set was grabbed from an artist tag.
+    pub fn is_artist(&self) -> bool {
+        self.tag_type == Some(TagType::Artist)
+    }
+
+    /// The plain tags of the search the set was grabbed with, leaving out negated and optional tags, qualifiers (e.g
+    /// `rating:s`), and wildcards.
+    pub fn searched_tags(&self) -> impl Iterator<Item = &str> {
+        self.name
+            .split(' ')
+            .filter(|e| !e.is_empty() && !e.starts_with(['-', '~']) && !e.contains([':', '*']))
+    }
 }
 
 impl Shorten<&str> for PostCollection {
-    /// Shortens [PostCollection] name if it's greater than 25 characters and attaches the delimiter at the end.
+    /// Shortens [PostCollection] name if it's longer than [SHORT_NAME_LENGTH] characters and attaches the delimiter at
+    /// the end.
     ///
     /// # Arguments
     ///
@@ -201,10 +396,9 @@ impl Shorten<&str> for PostCollection {
     ///
     /// returns: String
     fn shorten(&self, delimiter: &str) -> String {
-        if self.name.len() >= 25 {
-            let mut short_name = self.name[0..25].to_string();
-            short_name.push_str(delimiter);
-            short_name
+        let short_name = truncate_chars(&self.name, SHORT_NAME_LENGTH);
+        if short_name.len() < self.name.len() {
+            format!("{short_name}{delimiter}")
         } else {
             self.name.to_string()
         }
@@ -212,7 +406,8 @@ impl Shorten<&str> for PostCollection {
 }
 
 impl Shorten<char> for PostCollection {
-    /// Shortens [PostCollection] name if it's greater than 25 characters and attaches the delimiter at the end.
+    /// Shortens [PostCollection] name if it's longer than [SHORT_NAME_LENGTH] characters and attaches the delimiter at
+    /// the end.
     ///
     /// # Arguments
     ///
@@ -220,13 +415,7 @@ impl Shorten<char> for PostCollection {
     ///
     /// returns: String
     fn shorten(&self, delimiter: char) -> String {
-        if self.name.len() >= 25 {
-            let mut short_name = self.name[0..25].to_string();
-            short_name.push(delimiter);
-            short_name
-        } else {
-            self.name.to_string()
-        }
+        self.shorten(delimiter.encode_utf8(&mut [0; 4]) as &str)
     }
 }
 
@@ -247,7 +436,7 @@ impl From<(&SetEntry, Vec<GrabbedPost>)> for PostCollection {
 const POST_SEARCH_LIMIT: u8 = 5;
 
 /// Is a collector that grabs posts, categorizes them, and prepares them for the downloader to use in downloading.
-pub(crate) struct Grabber {
+pub struct Grabber {
     /// All grabbed posts.
     posts: Vec<PostCollection>,
     /// `RequestSender` for sending API calls.
@@ -256,6 +445,20 @@ pub(crate) struct Grabber {
     blacklist: Option<Rc<RefCell<Blacklist>>>,
     /// Is grabber in safe mode or not
     safe_mode: bool,
+    /// Spinner that displays the current tag, page, and running post count while grabbing.
+    spinner: ProgressBar,
+    /// The options of the group currently being grabbed.
+    group_options: GroupOptions,
+    /// Filters deciding which searched posts are grabbed.
+    filters: RefCell<Vec<Box<dyn PostFilter>>>,
+    /// The ids of every post grabbed so far, so a post is only kept in the first collection that grabbed it.
+    seen_posts: HashSet<i64>,
+    /// The newest post every tag grabbed in earlier runs, used by groups with `@incremental: true`.
+    tag_state: TagState,
+    /// Every post removed while grabbing, along with why.
+    audit_log: RefCell<AuditLog>,
+    /// The most tags the account can search at once.
+    tag_query_limit: usize,
 }
 
 impl Grabber {
@@ -267,31 +470,140 @@ impl Grabber {
     /// * `safe_mode`: Which mode the grabber will operate under.
     ///
     /// returns: Grabber
-    pub(crate) fn new(request_sender: RequestSender, safe_mode: bool) -> Self {
+    pub fn new(request_sender: RequestSender, safe_mode: bool) -> Self {
         Grabber {
             posts: vec![PostCollection::new("Single Posts", "", Vec::new())],
             request_sender,
             blacklist: None,
             safe_mode,
+            spinner: ProgressBar::hidden(),
+            group_options: GroupOptions::default(),
+            filters: RefCell::new(default_filters()),
+            seen_posts: HashSet::new(),
+            tag_state: TagState::load(),
+            audit_log: RefCell::new(AuditLog::default()),
+            tag_query_limit: DEFAULT_TAG_QUERY_LIMIT,
         }
     }
 
+    /// Initializes the spinner that displays grabbing progress.
+    pub fn initialize_spinner(&mut self) {
+        self.spinner = ProgressBarBuilder::spinner()
+            .style(
+                ProgressStyleBuilder::default()
+                    .template(
+                        "{spinner:.cyan} Grabbing {msg} [{pos} posts total] [{elapsed_precise}]",
+                    )
+                    .build(),
+            )
+            .draw_target(ProgressDrawTarget::stderr())
+            .steady_tick(Duration::from_millis(100))
+            .build();
+    }
+
+    /// Finishes and clears the grabbing spinner.
+    pub fn finish_spinner(&self) {
+        self.spinner.finish_and_clear();
+    }
+
+    /// Updates the spinner with the current search and adds the newly grabbed posts to the running count.
+    ///
+    /// # Arguments
+    ///
+    /// * `searching_tag`: The tag currently being searched.
+    /// * `page`: The page currently being searched.
+    /// * `grabbed`: The amount of posts grabbed from the page.
+    fn update_spinner(&self, searching_tag: &str, page: u16, grabbed: usize) {
+        self.spinner.inc(grabbed as u64);
+        self.spinner
+            .set_message(format!("\"{searching_tag}\" (page {page})"));
+    }
+
     /// All grabbed posts.
-    pub(crate) fn posts(&self) -> &Vec<PostCollection> {
+    pub fn posts(&self) -> &Vec<PostCollection> {
         &self.posts
     }
 
+    /// All grabbed posts, mutably.
+    pub fn posts_mut(&mut self) -> &mut Vec<PostCollection> {
+        &mut self.posts
+    }
+
+    /// Whether the grabber is in safe mode.
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    /// The newest post every incremental tag grabbed, along with the tags of earlier runs.
+    pub fn tag_state(&self) -> &TagState {
+        &self.tag_state
+    }
+
+    /// Replaces the grabbed posts with posts grabbed by an earlier run, adding the tag state saved with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The grabbed posts.
+    /// * `tag_state`: The tag state saved with the posts.
+    pub fn set_grabbed(&mut self, posts: Vec<PostCollection>, tag_state: &TagState) {
+        self.posts = posts;
+        self.tag_state.merge(tag_state);
+    }
+
     /// Sets the blacklist.
     ///
     /// # Arguments
     ///
     /// * `blacklist`: The new blacklist
-    pub(crate) fn set_blacklist(&mut self, blacklist: Rc<RefCell<Blacklist>>) {
+    pub fn set_blacklist(&mut self, blacklist: Rc<RefCell<Blacklist>>) {
         if !blacklist.borrow_mut().is_empty() {
             self.blacklist = Some(blacklist);
         }
     }
 
+    /// Adds a filter, which runs after every filter added before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: The filter to add.
+    pub fn add_filter(&mut self, filter: Box<dyn PostFilter>) {
+        self.filters.get_mut().push(filter);
+    }
+
+    /// Saves the newest post every incremental tag grabbed, so the next run only grabs posts newer than them.
+    ///
+    /// This should only be called once the posts are downloaded, otherwise the next run skips posts that never were.
+    ///
+    /// # Arguments
+    ///
+    /// * `failed_tags`: The lowercased names of the tags with posts that failed to download, left as last run saved them.
+    pub fn save_tag_state(&self, failed_tags: &HashSet<String>) {
+        if let Err(e) = self.tag_state.save(failed_tags) {
+            warn!("Unable to save {TAG_STATE_NAME}: {e}");
+        }
+    }
+
+    /// Writes every post removed while grabbing to the audit file.
+    pub fn save_audit_log(&self) {
+        let audit_log = self.audit_log.borrow();
+        match audit_log.save() {
+            Ok(()) => trace!(
+                "Wrote {} removed posts to {AUDIT_NAME}...",
+                audit_log.entries().len()
+            ),
+            Err(e) => warn!("Unable to save {AUDIT_NAME}: {e}"),
+        }
+    }
+
+    /// Sets the most tags the account can search at once, searches with more tags are skipped instead of sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `limit`: The most tags the account can search at once.
+    pub fn set_tag_query_limit(&mut self, limit: usize) {
+        self.tag_query_limit = limit;
+    }
+
     /// Sets safe mode.
     ///
     /// If set true, the grabber will go into safe mode and grab only safe posts,
@@ -300,35 +612,192 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `mode`: Which mode to run in
-    pub(crate) fn set_safe_mode(&mut self, mode: bool) {
+    pub fn set_safe_mode(&mut self, mode: bool) {
         self.safe_mode = mode;
     }
 
-    /// Grabs favorites from the user's favorites
-    pub(crate) fn grab_favorites(&mut self) {
+    /// Whether only safe posts should be grabbed, either from safe mode or the current group's options.
+    fn is_safe(&self) -> bool {
+        self.safe_mode || self.group_options.safe()
+    }
+
+    /// Returns the directory set by the current group's options, or the default category if it isn't set.
+    ///
+    /// # Arguments
+    ///
+    /// * `default`: The category used when the group doesn't set a directory.
+    ///
+    /// returns: &str
+    fn category<'a>(&'a self, default: &'a str) -> &'a str {
+        self.group_options.directory().unwrap_or(default)
+    }
+
+    /// The order posts are stored in within the current group's collections, from the group's options or the config.
+    fn collection_order(&self) -> CollectionOrder {
+        self.group_options
+            .order()
+            .unwrap_or_else(|| Config::get().collection_order())
+    }
+
+    /// Sorts grabbed posts into the order set for the collection, keeping the order they are in for the orders that
+    /// don't change it (e.g the search order, or the pool order after [Grabber::sort_pool_by_id]).
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts to sort.
+    fn sort_posts(&self, posts: &mut [PostEntry]) {
+        match self.collection_order() {
+            CollectionOrder::Default | CollectionOrder::Pool => {}
+            CollectionOrder::Oldest => posts.sort_by_key(|e| e.id),
+            CollectionOrder::Score => posts.sort_by_key(|e| Reverse(e.score.total)),
+        }
+    }
+
+    /// Whether the current group's post limit has been reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `grabbed`: The amount of posts grabbed so far.
+    ///
+    /// returns: bool
+    fn limit_reached(&self, grabbed: usize) -> bool {
+        self.group_options.limit().is_some_and(|e| grabbed >= e)
+    }
+
+    /// Grabs favorites from the user's favorites, skipping them if the search fails.
+    pub fn grab_favorites(&mut self) {
         let login = Login::get();
         if !login.username().is_empty() && login.download_favorites() {
             let tag = format!("fav:{}", login.username());
-            let posts = self.search(&tag, &TagSearchType::Special);
-            self.posts
-                .push(PostCollection::new(&tag, "", GrabbedPost::new_vec(posts)));
-            info!(
-                "{} grabbed!",
-                console::style(format!("\"{tag}\"")).color256(39).italic()
-            );
+            ProgressEvent::GrabStarted { tag: &tag }.emit();
+            let mut posts = match self.search(&tag, &TagSearchType::Special, None) {
+                Ok(posts) => posts,
+                Err(e) => {
+                    self.spinner.suspend(|| skip_item_error("favorites", &e));
+                    return;
+                }
+            };
+            self.sort_posts(&mut posts);
+
+            self.push_collection(PostCollection::new(&tag, "", GrabbedPost::new_vec(posts)));
+            self.spinner.suspend(|| {
+                info!(
+                    "{} grabbed!",
+                    console::style(format!("\"{tag}\"")).color256(39).italic()
+                );
+            });
         }
     }
 
     /// Grabs new posts by the given tag.
     ///
+    /// A tag that fails to be grabbed is skipped with a warning, and the rest of the tags are still grabbed.
+    ///
     /// # Arguments
     ///
     /// * `groups`: The group of tags to search for.
-    pub(crate) fn grab_posts_by_tags(&mut self, groups: &[Group]) {
-        let tags: Vec<&Tag> = groups.iter().flat_map(|e| e.tags()).collect();
-        for tag in tags {
-            self.grab_by_tag_type(tag);
+    pub fn grab_posts_by_tags(&mut self, groups: &[Group]) {
+        for group in groups {
+            self.group_options = group.options().clone();
+            for tag in group.tags() {
+                if is_cancelled() {
+                    break;
+                }
+
+                let collections = self.posts.len();
+                let single_posts = self.single_post_collection().posts.len();
+                if let Err(e) = self.grab_by_tag_type(tag) {
+                    self.spinner
+                        .suspend(|| skip_item_error(&format!("\"{}\"", tag.name()), &e));
+                }
+
+                let (favorite, upvote) =
+                    (self.group_options.favorite(), self.group_options.upvote());
+                if favorite || upvote {
+                    for post in self.new_posts_mut(collections, single_posts) {
+                        post.favorite |= favorite;
+                        post.upvote |= upvote;
+                    }
+                }
+            }
         }
+
+        self.group_options = GroupOptions::default();
+    }
+
+    /// Gets the posts grabbed after the grabber had the given amount of collections and single posts.
+    ///
+    /// # Arguments
+    ///
+    /// * `collections`: The amount of collections before grabbing.
+    /// * `single_posts`: The amount of posts in the single post collection before grabbing.
+    ///
+    /// returns: impl Iterator<Item = &mut GrabbedPost>
+    fn new_posts_mut(
+        &mut self,
+        collections: usize,
+        single_posts: usize,
+    ) -> impl Iterator<Item = &mut GrabbedPost> {
+        let (old, new) = self.posts.split_at_mut(collections);
+        old[0].posts[single_posts..]
+            .iter_mut()
+            .chain(new.iter_mut().flat_map(|e| e.posts.iter_mut()))
+    }
+
+    /// Adds a grabbed collection, leaving out posts an earlier collection already grabbed if `skipDuplicatePosts` is
+    /// enabled.
+    ///
+    /// This is the only place duplicates are left out, so every kind of collection is handled the same way. Collections
+    /// whose order matters are added with [push_ordered_collection](Self::push_ordered_collection) instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection to add.
+    fn push_collection(&mut self, mut collection: PostCollection) {
+        if Config::get().skip_duplicate_posts() {
+            let before = collection.posts.len();
+            let audit_log = self.audit_log.get_mut();
+            collection.posts.retain(|e| {
+                let unseen = self.seen_posts.insert(e.id());
+                if !unseen {
+                    audit_log.record(
+                        e.id(),
+                        &collection.name,
+                        String::from("already grabbed by an earlier tag"),
+                    );
+                }
+
+                unseen
+            });
+            let duplicates = before - collection.posts.len();
+            if duplicates > 0 {
+                self.spinner.suspend(|| {
+                    info!(
+                        "Left out {} posts already grabbed by an earlier tag...",
+                        console::style(duplicates).cyan().italic()
+                    );
+                });
+            }
+        }
+
+        self.posts.push(collection);
+    }
+
+    /// Adds a grabbed collection whose posts are read in order (a pool, or a set in pool order), keeping every post
+    /// even if `skipDuplicatePosts` is enabled so the collection has no gaps.
+    ///
+    /// Its posts still count as grabbed, so later collections leave them out.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection to add.
+    fn push_ordered_collection(&mut self, collection: PostCollection) {
+        if Config::get().skip_duplicate_posts() {
+            self.seen_posts
+                .extend(collection.posts.iter().map(|e| e.id()));
+        }
+
+        self.posts.push(collection);
     }
 
     /// Returns the single post [PostCollection].
@@ -348,17 +817,27 @@ impl Grabber {
     /// This function will not add the single post provided if it has no direct valid URL.
     fn add_single_post(&mut self, entry: PostEntry, id: i64) {
         match entry.file.url {
-            None => warn!(
+            None if !Self::can_fall_back_to_sample(&entry) => warn!(
                 "Post with ID {} has no URL!",
                 console::style(format!("\"{id}\"")).color256(39).italic()
             ),
-            Some(_) => {
+            _ if Config::get().skip_duplicate_posts() && !self.seen_posts.insert(id) => {
+                self.spinner.suspend(|| {
+                    info!(
+                        "Post with ID {} was already grabbed by an earlier tag, skipping...",
+                        console::style(format!("\"{id}\"")).color256(39).italic()
+                    );
+                });
+            }
+            _ => {
                 let grabbed_post = GrabbedPost::from((entry, Config::get().naming_convention()));
                 self.single_post_collection().posts.push(grabbed_post);
-                info!(
-                    "Post with ID {} grabbed!",
-                    console::style(format!("\"{id}\"")).color256(39).italic()
-                );
+                self.spinner.suspend(|| {
+                    info!(
+                        "Post with ID {} grabbed!",
+                        console::style(format!("\"{id}\"")).color256(39).italic()
+                    );
+                });
             }
         }
     }
@@ -368,14 +847,15 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_by_tag_type(&mut self, tag: &Tag) {
+    fn grab_by_tag_type(&mut self, tag: &Tag) -> Result<(), E621Error> {
+        ProgressEvent::GrabStarted { tag: tag.name() }.emit();
         match tag.tag_type() {
             TagType::Pool => self.grab_pool(tag),
             TagType::Set => self.grab_set(tag),
             TagType::Post => self.grab_post(tag),
             TagType::General | TagType::Artist => self.grab_general(tag),
             TagType::Unknown => unreachable!(),
-        };
+        }
     }
 
     /// Grabs general posts based on the given tag.
@@ -383,19 +863,42 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_general(&mut self, tag: &Tag) {
-        let posts = self.get_posts_from_tag(tag);
-        self.posts.push(PostCollection::new(
+    fn grab_general(&mut self, tag: &Tag) -> Result<(), E621Error> {
+        let incremental = self.group_options.incremental();
+        let mut posts = match self.tag_state.last_post_id(tag.name()) {
+            Some(last_post_id) if incremental => {
+                trace!(
+                    "Only grabbing posts newer than {last_post_id} for \"{}\"...",
+                    tag.name()
+                );
+                self.search(tag.name(), tag.search_type(), Some(last_post_id))?
+            }
+            _ => self.get_posts_from_tag(tag)?,
+        };
+
+        if let Some(newest) = posts.iter().map(|e| e.id).max().filter(|_| incremental) {
+            self.tag_state.update(tag.name(), newest);
+        }
+
+        self.sort_posts(&mut posts);
+
+        let mut collection = PostCollection::new(
             tag.name(),
-            "General Searches",
+            self.category("General Searches"),
             GrabbedPost::new_vec(posts),
-        ));
-        info!(
-            "{} grabbed!",
-            console::style(format!("\"{}\"", tag.name()))
-                .color256(39)
-                .italic()
         );
+        collection.set_tag_type(tag.tag_type().clone());
+        self.push_collection(collection);
+        self.spinner.suspend(|| {
+            info!(
+                "{} grabbed!",
+                console::style(format!("\"{}\"", tag.name()))
+                    .color256(39)
+                    .italic()
+            );
+        });
+
+        Ok(())
     }
 
     /// Grabs single post based on the given tag.
@@ -403,27 +906,31 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_post(&mut self, tag: &Tag) {
+    fn grab_post(&mut self, tag: &Tag) -> Result<(), E621Error> {
         let entry: PostEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "single");
+            .get_entry_from_appended_id(tag.name(), "single")?;
         let id = entry.id;
 
-        if self.safe_mode {
+        if self.is_safe() {
             match entry.rating.as_str() {
                 "s" => {
                     self.add_single_post(entry, id);
                 }
                 _ => {
-                    info!(
-                        "Skipping Post: {} due to being explicit or questionable",
-                        console::style(format!("\"{id}\"")).color256(39).italic()
-                    );
+                    self.spinner.suspend(|| {
+                        info!(
+                            "Skipping Post: {} due to being explicit or questionable",
+                            console::style(format!("\"{id}\"")).color256(39).italic()
+                        );
+                    });
                 }
             }
         } else {
             self.add_single_post(entry, id);
         }
+
+        Ok(())
     }
 
     /// Grabs a set based on the given tag.
@@ -431,22 +938,50 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_set(&mut self, tag: &Tag) {
+    fn grab_set(&mut self, tag: &Tag) -> Result<(), E621Error> {
         let entry: SetEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "set");
+            .get_entry_from_appended_id(tag.name(), "set")?;
 
         // Grabs posts from IDs in the set entry.
-        let posts = self.search(&format!("set:{}", entry.shortname), &TagSearchType::Special);
-        self.posts
-            .push(PostCollection::from((&entry, GrabbedPost::new_vec(posts))));
-
-        info!(
-            "{} grabbed!",
-            console::style(format!("\"{}\"", entry.name))
-                .color256(39)
-                .italic()
-        );
+        let mut posts = self.search(
+            &format!("set:{}", entry.shortname),
+            &TagSearchType::Special,
+            None,
+        )?;
+        if self.collection_order() == CollectionOrder::Pool {
+            let positions: HashMap<i64, usize> = entry
+                .post_ids
+                .iter()
+                .enumerate()
+                .map(|(i, id)| (*id, i))
+                .collect();
+            posts.sort_by_key(|e| positions.get(&e.id).copied().unwrap_or(usize::MAX));
+        } else {
+            self.sort_posts(&mut posts);
+        }
+
+        let mut collection = PostCollection::from((&entry, GrabbedPost::new_vec(posts)));
+        if let Some(directory) = self.group_options.directory() {
+            collection.category = directory.to_string();
+        }
+
+        if self.collection_order() == CollectionOrder::Pool {
+            self.push_ordered_collection(collection);
+        } else {
+            self.push_collection(collection);
+        }
+
+        self.spinner.suspend(|| {
+            info!(
+                "{} grabbed!",
+                console::style(format!("\"{}\"", entry.name))
+                    .color256(39)
+                    .italic()
+            );
+        });
+
+        Ok(())
     }
 
     /// Grabs pool based on the given tag.
@@ -454,12 +989,13 @@ impl Grabber {
     /// # Arguments
     ///
     /// * `tag`: The tag to search for.
-    fn grab_pool(&mut self, tag: &Tag) {
+    fn grab_pool(&mut self, tag: &Tag) -> Result<(), E621Error> {
         let mut entry: PoolEntry = self
             .request_sender
-            .get_entry_from_appended_id(tag.name(), "pool");
+            .get_entry_from_appended_id(tag.name(), "pool")?;
         let name = &entry.name;
-        let mut posts = self.search(&format!("pool:{}", entry.id), &TagSearchType::Special);
+        let mut posts =
+            self.search(&format!("pool:{}", entry.id), &TagSearchType::Special, None)?;
 
         // Updates entry post ids in case any posts were filtered in the search.
         entry
@@ -468,17 +1004,23 @@ impl Grabber {
 
         // Sorts the pool to the original order given by entry.
         Self::sort_pool_by_id(&entry, &mut posts);
+        self.sort_posts(&mut posts);
 
-        self.posts.push(PostCollection::new(
+        let collection = PostCollection::new(
             name,
-            "Pools",
+            self.category("Pools"),
             GrabbedPost::new_vec((posts, name.as_ref())),
-        ));
-
-        info!(
-            "{} grabbed!",
-            console::style(format!("\"{name}\"")).color256(39).italic()
         );
+        self.push_ordered_collection(collection);
+
+        self.spinner.suspend(|| {
+            info!(
+                "{} grabbed!",
+                console::style(format!("\"{name}\"")).color256(39).italic()
+            );
+        });
+
+        Ok(())
     }
 
     /// Sorts a pool by id based on the supplied [PoolEntry].
@@ -502,9 +1044,9 @@ impl Grabber {
     ///
     /// * `tag`: The tag to use for the search.
     ///
-    /// returns: Vec<PostEntry, Global>
-    fn get_posts_from_tag(&self, tag: &Tag) -> Vec<PostEntry> {
-        self.search(tag.name(), tag.search_type())
+    /// returns: Result<Vec<PostEntry, Global>, E621Error>
+    fn get_posts_from_tag(&self, tag: &Tag) -> Result<Vec<PostEntry>, E621Error> {
+        self.search(tag.name(), tag.search_type(), None)
     }
 
     /// Performs a search where it grabs posts.
@@ -517,38 +1059,99 @@ impl Grabber {
     ///
     /// * `searching_tag`: The tag used for the search.
     /// * `tag_search_type`: The type of search to happen.
+    /// * `newer_than`: The id only posts newer than are grabbed, if any.
     ///
-    /// returns: Vec<PostEntry, Global>
-    fn search(&self, searching_tag: &str, tag_search_type: &TagSearchType) -> Vec<PostEntry> {
+    /// returns: Result<Vec<PostEntry, Global>, E621Error>
+    fn search(
+        &self,
+        searching_tag: &str,
+        tag_search_type: &TagSearchType,
+        newer_than: Option<i64>,
+    ) -> Result<Vec<PostEntry>, E621Error> {
+        // Safe mode already searches e926, so the rating only has to be restricted for safe groups on e621.
+        let mut injected = Vec::new();
+        if !self.safe_mode && self.group_options.safe() {
+            injected.push(String::from("rating:s"));
+        }
+
+        if let Some(id) = newer_than {
+            injected.push(format!("id:>{id}"));
+        }
+
+        // The added tags count towards the limit just like the ones the user wrote.
+        let tags = searching_tag.split_whitespace().count() + injected.len();
+        if tags > self.tag_query_limit {
+            return Err(E621Error::TooManyTags {
+                tags,
+                injected: injected.len(),
+                limit: self.tag_query_limit,
+            });
+        }
+
+        let searching_tag = &once(searching_tag)
+            .chain(injected.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(" ");
+
         let mut posts: Vec<PostEntry> = Vec::new();
         let mut filtered = 0;
         let mut invalid_posts = 0;
+        let mut excluded = 0;
         match tag_search_type {
             TagSearchType::General => {
                 posts = Vec::with_capacity(320 * POST_SEARCH_LIMIT as usize);
-                self.general_search(searching_tag, &mut posts, &mut filtered, &mut invalid_posts);
+                self.general_search(
+                    searching_tag,
+                    &mut posts,
+                    &mut filtered,
+                    &mut invalid_posts,
+                    &mut excluded,
+                )?;
             }
             TagSearchType::Special => {
-                self.special_search(searching_tag, &mut posts, &mut filtered, &mut invalid_posts);
+                self.special_search(
+                    searching_tag,
+                    &mut posts,
+                    &mut filtered,
+                    &mut invalid_posts,
+                    &mut excluded,
+                )?;
             }
             TagSearchType::None => {}
         }
 
+        if let Some(limit) = self.group_options.limit() {
+            posts.truncate(limit);
+        }
+
         if filtered > 0 {
-            info!(
-                "Filtered {} total blacklisted posts from search...",
-                console::style(filtered).cyan().italic()
-            );
+            self.spinner.suspend(|| {
+                info!(
+                    "Filtered {} total blacklisted posts from search...",
+                    console::style(filtered).cyan().italic()
+                );
+            });
         }
 
         if invalid_posts > 0 {
-            info!(
-                "Filtered {} total invalid posts from search...",
-                console::style(invalid_posts).cyan().italic()
-            );
+            self.spinner.suspend(|| {
+                info!(
+                    "Filtered {} total invalid posts from search...",
+                    console::style(invalid_posts).cyan().italic()
+                );
+            });
+        }
+
+        if excluded > 0 {
+            self.spinner.suspend(|| {
+                info!(
+                    "Excluded {} total posts from search with filters...",
+                    console::style(excluded).cyan().italic()
+                );
+            });
         }
 
-        posts
+        Ok(posts)
     }
 
     /// Performs a special search to grab posts.
@@ -556,7 +1159,7 @@ impl Grabber {
     /// The difference between special/general searches are this.
     /// - Special searches aim to keep grabbing posts until there are not posts left to grab.
     /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page). You can refer to the
-    /// [POST_SEARCH_LIMIT] for the current search limit of the general search.
+    ///   [POST_SEARCH_LIMIT] for the current search limit of the general search.
     ///
     /// # Arguments
     ///
@@ -564,28 +1167,38 @@ impl Grabber {
     /// * `posts`:  The posts [Vec] to add searched posts into.
     /// * `filtered`: The total amount of posts filtered.
     /// * `invalid_posts`: The total amount of posts invalid by the [Blacklist].
+    /// * `excluded`: The total amount of posts excluded by the grab filters.
     fn special_search(
         &self,
         searching_tag: &str,
         posts: &mut Vec<PostEntry>,
-        filtered: &mut u16,
-        invalid_posts: &mut u16,
-    ) {
+        filtered: &mut usize,
+        invalid_posts: &mut usize,
+        excluded: &mut usize,
+    ) -> Result<(), E621Error> {
         let mut page = 1;
 
         loop {
-            let mut searched_posts = self.request_sender.bulk_search(searching_tag, page).posts;
+            let mut searched_posts = self.request_sender.bulk_search(searching_tag, page)?.posts;
             if searched_posts.is_empty() {
                 break;
             }
 
-            *filtered += self.filter_posts_with_blacklist(&mut searched_posts);
-            *invalid_posts += Self::remove_invalid_posts(&mut searched_posts);
+            *filtered += self.filter_posts_with_blacklist(searching_tag, &mut searched_posts);
+            *invalid_posts += self.remove_invalid_posts(searching_tag, &mut searched_posts);
+            *excluded += self.exclude_posts_with_filters(searching_tag, &mut searched_posts)?;
+            self.update_spinner(searching_tag, page, searched_posts.len());
 
             searched_posts.reverse();
             posts.append(&mut searched_posts);
+            if self.limit_reached(posts.len()) {
+                break;
+            }
+
             page += 1;
         }
+
+        Ok(())
     }
 
     /// Performs a general search to grab posts.
@@ -593,7 +1206,7 @@ impl Grabber {
     /// The difference between special/general searches are this.
     /// - Special searches aim to keep grabbing posts until there are not posts left to grab.
     /// - General searches aim to grab only a few pages of posts (commonly 320 posts per page). You can refer to the
-    /// [POST_SEARCH_LIMIT] for the current search limit of the general search.
+    ///   [POST_SEARCH_LIMIT] for the current search limit of the general search.
     ///
     /// # Arguments
     ///
@@ -601,45 +1214,127 @@ impl Grabber {
     /// * `posts`:  The posts [Vec] to add searched posts into.
     /// * `filtered`: The total amount of posts filtered.
     /// * `invalid_posts`: The total amount of posts invalid by the [Blacklist].
+    /// * `excluded`: The total amount of posts excluded by the grab filters.
     fn general_search(
         &self,
         searching_tag: &str,
         posts: &mut Vec<PostEntry>,
-        filtered: &mut u16,
-        invalid_posts: &mut u16,
-    ) {
+        filtered: &mut usize,
+        invalid_posts: &mut usize,
+        excluded: &mut usize,
+    ) -> Result<(), E621Error> {
         for page in 1..POST_SEARCH_LIMIT {
             let mut searched_posts: Vec<PostEntry> = self
                 .request_sender
-                .bulk_search(searching_tag, page as u16)
+                .bulk_search(searching_tag, page as u16)?
                 .posts;
             if searched_posts.is_empty() {
                 break;
             }
 
-            *filtered += self.filter_posts_with_blacklist(&mut searched_posts);
-            *invalid_posts += Self::remove_invalid_posts(&mut searched_posts);
+            *filtered += self.filter_posts_with_blacklist(searching_tag, &mut searched_posts);
+            *invalid_posts += self.remove_invalid_posts(searching_tag, &mut searched_posts);
+            *excluded += self.exclude_posts_with_filters(searching_tag, &mut searched_posts)?;
+            self.update_spinner(searching_tag, page as u16, searched_posts.len());
 
             searched_posts.reverse();
             posts.append(&mut searched_posts);
+            if self.limit_reached(posts.len()) {
+                break;
+            }
         }
+
+        Ok(())
+    }
+
+    /// Checks through posts and removes any that violets the blacklist, unless they have a tag the group whitelists.
+    ///
+    /// # Arguments
+    ///
+    /// * `searching_tag`: The tag the posts were searched with.
+    /// * `posts`: The posts to check
+    ///
+    /// returns: usize
+    fn filter_posts_with_blacklist(
+        &self,
+        searching_tag: &str,
+        posts: &mut Vec<PostEntry>,
+    ) -> usize {
+        let Some(ref blacklist) = self.blacklist else {
+            return 0;
+        };
+
+        let filtered_posts = blacklist
+            .borrow_mut()
+            .filter_posts_with_lines(posts, self.group_options.whitelist());
+        let mut audit_log = self.audit_log.borrow_mut();
+        for (id, line) in &filtered_posts {
+            audit_log.record(*id, searching_tag, format!("blacklisted by \"{line}\""));
+        }
+
+        filtered_posts.len()
     }
 
-    /// Checks through posts and removes any that violets the blacklist.
+    /// Runs every filter over the posts, removing any post that doesn't pass them.
+    ///
+    /// If a filter fails, an error is returned so the search is skipped instead of grabbing posts it didn't check.
     ///
     /// # Arguments
     ///
+    /// * `searching_tag`: The tag the posts were searched with.
     /// * `posts`: The posts to check
     ///
-    /// returns: u16
-    fn filter_posts_with_blacklist(&self, posts: &mut Vec<PostEntry>) -> u16 {
-        if self.request_sender.is_authenticated() {
-            if let Some(ref blacklist) = self.blacklist {
-                return blacklist.borrow_mut().filter_posts(posts);
+    /// returns: Result<usize, E621Error>
+    fn exclude_posts_with_filters(
+        &self,
+        searching_tag: &str,
+        posts: &mut Vec<PostEntry>,
+    ) -> Result<usize, E621Error> {
+        let mut excluded = 0;
+        let mut audit_log = self.audit_log.borrow_mut();
+        for filter in self.filters.borrow_mut().iter_mut() {
+            let before = posts.len();
+            let mut error = None;
+            posts.retain(|e| {
+                if error.is_some() {
+                    return true;
+                }
+
+                match filter.accepts(e, &self.group_options) {
+                    Ok(true) => true,
+                    Ok(false) => {
+                        audit_log.record(
+                            e.id,
+                            searching_tag,
+                            format!("excluded by the {} filter", filter.name()),
+                        );
+                        false
+                    }
+                    Err(e) => {
+                        error = Some(e);
+                        true
+                    }
+                }
+            });
+            if let Some(error) = error {
+                return Err(E621Error::Filter {
+                    name: filter.name().to_string(),
+                    error,
+                });
             }
+
+            let count = before - posts.len();
+            if count > 0 {
+                trace!(
+                    "{count} posts were excluded by the {} filter...",
+                    filter.name()
+                );
+            }
+
+            excluded += count;
         }
 
-        0
+        Ok(excluded)
     }
 
     /// Removes invalid posts (e.g posts with no urls, or invalid properties).
@@ -650,18 +1345,25 @@ impl Grabber {
     ///
     /// # Arguments
     ///
+    /// * `searching_tag`: The tag the posts were searched with.
     /// * `posts`: Posts to check.
     ///
-    /// returns: u16
-    fn remove_invalid_posts(posts: &mut Vec<PostEntry>) -> u16 {
+    /// returns: usize
+    fn remove_invalid_posts(&self, searching_tag: &str, posts: &mut Vec<PostEntry>) -> usize {
         let mut invalid_posts = 0;
+        let mut audit_log = self.audit_log.borrow_mut();
         posts.retain(|e| {
-            if !e.flags.deleted && e.file.url.is_some() {
-                true
+            let reason = if e.flags.deleted {
+                "deleted"
+            } else if e.file.url.is_none() && !Self::can_fall_back_to_sample(e) {
+                "no file url (e.g hidden from users who aren't logged in)"
             } else {
-                invalid_posts += 1;
-                false
-            }
+                return true;
+            };
+
+            audit_log.record(e.id, searching_tag, String::from(reason));
+            invalid_posts += 1;
+            false
         });
 
         Self::log_invalid_posts(&invalid_posts);
@@ -669,12 +1371,25 @@ impl Grabber {
         invalid_posts
     }
 
+    /// Checks if the post has no original url but can be downloaded through its sample instead.
+    ///
+    /// This is only possible when the sample fallback is enabled in the config.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to check.
+    ///
+    /// returns: bool
+    fn can_fall_back_to_sample(post: &PostEntry) -> bool {
+        Config::get().sample_fallback() && post.file.url.is_none() && post.sample.url.is_some()
+    }
+
     /// Traces invalid posts to the log file.
     ///
     /// # Arguments
     ///
     /// * `invalid_posts`: The total count of invalid posts.
-    fn log_invalid_posts(invalid_posts: &u16) {
+    fn log_invalid_posts(invalid_posts: &usize) {
         match invalid_posts.cmp(&1) {
             Ordering::Less => {}
             Ordering::Equal => {
@@ -0,0 +1,81 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+
+use anyhow::Error;
+use serde::Serialize;
+use serde_json::to_string;
+
+use crate::e621::io::state_directory;
+
+/// Name of the file every post removed during grabbing is written to, replaced every run.
+pub const AUDIT_NAME: &str = "filtered_posts.jsonl";
+
+/// A post that was removed while grabbing, along with why.
+#[derive(Serialize, Debug, Clone)]
+pub struct AuditEntry {
+    /// The id of the post.
+    #[serde(rename = "postId")]
+    pub post_id: i64,
+    /// The search (or collection) the post was removed from.
+    pub tag: String,
+    /// Why the post was removed (e.g the blacklist line that flagged it).
+    pub reason: String,
+}
+
+/// The posts removed while grabbing (by the blacklist, the invalid post check, the filters, or as duplicates), written
+/// to [AUDIT_NAME] as a line of JSON per post so users can check nothing they wanted was dropped.
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    /// Every post removed so far.
+    entries: Vec<AuditEntry>,
+}
+
+impl AuditLog {
+    /// Records a removed post.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post.
+    /// * `tag`: The search (or collection) the post was removed from.
+    /// * `reason`: Why the post was removed.
+    pub fn record(&mut self, post_id: i64, tag: &str, reason: String) {
+        self.entries.push(AuditEntry {
+            post_id,
+            tag: tag.to_string(),
+            reason,
+        });
+    }
+
+    /// Every post removed so far.
+    pub fn entries(&self) -> &[AuditEntry] {
+        &self.entries
+    }
+
+    /// Writes every removed post to the audit file, replacing the file of the last run.
+    pub fn save(&self) -> Result<(), Error> {
+        create_dir_all(state_directory())?;
+        let mut writer = BufWriter::new(File::create(state_directory().join(AUDIT_NAME))?);
+        for entry in &self.entries {
+            writeln!(writer, "{}", to_string(entry)?)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::env::consts::{ARCH, FAMILY, OS};
+use std::env::{current_dir, var};
+use std::fs::{read_to_string, File};
+use std::io::Write;
+use std::path::Path;
+
+use anyhow::{Context, Error};
+use reqwest::Url;
+use serde_json::{to_string_pretty, Value};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::args::Args;
+use crate::e621::io::report::{ERROR_REPORT_NAME, REPORT_NAME};
+use crate::e621::io::tag::tag_file_path;
+use crate::e621::io::{
+    config_directory, config_path, login_path, portable_directory, state_directory, Config,
+    API_KEY_VAR, LOG_NAME,
+};
+
+/// The name of the file in the bundle holding the version and system information.
+const SYSTEM_INFO_NAME: &str = "system.txt";
+
+/// What secrets are replaced with in the bundled files.
+const REDACTED: &str = "[REDACTED]";
+
+/// Bundles everything needed to look into a problem into a zip file that can be attached to an issue.
+///
+/// The bundle holds the log, config, login, tag file, and reports of the last run, along with the version of the
+/// downloader and information about the system. The API key and the password of the proxy are replaced with
+/// [REDACTED] everywhere they show up, and files that don't exist are left out.
+///
+/// # Arguments
+///
+/// * `path`: The path of the zip file to write.
+///
+/// returns: Result<(), Error>
+pub fn write_diagnostics(path: &Path) -> Result<(), Error> {
+    let secrets = secrets();
+    let file =
+        File::create(path).with_context(|| format!("Unable to create {}", path.display()))?;
+    let mut zip = ZipWriter::new(file);
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    zip.start_file(SYSTEM_INFO_NAME, options)?;
+    zip.write_all(system_info().as_bytes())?;
+
+    let mut files = vec![
+        config_path(),
+        tag_file_path(),
+        config_directory().join(LOG_NAME),
+        // The log of the run before, in case the log was rotated when the diagnose command started.
+        config_directory().join(LOG_NAME.replace(".log", ".1.log")),
+        state_directory().join(REPORT_NAME),
+        state_directory().join(ERROR_REPORT_NAME),
+    ];
+    files.dedup();
+    for file in files {
+        let Some(name) = file.file_name().and_then(|e| e.to_str()) else {
+            continue;
+        };
+
+        match read_to_string(&file) {
+            Ok(contents) => {
+                trace!("Adding {} to the diagnostics...", file.display());
+                zip.start_file(name, options)?;
+                zip.write_all(redact(&contents, &secrets).as_bytes())?;
+            }
+            Err(e) => trace!("Leaving {} out of the diagnostics: {e}", file.display()),
+        }
+    }
+
+    // The login is only bundled with its API key removed, as the key is as good as the password of the account.
+    if let Ok(contents) = read_to_string(login_path()) {
+        let login = match serde_json::from_str::<Value>(&contents) {
+            Ok(mut login) => {
+                if let Some(api_key) = login.get_mut("APIKey").filter(|e| e.as_str() != Some("")) {
+                    *api_key = Value::from(REDACTED);
+                }
+
+                to_string_pretty(&login)?
+            }
+            Err(e) => format!("The login file couldn't be parsed: {e}"),
+        };
+
+        zip.start_file(login_path().file_name().unwrap().to_string_lossy(), options)?;
+        zip.write_all(redact(&login, &secrets).as_bytes())?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Gets the version of the downloader and information about the system it runs on.
+fn system_info() -> String {
+    let lines = [
+        format!("Version: {}", env!("CARGO_PKG_VERSION")),
+        format!("OS: {OS}"),
+        format!("Arch: {ARCH}"),
+        format!("Family: {FAMILY}"),
+        format!(
+            "Portable: {}",
+            portable_directory().map_or(String::from("no"), |e| e.display().to_string())
+        ),
+        format!("Profile: {}", Args::get().profile().unwrap_or("none")),
+        format!("Config file: {}", config_path().display()),
+        format!("Tag file: {}", tag_file_path().display()),
+        format!("Config directory: {}", config_directory().display()),
+        format!("State directory: {}", state_directory().display()),
+        format!(
+            "Working directory: {}",
+            current_dir().unwrap_or_default().display()
+        ),
+    ];
+
+    lines.join("\n") + "\n"
+}
+
+/// Gets the secrets that have to be left out of the bundle, which are the API key (from the login file or the
+/// environment) and the password of the proxy.
+fn secrets() -> Vec<String> {
+    let mut secrets = Vec::new();
+    if let Some(api_key) = read_to_string(login_path())
+        .ok()
+        .and_then(|e| serde_json::from_str::<Value>(&e).ok())
+        .and_then(|e| e.get("APIKey").and_then(Value::as_str).map(String::from))
+    {
+        secrets.push(api_key);
+    }
+
+    if let Ok(api_key) = var(API_KEY_VAR) {
+        secrets.push(api_key);
+    }
+
+    if let Some(password) = Config::peek()
+        .proxy()
+        .and_then(|e| Url::parse(e).ok())
+        .and_then(|e| e.password().map(String::from))
+    {
+        secrets.push(password);
+    }
+
+    secrets.retain(|e| !e.trim().is_empty());
+    secrets
+}
+
+/// Replaces every secret in a text with [REDACTED].
+///
+/// # Arguments
+///
+/// * `text`: The text to redact.
+/// * `secrets`: The secrets to replace.
+///
+/// returns: String
+fn redact(text: &str, secrets: &[String]) -> String {
+    secrets.iter().fold(text.to_string(), |text, secret| {
+        text.replace(secret, REDACTED)
+    })
+}
@@ -0,0 +1,87 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io;
+use std::path::Path;
+
+/// The attribute the id of the post is saved in.
+pub const POST_ID_ATTRIBUTE: &str = "user.e621.post_id";
+
+/// The attribute the url the file was downloaded from is saved in, which is the attribute browsers use for the same
+/// thing on Linux, so file managers show it as the origin of the file.
+pub const SOURCE_URL_ATTRIBUTE: &str = "user.xdg.origin.url";
+
+/// Saves the id of a post and the url its file was downloaded from in the extended attributes of the file, so the
+/// file can be matched to its post again even after it's renamed.
+///
+/// On Windows, the attributes are saved as alternate data streams (e.g `file.png:user.e621.post_id`) instead.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `post_id`: The id of the post.
+/// * `source_url`: The url the file was downloaded from.
+///
+/// returns: io::Result<()>
+pub fn write_post_attributes(path: &Path, post_id: i64, source_url: &str) -> io::Result<()> {
+    write_attribute(path, POST_ID_ATTRIBUTE, post_id.to_string().as_bytes())?;
+    write_attribute(path, SOURCE_URL_ATTRIBUTE, source_url.as_bytes())
+}
+
+/// Writes an extended attribute of a file.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `name`: The name of the attribute.
+/// * `value`: The value of the attribute.
+///
+/// returns: io::Result<()>
+#[cfg(unix)]
+fn write_attribute(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    xattr::set(path, name, value)
+}
+
+/// Writes an alternate data stream of a file, which NTFS offers in place of extended attributes.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `name`: The name of the stream.
+/// * `value`: The contents of the stream.
+///
+/// returns: io::Result<()>
+#[cfg(windows)]
+fn write_attribute(path: &Path, name: &str, value: &[u8]) -> io::Result<()> {
+    let mut stream = path.as_os_str().to_owned();
+    stream.push(":");
+    stream.push(name);
+    std::fs::write(stream, value)
+}
+
+/// Other platforms have no extended attributes.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `name`: The name of the attribute.
+/// * `value`: The value of the attribute.
+///
+/// returns: io::Result<()>
+#[cfg(not(any(unix, windows)))]
+fn write_attribute(_path: &Path, _name: &str, _value: &[u8]) -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::Unsupported))
+}
@@ -0,0 +1,238 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, read_dir, read_to_string, write, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string};
+
+use crate::e621::io::state_directory;
+
+/// Name of the file the md5 of every hashed file is cached in.
+pub const FILE_INDEX_NAME: &str = "file_index.json";
+
+/// The amount of bytes read from a file at once while hashing it.
+const HASH_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The md5 of a file that had to be hashed, along with what the file looked like when it was hashed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default)]
+struct HashedFile {
+    /// The size of the file in bytes.
+    size: u64,
+    /// When the file was last modified, in seconds since the unix epoch.
+    modified: u64,
+    /// The md5 of the file.
+    md5: String,
+}
+
+/// The files cached between runs, so files that aren't named by their md5 are only hashed once.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct FileCache {
+    /// The hashed files, by their path.
+    files: HashMap<String, HashedFile>,
+}
+
+/// An index of the md5 of every file under the download directory, used to leave out posts that were already
+/// downloaded somewhere else in the directory (e.g moved to another folder, or saved under another name).
+///
+/// Files named by their md5 (the default naming convention) are indexed by their name, other files are hashed once
+/// and cached in [FILE_INDEX_NAME].
+#[derive(Debug, Clone, Default)]
+pub struct FileIndex {
+    /// The md5 of every indexed file, lowercased.
+    md5s: HashSet<String>,
+}
+
+impl FileIndex {
+    /// Indexes every file under the directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `root`: The directory to index.
+    ///
+    /// returns: FileIndex
+    pub fn scan(root: &Path) -> Self {
+        let cache = FileIndex::load_cache();
+        let mut index = FileIndex::default();
+        let mut scanned = FileCache::default();
+        index.scan_directory(root, &cache, &mut scanned);
+
+        // Files that were removed since the last scan are dropped from the cache as well.
+        if scanned.files != cache.files {
+            if let Err(e) = FileIndex::save_cache(&scanned) {
+                warn!("Unable to save {FILE_INDEX_NAME}: {e}");
+            }
+        }
+
+        index
+    }
+
+    /// Whether a file with the md5 is in the index.
+    ///
+    /// # Arguments
+    ///
+    /// * `md5`: The md5 to look for.
+    ///
+    /// returns: bool
+    pub fn contains(&self, md5: &str) -> bool {
+        self.md5s.contains(&md5.to_lowercase())
+    }
+
+    /// The amount of files in the index.
+    pub fn len(&self) -> usize {
+        self.md5s.len()
+    }
+
+    /// Whether the index has no files.
+    pub fn is_empty(&self) -> bool {
+        self.md5s.is_empty()
+    }
+
+    /// Indexes every file in a directory and its subdirectories, skipping symlinked directories.
+    ///
+    /// # Arguments
+    ///
+    /// * `directory`: The directory to index.
+    /// * `cache`: The files hashed in earlier runs.
+    /// * `scanned`: The hashed files found in this scan, which replace the cache afterward.
+    fn scan_directory(&mut self, directory: &Path, cache: &FileCache, scanned: &mut FileCache) {
+        let Ok(entries) = read_dir(directory) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            let path = entry.path();
+            if file_type.is_dir() {
+                self.scan_directory(&path, cache, scanned);
+                continue;
+            }
+
+            if let Some(md5) = path
+                .file_stem()
+                .and_then(|e| e.to_str())
+                .filter(|e| is_md5(e))
+            {
+                self.md5s.insert(md5.to_lowercase());
+                continue;
+            }
+
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+
+            let key = path.to_string_lossy().to_string();
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|e| e.duration_since(UNIX_EPOCH).ok())
+                .map(|e| e.as_secs())
+                .unwrap_or_default();
+            let file = match cache.files.get(&key) {
+                Some(file) if file.size == metadata.len() && file.modified == modified => {
+                    file.clone()
+                }
+                _ => match hash_file(&path) {
+                    Ok(md5) => HashedFile {
+                        size: metadata.len(),
+                        modified,
+                        md5,
+                    },
+                    Err(e) => {
+                        trace!("Unable to hash {}: {e}", path.display());
+                        continue;
+                    }
+                },
+            };
+
+            self.md5s.insert(file.md5.clone());
+            scanned.files.insert(key, file);
+        }
+    }
+
+    /// Loads the files hashed in earlier runs, returning an empty cache if there is none or it can't be read.
+    fn load_cache() -> FileCache {
+        let path = state_directory().join(FILE_INDEX_NAME);
+        if !path.exists() {
+            return FileCache::default();
+        }
+
+        match read_to_string(path).map(|e| from_str::<FileCache>(&e)) {
+            Ok(Ok(cache)) => cache,
+            Ok(Err(e)) => {
+                warn!("Unable to parse {FILE_INDEX_NAME}: {e}");
+                FileCache::default()
+            }
+            Err(e) => {
+                warn!("Unable to read {FILE_INDEX_NAME}: {e}");
+                FileCache::default()
+            }
+        }
+    }
+
+    /// Writes the hashed files to the cache file.
+    ///
+    /// # Arguments
+    ///
+    /// * `cache`: The hashed files.
+    fn save_cache(cache: &FileCache) -> Result<(), Error> {
+        create_dir_all(state_directory())?;
+        write(state_directory().join(FILE_INDEX_NAME), to_string(cache)?)?;
+        Ok(())
+    }
+}
+
+/// Checks if a file name is an md5 (32 hexadecimal characters).
+///
+/// # Arguments
+///
+/// * `name`: The name to check.
+///
+/// returns: bool
+fn is_md5(name: &str) -> bool {
+    name.len() == 32 && name.chars().all(|e| e.is_ascii_hexdigit())
+}
+
+/// Hashes a file without reading all of it into memory at once.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+///
+/// returns: io::Result<String>
+fn hash_file(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut context = md5::Context::new();
+    let mut chunk = vec![0; HASH_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(format!("{:x}", context.compute()));
+        }
+
+        context.consume(&chunk[..read]);
+    }
+}
@@ -0,0 +1,123 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{read_to_string, write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::e621::grabber::PostCollection;
+use crate::e621::io::tag_state::TagState;
+
+/// The version of the grab file layout, bumped when a grab file of an older version can't be downloaded anymore.
+pub const GRAB_FILE_VERSION: u64 = 1;
+
+/// Posts grabbed (and filtered) by `grab`, saved so they can be looked over, edited, and downloaded later by
+/// `download`, as many times as needed.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct GrabFile {
+    /// The version of the grab file layout.
+    version: u64,
+    /// Whether the posts were grabbed in safe mode.
+    #[serde(rename = "safeMode")]
+    safe_mode: bool,
+    /// The grabbed posts, by collection.
+    collections: Vec<PostCollection>,
+    /// The newest post every incremental tag grabbed, saved when the posts are downloaded.
+    #[serde(rename = "tagState")]
+    tag_state: TagState,
+}
+
+impl GrabFile {
+    /// Creates a grab file.
+    ///
+    /// # Arguments
+    ///
+    /// * `safe_mode`: Whether the posts were grabbed in safe mode.
+    /// * `collections`: The grabbed posts, by collection.
+    /// * `tag_state`: The newest post every incremental tag grabbed.
+    ///
+    /// returns: GrabFile
+    pub fn new(safe_mode: bool, collections: Vec<PostCollection>, tag_state: TagState) -> Self {
+        GrabFile {
+            version: GRAB_FILE_VERSION,
+            safe_mode,
+            collections,
+            tag_state,
+        }
+    }
+
+    /// Whether the posts were grabbed in safe mode.
+    pub fn safe_mode(&self) -> bool {
+        self.safe_mode
+    }
+
+    /// The grabbed posts, by collection.
+    pub fn collections(&self) -> &[PostCollection] {
+        &self.collections
+    }
+
+    /// The amount of grabbed posts.
+    pub fn post_count(&self) -> usize {
+        self.collections.iter().map(|e| e.posts().len()).sum()
+    }
+
+    /// Splits the grab file into its collections and tag state.
+    pub fn into_parts(self) -> (Vec<PostCollection>, TagState) {
+        (self.collections, self.tag_state)
+    }
+
+    /// Loads a grab file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the grab file.
+    ///
+    /// returns: Result<GrabFile, Error>
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let contents = read_to_string(path)
+            .with_context(|| format!("Unable to read the grab file {}", path.display()))?;
+        let grab_file: GrabFile = from_str(&contents)
+            .with_context(|| format!("Unable to parse the grab file {}", path.display()))?;
+        if grab_file.version > GRAB_FILE_VERSION {
+            bail!(
+                "The grab file {} is version {}, but this version of the downloader only reads up to version \
+                 {GRAB_FILE_VERSION}",
+                path.display(),
+                grab_file.version
+            );
+        }
+
+        Ok(grab_file)
+    }
+
+    /// Writes the grab file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path to write the grab file to.
+    ///
+    /// returns: Result<(), Error>
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        write(path, to_string_pretty(self)?)
+            .with_context(|| format!("Unable to write the grab file {}", path.display()))?;
+
+        Ok(())
+    }
+}
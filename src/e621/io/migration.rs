@@ -0,0 +1,314 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{copy, read_to_string, write};
+use std::path::Path;
+
+use anyhow::{bail, Context, Error};
+use serde_json::{from_str, to_string_pretty, to_value, Map, Value};
+use toml_edit::{DocumentMut, Item};
+
+use crate::e621::io::{is_toml_config, Config, Login};
+
+/// The key of the version in the config file.
+pub const CONFIG_VERSION_KEY: &str = "configVersion";
+
+/// The key of the version in the login file.
+pub const LOGIN_VERSION_KEY: &str = "LoginVersion";
+
+/// The current version of the config file.
+pub const CONFIG_VERSION: u64 = CONFIG_MIGRATIONS.len() as u64;
+
+/// The current version of the login file.
+pub const LOGIN_VERSION: u64 = LOGIN_MIGRATIONS.len() as u64;
+
+/// A step that upgrades a file by one version, changing the fields of the file in place.
+type Migration = fn(&mut Map<String, Value>) -> Result<(), Error>;
+
+/// The migrations of the config file, where the migration at index `n` upgrades version `n` to `n + 1`.
+///
+/// Migrations are only needed when an option is renamed or changes meaning, options that are only added don't need
+/// one (or a new version), since [merge_defaults] adds every missing option with its default whenever the file is
+/// loaded. New migrations are only ever added to the end, which also bumps [CONFIG_VERSION].
+/// - `0`: The original layout, without a version.
+/// - `1`: The versioned layout.
+const CONFIG_MIGRATIONS: [Migration; 1] = [add_version];
+
+/// The migrations of the login file, where the migration at index `n` upgrades version `n` to `n + 1`.
+///
+/// New migrations are only ever added to the end, which also bumps [LOGIN_VERSION].
+const LOGIN_MIGRATIONS: [Migration; 1] = [add_version];
+
+/// Upgrades the config file to the current version.
+///
+/// # Arguments
+///
+/// * `path`: The path of the config file.
+pub fn migrate_config(path: &Path) -> Result<(), Error> {
+    migrate(
+        path,
+        CONFIG_VERSION_KEY,
+        &CONFIG_MIGRATIONS,
+        to_value(Config::default())?,
+    )
+}
+
+/// Upgrades the login file to the current version.
+///
+/// # Arguments
+///
+/// * `path`: The path of the login file.
+pub fn migrate_login(path: &Path) -> Result<(), Error> {
+    migrate(
+        path,
+        LOGIN_VERSION_KEY,
+        &LOGIN_MIGRATIONS,
+        to_value(Login::default())?,
+    )
+}
+
+/// Upgrades the structured (TOML) config file to the current version, adding any missing option with its default.
+///
+/// The migrations are run on the fields of the file, and only the fields they change (or add) are written back to the
+/// document, so the user's comments and formatting are kept.
+///
+/// # Arguments
+///
+/// * `path`: The path of the config file.
+pub fn migrate_toml_config(path: &Path) -> Result<(), Error> {
+    let contents = read_to_string(path)
+        .with_context(|| format!("Unable to read {} for migration!", path.display()))?;
+    let mut document: DocumentMut = contents
+        .parse()
+        .with_context(|| format!("{} is not valid toml!", path.display()))?;
+    let original: Map<String, Value> = toml::from_str(&contents)
+        .with_context(|| format!("{} is not valid toml!", path.display()))?;
+
+    let mut map = original.clone();
+    let migrated = run_migrations(path, &mut map, CONFIG_VERSION_KEY, &CONFIG_MIGRATIONS)?;
+    let merged = merge_defaults(&mut map, to_value(Config::default())?);
+    if migrated.is_none() && merged.is_empty() {
+        return Ok(());
+    }
+
+    for key in original.keys().filter(|e| !map.contains_key(*e)) {
+        document.remove(key);
+    }
+
+    // Toml has no null, so unset options are left out of the file.
+    for (key, value) in &map {
+        if value.is_null() {
+            document.remove(key);
+        } else if original.get(key) != Some(value) {
+            document.insert(key, to_toml_item(key, value)?);
+        }
+    }
+
+    write_upgraded(path, migrated, &merged, document.to_string())
+}
+
+/// Replaces options of the config file in place, leaving the rest of the file (and the comments of a toml file) as it
+/// was.
+///
+/// # Arguments
+///
+/// * `path`: The path of the config file.
+/// * `options`: The options to replace, by their name in the file.
+pub fn replace_config_options(path: &Path, options: &Map<String, Value>) -> Result<(), Error> {
+    let contents = read_to_string(path)
+        .with_context(|| format!("Unable to read {} to replace options!", path.display()))?;
+    let contents = if is_toml_config(path) {
+        let mut document: DocumentMut = contents
+            .parse()
+            .with_context(|| format!("{} is not valid toml!", path.display()))?;
+        for (key, value) in options {
+            document.insert(key, to_toml_item(key, value)?);
+        }
+
+        document.to_string()
+    } else {
+        let mut value: Value = from_str(&contents)?;
+        let Some(map) = value.as_object_mut() else {
+            bail!("{} is not a json object!", path.display());
+        };
+
+        map.extend(options.clone());
+        to_string_pretty(&value)?
+    };
+
+    write(path, contents)?;
+    Ok(())
+}
+
+/// Runs every migration newer than the file's version and adds any missing field with its default, backing up the file
+/// before writing the upgraded version.
+///
+/// Files without a version are treated as version `0`, which is the layout before versioning was added.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `version_key`: The key of the version in the file.
+/// * `migrations`: The migrations of the file.
+/// * `defaults`: The default fields of the file.
+fn migrate(
+    path: &Path,
+    version_key: &str,
+    migrations: &[Migration],
+    defaults: Value,
+) -> Result<(), Error> {
+    let contents = read_to_string(path)
+        .with_context(|| format!("Unable to read {} for migration!", path.display()))?;
+    let mut value: Value =
+        from_str(&contents).with_context(|| format!("{} is not valid json!", path.display()))?;
+    let Some(map) = value.as_object_mut() else {
+        bail!("{} must contain a json object!", path.display());
+    };
+
+    let migrated = run_migrations(path, map, version_key, migrations)?;
+    let merged = merge_defaults(map, defaults);
+    if migrated.is_none() && merged.is_empty() {
+        return Ok(());
+    }
+
+    write_upgraded(path, migrated, &merged, to_string_pretty(&value)?)
+}
+
+/// Runs every migration newer than the version in the fields, then stamps the fields with the current version.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file (used for logging).
+/// * `map`: The fields of the file.
+/// * `version_key`: The key of the version in the file.
+/// * `migrations`: The migrations of the file.
+///
+/// returns: Result<Option<(u64, u64)>, Error> (the old and new version, or [None] if nothing was migrated)
+fn run_migrations(
+    path: &Path,
+    map: &mut Map<String, Value>,
+    version_key: &str,
+    migrations: &[Migration],
+) -> Result<Option<(u64, u64)>, Error> {
+    let version = map.get(version_key).and_then(Value::as_u64).unwrap_or(0);
+    let latest = migrations.len() as u64;
+    if version > latest {
+        warn!(
+            "{} is from a newer version of the program (version {version}, expected {latest})...",
+            path.display()
+        );
+        return Ok(None);
+    }
+
+    if version == latest {
+        return Ok(None);
+    }
+
+    for (from, migration) in migrations.iter().enumerate().skip(version as usize) {
+        trace!("Migrating {} from version {from}...", path.display());
+        migration(map).with_context(|| {
+            format!("Unable to migrate {} from version {from}!", path.display())
+        })?;
+    }
+
+    map.insert(version_key.to_string(), Value::from(latest));
+    Ok(Some((version, latest)))
+}
+
+/// Replaces the file with the upgraded contents, backing it up first if it was migrated to a new version.
+///
+/// # Arguments
+///
+/// * `path`: The path of the file.
+/// * `migrated`: The version the file was upgraded from and to, if it was migrated.
+/// * `merged`: The fields that were added with their defaults.
+/// * `contents`: The upgraded contents of the file.
+fn write_upgraded(
+    path: &Path,
+    migrated: Option<(u64, u64)>,
+    merged: &[String],
+    contents: String,
+) -> Result<(), Error> {
+    if let Some((version, latest)) = migrated {
+        let backup = path.with_extension(format!("v{version}.bak"));
+        copy(path, &backup)
+            .with_context(|| format!("Unable to back up {} before migrating!", path.display()))?;
+        info!(
+            "Upgraded {} from version {version} to {latest} (the old file was saved to {})...",
+            path.display(),
+            backup.display()
+        );
+    }
+
+    write(path, contents)?;
+    if !merged.is_empty() {
+        info!(
+            "Added the new options {} to {}...",
+            merged.join(", "),
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Converts a field into a toml item.
+///
+/// # Arguments
+///
+/// * `key`: The key of the field.
+/// * `value`: The value of the field.
+///
+/// returns: Result<Item, Error>
+fn to_toml_item(key: &str, value: &Value) -> Result<Item, Error> {
+    let mut table = Map::new();
+    table.insert(key.to_string(), value.clone());
+    let document: DocumentMut = toml::to_string(&table)?.parse()?;
+    document
+        .get(key)
+        .cloned()
+        .with_context(|| format!("Unable to convert \"{key}\" to toml!"))
+}
+
+/// Adds every field missing from the file with its default value, so new options are visible to the user.
+///
+/// Fields the file already has are left alone, so merging the defaults again doesn't change the file. Unset options are
+/// left out, since they load as unset anyway and toml can't store them.
+///
+/// # Arguments
+///
+/// * `map`: The fields of the file.
+/// * `defaults`: The default fields of the file.
+///
+/// returns: Vec<String, Global> (the keys of the added fields)
+fn merge_defaults(map: &mut Map<String, Value>, defaults: Value) -> Vec<String> {
+    let mut merged = Vec::new();
+    if let Value::Object(defaults) = defaults {
+        for (key, value) in defaults {
+            if !value.is_null() && !map.contains_key(&key) {
+                merged.push(key.clone());
+                map.insert(key, value);
+            }
+        }
+    }
+
+    merged
+}
+
+/// Version `0` to `1`: The original layout only lacks the version, which is added after the migrations run.
+fn add_version(_map: &mut Map<String, Value>) -> Result<(), Error> {
+    Ok(())
+}
@@ -14,53 +14,464 @@
  * limitations under the License.
  */
 
-use std::fs::{read_to_string, write};
+use std::borrow::Cow;
+use std::env::{current_dir, current_exe, set_current_dir, var};
+use std::fs::{create_dir_all, read_to_string, write};
 use std::io;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
-use anyhow::Error;
+use anyhow::{bail, Context, Error};
+use console::user_attended;
+use dialoguer::Confirm;
+use dirs::config_dir;
 use once_cell::sync::OnceCell;
+use reqwest::header::HeaderValue;
+use reqwest::{Proxy, Url};
 use serde::{Deserialize, Serialize};
-use serde_json::{from_str, to_string_pretty};
+use serde_json::{from_str, to_string_pretty, Map, Value};
 
-pub(crate) mod parser;
-pub(crate) mod tag;
+use crate::args::Args;
+use crate::e621::error::E621Error;
+use crate::e621::io::migration::{
+    migrate_config, migrate_login, migrate_toml_config, replace_config_options, CONFIG_VERSION,
+    LOGIN_VERSION,
+};
+use crate::e621::io::tag::CollectionOrder;
+use crate::e621::sanitize::{
+    is_invalid_char, DEFAULT_REPLACEMENT, MAX_FILE_NAME_BYTES, MIN_FILE_NAME_BYTES,
+};
+use crate::program::ExitStatus;
+
+pub mod audit;
+pub mod diagnostics;
+pub mod file_attributes;
+pub mod file_index;
+pub mod grab_file;
+pub mod migration;
+pub mod parser;
+pub mod preflight;
+pub mod report;
+pub mod response_cache;
+pub mod saved_search;
+pub mod tag;
+pub mod tag_state;
+pub mod user_cache;
+pub mod validate;
 
 /// Name of the configuration file.
-pub(crate) const CONFIG_NAME: &str = "config.json";
+pub const CONFIG_NAME: &str = "config.json";
+
+/// Name of the log file, which is written to the config directory.
+pub const LOG_NAME: &str = "e621_downloader.log";
+
+/// Name of the directory created in the platform config directory.
+pub const CONFIG_DIRECTORY_NAME: &str = "e621_downloader";
+
+/// Directory holding the profiles, where each profile has its own config, login, tag file, and downloads.
+pub const PROFILES_DIRECTORY: &str = "profiles";
+
+/// Name of the file that, placed beside the executable, runs the program portable from the executable's directory.
+pub const PORTABLE_MARKER_NAME: &str = "portable.txt";
+
+/// Name of the file that, placed beside the executable, opts into running the program from the platform directories.
+pub const INSTALLED_MARKER_NAME: &str = "installed.txt";
+
+/// Name of the directory, inside the config directory, generated state is kept in when no state directory is set.
+pub const STATE_DIRECTORY_NAME: &str = "state";
+
+/// Directory, inside the state directory, where raw responses that failed to deserialize are saved.
+pub const DEBUG_DIRECTORY: &str = "debug";
+
+/// The state files and directories earlier versions kept in the config directory, moved to the state directory the
+/// first time it's used.
+const LEGACY_STATE_NAMES: [&str; 8] = [
+    tag_state::TAG_STATE_NAME,
+    user_cache::USER_CACHE_NAME,
+    file_index::FILE_INDEX_NAME,
+    audit::AUDIT_NAME,
+    report::REPORT_NAME,
+    report::ERROR_REPORT_NAME,
+    response_cache::RESPONSE_CACHE_DIRECTORY,
+    DEBUG_DIRECTORY,
+];
+
+/// Name of the structured configuration file, used instead of [CONFIG_NAME] when it exists.
+pub const CONFIG_TOML_NAME: &str = "config.toml";
 
 /// Name of the login file.
-pub(crate) const LOGIN_NAME: &str = "login.json";
+pub const LOGIN_NAME: &str = "login.json";
+
+/// Environment variable for the username, used when the login file doesn't exist.
+pub const USERNAME_VAR: &str = "E621_USERNAME";
+
+/// Environment variable for the API key, used when the login file doesn't exist.
+pub const API_KEY_VAR: &str = "E621_API_KEY";
+
+/// Environment variable for whether to download favorites, used when the login file doesn't exist.
+pub const DOWNLOAD_FAVORITES_VAR: &str = "E621_DOWNLOAD_FAVORITES";
+
+/// The most posts that are downloaded at once, whatever the config sets.
+pub const MAX_DOWNLOAD_THREADS: usize = 8;
 
 /// Config that is used to do general setup.
 #[derive(Serialize, Deserialize, Debug, Clone)]
-pub(crate) struct Config {
+#[serde(default)]
+pub struct Config {
+    /// The version of the config layout, used to upgrade older config files.
+    #[serde(rename = "configVersion")]
+    version: u64,
     /// The location of the download directory.
     #[serde(rename = "downloadDirectory")]
     download_directory: String,
+    /// The directory generated state (e.g tag state, caches, and reports) is kept in, relative to the config directory
+    /// unless absolute, if unset a `state` directory inside the config directory is used.
+    #[serde(rename = "stateDirectory")]
+    state_directory: Option<String>,
     /// The file naming convention (e.g "md5", "id").
     #[serde(rename = "fileNamingConvention")]
     naming_convention: String,
+    /// What to do when the download won't fit on the download volume (e.g "warn", "abort").
+    #[serde(rename = "insufficientSpaceAction")]
+    insufficient_space_action: String,
+    /// Only posts with these file extensions will be grabbed (empty to allow all).
+    #[serde(rename = "onlyFileTypes")]
+    only_file_types: Vec<String>,
+    /// Posts with these file extensions will not be grabbed.
+    #[serde(rename = "excludeFileTypes")]
+    exclude_file_types: Vec<String>,
+    /// Posts with a score lower than this will not be grabbed.
+    #[serde(rename = "minScore")]
+    min_score: Option<i64>,
+    /// Posts with less favorites than this will not be grabbed.
+    #[serde(rename = "minFavCount")]
+    min_fav_count: Option<i64>,
+    /// The quality of the files to download (e.g "original", "sample").
+    #[serde(rename = "downloadQuality")]
+    download_quality: String,
+    /// Whether to download the sample of posts that have no original file url (e.g hidden from anonymous users).
+    #[serde(rename = "sampleFallback")]
+    sample_fallback: bool,
+    /// Whether posts already grabbed by an earlier tag in the run are left out, so each post is only kept in the first
+    /// collection that grabbed it. Pools and sets in pool order keep every post, so they have no gaps.
+    #[serde(rename = "skipDuplicatePosts")]
+    skip_duplicate_posts: bool,
+    /// A program that's asked whether each searched post should be grabbed (see [ScriptFilter]).
+    ///
+    /// [ScriptFilter]: crate::e621::filter::ScriptFilter
+    #[serde(rename = "filterScript")]
+    filter_script: Option<String>,
+    /// Whether the info and external links of the artist are saved to `artist_info.json` in artist collections.
+    #[serde(rename = "saveArtistInfo")]
+    save_artist_info: bool,
+    /// Whether the notes (e.g translations) of posts are saved next to the downloaded files.
+    #[serde(rename = "saveNotes")]
+    save_notes: bool,
+    /// How the comments of posts are saved next to the downloaded files, `none`, `json`, or `markdown`.
+    #[serde(rename = "saveComments")]
+    save_comments: String,
+    /// Whether the wiki pages of the searched tags are saved to `README.md` in the collections of tag searches.
+    #[serde(rename = "saveWikiPages")]
+    save_wiki_pages: bool,
+    /// The url of the instance the API is requested from (e.g `https://e621.net`, `https://e926.net`, or
+    /// `https://e6ai.net`).
+    #[serde(rename = "instanceUrl")]
+    instance_url: String,
+    /// The proxy every request is sent through (e.g `http://127.0.0.1:8080` or `socks5://127.0.0.1:1080`), if unset
+    /// the `HTTPS_PROXY` and `HTTP_PROXY` environment variables are used.
+    #[serde(rename = "proxy")]
+    proxy: Option<String>,
+    /// Text appended to the user agent of every request to identify who runs the program (e.g `operated by username`).
+    #[serde(rename = "userAgentSuffix")]
+    user_agent_suffix: Option<String>,
+    /// The seconds a request (including the download of its file) can take before it's cancelled, `0` never cancels.
+    #[serde(rename = "timeoutSecs")]
+    timeout_secs: u64,
+    /// The seconds connecting to the server can take before the request is cancelled, `0` never cancels.
+    #[serde(rename = "connectTimeoutSecs")]
+    connect_timeout_secs: u64,
+    /// The seconds between TCP keepalive probes on open connections, `0` turns keepalive off.
+    #[serde(rename = "keepaliveSecs")]
+    keepalive_secs: u64,
+    /// The amount of idle connections kept open for later requests, if unset there is no limit.
+    #[serde(rename = "maxIdleConnections")]
+    max_idle_connections: Option<usize>,
+    /// The hours tag, alias, pool, and set lookups are cached for before the server is asked again, `0` turns the
+    /// cache off.
+    #[serde(rename = "metadataCacheTtlHours")]
+    metadata_cache_ttl_hours: u64,
+    /// The order posts are stored (and numbered, in pools) in within collections, `default`, `pool`, `oldest`, or
+    /// `score` (see [CollectionOrder]).
+    #[serde(rename = "collectionOrder")]
+    collection_order: String,
+    /// Whether every file is saved straight into the download directory, instead of category and collection folders.
+    #[serde(rename = "flattenDownloads")]
+    flatten_downloads: bool,
+    /// The amount of posts of a collection downloaded at once (at most [MAX_DOWNLOAD_THREADS]), `1` downloads one post
+    /// at a time.
+    #[serde(rename = "downloadThreads")]
+    download_threads: usize,
+    /// The most kilobytes a second that post files are downloaded at, shared by every download at once, if unset (or
+    /// `0`) there is no limit. API requests aren't limited.
+    #[serde(rename = "maxDownloadSpeed")]
+    max_download_speed: Option<u64>,
+    /// The times a post is downloaded before giving up when the saved file doesn't match the md5 the API reported, `0`
+    /// doesn't check the md5.
+    #[serde(rename = "verifyAttempts")]
+    verify_attempts: u64,
+    /// What characters that can't be used in file names are replaced with.
+    #[serde(rename = "fileNameReplacement")]
+    file_name_replacement: String,
+    /// The most bytes a file or directory name can take, between [MIN_FILE_NAME_BYTES] and [MAX_FILE_NAME_BYTES].
+    #[serde(rename = "maxFileNameLength")]
+    max_file_name_length: usize,
+    /// Whether every file under the download directory is indexed by its md5 before downloading, so posts already
+    /// saved anywhere in it are left out.
+    #[serde(rename = "scanDownloads")]
+    scan_downloads: bool,
+    /// Whether the modified date of downloaded files is set to when their post was uploaded.
+    #[serde(rename = "setFileDates")]
+    set_file_dates: bool,
+    /// Whether the id and source url of posts are saved in the extended attributes of their files.
+    #[serde(rename = "writeFileAttributes")]
+    write_file_attributes: bool,
+    /// Whether GitHub is asked for a newer release on startup.
+    #[serde(rename = "checkForUpdates")]
+    check_for_updates: bool,
+    /// Whether e621, the login, the download directory, and the clock are checked before grabbing.
+    #[serde(rename = "preflightChecks")]
+    preflight_checks: bool,
+    /// Whether aliases in the tag file are replaced with the tags they are aliased to.
+    #[serde(rename = "rewriteAliases")]
+    rewrite_aliases: bool,
+    /// Whether to download from e926 (safe mode), if unset the user is asked every run.
+    #[serde(rename = "safeMode")]
+    safe_mode: Option<bool>,
+    /// The amount of old log files to keep.
+    #[serde(rename = "logHistory")]
+    log_history: usize,
+    /// The size in megabytes the log can grow to before it's rotated, `0` rotates the log every run.
+    #[serde(rename = "logMaxSizeMb")]
+    log_max_size_mb: u64,
 }
 
 static CONFIG: OnceCell<Config> = OnceCell::new();
 
 impl Config {
     /// The location of the download directory.
-    pub(crate) fn download_directory(&self) -> &str {
+    pub fn download_directory(&self) -> &str {
         &self.download_directory
     }
 
+    /// The directory generated state is kept in, if one is set.
+    pub fn state_directory(&self) -> Option<&str> {
+        self.state_directory.as_deref().filter(|e| !e.is_empty())
+    }
+
     /// The file naming convention (e.g "md5", "id").
-    pub(crate) fn naming_convention(&self) -> &str {
+    pub fn naming_convention(&self) -> &str {
         &self.naming_convention
     }
 
+    /// What to do when the download won't fit on the download volume (e.g "warn", "abort").
+    pub fn insufficient_space_action(&self) -> &str {
+        &self.insufficient_space_action
+    }
+
+    /// Only posts with these file extensions will be grabbed (empty to allow all).
+    pub fn only_file_types(&self) -> &[String] {
+        &self.only_file_types
+    }
+
+    /// Posts with these file extensions will not be grabbed.
+    pub fn exclude_file_types(&self) -> &[String] {
+        &self.exclude_file_types
+    }
+
+    /// Posts with a score lower than this will not be grabbed.
+    pub fn min_score(&self) -> Option<i64> {
+        self.min_score
+    }
+
+    /// Posts with less favorites than this will not be grabbed.
+    pub fn min_fav_count(&self) -> Option<i64> {
+        self.min_fav_count
+    }
+
+    /// The quality of the files to download (e.g "original", "sample").
+    pub fn download_quality(&self) -> &str {
+        &self.download_quality
+    }
+
+    /// Whether to download the sample of posts that have no original file url (e.g hidden from anonymous users).
+    pub fn sample_fallback(&self) -> bool {
+        self.sample_fallback
+    }
+
+    /// Whether posts already grabbed by an earlier tag in the run are left out, so each post is only kept in the first
+    /// collection that grabbed it. Pools and sets in pool order keep every post, so they have no gaps.
+    pub fn skip_duplicate_posts(&self) -> bool {
+        self.skip_duplicate_posts
+    }
+
+    /// A program that's asked whether each searched post should be grabbed, if any.
+    pub fn filter_script(&self) -> Option<&str> {
+        self.filter_script.as_deref()
+    }
+
+    /// Whether the info and external links of the artist are saved in artist collections.
+    pub fn save_artist_info(&self) -> bool {
+        self.save_artist_info
+    }
+
+    /// Whether the notes of posts are saved next to the downloaded files.
+    pub fn save_notes(&self) -> bool {
+        self.save_notes
+    }
+
+    /// How the comments of posts are saved next to the downloaded files, `none`, `json`, or `markdown`.
+    pub fn save_comments(&self) -> &str {
+        &self.save_comments
+    }
+
+    /// Whether the wiki pages of the searched tags are saved in the collections of tag searches.
+    pub fn save_wiki_pages(&self) -> bool {
+        self.save_wiki_pages
+    }
+
+    /// The url of the instance the API is requested from, without a trailing slash.
+    pub fn instance_url(&self) -> &str {
+        self.instance_url.trim_end_matches('/')
+    }
+
+    /// The proxy every request is sent through, if any.
+    pub fn proxy(&self) -> Option<&str> {
+        self.proxy.as_deref()
+    }
+
+    /// Text appended to the user agent of every request, if any.
+    pub fn user_agent_suffix(&self) -> Option<&str> {
+        self.user_agent_suffix.as_deref()
+    }
+
+    /// The seconds a request can take before it's cancelled, `0` never cancels.
+    pub fn timeout_secs(&self) -> u64 {
+        self.timeout_secs
+    }
+
+    /// The seconds connecting to the server can take before the request is cancelled, `0` never cancels.
+    pub fn connect_timeout_secs(&self) -> u64 {
+        self.connect_timeout_secs
+    }
+
+    /// The seconds between TCP keepalive probes on open connections, `0` turns keepalive off.
+    pub fn keepalive_secs(&self) -> u64 {
+        self.keepalive_secs
+    }
+
+    /// The amount of idle connections kept open for later requests, if any limit is set.
+    pub fn max_idle_connections(&self) -> Option<usize> {
+        self.max_idle_connections
+    }
+
+    /// The hours tag, alias, pool, and set lookups are cached for before the server is asked again, `0` turns the
+    /// cache off.
+    pub fn metadata_cache_ttl_hours(&self) -> u64 {
+        self.metadata_cache_ttl_hours
+    }
+
+    /// The order posts are stored (and numbered, in pools) in within collections, unless a group sets its own.
+    pub fn collection_order(&self) -> CollectionOrder {
+        CollectionOrder::from_name(&self.collection_order).unwrap_or_default()
+    }
+
+    /// Whether every file is saved straight into the download directory, instead of category and collection folders.
+    pub fn flatten_downloads(&self) -> bool {
+        self.flatten_downloads
+    }
+
+    /// The amount of posts of a collection downloaded at once, between `1` and [MAX_DOWNLOAD_THREADS].
+    pub fn download_threads(&self) -> usize {
+        self.download_threads.clamp(1, MAX_DOWNLOAD_THREADS)
+    }
+
+    /// The most kilobytes a second that are downloaded, if there is a limit.
+    pub fn max_download_speed(&self) -> Option<u64> {
+        self.max_download_speed.filter(|e| *e > 0)
+    }
+
+    /// The times a post is downloaded before giving up when the saved file doesn't match its md5, `0` doesn't check.
+    pub fn verify_attempts(&self) -> u64 {
+        self.verify_attempts
+    }
+
+    /// What characters that can't be used in file names are replaced with, [DEFAULT_REPLACEMENT] if the config sets an
+    /// empty replacement or one that can't be used in file names itself.
+    pub fn file_name_replacement(&self) -> &str {
+        if self.file_name_replacement.is_empty()
+            || self.file_name_replacement.contains(is_invalid_char)
+        {
+            DEFAULT_REPLACEMENT
+        } else {
+            &self.file_name_replacement
+        }
+    }
+
+    /// Whether every file under the download directory is indexed by its md5 before downloading.
+    pub fn scan_downloads(&self) -> bool {
+        self.scan_downloads
+    }
+
+    /// Whether the modified date of downloaded files is set to when their post was uploaded.
+    pub fn set_file_dates(&self) -> bool {
+        self.set_file_dates
+    }
+
+    /// Whether the id and source url of posts are saved in the extended attributes of their files.
+    pub fn write_file_attributes(&self) -> bool {
+        self.write_file_attributes
+    }
+
+    /// Whether GitHub is asked for a newer release on startup.
+    pub fn check_for_updates(&self) -> bool {
+        self.check_for_updates
+    }
+
+    /// Whether e621, the login, the download directory, and the clock are checked before grabbing.
+    pub fn preflight_checks(&self) -> bool {
+        self.preflight_checks
+    }
+
+    /// Whether aliases in the tag file are replaced with the tags they are aliased to.
+    pub fn rewrite_aliases(&self) -> bool {
+        self.rewrite_aliases
+    }
+
+    /// The most bytes a file or directory name can take, between [MIN_FILE_NAME_BYTES] and [MAX_FILE_NAME_BYTES].
+    pub fn max_file_name_length(&self) -> usize {
+        self.max_file_name_length
+            .clamp(MIN_FILE_NAME_BYTES, MAX_FILE_NAME_BYTES)
+    }
+
+    /// Whether to download from e926 (safe mode), if unset the user is asked every run.
+    pub fn safe_mode(&self) -> Option<bool> {
+        self.safe_mode
+    }
+
+    /// The amount of old log files to keep.
+    pub fn log_history(&self) -> usize {
+        self.log_history
+    }
+
+    /// The size in megabytes the log can grow to before it's rotated, `0` rotates the log every run.
+    pub fn log_max_size_mb(&self) -> u64 {
+        self.log_max_size_mb
+    }
+
     /// Checks config and ensure it isn't missing.
-    pub(crate) fn config_exists() -> bool {
-        if !Path::new(CONFIG_NAME).exists() {
-            trace!("config.json: does not exist!");
+    pub fn config_exists() -> bool {
+        if !config_path().exists() {
+            trace!("{}: does not exist!", config_path().display());
             return false;
         }
 
@@ -68,45 +479,414 @@ impl Config {
     }
 
     /// Creates config file.
-    pub(crate) fn create_config() -> Result<(), Error> {
-        let json = to_string_pretty(&Config::default())?;
-        write(Path::new(CONFIG_NAME), json)?;
+    pub fn create_config() -> Result<(), Error> {
+        Config::default().save()
+    }
+
+    /// Creates a default config with the given download settings.
+    ///
+    /// # Arguments
+    ///
+    /// * `download_directory`: The location of the download directory.
+    /// * `naming_convention`: The file naming convention (e.g "md5", "id").
+    ///
+    /// returns: Config
+    pub fn with_download_settings(download_directory: &str, naming_convention: &str) -> Self {
+        Config {
+            download_directory: download_directory.to_string(),
+            naming_convention: naming_convention.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Writes the config to the config file.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = config_path();
+        let contents = if is_toml_config(&path) {
+            toml::to_string_pretty(self)?
+        } else {
+            to_string_pretty(self)?
+        };
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+
+        write(path, contents)?;
 
         Ok(())
     }
 
+    /// Parses the contents of a config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `contents`: The contents of the config file.
+    /// * `path`: The path of the config file, which decides if it's parsed as toml or json.
+    ///
+    /// returns: Result<Config, Error>
+    pub fn parse(contents: &str, path: &Path) -> Result<Self, Error> {
+        if is_toml_config(path) {
+            Ok(toml::from_str(contents)?)
+        } else {
+            Ok(from_str(contents)?)
+        }
+    }
+
+    /// Reads the config file without upgrading or validating it, falling back to the default config on any error.
+    ///
+    /// This is only meant for settings needed before the logger exists, since loading the config normally logs.
+    pub fn peek() -> Self {
+        let path = config_path();
+        read_to_string(&path)
+            .ok()
+            .and_then(|e| Config::parse(&e, &path).ok())
+            .unwrap_or_default()
+    }
+
     /// Get the global instance of the `Config`.
-    pub(crate) fn get() -> &'static Self {
+    pub fn get() -> &'static Self {
         CONFIG.get_or_init(|| Self::get_config().unwrap())
     }
 
+    /// Gets the global instance of the `Config` if it was already loaded, or reads the config file without loading it
+    /// otherwise (see [Config::peek]), for settings needed before there may be a config (e.g in the setup wizard).
+    ///
+    /// returns: Cow<Config>
+    pub fn loaded_or_peek() -> Cow<'static, Self> {
+        match CONFIG.get() {
+            Some(config) => Cow::Borrowed(config),
+            None => Cow::Owned(Config::peek()),
+        }
+    }
+
+    /// Sets the global instance of the `Config` instead of loading the config file, for programs embedding the
+    /// downloader.
+    ///
+    /// This has to be called before anything reads the config, returns false if it was already loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The config to use.
+    ///
+    /// returns: bool
+    pub fn set(config: Config) -> bool {
+        CONFIG.set(config).is_ok()
+    }
+
+    /// Loads the global instance of the `Config`, returning an error instead of panicking if it can't be loaded.
+    ///
+    /// returns: Result<&Config, E621Error>
+    pub fn load() -> Result<&'static Self, E621Error> {
+        CONFIG
+            .get_or_try_init(Self::get_config)
+            .map_err(E621Error::Config)
+    }
+
     /// Loads and returns `config` for quick management and settings.
     fn get_config() -> Result<Self, Error> {
-        let mut config: Config = from_str(&read_to_string(CONFIG_NAME).unwrap())?;
-        config.naming_convention = config.naming_convention.to_lowercase();
-        let convention = ["md5", "id"];
-        if !convention
-            .iter()
-            .any(|e| *e == config.naming_convention.as_str())
+        let path = config_path();
+        if is_toml_config(&path) {
+            migrate_toml_config(&path)?;
+        } else {
+            migrate_config(&path)?;
+        }
+
+        let contents =
+            read_to_string(&path).with_context(|| format!("Unable to read {}!", path.display()))?;
+        let mut config = Config::parse(&contents, &path)?;
+        for ext in config
+            .only_file_types
+            .iter_mut()
+            .chain(config.exclude_file_types.iter_mut())
         {
-            error!(
-                "There is no naming convention {}!",
-                config.naming_convention
-            );
-            info!("The naming convention can only be [\"md5\", \"id\"]");
-            emergency_exit("Naming convention is incorrect!");
+            *ext = ext.trim_start_matches('.').to_lowercase();
+        }
+
+        match Url::parse(&config.instance_url) {
+            Ok(url) if matches!(url.scheme(), "http" | "https") => {}
+            _ => bail!(
+                "The instance url \"{}\" in {} is not a valid http(s) url!",
+                config.instance_url,
+                path.display()
+            ),
+        }
+
+        if let Some(proxy) = &config.proxy {
+            Proxy::all(proxy).with_context(|| {
+                format!(
+                    "The proxy \"{proxy}\" in {} is not a valid url!",
+                    path.display()
+                )
+            })?;
+        }
+
+        if let Some(suffix) = &config.user_agent_suffix {
+            HeaderValue::from_str(suffix).with_context(|| {
+                format!(
+                    "The user agent suffix \"{suffix}\" in {} is not visible ASCII!",
+                    path.display()
+                )
+            })?;
+        }
+
+        let invalid = config.validate();
+        if !invalid.is_empty() {
+            config.fix_invalid_options(&invalid, &path)?;
         }
 
         Ok(config)
     }
+
+    /// Checks every option that only allows certain values or a range of numbers, returning the options that are
+    /// invalid.
+    ///
+    /// Valid choice options are lowercased so they can be compared directly.
+    ///
+    /// returns: Vec<InvalidOption, Global>
+    fn validate(&mut self) -> Vec<InvalidOption> {
+        let mut invalid: Vec<InvalidOption> = CHOICE_OPTIONS
+            .iter()
+            .filter(|option| {
+                let value = (option.field)(self);
+                *value = value.to_lowercase();
+                !option.allowed.contains(&value.as_str())
+            })
+            .map(InvalidOption::Choice)
+            .collect();
+        invalid.extend(
+            RANGE_OPTIONS
+                .iter()
+                .filter(|option| !(option.min..=option.max).contains((option.field)(self)))
+                .map(InvalidOption::Range),
+        );
+        invalid
+    }
+
+    /// Reports every invalid option, then offers to reset them to their defaults in the config file.
+    ///
+    /// Only the invalid options are replaced in the file, so the rest of it (and its comments) are kept.
+    /// If the user declines, or nobody is there to answer, an error is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `invalid`: The invalid options.
+    /// * `path`: The path of the config file.
+    fn fix_invalid_options(&mut self, invalid: &[InvalidOption], path: &Path) -> Result<(), Error> {
+        let mut defaults = Config::default();
+        error!(
+            "{} has {} invalid option(s):",
+            path.display(),
+            invalid.len()
+        );
+        for option in invalid {
+            error!("{}", option.describe(self, &mut defaults));
+        }
+
+        let fix = user_attended()
+            && Confirm::new()
+                .with_prompt("Reset the invalid options to their defaults in the config?")
+                .default(true)
+                .interact()
+                .unwrap_or(false);
+        if !fix {
+            bail!("The config has invalid options!");
+        }
+
+        let mut options = Map::new();
+        for option in invalid {
+            options.insert(option.name().to_string(), option.reset(self, &mut defaults));
+        }
+
+        replace_config_options(path, &options)?;
+        info!("The invalid options were reset in {}...", path.display());
+        Ok(())
+    }
+}
+
+/// An option of the config that isn't valid.
+enum InvalidOption {
+    /// An option that isn't one of the values it allows.
+    Choice(&'static ChoiceOption),
+    /// An option that is outside the range it allows.
+    Range(&'static RangeOption),
+}
+
+impl InvalidOption {
+    /// The name of the option in the config file.
+    fn name(&self) -> &'static str {
+        match self {
+            InvalidOption::Choice(option) => option.name,
+            InvalidOption::Range(option) => option.name,
+        }
+    }
+
+    /// Describes why the option is invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The config with the invalid option.
+    /// * `defaults`: The default config.
+    ///
+    /// returns: String
+    fn describe(&self, config: &mut Config, defaults: &mut Config) -> String {
+        match self {
+            InvalidOption::Choice(option) => format!(
+                "\"{}\" is \"{}\", but it can only be one of {:?} (default: \"{}\")",
+                option.name,
+                (option.field)(config),
+                option.allowed,
+                (option.field)(defaults)
+            ),
+            InvalidOption::Range(option) => format!(
+                "\"{}\" is {}, but it can only be between {} and {} (default: {})",
+                option.name,
+                (option.field)(config),
+                option.min,
+                option.max,
+                (option.field)(defaults)
+            ),
+        }
+    }
+
+    /// Resets the option of the config to its default, returning the default.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The config with the invalid option.
+    /// * `defaults`: The default config.
+    ///
+    /// returns: Value
+    fn reset(&self, config: &mut Config, defaults: &mut Config) -> Value {
+        match self {
+            InvalidOption::Choice(option) => {
+                let default = (option.field)(defaults).clone();
+                *(option.field)(config) = default.clone();
+                Value::from(default)
+            }
+            InvalidOption::Range(option) => {
+                let default = *(option.field)(defaults);
+                *(option.field)(config) = default;
+                Value::from(default)
+            }
+        }
+    }
+}
+
+/// A config option that can only be one of a few values.
+struct ChoiceOption {
+    /// The name of the option in the config file.
+    name: &'static str,
+    /// The values the option allows.
+    allowed: &'static [&'static str],
+    /// Gets the option from the config.
+    field: fn(&mut Config) -> &mut String,
 }
 
+/// A config option that can only be a number within a range.
+struct RangeOption {
+    /// The name of the option in the config file.
+    name: &'static str,
+    /// The smallest value the option allows.
+    min: usize,
+    /// The largest value the option allows.
+    max: usize,
+    /// Gets the option from the config.
+    field: fn(&mut Config) -> &mut usize,
+}
+
+/// Every config option that can only be a number within a range.
+const RANGE_OPTIONS: [RangeOption; 2] = [
+    RangeOption {
+        name: "downloadThreads",
+        min: 1,
+        max: MAX_DOWNLOAD_THREADS,
+        field: |e| &mut e.download_threads,
+    },
+    RangeOption {
+        name: "maxFileNameLength",
+        min: MIN_FILE_NAME_BYTES,
+        max: MAX_FILE_NAME_BYTES,
+        field: |e| &mut e.max_file_name_length,
+    },
+];
+
+/// The naming conventions a config can use.
+pub const NAMING_CONVENTIONS: [&str; 2] = ["md5", "id"];
+
+/// Every config option that can only be one of a few values.
+const CHOICE_OPTIONS: [ChoiceOption; 5] = [
+    ChoiceOption {
+        name: "fileNamingConvention",
+        allowed: &NAMING_CONVENTIONS,
+        field: |e| &mut e.naming_convention,
+    },
+    ChoiceOption {
+        name: "insufficientSpaceAction",
+        allowed: &["warn", "abort"],
+        field: |e| &mut e.insufficient_space_action,
+    },
+    ChoiceOption {
+        name: "downloadQuality",
+        allowed: &["original", "sample"],
+        field: |e| &mut e.download_quality,
+    },
+    ChoiceOption {
+        name: "saveComments",
+        allowed: &["none", "json", "markdown"],
+        field: |e| &mut e.save_comments,
+    },
+    ChoiceOption {
+        name: "collectionOrder",
+        allowed: &CollectionOrder::NAMES,
+        field: |e| &mut e.collection_order,
+    },
+];
+
 impl Default for Config {
     /// The default configuration for `Config`.
     fn default() -> Self {
         Config {
+            version: CONFIG_VERSION,
             download_directory: String::from("downloads/"),
+            state_directory: None,
             naming_convention: String::from("md5"),
+            insufficient_space_action: String::from("warn"),
+            only_file_types: Vec::new(),
+            exclude_file_types: Vec::new(),
+            min_score: None,
+            min_fav_count: None,
+            download_quality: String::from("original"),
+            sample_fallback: false,
+            skip_duplicate_posts: false,
+            filter_script: None,
+            save_artist_info: false,
+            save_notes: false,
+            save_comments: String::from("none"),
+            save_wiki_pages: false,
+            instance_url: String::from("https://e621.net"),
+            proxy: None,
+            user_agent_suffix: None,
+            timeout_secs: 60,
+            connect_timeout_secs: 0,
+            keepalive_secs: 30,
+            max_idle_connections: None,
+            metadata_cache_ttl_hours: 0,
+            collection_order: String::from("default"),
+            flatten_downloads: false,
+            download_threads: 3,
+            max_download_speed: None,
+            verify_attempts: 3,
+            file_name_replacement: String::from(DEFAULT_REPLACEMENT),
+            max_file_name_length: MAX_FILE_NAME_BYTES,
+            scan_downloads: false,
+            set_file_dates: false,
+            write_file_attributes: false,
+            check_for_updates: true,
+            preflight_checks: true,
+            rewrite_aliases: false,
+            safe_mode: None,
+            log_history: 5,
+            log_max_size_mb: 0,
         }
     }
 }
@@ -114,7 +894,10 @@ impl Default for Config {
 /// `Login` contains all login information for obtaining information about a certain user.
 /// This is currently only used for the blacklist.
 #[derive(Serialize, Deserialize, Clone)]
-pub(crate) struct Login {
+pub struct Login {
+    /// The version of the login layout, used to upgrade older login files.
+    #[serde(rename = "LoginVersion", default)]
+    version: u64,
     /// Username of user.
     #[serde(rename = "Username")]
     username: String,
@@ -129,23 +912,71 @@ pub(crate) struct Login {
 static LOGIN: OnceCell<Login> = OnceCell::new();
 
 impl Login {
+    /// Creates a new login.
+    ///
+    /// # Arguments
+    ///
+    /// * `username`: Username of user.
+    /// * `api_key`: The API key for the user.
+    /// * `download_favorites`: Whether or not the user wishes to download their favorites.
+    ///
+    /// returns: Login
+    pub fn new(username: &str, api_key: &str, download_favorites: bool) -> Self {
+        Login {
+            version: LOGIN_VERSION,
+            username: username.to_string(),
+            api_key: api_key.to_string(),
+            download_favorites,
+        }
+    }
+
+    /// Checks if the login file exists.
+    pub fn login_exists() -> bool {
+        login_path().exists()
+    }
+
+    /// Writes the login to the login file.
+    pub fn save(&self) -> Result<(), Error> {
+        let path = login_path();
+        if let Some(directory) = path.parent() {
+            create_dir_all(directory)?;
+        }
+
+        write(path, to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
     /// Username of user.
-    pub(crate) fn username(&self) -> &str {
+    pub fn username(&self) -> &str {
         &self.username
     }
 
     /// The password hash (also known as the API key) for the user.
-    pub(crate) fn api_key(&self) -> &str {
+    pub fn api_key(&self) -> &str {
         &self.api_key
     }
 
     /// Whether or not the user wishes to download their favorites.
-    pub(crate) fn download_favorites(&self) -> bool {
+    pub fn download_favorites(&self) -> bool {
         self.download_favorites
     }
 
+    /// Sets the global instance of [Login] instead of loading the login file, for programs embedding the downloader.
+    ///
+    /// This has to be called before anything reads the login, returns false if it was already loaded.
+    ///
+    /// # Arguments
+    ///
+    /// * `login`: The login to use.
+    ///
+    /// returns: bool
+    pub fn set(login: Login) -> bool {
+        LOGIN.set(login).is_ok()
+    }
+
     /// Gets the global instance of [Login].
-    pub(crate) fn get() -> &'static Self {
+    pub fn get() -> &'static Self {
         LOGIN.get_or_init(|| Self::load().unwrap_or_else(|e| {
             error!("Unable to load `login.json`. Error: {}", e);
             warn!("The program will use default values, but it is highly recommended to check your login.json file to \
@@ -155,11 +986,18 @@ impl Login {
     }
 
     /// Loads the login file or creates one if it doesn't exist.
+    ///
+    /// If the login file doesn't exist, the login is loaded from the environment variables
+    /// [USERNAME_VAR], [API_KEY_VAR], and [DOWNLOAD_FAVORITES_VAR] before falling back to creating the file.
     fn load() -> Result<Self, Error> {
         let mut login = Login::default();
-        let login_path = Path::new(LOGIN_NAME);
+        let login_path = login_path();
         if login_path.exists() {
+            migrate_login(&login_path)?;
             login = from_str(&read_to_string(login_path)?)?;
+        } else if let Some(env_login) = Self::from_env() {
+            trace!("Login loaded from environment variables...");
+            login = env_login;
         } else {
             login.create_login()?;
         }
@@ -167,8 +1005,28 @@ impl Login {
         Ok(login)
     }
 
+    /// Loads the login from environment variables, returning [None] if the username or API key isn't set.
+    fn from_env() -> Option<Self> {
+        let username = var(USERNAME_VAR).ok().filter(|e| !e.is_empty())?;
+        let api_key = var(API_KEY_VAR).ok().filter(|e| !e.is_empty())?;
+        let download_favorites = match var(DOWNLOAD_FAVORITES_VAR) {
+            Ok(value) => value.trim().to_lowercase().parse::<bool>().unwrap_or_else(|_| {
+                warn!("{DOWNLOAD_FAVORITES_VAR} must be `true` or `false`, defaulting to `true`...");
+                true
+            }),
+            Err(_) => true,
+        };
+
+        Some(Login {
+            version: LOGIN_VERSION,
+            username,
+            api_key,
+            download_favorites,
+        })
+    }
+
     /// Checks if the login user and password is empty.
-    pub(crate) fn is_empty(&self) -> bool {
+    pub fn is_empty(&self) -> bool {
         if self.username.is_empty() || self.api_key.is_empty() {
             return true;
         }
@@ -178,7 +1036,7 @@ impl Login {
 
     /// Creates a new login file.
     fn create_login(&self) -> Result<(), Error> {
-        write(LOGIN_NAME, to_string_pretty(self)?)?;
+        self.save()?;
 
         info!("The login file was created.");
         info!(
@@ -198,6 +1056,7 @@ impl Default for Login {
     /// The default state for the login if none exists.
     fn default() -> Self {
         Login {
+            version: LOGIN_VERSION,
             username: String::new(),
             api_key: String::new(),
             download_favorites: true,
@@ -205,17 +1064,237 @@ impl Default for Login {
     }
 }
 
+/// The path of the config file.
+///
+/// This is the path passed with `--config`, otherwise [CONFIG_TOML_NAME] is preferred over [CONFIG_NAME] in the
+/// [config_directory] when it exists.
+pub fn config_path() -> PathBuf {
+    if let Some(path) = Args::get().config() {
+        return path.to_path_buf();
+    }
+
+    let toml_path = config_directory().join(CONFIG_TOML_NAME);
+    if toml_path.exists() {
+        toml_path
+    } else {
+        config_directory().join(CONFIG_NAME)
+    }
+}
+
+/// Checks if the config file at the path is a structured (toml) config file.
+///
+/// # Arguments
+///
+/// * `path`: The path of the config file.
+///
+/// returns: bool
+pub fn is_toml_config(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "toml")
+}
+
+/// The path of the login file, which is [LOGIN_NAME] next to the config file.
+pub fn login_path() -> PathBuf {
+    config_path().with_file_name(LOGIN_NAME)
+}
+
+/// Switches the working directory to the profile's directory when running portable.
+///
+/// Since downloads are saved relative to the working directory, this gives a portable profile its own download
+/// directory by default. Profiles in the platform config directory keep downloading to the working directory.
+///
+/// # Arguments
+///
+/// * `name`: The name of the profile.
+pub fn enter_profile(name: &str) -> Result<(), Error> {
+    let directory = config_directory();
+    trace!("Using profile \"{name}\" in {}...", directory.display());
+    if is_portable() {
+        create_dir_all(directory).with_context(|| {
+            format!(
+                "Unable to create profile directory {}!",
+                directory.display()
+            )
+        })?;
+        set_current_dir(directory).with_context(|| {
+            format!("Unable to enter profile directory {}!", directory.display())
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Switches the working directory to the executable's directory when running portable from it, so downloads are saved
+/// beside the executable no matter where the program was started from.
+pub fn enter_portable_directory() -> Result<(), Error> {
+    let Some(directory) = portable_directory() else {
+        trace!("Running installed, using the platform directories...");
+        return Ok(());
+    };
+
+    trace!("Running portable from {}...", directory.display());
+    if current_dir().is_ok_and(|e| e != directory) {
+        set_current_dir(directory).with_context(|| {
+            format!(
+                "Unable to enter portable directory {}!",
+                directory.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+/// Whether the config, login, and tag files are kept in the [portable_directory] instead of the platform config
+/// directory.
+pub fn is_portable() -> bool {
+    portable_directory().is_some()
+}
+
+/// The directory everything is kept in when running portable, or [None] when running installed.
+///
+/// The first of these that applies picks the mode:
+/// 1. A [PORTABLE_MARKER_NAME] file beside the executable runs portable from the executable's directory.
+/// 2. `--portable` runs portable from the working directory.
+/// 3. An [INSTALLED_MARKER_NAME] file beside the executable runs installed.
+/// 4. A config file or profiles beside the executable run portable from the executable's directory.
+/// 5. A config file or profiles in the working directory run portable from it.
+/// 6. A config file or profiles in the platform config directory run installed.
+///
+/// Otherwise the program runs portable from the working directory, so the platform config directory is only used once
+/// it's opted into.
+pub fn portable_directory() -> Option<&'static Path> {
+    static PORTABLE_DIRECTORY: OnceCell<Option<PathBuf>> = OnceCell::new();
+    PORTABLE_DIRECTORY
+        .get_or_init(|| {
+            let working_directory = current_dir().unwrap_or_default();
+            let executable_directory = current_exe()
+                .ok()
+                .and_then(|e| e.parent().map(Path::to_path_buf));
+            let has_marker = |name: &str| {
+                executable_directory
+                    .as_ref()
+                    .is_some_and(|e| e.join(name).exists())
+            };
+            let has_config = |directory: &Path| {
+                [CONFIG_NAME, CONFIG_TOML_NAME, PROFILES_DIRECTORY]
+                    .iter()
+                    .any(|e| directory.join(e).exists())
+            };
+
+            if has_marker(PORTABLE_MARKER_NAME) {
+                return executable_directory;
+            }
+
+            if Args::get().portable() {
+                return Some(working_directory);
+            }
+
+            if has_marker(INSTALLED_MARKER_NAME) {
+                return None;
+            }
+
+            if let Some(directory) = executable_directory.filter(|e| has_config(e)) {
+                return Some(directory);
+            }
+
+            if has_config(&working_directory) {
+                return Some(working_directory);
+            }
+
+            if platform_config_directory().is_some_and(|e| has_config(&e)) {
+                return None;
+            }
+
+            Some(working_directory)
+        })
+        .as_deref()
+}
+
+/// The directory holding the config, login, tag, report, and log files.
+///
+/// This is the [portable_directory] when running portable, otherwise it is the [platform_config_directory]. When a
+/// profile is used, it is the profile's directory inside of it.
+///
+/// The directory may not exist yet, whatever writes to it first creates it.
+pub fn config_directory() -> &'static Path {
+    static CONFIG_DIRECTORY: OnceCell<PathBuf> = OnceCell::new();
+    CONFIG_DIRECTORY.get_or_init(|| {
+        let root = match portable_directory() {
+            Some(directory) => directory.to_path_buf(),
+            None => platform_config_directory().unwrap_or_default(),
+        };
+
+        match Args::get().profile() {
+            Some(profile) => root.join(PROFILES_DIRECTORY).join(profile),
+            None => root,
+        }
+    })
+}
+
+/// The platform config directory of the program (e.g `$XDG_CONFIG_HOME/e621_downloader` or
+/// `%APPDATA%\e621_downloader`), or [None] if the platform doesn't have one.
+fn platform_config_directory() -> Option<PathBuf> {
+    config_dir().map(|e| e.join(CONFIG_DIRECTORY_NAME))
+}
+
+/// The directory generated state (e.g tag state, caches, reports, and debug dumps) is kept in, so it stays out of the
+/// download directory and the working directory.
+///
+/// This is the state directory set in the config, resolved against [config_directory] when it's relative, otherwise
+/// [STATE_DIRECTORY_NAME] inside of [config_directory].
+///
+/// The directory may not exist yet, whatever writes to it first creates it.
+pub fn state_directory() -> &'static Path {
+    static STATE_DIRECTORY: OnceCell<PathBuf> = OnceCell::new();
+    STATE_DIRECTORY.get_or_init(|| match Config::loaded_or_peek().state_directory() {
+        Some(directory) => config_directory().join(directory),
+        None => config_directory().join(STATE_DIRECTORY_NAME),
+    })
+}
+
+/// Moves the state files earlier versions kept in the config directory to the [state_directory], leaving alone any
+/// the state directory already has.
+pub fn move_legacy_state() {
+    let directory = state_directory();
+    if directory == config_directory() {
+        return;
+    }
+
+    for name in LEGACY_STATE_NAMES {
+        let (from, to) = (config_directory().join(name), directory.join(name));
+        if !from.exists() || to.exists() {
+            continue;
+        }
+
+        if let Err(e) = create_dir_all(directory) {
+            warn!("Unable to create {}: {e}", directory.display());
+            return;
+        }
+
+        match std::fs::rename(&from, &to) {
+            Ok(()) => trace!("Moved {} to {}", from.display(), to.display()),
+            Err(e) => warn!("Unable to move {} to {}: {e}", from.display(), to.display()),
+        }
+    }
+}
+
 /// Exits the program after message explaining the error and prompting the user to press `ENTER`.
 ///
+/// The prompt goes to stderr so it never ends up in the `--progress-json` stream, and is skipped when nobody is there
+/// to press `ENTER` or the progress is read by another program.
+///
 /// # Arguments
 ///
 /// * `error`: The error message to print.
-pub(crate) fn emergency_exit(error: &str) {
+/// * `status`: The status the program exits with.
+pub fn emergency_exit(error: &str, status: ExitStatus) -> ! {
     info!("{error}");
-    println!("Press ENTER to close the application...");
+    if user_attended() && !Args::get().progress_json() {
+        eprintln!("Press ENTER to close the application...");
 
-    let mut line = String::new();
-    io::stdin().read_line(&mut line).unwrap_or_default();
+        let mut line = String::new();
+        io::stdin().read_line(&mut line).unwrap_or_default();
+    }
 
-    exit(0x00FF);
+    exit(status.code());
 }
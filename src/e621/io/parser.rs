@@ -14,14 +14,14 @@
  * limitations under the License.
  */
 
-use crate::e621::io::emergency_exit;
+use crate::e621::error::skip_item;
 
 /// A parser that's responsible for parsing files character-by-character without any inherit rule.
 ///
 /// This is a thin blanket for other parsers to use and build rules, allowing for quick and easy
 /// parsing for any file.
 #[derive(Default)]
-pub(crate) struct BaseParser {
+pub struct BaseParser {
     /// Current cursor position in the array of characters.
     pos: usize,
     /// Input used for parsing.
@@ -36,7 +36,7 @@ pub(crate) struct BaseParser {
 
 impl BaseParser {
     /// Creates a new `BaseParser` with the given input.
-    pub(crate) fn new(input: String) -> Self {
+    pub fn new(input: String) -> Self {
         let mut parser = BaseParser {
             input: input.trim().to_string(),
             total_len: input.len(),
@@ -48,7 +48,7 @@ impl BaseParser {
     }
 
     /// Consume and discard zero or more whitespace characters.
-    pub(crate) fn consume_whitespace(&mut self) {
+    pub fn consume_whitespace(&mut self) {
         self.consume_while(char::is_whitespace);
     }
 
@@ -59,7 +59,7 @@ impl BaseParser {
     /// * `test`: The function to test against.
     ///
     /// returns: String
-    pub(crate) fn consume_while<F>(&mut self, test: F) -> String
+    pub fn consume_while<F>(&mut self, test: F) -> String
     where
         F: Fn(char) -> bool,
     {
@@ -72,7 +72,7 @@ impl BaseParser {
     }
 
     /// Returns current char and pushes `self.pos` to the next char.
-    pub(crate) fn consume_char(&mut self) -> char {
+    pub fn consume_char(&mut self) -> char {
         let mut iter = self.get_current_input().char_indices();
         let (_, cur_char) = iter.next().unwrap();
         let (next_pos, next_char) = iter.next().unwrap_or((1, ' '));
@@ -87,7 +87,7 @@ impl BaseParser {
     }
 
     /// Read the current char without consuming it.
-    pub(crate) fn next_char(&mut self) -> char {
+    pub fn next_char(&mut self) -> char {
         self.get_current_input().chars().next().unwrap()
     }
 
@@ -98,32 +98,32 @@ impl BaseParser {
     /// * `s`: The string to compare the start of the current input with.
     ///
     /// returns: bool
-    pub(crate) fn starts_with(&self, s: &str) -> bool {
+    pub fn starts_with(&self, s: &str) -> bool {
         self.get_current_input().starts_with(s)
     }
 
     /// Gets current input from current `pos` onward.
-    pub(crate) fn get_current_input(&self) -> &str {
+    pub fn get_current_input(&self) -> &str {
         &self.input[self.pos..]
     }
 
     /// Checks whether or not `pos` is at end of file.
-    pub(crate) fn eof(&self) -> bool {
+    pub fn eof(&self) -> bool {
         self.pos >= self.input.len()
     }
 
-    /// Reports an error to the parser so that it can exit gracefully.
+    /// Reports an error to the parser and skips the rest of the column, so that parsing can continue after it.
     ///
-    /// This will print a message to the console through the `error!` macro.
-    /// After this, it will also attach the current character number and column number to the message.
+    /// This will print a warning to the console with the current character number and column number attached to the
+    /// message.
     ///
     /// # Arguments
     ///
     /// * `msg`: Error message to print.
-    pub(crate) fn report_error(&self, msg: &str) {
-        error!(
-            "Error parsing file at character {} (column {}): {msg}",
-            self.pos, self.current_column
+    pub fn report_error(&mut self, msg: &str) {
+        skip_item(
+            &format!("the rest of column {}", self.current_column),
+            &format!("Error parsing file at character {}: {msg}", self.pos),
         );
         trace!(
             "Total characters: {}, total columns: {}",
@@ -131,6 +131,6 @@ impl BaseParser {
             self.total_columns
         );
 
-        emergency_exit("Parser error encountered.");
+        self.consume_while(|e| e != '\n');
     }
 }
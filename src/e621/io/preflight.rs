@@ -0,0 +1,332 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt::{Display, Formatter};
+use std::fs::{remove_file, OpenOptions};
+use std::io::{self, ErrorKind};
+use std::net::ToSocketAddrs;
+use std::path::{absolute, Path};
+use std::time::{Duration, SystemTime};
+
+use anyhow::Error;
+use console::style;
+use reqwest::Url;
+
+use crate::e621::error::E621Error;
+use crate::e621::io::{Config, Login};
+use crate::e621::sender::RequestSender;
+
+/// How far the clock can be off from the clock of e621 before it's warned about.
+const MAX_CLOCK_SKEW: Duration = Duration::from_secs(5 * 60);
+
+/// Name of the file written to check that the download directory is writable, which is removed right after.
+const WRITE_TEST_NAME: &str = ".e621_downloader_write_test";
+
+/// A check run before grabbing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Check {
+    /// The host of the API resolves to an address.
+    Dns,
+    /// The API answers over a secure connection.
+    Connection,
+    /// The clock is close to the clock of e621.
+    Clock,
+    /// The login credentials are accepted by e621.
+    Login,
+    /// Files can be written to the download directory.
+    DownloadDirectory,
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Check::Dns => "DNS",
+            Check::Connection => "Connection",
+            Check::Clock => "Clock",
+            Check::Login => "Login",
+            Check::DownloadDirectory => "Download directory",
+        };
+        f.pad(name)
+    }
+}
+
+/// How a check ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    /// Nothing is wrong.
+    Pass,
+    /// Something is off, but the run can continue.
+    Warn,
+    /// The run can't continue.
+    Fail,
+    /// The check doesn't apply (e.g the login check when not logged in).
+    Skipped,
+}
+
+/// The result of a check.
+#[derive(Debug)]
+pub struct CheckResult {
+    /// The check that was run.
+    pub check: Check,
+    /// How the check ended.
+    pub status: CheckStatus,
+    /// What was found.
+    pub detail: String,
+    /// The error the check failed with, if any.
+    pub error: Option<Error>,
+}
+
+impl CheckResult {
+    /// Creates a result without an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `check`: The check that was run.
+    /// * `status`: How the check ended.
+    /// * `detail`: What was found.
+    ///
+    /// returns: CheckResult
+    fn new(check: Check, status: CheckStatus, detail: impl Into<String>) -> Self {
+        CheckResult {
+            check,
+            status,
+            detail: detail.into(),
+            error: None,
+        }
+    }
+
+    /// Creates a failed result from the error the check failed with.
+    ///
+    /// # Arguments
+    ///
+    /// * `check`: The check that was run.
+    /// * `error`: The error the check failed with.
+    ///
+    /// returns: CheckResult
+    fn failed(check: Check, error: impl Into<Error>) -> Self {
+        let error = error.into();
+        CheckResult {
+            check,
+            status: CheckStatus::Fail,
+            detail: format!("{error:#}"),
+            error: Some(error),
+        }
+    }
+}
+
+/// Checks that e621 can be reached, the login is valid, the download directory is writable, and the clock is right
+/// before grabbing, so problems are found up front instead of partway through a run.
+pub struct Preflight<'a> {
+    /// The sender used for the API calls.
+    request_sender: &'a RequestSender,
+}
+
+impl<'a> Preflight<'a> {
+    /// Creates a new preflight.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for the API calls.
+    pub fn new(request_sender: &'a RequestSender) -> Self {
+        Preflight { request_sender }
+    }
+
+    /// Runs every check and prints the results.
+    ///
+    /// The checks after a failed DNS or connection check that need e621 are skipped.
+    ///
+    /// returns: Vec<CheckResult>
+    pub fn run(&self) -> Vec<CheckResult> {
+        info!("Running preflight checks...");
+        let mut results = vec![self.check_dns()];
+        if results[0].status == CheckStatus::Fail {
+            results.push(skipped(Check::Connection));
+            results.push(skipped(Check::Clock));
+            results.push(skipped(Check::Login));
+        } else {
+            match self.request_sender.ping() {
+                Ok(date) => {
+                    let instance = Config::get().instance_url();
+                    results.push(CheckResult::new(
+                        Check::Connection,
+                        CheckStatus::Pass,
+                        format!("{instance} answered"),
+                    ));
+                    results.push(check_clock(date));
+                    results.push(self.check_login());
+                }
+                Err(e) => {
+                    results.push(CheckResult::failed(Check::Connection, e));
+                    results.push(skipped(Check::Clock));
+                    results.push(skipped(Check::Login));
+                }
+            }
+        }
+
+        results.push(check_download_directory(Path::new(
+            Config::get().download_directory(),
+        )));
+        for result in &results {
+            print_result(result);
+        }
+
+        results
+    }
+
+    /// Checks that the host of the API resolves, which is left to the proxy when one is set.
+    fn check_dns(&self) -> CheckResult {
+        if Config::get().proxy().is_some() {
+            return CheckResult::new(Check::Dns, CheckStatus::Skipped, "resolved by the proxy");
+        }
+
+        let host = match Url::parse(Config::get().instance_url()) {
+            Ok(url) => url.host_str().unwrap_or_default().to_string(),
+            Err(e) => return CheckResult::failed(Check::Dns, e),
+        };
+        let address = (host.as_str(), 443).to_socket_addrs().and_then(|mut e| {
+            e.next()
+                .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "the host has no addresses"))
+        });
+        match address {
+            Ok(address) => CheckResult::new(
+                Check::Dns,
+                CheckStatus::Pass,
+                format!("{host} resolved to {}", address.ip()),
+            ),
+            Err(error) => CheckResult::failed(Check::Dns, E621Error::Resolve { host, error }),
+        }
+    }
+
+    /// Checks that the login credentials are accepted, if there are any.
+    fn check_login(&self) -> CheckResult {
+        let login = Login::get();
+        if login.is_empty() {
+            return CheckResult::new(Check::Login, CheckStatus::Skipped, "not logged in");
+        }
+
+        match self.request_sender.validate_credentials(login.username()) {
+            Ok(()) => CheckResult::new(
+                Check::Login,
+                CheckStatus::Pass,
+                format!("logged in as {}", login.username()),
+            ),
+            Err(e) => CheckResult::failed(Check::Login, e),
+        }
+    }
+}
+
+/// Creates the result of a check skipped because an earlier check failed.
+///
+/// # Arguments
+///
+/// * `check`: The skipped check.
+///
+/// returns: CheckResult
+fn skipped(check: Check) -> CheckResult {
+    CheckResult::new(check, CheckStatus::Skipped, "e621 couldn't be reached")
+}
+
+/// Checks that the clock is close to the time e621 reported, as a clock that's far off breaks secure connections and
+/// gives downloads the wrong dates.
+///
+/// # Arguments
+///
+/// * `date`: The time e621 reported, if any.
+///
+/// returns: CheckResult
+fn check_clock(date: Option<SystemTime>) -> CheckResult {
+    let Some(date) = date else {
+        return CheckResult::new(
+            Check::Clock,
+            CheckStatus::Skipped,
+            "e621 didn't report its time",
+        );
+    };
+
+    let now = SystemTime::now();
+    let (skew, direction) = match now.duration_since(date) {
+        Ok(skew) => (skew, "ahead of"),
+        Err(e) => (e.duration(), "behind"),
+    };
+    if skew > MAX_CLOCK_SKEW {
+        CheckResult::new(
+            Check::Clock,
+            CheckStatus::Warn,
+            format!(
+                "the clock is {} minute(s) {direction} e621, which can break secure connections",
+                skew.as_secs() / 60
+            ),
+        )
+    } else {
+        CheckResult::new(Check::Clock, CheckStatus::Pass, "in sync with e621")
+    }
+}
+
+/// Checks that files can be written to the download directory, or to the closest directory above it that exists when
+/// it doesn't exist yet, without creating it.
+///
+/// # Arguments
+///
+/// * `directory`: The download directory.
+///
+/// returns: CheckResult
+fn check_download_directory(directory: &Path) -> CheckResult {
+    let absolute_directory = absolute(directory).unwrap_or_else(|_| directory.to_path_buf());
+    let Some(existing) = absolute_directory.ancestors().find(|e| e.is_dir()) else {
+        return CheckResult::new(
+            Check::DownloadDirectory,
+            CheckStatus::Fail,
+            format!("{} doesn't exist", directory.display()),
+        );
+    };
+
+    // A test file left behind by an interrupted run is reused instead of failing the check.
+    let test_file = existing.join(WRITE_TEST_NAME);
+    match OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&test_file)
+    {
+        Ok(_) => {
+            remove_file(&test_file).unwrap_or_default();
+            CheckResult::new(
+                Check::DownloadDirectory,
+                CheckStatus::Pass,
+                format!("{} is writable", directory.display()),
+            )
+        }
+        Err(e) => CheckResult::failed(
+            Check::DownloadDirectory,
+            Error::from(e).context(format!("Unable to write to {}", existing.display())),
+        ),
+    }
+}
+
+/// Prints the result of a check.
+///
+/// # Arguments
+///
+/// * `result`: The result to print.
+fn print_result(result: &CheckResult) {
+    let line = format!("  {:<19} {}", result.check, result.detail);
+    match result.status {
+        CheckStatus::Pass => info!("{} {line}", style("PASS").green()),
+        CheckStatus::Warn => warn!("{} {line}", style("WARN").yellow()),
+        CheckStatus::Fail => error!("{} {line}", style("FAIL").red()),
+        CheckStatus::Skipped => info!("{} {line}", style("SKIP").dim()),
+    }
+}
@@ -0,0 +1,227 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::env::consts::{ARCH, OS};
+use std::fs::{create_dir_all, read_to_string, remove_file, write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::e621::error::{error_causes, run_stage, RunStage, SkippedItem};
+use crate::e621::io::state_directory;
+use crate::e621::io::user_cache::now;
+
+/// Name of the report file written at the end of every run.
+pub const REPORT_NAME: &str = "last_run.json";
+
+/// Name of the file summarizing the failures of the last run, written when a run doesn't succeed.
+pub const ERROR_REPORT_NAME: &str = "last_run_errors.json";
+
+/// A summary of a finished run, saved so it can be viewed later.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RunReport {
+    /// When the run finished, in seconds since the unix epoch.
+    #[serde(rename = "finishedAt")]
+    pub finished_at: u64,
+    /// The amount of collections grabbed.
+    pub collections: usize,
+    /// The amount of posts downloaded.
+    pub downloaded: u64,
+    /// The amount of posts skipped.
+    pub skipped: u64,
+    /// The total size of all grabbed posts in bytes.
+    #[serde(rename = "totalBytes")]
+    pub total_bytes: u64,
+    /// The blacklist lines that filtered posts, from the most posts filtered to the least.
+    #[serde(rename = "blacklistHits")]
+    pub blacklist_hits: Vec<BlacklistHit>,
+    /// The requests sent during the run, by endpoint.
+    pub requests: Vec<RequestStats>,
+    /// The amount of requests that had to be sent again.
+    pub retries: u64,
+    /// The tags, pools, sets, and posts skipped because of an error, along with the error.
+    pub failures: Vec<SkippedItem>,
+}
+
+/// A blacklist line along with how many posts it filtered during the run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BlacklistHit {
+    /// The line as it was written in the blacklist.
+    pub line: String,
+    /// The amount of posts the line filtered.
+    pub posts: u64,
+}
+
+/// The requests sent to one endpoint during the run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct RequestStats {
+    /// The endpoint the requests were sent to (e.g `/posts.json`, or `files` for downloads).
+    pub endpoint: String,
+    /// The amount of requests sent.
+    pub requests: u64,
+    /// The amount of requests that failed or were answered with an error status.
+    pub failures: u64,
+    /// The amount of bytes received.
+    pub bytes: u64,
+    /// The average time spent waiting for a response, in milliseconds.
+    #[serde(rename = "averageMs")]
+    pub average_ms: u64,
+}
+
+impl RunReport {
+    /// Creates a new report, stamped with the current time.
+    pub fn new() -> Self {
+        RunReport {
+            finished_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|e| e.as_secs())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// Loads the report of the last run, returning [None] if there is no report.
+    pub fn load() -> Option<Self> {
+        let path = state_directory().join(REPORT_NAME);
+        if !path.exists() {
+            return None;
+        }
+
+        match read_to_string(path).map(|e| from_str::<RunReport>(&e)) {
+            Ok(Ok(report)) => Some(report),
+            Ok(Err(e)) => {
+                warn!("Unable to parse {REPORT_NAME}: {e}");
+                None
+            }
+            Err(e) => {
+                warn!("Unable to read {REPORT_NAME}: {e}");
+                None
+            }
+        }
+    }
+
+    /// Writes the report to the report file.
+    pub fn save(&self) -> Result<(), Error> {
+        create_dir_all(state_directory())?;
+        write(state_directory().join(REPORT_NAME), to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+}
+
+/// A summary of every failure of a run that didn't succeed, saved so it can be attached to an issue instead of a
+/// screenshot of the terminal.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ErrorReport {
+    /// When the run finished, in seconds since the unix epoch.
+    #[serde(rename = "finishedAt")]
+    pub finished_at: u64,
+    /// The version of the downloader the run was made with.
+    pub version: String,
+    /// The operating system and architecture the run was made on (e.g `linux x86_64`).
+    pub platform: String,
+    /// The status the run exited with.
+    #[serde(rename = "exitCode")]
+    pub exit_code: i32,
+    /// Every failure of the run, in the order they happened.
+    pub failures: Vec<Failure>,
+}
+
+/// A failure of a run, along with the whole chain of errors behind it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Failure {
+    /// The stage the run was in when it failed.
+    pub stage: RunStage,
+    /// A description of the item that failed (e.g `post 1234`), or `run` for the error that ended the run.
+    pub item: String,
+    /// The error, followed by the errors that caused it, down to the root cause.
+    pub chain: Vec<String>,
+}
+
+impl Failure {
+    /// Creates the failure of the error that ended the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error that ended the run.
+    ///
+    /// returns: Failure
+    pub fn from_error(error: &Error) -> Self {
+        let mut chain: Vec<String> = error.chain().map(|e| e.to_string()).collect();
+        chain.extend(error_causes(error.root_cause()));
+        Failure {
+            stage: run_stage(),
+            item: String::from("run"),
+            chain,
+        }
+    }
+}
+
+impl From<SkippedItem> for Failure {
+    fn from(skipped_item: SkippedItem) -> Self {
+        Failure {
+            stage: skipped_item.stage,
+            item: skipped_item.item,
+            chain: [vec![skipped_item.reason], skipped_item.causes].concat(),
+        }
+    }
+}
+
+impl ErrorReport {
+    /// Creates a new report, stamped with the current time.
+    ///
+    /// # Arguments
+    ///
+    /// * `exit_code`: The status the run exited with.
+    /// * `failures`: Every failure of the run.
+    ///
+    /// returns: ErrorReport
+    pub fn new(exit_code: i32, failures: Vec<Failure>) -> Self {
+        ErrorReport {
+            finished_at: now(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            platform: format!("{OS} {ARCH}"),
+            exit_code,
+            failures,
+        }
+    }
+
+    /// The path of the error report.
+    pub fn path() -> PathBuf {
+        state_directory().join(ERROR_REPORT_NAME)
+    }
+
+    /// Writes the report to the error report file.
+    pub fn save(&self) -> Result<(), Error> {
+        create_dir_all(state_directory())?;
+        write(Self::path(), to_string_pretty(self)?)?;
+
+        Ok(())
+    }
+
+    /// Removes the error report of an earlier run, so it isn't mistaken for the report of a run that succeeded.
+    pub fn remove() {
+        remove_file(Self::path()).unwrap_or_default();
+    }
+}
@@ -0,0 +1,208 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string};
+use sha2::{Digest, Sha256};
+
+use crate::e621::io::state_directory;
+use crate::e621::io::user_cache::now;
+
+/// Directory, inside the state directory, where cached responses are saved.
+pub const RESPONSE_CACHE_DIRECTORY: &str = "cache";
+
+/// How long, in seconds, a response is kept after it stops being fresh, so it can still be confirmed with its ETag.
+/// Responses that weren't requested for this long are removed.
+const RESPONSE_RETENTION: u64 = 7 * 24 * 60 * 60;
+
+/// A response saved along with when it was saved and the ETag the server sent for it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct CachedResponse {
+    /// The login, url, and query the response was requested with.
+    key: String,
+    /// The ETag of the response, sent back in `If-None-Match` to check if the response changed.
+    etag: Option<String>,
+    /// When the response was saved (or last confirmed unchanged), in seconds since the unix epoch.
+    #[serde(rename = "cachedAt")]
+    cached_at: u64,
+    /// The body of the response.
+    body: String,
+}
+
+impl CachedResponse {
+    /// The ETag of the response, if the server sent one.
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    /// Whether the response was saved less than `ttl` seconds ago, and can be used without asking the server.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: The seconds a response is used for, `0` always asks the server.
+    ///
+    /// returns: bool
+    pub fn is_fresh(&self, ttl: u64) -> bool {
+        now().saturating_sub(self.cached_at) < ttl
+    }
+
+    /// The body of the response.
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+}
+
+/// A cache of API responses saved between runs, so metadata (e.g tags, aliases, pools, and sets) is reused for a while
+/// without asking the server, and after that confirmed with a conditional request instead of downloaded again.
+///
+/// Every response is saved to its own file in [RESPONSE_CACHE_DIRECTORY], named after the sha256 of its key, so the
+/// name stays the same across builds and platforms.
+pub struct ResponseCache {
+    /// The directory the responses are saved in.
+    directory: PathBuf,
+}
+
+impl ResponseCache {
+    /// Creates a cache saving its responses in the state directory.
+    pub fn new() -> Self {
+        ResponseCache {
+            directory: state_directory().join(RESPONSE_CACHE_DIRECTORY),
+        }
+    }
+
+    /// Removes the responses that weren't saved or confirmed within `ttl` and [RESPONSE_RETENTION], or every response
+    /// when the cache is off.
+    ///
+    /// # Arguments
+    ///
+    /// * `ttl`: The seconds a response is used for, `0` turns the cache off.
+    pub fn prune(&self, ttl: u64) {
+        if !self.directory.exists() {
+            return;
+        }
+
+        if ttl == 0 {
+            if let Err(e) = remove_dir_all(&self.directory) {
+                warn!("Unable to remove the response cache: {e}");
+            }
+
+            return;
+        }
+
+        let Ok(entries) = read_dir(&self.directory) else {
+            return;
+        };
+
+        let mut removed = 0;
+        for path in entries.flatten().map(|e| e.path()) {
+            if Self::is_expired(&path, ttl + RESPONSE_RETENTION) && remove_file(&path).is_ok() {
+                removed += 1;
+            }
+        }
+
+        if removed > 0 {
+            trace!("Removed {removed} expired responses from the response cache...");
+        }
+    }
+
+    /// Whether the response in the file is older than `max_age` seconds, or can't be read.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The file of the response.
+    /// * `max_age`: The seconds a response is kept for.
+    ///
+    /// returns: bool
+    fn is_expired(path: &Path, max_age: u64) -> bool {
+        read_to_string(path)
+            .ok()
+            .and_then(|e| from_str::<CachedResponse>(&e).ok())
+            .is_none_or(|e| !e.is_fresh(max_age))
+    }
+
+    /// Creates the key of a request.
+    ///
+    /// The login is part of the key, since authenticated responses can differ between accounts (e.g private sets).
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url of the request.
+    /// * `query`: The query parameters of the request.
+    /// * `login`: The username the request is sent with, or `anon` when it isn't sent with a login.
+    ///
+    /// returns: String
+    pub fn key(url: &str, query: &[(&str, &str)], login: &str) -> String {
+        let query: Vec<String> = query.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        format!("{login}@{url}?{}", query.join("&"))
+    }
+
+    /// Gets the cached response of a request, if there is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the request.
+    ///
+    /// returns: Option<CachedResponse>
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        read_to_string(self.path(key))
+            .ok()
+            .and_then(|e| from_str::<CachedResponse>(&e).ok())
+            .filter(|e| e.key == key)
+    }
+
+    /// Caches the response of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the request.
+    /// * `etag`: The ETag of the response, if the server sent one.
+    /// * `body`: The body of the response.
+    pub fn insert(&self, key: &str, etag: Option<&str>, body: &str) -> Result<(), Error> {
+        create_dir_all(&self.directory)?;
+        let response = CachedResponse {
+            key: key.to_string(),
+            etag: etag.map(String::from),
+            cached_at: now(),
+            body: body.to_string(),
+        };
+        write(self.path(key), to_string(&response)?)?;
+
+        Ok(())
+    }
+
+    /// The path of the file a response is cached in.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The key of the request.
+    ///
+    /// returns: PathBuf
+    fn path(&self, key: &str) -> PathBuf {
+        self.directory
+            .join(format!("{:x}.json", Sha256::digest(key)))
+    }
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,146 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fs::{read_to_string, write};
+
+use anyhow::{Context, Error};
+use toml::Value;
+
+use crate::e621::io::tag::{
+    is_toml_tag_file, parse_tag_file_unchecked, tag_file_path, Group, TagIdentifier,
+};
+use crate::e621::sender::entries::SavedSearchEntry;
+use crate::e621::sender::RequestSender;
+
+/// The label used for saved searches that don't have one.
+const UNLABELED: &str = "saved searches";
+
+/// Sorts the saved searches by their first label, keeping the order of the searches within each label.
+///
+/// # Arguments
+///
+/// * `searches`: The saved searches to sort.
+///
+/// returns: BTreeMap<&str, Vec<&str>>
+fn by_label(searches: &[SavedSearchEntry]) -> BTreeMap<&str, Vec<&str>> {
+    let mut labels: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+    for search in searches {
+        let label = search.labels.first().map_or(UNLABELED, String::as_str);
+        labels.entry(label).or_default().push(search.query.trim());
+    }
+
+    labels
+}
+
+/// Converts saved searches into groups of general tags, with a group for every label, so they can be downloaded
+/// without adding them to the tag file.
+///
+/// Searches that can't be identified are skipped with a warning.
+///
+/// # Arguments
+///
+/// * `searches`: The saved searches to convert.
+/// * `request_sender`: The sender used to identify the searched tags.
+///
+/// returns: Vec<Group, Global>
+pub fn saved_search_groups(
+    searches: &[SavedSearchEntry],
+    request_sender: &RequestSender,
+) -> Vec<Group> {
+    by_label(searches)
+        .into_iter()
+        .map(|(label, queries)| {
+            let tags = queries
+                .into_iter()
+                .filter_map(|e| TagIdentifier::id_tag(e, request_sender.clone()))
+                .collect();
+            Group::with_tags(label.to_string(), tags)
+        })
+        .collect()
+}
+
+/// Adds saved searches to the tag file as groups of general tags, with a group for every label.
+///
+/// Searches that are already in the tag file (or one of its includes) are left out.
+///
+/// # Arguments
+///
+/// * `searches`: The saved searches to add.
+/// * `request_sender`: The sender held by the tag parser (no API calls are made).
+///
+/// returns: Result<usize, Error> (the amount of searches added)
+pub fn add_saved_searches(
+    searches: &[SavedSearchEntry],
+    request_sender: &RequestSender,
+) -> Result<usize, Error> {
+    let existing: Vec<String> = parse_tag_file_unchecked(request_sender)?
+        .iter()
+        .flat_map(|e| e.tags())
+        .map(|e| e.name().to_string())
+        .collect();
+    let path = tag_file_path();
+    let toml = is_toml_tag_file(&path);
+    let mut added = 0;
+    let mut contents = String::new();
+    for (label, queries) in by_label(searches) {
+        let queries: Vec<&str> = queries
+            .into_iter()
+            .filter(|e| !existing.iter().any(|tag| tag == e))
+            .collect();
+        if queries.is_empty() {
+            continue;
+        }
+
+        added += queries.len();
+        if toml {
+            let tags: Vec<String> = queries
+                .iter()
+                .map(|e| Value::from(*e).to_string())
+                .collect();
+            contents.push_str(&format!(
+                "\n[[group]]\nname = {}\nkind = \"general\"\ntags = [{}]\n",
+                Value::from(label),
+                tags.join(", ")
+            ));
+        } else {
+            match label {
+                UNLABELED => contents.push_str("\n# Saved searches\n[general]\n"),
+                label => contents.push_str(&format!(
+                    "\n# Saved searches labeled \"{label}\"\n[general]\n"
+                )),
+            }
+            for query in queries {
+                contents.push_str(query);
+                contents.push('\n');
+            }
+        }
+    }
+
+    if added > 0 {
+        let mut tag_file = read_to_string(&path)
+            .with_context(|| format!("Unable to read tag file {}!", path.display()))?;
+        if !tag_file.is_empty() && !tag_file.ends_with('\n') {
+            tag_file.push('\n');
+        }
+
+        tag_file.push_str(&contents);
+        write(&path, tag_file)
+            .with_context(|| format!("Unable to write tag file {}!", path.display()))?;
+    }
+
+    Ok(added)
+}
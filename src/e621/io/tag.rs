@@ -14,24 +14,40 @@
  * limitations under the License.
  */
 
-use std::fs::read_to_string;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{copy, read_to_string, write};
+use std::path::{Path, PathBuf};
 
-use anyhow::{Context, Error};
+use anyhow::{bail, Context, Error};
+use serde::{Deserialize, Serialize};
+use toml_edit::{DocumentMut, Value};
 
-use crate::e621::io::emergency_exit;
+use crate::args::Args;
+use crate::e621::error::{skip_item, skip_item_error, E621Error};
 use crate::e621::io::parser::BaseParser;
+use crate::e621::io::{config_directory, Config};
 use crate::e621::sender::entries::TagEntry;
 use crate::e621::sender::RequestSender;
 
 /// Constant of the tag file's name.
-pub(crate) const TAG_NAME: &str = "tags.txt";
+pub const TAG_NAME: &str = "tags.txt";
+
+/// Constant of the structured tag file's name, used instead of [TAG_NAME] when it exists.
+pub const TAG_TOML_NAME: &str = "tags.toml";
+
+/// An alias found while identifying tags, as the alias and the tag it is aliased to.
+type ResolvedAlias = (String, String);
+
+/// The most tags a single tag is expanded into by its implications, so a broad tag (e.g `mammal`) doesn't turn into
+/// thousands of searches.
+const MAX_IMPLIED_TAGS: usize = 200;
 
 /// An example file for newly created tag files.
-pub(crate) const TAG_FILE_EXAMPLE: &str = include_str!("tags.txt");
+pub const TAG_FILE_EXAMPLE: &str = include_str!("tags.txt");
 
 /// A tag that can be either general or special.
 #[derive(Debug, Clone, PartialOrd, PartialEq, Eq)]
-pub(crate) enum TagSearchType {
+pub enum TagSearchType {
     /// A general tag that is used for everything except artist and sometimes character (depending on the amount of posts tied to it)
     General,
     /// A special tag that is searched differently from general tags (artist and characters).
@@ -41,8 +57,9 @@ pub(crate) enum TagSearchType {
 }
 
 /// The type a tag can be.
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) enum TagType {
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TagType {
     Pool,
     Set,
     General,
@@ -53,7 +70,7 @@ pub(crate) enum TagType {
 
 /// A tag that contains its name, search type, and tag type.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct Tag {
+pub struct Tag {
     /// The name of the tag.
     name: String,
     /// The search type of the tag.
@@ -63,7 +80,7 @@ pub(crate) struct Tag {
 }
 
 impl Tag {
-    fn new(tag: &str, category: TagSearchType, tag_type: TagType) -> Self {
+    pub fn new(tag: &str, category: TagSearchType, tag_type: TagType) -> Self {
         Tag {
             name: String::from(tag),
             search_type: category,
@@ -72,17 +89,17 @@ impl Tag {
     }
 
     /// The name of the tag.
-    pub(crate) fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
     /// The search type of the tag.
-    pub(crate) fn search_type(&self) -> &TagSearchType {
+    pub fn search_type(&self) -> &TagSearchType {
         &self.search_type
     }
 
     /// The tag type of the tag.
-    pub(crate) fn tag_type(&self) -> &TagType {
+    pub fn tag_type(&self) -> &TagType {
         &self.tag_type
     }
 }
@@ -97,79 +114,827 @@ impl Default for Tag {
     }
 }
 
+/// The order posts are stored (and numbered, in pools) in within a collection.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum CollectionOrder {
+    /// Pools in pool order, everything else in the order it was searched.
+    #[default]
+    Default,
+    /// Pools and sets in their own order, everything else in the order it was searched.
+    Pool,
+    /// The oldest upload first.
+    Oldest,
+    /// The highest score first.
+    Score,
+}
+
+impl CollectionOrder {
+    /// The names of every order, as written in the config and tag file.
+    pub const NAMES: [&'static str; 4] = ["default", "pool", "oldest", "score"];
+
+    /// Gets the order with the given name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the order (see [CollectionOrder::NAMES]).
+    ///
+    /// returns: Option<CollectionOrder>
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "default" => Some(CollectionOrder::Default),
+            "pool" => Some(CollectionOrder::Pool),
+            "oldest" => Some(CollectionOrder::Oldest),
+            "score" => Some(CollectionOrder::Score),
+            _ => None,
+        }
+    }
+}
+
+/// Options set on a group with `@option: value` lines, these override the global options in the config.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct GroupOptions {
+    /// Only posts with these file extensions will be grabbed.
+    #[serde(rename = "only")]
+    only_file_types: Option<Vec<String>>,
+    /// Posts with these file extensions will not be grabbed.
+    #[serde(rename = "exclude")]
+    exclude_file_types: Option<Vec<String>>,
+    /// Posts with a score lower than this will not be grabbed.
+    min_score: Option<i64>,
+    /// Posts with less favorites than this will not be grabbed.
+    min_fav_count: Option<i64>,
+    /// Posts narrower than this will not be grabbed.
+    min_width: Option<i64>,
+    /// Posts shorter than this will not be grabbed.
+    min_height: Option<i64>,
+    /// Whether the group only grabs safe posts, even if the rest of the run doesn't.
+    safe: Option<bool>,
+    /// The most posts grabbed for each entry in the group.
+    limit: Option<usize>,
+    /// The directory the group's collections are saved to, instead of their category directory.
+    directory: Option<String>,
+    /// Posts with any of these tags aren't removed by the blacklist in the group (`!tag` lines).
+    #[serde(default)]
+    whitelist: Vec<String>,
+    /// Whether the group's posts are added to the user's favorites once they are downloaded.
+    favorite: Option<bool>,
+    /// Whether the group's posts are upvoted once they are downloaded.
+    upvote: Option<bool>,
+    /// Whether the group's tags only grab posts newer than the ones the last run downloaded.
+    incremental: Option<bool>,
+    /// The order posts are stored in within the group's collections.
+    order: Option<CollectionOrder>,
+    /// Whether the tags implying the group's tags are grabbed as entries of their own.
+    implications: Option<bool>,
+}
+
+impl GroupOptions {
+    /// Only posts with these file extensions will be grabbed.
+    pub fn only_file_types(&self) -> Option<&[String]> {
+        self.only_file_types.as_deref()
+    }
+
+    /// Posts with these file extensions will not be grabbed.
+    pub fn exclude_file_types(&self) -> Option<&[String]> {
+        self.exclude_file_types.as_deref()
+    }
+
+    /// Posts with a score lower than this will not be grabbed.
+    pub fn min_score(&self) -> Option<i64> {
+        self.min_score
+    }
+
+    /// Posts with less favorites than this will not be grabbed.
+    pub fn min_fav_count(&self) -> Option<i64> {
+        self.min_fav_count
+    }
+
+    /// Posts narrower than this will not be grabbed.
+    pub fn min_width(&self) -> Option<i64> {
+        self.min_width
+    }
+
+    /// Posts shorter than this will not be grabbed.
+    pub fn min_height(&self) -> Option<i64> {
+        self.min_height
+    }
+
+    /// Whether the group only grabs safe posts, even if the rest of the run doesn't.
+    pub fn safe(&self) -> bool {
+        self.safe.unwrap_or(false)
+    }
+
+    /// The most posts grabbed for each entry in the group.
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// The directory the group's collections are saved to, instead of their category directory.
+    pub fn directory(&self) -> Option<&str> {
+        self.directory.as_deref()
+    }
+
+    /// Posts with any of these tags aren't removed by the blacklist in the group.
+    pub fn whitelist(&self) -> &[String] {
+        &self.whitelist
+    }
+
+    /// Whether the group's posts are added to the user's favorites once they are downloaded.
+    pub fn favorite(&self) -> bool {
+        self.favorite.unwrap_or(false)
+    }
+
+    /// Whether the group's posts are upvoted once they are downloaded.
+    pub fn upvote(&self) -> bool {
+        self.upvote.unwrap_or(false)
+    }
+
+    /// Whether the group's tags only grab posts newer than the ones the last run downloaded.
+    pub fn incremental(&self) -> bool {
+        self.incremental.unwrap_or(false)
+    }
+
+    /// The order posts are stored in within the group's collections, if the group sets it.
+    pub fn order(&self) -> Option<CollectionOrder> {
+        self.order
+    }
+
+    /// Whether the tags implying the group's tags are grabbed as entries of their own.
+    pub fn implications(&self) -> bool {
+        self.implications.unwrap_or(false)
+    }
+}
+
 /// Group object generated from parsed code.
 #[derive(Debug, Clone)]
-pub(crate) struct Group {
+pub struct Group {
     /// The name of group.
     name: String,
     /// A [Vec] containing all the tags parsed.
     tags: Vec<Tag>,
+    /// The options set for the group.
+    options: GroupOptions,
 }
 
 impl Group {
-    pub(crate) fn new(name: String) -> Self {
+    pub fn new(name: String) -> Self {
         Group {
             name,
             tags: Vec::new(),
+            options: GroupOptions::default(),
+        }
+    }
+
+    /// Creates a group with the given tags and the default options.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the group.
+    /// * `tags`: The tags of the group.
+    ///
+    /// returns: Group
+    pub fn with_tags(name: String, tags: Vec<Tag>) -> Self {
+        Group {
+            name,
+            tags,
+            options: GroupOptions::default(),
+        }
+    }
+
+    /// Creates a group with the given tags and options.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the group.
+    /// * `tags`: The tags of the group.
+    /// * `options`: The options of the group.
+    ///
+    /// returns: Group
+    pub fn with_options(name: String, tags: Vec<Tag>, options: GroupOptions) -> Self {
+        Group {
+            name,
+            tags,
+            options,
         }
     }
 
     /// The name of group.
-    pub(crate) fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         &self.name
     }
 
     /// A [Vec] containing all the tags parsed.
-    pub(crate) fn tags(&self) -> &Vec<Tag> {
+    pub fn tags(&self) -> &Vec<Tag> {
         &self.tags
     }
+
+    /// The options set for the group.
+    pub fn options(&self) -> &GroupOptions {
+        &self.options
+    }
+}
+
+/// Returns the path of the tag file in use.
+///
+/// This is the path passed with `--tags`, otherwise [TAG_TOML_NAME] is preferred over [TAG_NAME] when it exists.
+pub fn tag_file_path() -> PathBuf {
+    if let Some(path) = Args::get().tags() {
+        return path.to_path_buf();
+    }
+
+    let toml_path = config_directory().join(TAG_TOML_NAME);
+    if toml_path.exists() {
+        toml_path
+    } else {
+        config_directory().join(TAG_NAME)
+    }
+}
+
+/// Checks if the tag file exists.
+pub fn tag_file_exists() -> bool {
+    tag_file_path().exists()
+}
+
+/// Checks if the tag file at the path is a structured tag file.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file.
+///
+/// returns: bool
+pub fn is_toml_tag_file(path: &Path) -> bool {
+    path.extension().is_some_and(|e| e == "toml")
 }
 
 /// Parses the tag file and returns the serialized form of it.
 ///
+/// If [TAG_TOML_NAME] exists, it is loaded instead of the legacy [TAG_NAME].
+///
+/// # Arguments
+///
+/// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
+///
+/// returns: Result<Vec<Group, Global>, Error>
+pub fn parse_tag_file(request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
+    load_tag_file(&tag_file_path(), request_sender, true, &mut Vec::new())
+}
+
+/// Parses the tag file without identifying the tags through the API.
+///
+/// Every tag is returned with [TagSearchType::None], which lets the tags be checked separately without the parser
+/// exiting on the first tag it can't find.
+///
 /// # Arguments
 ///
+/// * `request_sender`: The sender held by the parser (no API calls are made).
+///
+/// returns: Result<Vec<Group, Global>, Error>
+pub fn parse_tag_file_unchecked(request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
+    load_tag_file(&tag_file_path(), request_sender, false, &mut Vec::new())
+}
+
+/// Parses the tag file at the path, instead of the one in the config directory or passed with `--tags`.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file.
+/// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
+///
+/// returns: Result<Vec<Group, Global>, Error>
+pub fn parse_tag_file_at(path: &Path, request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
+    load_tag_file(path, request_sender, true, &mut Vec::new())
+}
+
+/// Loads a tag file in either format, along with every file it includes.
+///
+/// Files ending in `.toml` are loaded as a structured tag file, every other file is parsed as a legacy tag file.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file.
 /// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
+/// * `identify`: Whether general tags are identified through the API.
+/// * `included`: Every tag file loaded so far, used to stop a file from being included twice.
 ///
 /// returns: Result<Vec<Group, Global>, Error>
-pub(crate) fn parse_tag_file(request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
-    TagParser {
-        parser: BaseParser::new(
-            read_to_string(TAG_NAME)
-                .with_context(|| {
-                    error!("Unable to read tag file!");
-                    "Possible I/O block when trying to read tag file..."
-                })
-                .unwrap(),
-        ),
-        request_sender: request_sender.clone(),
+fn load_tag_file(
+    path: &Path,
+    request_sender: &RequestSender,
+    identify: bool,
+    included: &mut Vec<PathBuf>,
+) -> Result<Vec<Group>, Error> {
+    let canonical = path
+        .canonicalize()
+        .with_context(|| format!("Unable to find tag file {}!", path.display()))?;
+    if included.contains(&canonical) {
+        bail!("Tag file {} is included more than once!", path.display());
+    }
+
+    trace!("Loading tag file {}...", path.display());
+    included.push(canonical);
+    let contents = read_to_string(path)
+        .with_context(|| format!("Unable to read tag file {}!", path.display()))?;
+    let (groups, aliases) = if is_toml_tag_file(path) {
+        parse_toml_tag_file(&contents, path, request_sender, identify, included)?
+    } else {
+        let mut parser = TagParser {
+            parser: BaseParser::new(contents.clone()),
+            request_sender: request_sender.clone(),
+            identify,
+            path: path.to_path_buf(),
+            included,
+            aliases: Vec::new(),
+        };
+        (parser.parse_groups()?, parser.aliases)
+    };
+
+    if !aliases.is_empty() && Config::get().rewrite_aliases() {
+        if let Err(e) = rewrite_aliases(path, &contents, &aliases) {
+            warn!("Unable to replace the aliases in {}: {e:#}", path.display());
+        }
+    }
+
+    Ok(groups)
+}
+
+/// Replaces the aliases in a tag file with the tags they are aliased to, after saving the original file next to it
+/// (e.g `tags.txt.bak`, or `tags.txt.1.bak` if that backup already exists, see [backup_path]).
+///
+/// Only the entries of `artists` and `general` groups are changed, everything else (e.g comments and options) is kept
+/// as it is.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file.
+/// * `contents`: The contents the tag file was parsed from.
+/// * `aliases`: The aliases found while identifying the tags of the file.
+///
+/// returns: Result<(), Error>
+fn rewrite_aliases(path: &Path, contents: &str, aliases: &[ResolvedAlias]) -> Result<(), Error> {
+    let rewritten = if is_toml_tag_file(path) {
+        replace_toml_aliases(contents, aliases)?
+    } else {
+        replace_text_aliases(contents, aliases)
+    };
+    if rewritten == contents {
+        return Ok(());
+    }
+
+    let backup = backup_path(path);
+    copy(path, &backup).with_context(|| format!("Unable to back up {}!", path.display()))?;
+    write(path, rewritten)?;
+    for (alias, tag) in aliases {
+        info!(
+            "Replaced the alias \"{alias}\" with \"{tag}\" in {}...",
+            path.display()
+        );
+    }
+    info!("The original tag file was saved to {}.", backup.display());
+
+    Ok(())
+}
+
+/// Finds a path next to the tag file its backup can be saved to without replacing an earlier backup, so the file as
+/// it was before the first rewrite is never lost.
+///
+/// The first backup is saved with `.bak` added to the name of the tag file (e.g `tags.txt.bak`), and later backups are
+/// numbered (e.g `tags.txt.1.bak`, `tags.txt.2.bak`).
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file.
+///
+/// returns: PathBuf
+fn backup_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut backup = path.with_file_name(format!("{file_name}.bak"));
+    let mut number = 0;
+    while backup.exists() {
+        number += 1;
+        backup = path.with_file_name(format!("{file_name}.{number}.bak"));
+    }
+
+    backup
+}
+
+/// Replaces the aliases in the `artists` and `general` groups of a legacy tag file.
+///
+/// # Arguments
+///
+/// * `contents`: The contents of the tag file.
+/// * `aliases`: The aliases to replace.
+///
+/// returns: String
+fn replace_text_aliases(contents: &str, aliases: &[ResolvedAlias]) -> String {
+    let mut in_tag_group = false;
+    let mut rewritten = String::with_capacity(contents.len());
+    for line in contents.split_inclusive('\n') {
+        let (text, ending) = match line.find(['\r', '\n']) {
+            Some(index) => line.split_at(index),
+            None => (line, ""),
+        };
+        let trimmed = text.trim_start();
+        if let Some(group) = trimmed.strip_prefix('[') {
+            in_tag_group = group.starts_with("artists]") || group.starts_with("general]");
+        }
+
+        if !in_tag_group || trimmed.is_empty() || trimmed.starts_with(['[', '#', '@', '!']) {
+            rewritten.push_str(line);
+            continue;
+        }
+
+        let indent = &text[..text.len() - trimmed.len()];
+        rewritten.push_str(indent);
+        rewritten.push_str(&replace_aliases(trimmed, aliases));
+        rewritten.push_str(ending);
+    }
+
+    rewritten
+}
+
+/// Replaces the aliases in the `artists` and `general` groups of a structured tag file, keeping its formatting and
+/// comments.
+///
+/// # Arguments
+///
+/// * `contents`: The contents of the tag file.
+/// * `aliases`: The aliases to replace.
+///
+/// returns: Result<String, Error>
+fn replace_toml_aliases(contents: &str, aliases: &[ResolvedAlias]) -> Result<String, Error> {
+    let mut document: DocumentMut = contents.parse()?;
+    let Some(groups) = document
+        .get_mut("group")
+        .and_then(|e| e.as_array_of_tables_mut())
+    else {
+        return Ok(contents.to_string());
+    };
+
+    for group in groups.iter_mut() {
+        let kind = group
+            .get("kind")
+            .or_else(|| group.get("name"))
+            .and_then(|e| e.as_str());
+        if !matches!(kind, Some("artists" | "general")) {
+            continue;
+        }
+
+        let Some(tags) = group.get_mut("tags").and_then(|e| e.as_array_mut()) else {
+            continue;
+        };
+        for entry in tags.iter_mut() {
+            let Some(replaced) = entry
+                .as_str()
+                .map(|e| replace_aliases(e, aliases))
+                .filter(|e| Some(e.as_str()) != entry.as_str())
+            else {
+                continue;
+            };
+
+            let decor = entry.decor().clone();
+            *entry = Value::from(replaced);
+            *entry.decor_mut() = decor;
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+/// Replaces every aliased tag of an entry (e.g `-old_name ~wolf`) with the tag it is aliased to, keeping exclusions and
+/// optional tags.
+///
+/// # Arguments
+///
+/// * `tags`: The tags of the entry.
+/// * `aliases`: The aliases to replace.
+///
+/// returns: String
+fn replace_aliases(tags: &str, aliases: &[ResolvedAlias]) -> String {
+    tags.split(' ')
+        .map(|tag| {
+            let name = tag.trim_start_matches(['-', '~']);
+            match aliases.iter().find(|(alias, _)| alias == name) {
+                Some((_, replacement)) => {
+                    format!("{}{replacement}", &tag[..tag.len() - name.len()])
+                }
+                None => tag.to_string(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Loads the groups of an included tag file, skipping the include with a warning if it can't be loaded.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file with the include.
+/// * `include`: The included path.
+/// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
+/// * `identify`: Whether general tags are identified through the API.
+/// * `included`: Every tag file loaded so far.
+///
+/// returns: Vec<Group, Global>
+fn load_include(
+    path: &Path,
+    include: &str,
+    request_sender: &RequestSender,
+    identify: bool,
+    included: &mut Vec<PathBuf>,
+) -> Vec<Group> {
+    load_tag_file(
+        &resolve_include(path, include),
+        request_sender,
+        identify,
+        included,
+    )
+    .unwrap_or_else(|e| {
+        skip_item(&format!("include \"{include}\""), &format!("{e:#}"));
+        Vec::new()
+    })
+}
+
+/// Resolves an included tag file relative to the directory of the tag file including it.
+///
+/// # Arguments
+///
+/// * `path`: The path of the tag file with the include.
+/// * `include`: The included path.
+///
+/// returns: PathBuf
+fn resolve_include(path: &Path, include: &str) -> PathBuf {
+    path.parent().unwrap_or(Path::new("")).join(include)
+}
+
+/// Processes the tag type and creates the appropriate tag for it.
+///
+/// # Arguments
+///
+/// * `tags`: Tags to use.
+/// * `tag_entry`: The tag entry related to the tags.
+///
+/// returns: Tag
+fn create_tag(tags: &str, tag_entry: &TagEntry) -> Tag {
+    let tag_type = tag_entry.to_tag_type();
+    let category = match tag_type {
+        TagType::General => {
+            const CHARACTER_CATEGORY: u8 = 4;
+            if tag_entry.category == CHARACTER_CATEGORY {
+                if tag_entry.post_count > 1500 {
+                    TagSearchType::General
+                } else {
+                    TagSearchType::Special
+                }
+            } else {
+                TagSearchType::General
+            }
+        }
+        TagType::Artist => TagSearchType::Special,
+        _ => unreachable!(),
+    };
+
+    Tag::new(tags, category, tag_type)
+}
+
+/// Adds the tags implying each tag of a group (e.g `wolf` and `fox` for `canine`), and the tags implying those, to the
+/// group as entries of their own.
+///
+/// Only entries that are a single tag are expanded, searches with several tags, exclusions, or metatags are left as
+/// they are. Each implied tag is looked up so it is searched the same way it would be if it was in the tag file.
+///
+/// # Arguments
+///
+/// * `group`: The group to expand.
+/// * `request_sender`: The sender to use for the API calls.
+fn expand_implications(group: &mut Group, request_sender: &RequestSender) {
+    let mut seen: HashSet<String> = group.tags.iter().map(|e| e.name.clone()).collect();
+    let mut implied = Vec::new();
+    for tag in &group.tags {
+        let name = tag.name();
+        if tag.tag_type != TagType::General || name.contains([' ', ':']) || name.starts_with('-') {
+            continue;
+        }
+
+        let mut count = 0;
+        let mut queue = VecDeque::from([name.to_string()]);
+        while let Some(consequent) = queue.pop_front() {
+            let entries = match request_sender.get_implications_of(&consequent) {
+                Ok(entries) => entries,
+                Err(e) => {
+                    skip_item_error(&format!("implications of \"{consequent}\""), &e);
+                    continue;
+                }
+            };
+
+            for entry in entries {
+                if count == MAX_IMPLIED_TAGS {
+                    warn!(
+                        "\"{name}\" is implied by more than {MAX_IMPLIED_TAGS} tags, only the first \
+                         {MAX_IMPLIED_TAGS} are grabbed."
+                    );
+                    queue.clear();
+                    break;
+                }
+
+                let antecedent = entry.antecedent_name;
+                if !seen.insert(antecedent.clone()) {
+                    continue;
+                }
+
+                let tag = match request_sender.get_tags_by_name(&antecedent) {
+                    Ok(entries) => match entries.first() {
+                        Some(tag_entry) => create_tag(&antecedent, tag_entry),
+                        None => Tag::new(&antecedent, TagSearchType::General, TagType::General),
+                    },
+                    Err(e) => {
+                        skip_item_error(&format!("\"{antecedent}\""), &e);
+                        continue;
+                    }
+                };
+
+                count += 1;
+                queue.push_back(antecedent);
+                implied.push(tag);
+            }
+        }
+
+        if count > 0 {
+            info!("Expanded \"{name}\" into {count} implied tags...");
+        }
+    }
+
+    group.tags.append(&mut implied);
+}
+
+/// The structured tag file, which is a list of `[[group]]` tables.
+#[derive(Deserialize, Debug)]
+struct TomlTagFile {
+    /// Other tag files to load along with this one.
+    #[serde(default)]
+    include: Vec<String>,
+    /// The groups in the tag file.
+    #[serde(default, rename = "group")]
+    groups: Vec<TomlGroup>,
+}
+
+/// A group in the structured tag file.
+#[derive(Deserialize, Debug)]
+struct TomlGroup {
+    /// The name of the group.
+    name: String,
+    /// The kind of entries in the group (`artists`, `general`, `pools`, `sets`, or `single-post`), defaults to the
+    /// name of the group.
+    kind: Option<String>,
+    /// The tags, or ids, to grab.
+    #[serde(default)]
+    tags: Vec<String>,
+    /// The options of the group.
+    #[serde(flatten)]
+    options: GroupOptions,
+}
+
+/// Parses a structured tag file and converts it into groups, loading the groups of its included files first.
+///
+/// # Arguments
+///
+/// * `contents`: The contents of the tag file.
+/// * `path`: The path of the tag file.
+/// * `request_sender`: The sender to use for the API call (this is used for tag and alias checks).
+/// * `identify`: Whether general tags are identified through the API.
+/// * `included`: Every tag file loaded so far.
+///
+/// returns: Result<(Vec<Group, Global>, Vec<ResolvedAlias, Global>), Error>
+fn parse_toml_tag_file(
+    contents: &str,
+    path: &Path,
+    request_sender: &RequestSender,
+    identify: bool,
+    included: &mut Vec<PathBuf>,
+) -> Result<(Vec<Group>, Vec<ResolvedAlias>), Error> {
+    let file: TomlTagFile =
+        toml::from_str(contents).with_context(|| format!("{} is invalid!", path.display()))?;
+
+    let mut groups = Vec::with_capacity(file.groups.len());
+    let mut aliases = Vec::new();
+    for include in &file.include {
+        groups.append(&mut load_include(
+            path,
+            include,
+            request_sender,
+            identify,
+            included,
+        ));
     }
-    .parse_groups()
+
+    for toml_group in file.groups {
+        let kind = toml_group.kind.as_deref().unwrap_or(&toml_group.name);
+        let tag_type = match kind {
+            "artists" | "general" => TagType::General,
+            "pools" => TagType::Pool,
+            "sets" => TagType::Set,
+            "single-post" => TagType::Post,
+            _ => {
+                skip_item(
+                    &format!("group \"{}\"", toml_group.name),
+                    &format!("unknown kind \"{kind}\""),
+                );
+                continue;
+            }
+        };
+
+        let mut tags = Vec::with_capacity(toml_group.tags.len());
+        for entry in toml_group.tags.iter().map(|e| e.trim()) {
+            if tag_type == TagType::General {
+                if !identify {
+                    tags.push(Tag::new(entry, TagSearchType::None, TagType::General));
+                } else {
+                    let (tag, mut resolved) =
+                        TagIdentifier::id_tag_with_aliases(entry, request_sender.clone());
+                    aliases.append(&mut resolved);
+                    tags.extend(tag);
+                }
+
+                continue;
+            }
+
+            if entry.is_empty() || !entry.chars().all(valid_id) {
+                skip_item(
+                    &format!("\"{entry}\" in group \"{}\"", toml_group.name),
+                    &"pools, sets, and single-post entries must be a number",
+                );
+                continue;
+            }
+
+            tags.push(Tag::new(entry, TagSearchType::Special, tag_type.clone()));
+        }
+
+        let mut options = toml_group.options;
+        options.only_file_types = options.only_file_types.map(|e| parse_list(&e.join(",")));
+        options.exclude_file_types = options.exclude_file_types.map(|e| parse_list(&e.join(",")));
+        let mut group = Group {
+            name: toml_group.name,
+            tags,
+            options,
+        };
+        if identify && group.options.implications() {
+            expand_implications(&mut group, request_sender);
+        }
+
+        groups.push(group);
+    }
+
+    Ok((groups, aliases))
 }
 
 /// Identifier to help categorize tags.
-pub(crate) struct TagIdentifier {
+pub struct TagIdentifier {
     /// Request sender for making any needed API calls.
     request_sender: RequestSender,
+    /// The aliases found while identifying the tags.
+    aliases: Vec<ResolvedAlias>,
 }
 
 impl TagIdentifier {
     /// Creates new identifier.
     fn new(request_sender: RequestSender) -> Self {
-        TagIdentifier { request_sender }
+        TagIdentifier {
+            request_sender,
+            aliases: Vec::new(),
+        }
     }
 
-    /// Identifies tags to ensure they exist.
+    /// Identifies tags to ensure they exist, skipping them with a warning if they don't or can't be looked up.
     ///
     /// # Arguments
     ///
     /// * `tags`: Tags to id.
     /// * `request_sender`: The sender to use for the API calls.
     ///
-    /// returns: Tag
-    fn id_tag(tags: &str, request_sender: RequestSender) -> Tag {
-        let identifier = TagIdentifier::new(request_sender);
-        identifier.search_for_tag(tags)
+    /// returns: Option<Tag>
+    pub fn id_tag(tags: &str, request_sender: RequestSender) -> Option<Tag> {
+        TagIdentifier::id_tag_with_aliases(tags, request_sender).0
+    }
+
+    /// Identifies tags like [TagIdentifier::id_tag], also returning the aliases (and the tags they are aliased to)
+    /// found along the way.
+    ///
+    /// # Arguments
+    ///
+    /// * `tags`: Tags to id.
+    /// * `request_sender`: The sender to use for the API calls.
+    ///
+    /// returns: (Option<Tag>, Vec<(String, String), Global>)
+    pub fn id_tag_with_aliases(
+        tags: &str,
+        request_sender: RequestSender,
+    ) -> (Option<Tag>, Vec<(String, String)>) {
+        let mut identifier = TagIdentifier::new(request_sender);
+        let tag = identifier.search_for_tag(tags).unwrap_or_else(|e| {
+            skip_item_error(&format!("\"{tags}\""), &e);
+            None
+        });
+        (tag, identifier.aliases)
     }
 
     /// Search for tag on e621.
@@ -178,38 +943,44 @@ impl TagIdentifier {
     ///
     /// * `tags`: Tags to search for.
     ///
-    /// returns: Tag
-    fn search_for_tag(&self, tags: &str) -> Tag {
+    /// returns: Result<Option<Tag>, E621Error>
+    fn search_for_tag(&mut self, tags: &str) -> Result<Option<Tag>, E621Error> {
         // Splits the tags and cycles through each one, checking if they are valid and searchable tags
         // If the tag isn't searchable, the tag will default and consider itself invalid. Which will
         // then be filtered through the last step.
-        let mut map = tags
-            .split(' ')
-            .map(|e| {
-                let temp = e.trim_start_matches('-');
-                match self.request_sender.get_tags_by_name(temp).first() {
-                    Some(entry) => self.create_tag(tags, entry),
-                    None => {
-                        if let Some(alias_tag) = self.get_tag_from_alias(temp) {
-                            self.create_tag(tags, &alias_tag)
-                        } else if temp.contains(':') {
-                            Tag::default()
-                        } else {
-                            self.exit_tag_failure(temp);
-                            unreachable!();
-                        }
+        let mut identified = Vec::new();
+        for tag in tags.split(' ') {
+            let temp = tag.trim_start_matches(['-', '~']);
+            let tag = match self.request_sender.get_tags_by_name(temp)?.first() {
+                Some(entry) => create_tag(tags, entry),
+                None => {
+                    if let Some(alias_tag) = self.get_tag_from_alias(temp)? {
+                        create_tag(tags, &alias_tag)
+                    } else if temp.contains(':') {
+                        Tag::default()
+                    } else {
+                        self.skip_tag_failure(tags, temp);
+                        return Ok(None);
                     }
                 }
-            })
-            .filter(|e| *e != Tag::default());
+            };
+
+            if tag != Tag::default() {
+                identified.push(tag);
+            }
+        }
 
-        // Tries to return any tag in the map with category special, return the last element otherwise.
+        // Tries to return any tag with category special, return the last element otherwise.
         // If returning the last element fails, assume the tag is syntax only and default.
-        map.find(|e| e.search_type == TagSearchType::Special)
-            .unwrap_or_else(|| {
-                map.last()
-                    .unwrap_or_else(|| Tag::new(tags, TagSearchType::General, TagType::General))
-            })
+        let special = identified
+            .iter()
+            .position(|e| e.search_type == TagSearchType::Special);
+        Ok(Some(match special {
+            Some(index) => identified.swap_remove(index),
+            None => identified
+                .pop()
+                .unwrap_or_else(|| Tag::new(tags, TagSearchType::General, TagType::General)),
+        }))
     }
 
     /// Checks if the tag is an alias and searches for the tag it is aliased to, returning it.
@@ -218,78 +989,65 @@ impl TagIdentifier {
     ///
     /// * `tag`: Alias to check for.
     ///
-    /// returns: Option<TagEntry>
-    fn get_tag_from_alias(&self, tag: &str) -> Option<TagEntry> {
-        let entry = match self.request_sender.query_aliases(tag) {
-            Some(e) => e.first().unwrap().clone(),
+    /// returns: Result<Option<TagEntry>, E621Error>
+    fn get_tag_from_alias(&mut self, tag: &str) -> Result<Option<TagEntry>, E621Error> {
+        let entry = match self
+            .request_sender
+            .query_aliases(tag)?
+            .and_then(|e| e.into_iter().next())
+        {
+            Some(e) => e,
             None => {
-                return None;
+                return Ok(None);
             }
         };
 
-        // Is there possibly a way to make this better?
-        Some(
-            self.request_sender
-                .get_tags_by_name(&entry.consequent_name)
-                .first()
-                .unwrap()
-                .clone(),
-        )
+        let tag_entry = self
+            .request_sender
+            .get_tags_by_name(&entry.consequent_name)?
+            .into_iter()
+            .next();
+        if tag_entry.is_some() {
+            self.aliases.push((tag.to_string(), entry.consequent_name));
+        }
+
+        Ok(tag_entry)
     }
 
-    /// Emergency exits if a tag isn't identified.
+    /// Skips the tags with a warning if one of them isn't identified.
     ///
     /// # Arguments
     ///
+    /// * `tags`: Tags being identified.
     /// * `tag`: Tag to log for.
-    fn exit_tag_failure(&self, tag: &str) {
-        error!("{tag} is invalid!");
+    fn skip_tag_failure(&self, tags: &str, tag: &str) {
+        skip_item(
+            &format!("\"{tags}\""),
+            &format!("The server API call was unable to find tag: {tag}!"),
+        );
         info!("The tag may be a typo, be sure to double check and ensure that the tag is correct.");
-        emergency_exit(format!("The server API call was unable to find tag: {tag}!").as_str());
-    }
-
-    /// Processes the tag type and creates the appropriate tag for it.
-    ///
-    /// # Arguments
-    ///
-    /// * `tags`: Tags to use.
-    /// * `tag_entry`: The tag entry related to the tags.
-    ///
-    /// returns: Tag
-    fn create_tag(&self, tags: &str, tag_entry: &TagEntry) -> Tag {
-        let tag_type = tag_entry.to_tag_type();
-        let category = match tag_type {
-            TagType::General => {
-                const CHARACTER_CATEGORY: u8 = 4;
-                if tag_entry.category == CHARACTER_CATEGORY {
-                    if tag_entry.post_count > 1500 {
-                        TagSearchType::General
-                    } else {
-                        TagSearchType::Special
-                    }
-                } else {
-                    TagSearchType::General
-                }
-            }
-            TagType::Artist => TagSearchType::Special,
-            _ => unreachable!(),
-        };
-
-        Tag::new(tags, category, tag_type)
     }
 }
 
 /// Parser that reads a tag file and parses the tags.
-struct TagParser {
+struct TagParser<'a> {
     /// Low-level parser for parsing raw data.
     parser: BaseParser,
     /// Request sender for any needed API calls.
     request_sender: RequestSender,
+    /// Whether general tags are identified through the API while parsing.
+    identify: bool,
+    /// The path of the tag file being parsed.
+    path: PathBuf,
+    /// Every tag file loaded so far.
+    included: &'a mut Vec<PathBuf>,
+    /// The aliases found while identifying the tags of the tag file.
+    aliases: Vec<ResolvedAlias>,
 }
 
-impl TagParser {
+impl TagParser<'_> {
     /// Parses each group with all tags tied to them before returning a vector with all groups in it.
-    pub(crate) fn parse_groups(&mut self) -> Result<Vec<Group>, Error> {
+    pub fn parse_groups(&mut self) -> Result<Vec<Group>, Error> {
         let mut groups: Vec<Group> = Vec::new();
         loop {
             self.parser.consume_whitespace();
@@ -297,12 +1055,17 @@ impl TagParser {
                 break;
             }
 
+            if self.parser.starts_with("#include") {
+                groups.append(&mut self.parse_include()?);
+                continue;
+            }
+
             if self.check_and_parse_comment() {
                 continue;
             }
 
             if self.parser.starts_with("[") {
-                groups.push(self.parse_group());
+                groups.extend(self.parse_group());
             } else {
                 self.parser.report_error("Tags must be in groups!");
             }
@@ -312,15 +1075,30 @@ impl TagParser {
     }
 
     /// Parses a group and all tags tied to it before returning the result.
-    fn parse_group(&mut self) -> Group {
+    ///
+    /// Returns [None] when the group name is invalid, after skipping the group up to the next one with a warning.
+    ///
+    /// returns: Option<Group>
+    fn parse_group(&mut self) -> Option<Group> {
         assert_eq!(self.parser.consume_char(), '[');
         let group_name = self.parser.consume_while(valid_group);
-        assert_eq!(self.parser.consume_char(), ']');
+        if !self.parser.starts_with("]") {
+            self.parser.report_error(&format!(
+                "Group \"{group_name}\" has an invalid name or is missing its closing bracket!"
+            ));
+            self.parser.consume_while(|e| e != '[');
+            return None;
+        }
+
+        self.parser.consume_char();
 
         let mut group = Group::new(group_name);
         self.parse_tags(&mut group);
+        if self.identify && group.options.implications() {
+            expand_implications(&mut group, &self.request_sender);
+        }
 
-        group
+        Some(group)
     }
 
     /// Parses all tags for a group and stores it.
@@ -332,10 +1110,25 @@ impl TagParser {
         let mut tags: Vec<Tag> = Vec::new();
         loop {
             self.parser.consume_whitespace();
+            // Includes end the group, so they are loaded by `parse_groups`.
+            if self.parser.starts_with("#include") {
+                break;
+            }
+
             if self.check_and_parse_comment() {
                 continue;
             }
 
+            if self.parser.starts_with("@") {
+                self.parse_option(&mut group.options);
+                continue;
+            }
+
+            if self.parser.starts_with("!") {
+                self.parse_whitelist(&mut group.options);
+                continue;
+            }
+
             if self.parser.starts_with("[") {
                 break;
             }
@@ -344,29 +1137,67 @@ impl TagParser {
                 break;
             }
 
-            tags.push(self.parse_tag(group.name()));
+            if let Some(tag) = self.parse_tag(group.name()) {
+                tags.push(tag);
+            }
         }
 
         group.tags = tags;
     }
 
+    /// Parses an include (e.g `#include artists.txt`) and loads the groups of the included tag file.
+    fn parse_include(&mut self) -> Result<Vec<Group>, Error> {
+        let line = self.parser.consume_while(valid_comment);
+        let include = line.trim_start_matches("#include").trim();
+        if include.is_empty() {
+            self.parser
+                .report_error("Include is missing the tag file to include!");
+            return Ok(Vec::new());
+        }
+
+        Ok(load_include(
+            &self.path,
+            include,
+            &self.request_sender,
+            self.identify,
+            self.included,
+        ))
+    }
+
     /// Parses a single tag and identifies it before returning the result.
     ///
+    /// Returns [None] when the tag is invalid, after skipping it with a warning.
+    ///
     /// # Arguments
     ///
     /// * `group_name`:  Group name to parse the tag for.
     ///
-    /// returns: Tag
-    fn parse_tag(&mut self, group_name: &str) -> Tag {
+    /// returns: Option<Tag>
+    fn parse_tag(&mut self, group_name: &str) -> Option<Tag> {
         match group_name {
             "artists" | "general" => {
                 let tag = self.parser.consume_while(valid_tag);
-                TagIdentifier::id_tag(tag.trim(), self.request_sender.clone())
+                if tag.is_empty() {
+                    self.parser.report_error("Invalid character in tag!");
+                    return None;
+                }
+
+                if !self.identify {
+                    return Some(Tag::new(tag.trim(), TagSearchType::None, TagType::General));
+                }
+
+                let (tag, mut aliases) =
+                    TagIdentifier::id_tag_with_aliases(tag.trim(), self.request_sender.clone());
+                self.aliases.append(&mut aliases);
+                tag
             }
             e => {
                 let temp_char = self.parser.next_char();
                 if !char::is_ascii_digit(&temp_char) && temp_char != '#' {
-                    panic!("Invalid tag type! Pools, sets, and single-post tags must be a number!");
+                    self.parser.report_error(
+                        "Invalid tag type! Pools, sets, and single-post tags must be a number!",
+                    );
+                    return None;
                 }
 
                 let tag = self.parser.consume_while(valid_id);
@@ -376,15 +1207,134 @@ impl TagParser {
                     "single-post" => TagType::Post,
                     _ => {
                         self.parser.report_error("Unknown tag type!");
-                        TagType::Unknown
+                        return None;
                     }
                 };
 
-                Tag::new(tag.trim(), TagSearchType::Special, tag_type)
+                Some(Tag::new(tag.trim(), TagSearchType::Special, tag_type))
             }
         }
     }
 
+    /// Parses a whitelist entry (e.g `!braeburned`) and stores it in the group's options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The group options to store the whitelisted tag in.
+    fn parse_whitelist(&mut self, options: &mut GroupOptions) {
+        assert_eq!(self.parser.consume_char(), '!');
+        let tag = self.parser.consume_while(valid_tag);
+        let tag = tag.trim();
+        if tag.is_empty() {
+            self.parser
+                .report_error("Whitelist entry is missing its tag!");
+            return;
+        }
+
+        options.whitelist.push(tag.to_lowercase());
+    }
+
+    /// Parses a group option (e.g `@only: png,jpg`) and stores it in the group's options.
+    ///
+    /// # Arguments
+    ///
+    /// * `options`: The group options to store the parsed option in.
+    fn parse_option(&mut self, options: &mut GroupOptions) {
+        assert_eq!(self.parser.consume_char(), '@');
+        let key = self.parser.consume_while(valid_option_key);
+        if self.parser.eof() || self.parser.consume_char() != ':' {
+            self.parser
+                .report_error(format!("Group option \"{key}\" is missing a `:`!").as_str());
+            return;
+        }
+
+        let value = self.parser.consume_while(valid_option_value);
+        let value = value.trim();
+        match key.as_str() {
+            "only" => options.only_file_types = Some(parse_list(value)),
+            "exclude" => options.exclude_file_types = Some(parse_list(value)),
+            "min_score" => options.min_score = self.parse_number(&key, value),
+            "min_fav_count" => options.min_fav_count = self.parse_number(&key, value),
+            "min_width" => options.min_width = self.parse_number(&key, value),
+            "min_height" => options.min_height = self.parse_number(&key, value),
+            "safe" => options.safe = self.parse_bool(&key, value),
+            "limit" => options.limit = self.parse_number(&key, value).map(|e| e.max(0) as usize),
+            "directory" => options.directory = Some(value.to_string()),
+            "favorite" => options.favorite = self.parse_bool(&key, value),
+            "upvote" => options.upvote = self.parse_bool(&key, value),
+            "incremental" => options.incremental = self.parse_bool(&key, value),
+            "order" => options.order = self.parse_order(&key, value),
+            "implications" => options.implications = self.parse_bool(&key, value),
+            _ => {
+                self.parser
+                    .report_error(format!("Unknown group option: {key}").as_str());
+            }
+        }
+    }
+
+    /// Parses the value of a numeric group option, reporting an error (and leaving the option unset) if it isn't a
+    /// number.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The option being parsed.
+    /// * `value`: The value to parse.
+    ///
+    /// returns: Option<i64>
+    fn parse_number(&mut self, key: &str, value: &str) -> Option<i64> {
+        let number = value.parse::<i64>().ok();
+        if number.is_none() {
+            self.parser
+                .report_error(format!("Group option \"{key}\" must be a number!").as_str());
+        }
+
+        number
+    }
+
+    /// Parses the value of a boolean group option, reporting an error (and leaving the option unset) if it isn't
+    /// `true` or `false`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The option being parsed.
+    /// * `value`: The value to parse.
+    ///
+    /// returns: Option<bool>
+    fn parse_bool(&mut self, key: &str, value: &str) -> Option<bool> {
+        let boolean = value.to_lowercase().parse::<bool>().ok();
+        if boolean.is_none() {
+            self.parser.report_error(
+                format!("Group option \"{key}\" must be `true` or `false`!").as_str(),
+            );
+        }
+
+        boolean
+    }
+
+    /// Parses the value of a collection order group option, reporting an error (and leaving the option unset) if it
+    /// isn't an order.
+    ///
+    /// # Arguments
+    ///
+    /// * `key`: The option being parsed.
+    /// * `value`: The value to parse.
+    ///
+    /// returns: Option<CollectionOrder>
+    fn parse_order(&mut self, key: &str, value: &str) -> Option<CollectionOrder> {
+        let order = CollectionOrder::from_name(value);
+        if order.is_none() {
+            self.parser.report_error(
+                format!(
+                    "Group option \"{key}\" must be one of {}!",
+                    CollectionOrder::NAMES.join(", ")
+                )
+                .as_str(),
+            );
+        }
+
+        order
+    }
+
     /// Checks if next character is comment identifier and parses it if it is.
     fn check_and_parse_comment(&mut self) -> bool {
         if self.parser.starts_with("#") {
@@ -444,6 +1394,46 @@ fn valid_group(c: char) -> bool {
     matches!(c, 'A'..='Z' | 'a'..='z' | '-')
 }
 
+/// Splits a comma separated option value into a lowercase list.
+///
+/// # Arguments
+///
+/// * `value`: The option value to split.
+///
+/// returns: Vec<String, Global>
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+/// Validates character for option key.
+///
+/// # Arguments
+///
+/// * `c`: The character to check.
+///
+/// returns: bool
+fn valid_option_key(c: char) -> bool {
+    matches!(c, 'a'..='z' | '_')
+}
+
+/// Validates character for option value.
+///
+/// # Arguments
+///
+/// * `c`: The character to check.
+///
+/// returns: bool
+fn valid_option_value(c: char) -> bool {
+    match c {
+        ' '..='\"' | '$'..='~' => true,
+        _ => c != '\n' && c != '\r' && c != '#' && c.is_alphanumeric(),
+    }
+}
+
 /// Validates character for comment.
 ///
 /// # Arguments
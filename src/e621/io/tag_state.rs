@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, read_to_string, write};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::e621::io::state_directory;
+use crate::e621::io::user_cache::now;
+
+/// Name of the file the state of every tag is saved in.
+pub const TAG_STATE_NAME: &str = "tag_state.json";
+
+/// What the downloader remembers about a tag between runs.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct TagRecord {
+    /// The id of the newest post grabbed by the tag.
+    #[serde(rename = "lastPostId")]
+    last_post_id: i64,
+    /// When the tag was last downloaded, in seconds since the unix epoch.
+    #[serde(rename = "lastRun")]
+    last_run: u64,
+}
+
+/// The state of every tag, saved between runs so groups with `@incremental: true` only grab posts newer than the ones
+/// the last run downloaded.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct TagState {
+    /// The state of every tag, by its lowercased name.
+    tags: HashMap<String, TagRecord>,
+    /// Whether any tags were updated since the state was loaded.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl TagState {
+    /// Loads the state, returning an empty state if there is none or it can't be read.
+    pub fn load() -> Self {
+        let path = state_directory().join(TAG_STATE_NAME);
+        if !path.exists() {
+            return TagState::default();
+        }
+
+        match read_to_string(path).map(|e| from_str::<TagState>(&e)) {
+            Ok(Ok(state)) => state,
+            Ok(Err(e)) => {
+                warn!("Unable to parse {TAG_STATE_NAME}: {e}");
+                TagState::default()
+            }
+            Err(e) => {
+                warn!("Unable to read {TAG_STATE_NAME}: {e}");
+                TagState::default()
+            }
+        }
+    }
+
+    /// Gets the id of the newest post the tag grabbed in an earlier run, if it was downloaded before.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The name of the tag.
+    ///
+    /// returns: Option<i64>
+    pub fn last_post_id(&self, tag: &str) -> Option<i64> {
+        self.tags.get(&tag.to_lowercase()).map(|e| e.last_post_id)
+    }
+
+    /// Remembers the newest post a tag grabbed, keeping the id of an earlier run if it's newer.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The name of the tag.
+    /// * `post_id`: The id of the newest post the tag grabbed.
+    pub fn update(&mut self, tag: &str, post_id: i64) {
+        let record = self.tags.entry(tag.to_lowercase()).or_default();
+        record.last_post_id = record.last_post_id.max(post_id);
+        record.last_run = now();
+        self.changed = true;
+    }
+
+    /// Adds the tags of another state (e.g one saved with grabbed posts), keeping the newest post of each tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The state to add.
+    pub fn merge(&mut self, other: &TagState) {
+        for (tag, other_record) in &other.tags {
+            let record = self.tags.entry(tag.clone()).or_default();
+            record.last_post_id = record.last_post_id.max(other_record.last_post_id);
+            record.last_run = record.last_run.max(other_record.last_run);
+            self.changed = true;
+        }
+    }
+
+    /// Writes the state to the state file, if any tags were updated.
+    ///
+    /// Tags with posts that failed to download keep the state saved by the last run, so the next run grabs those posts
+    /// again.
+    ///
+    /// # Arguments
+    ///
+    /// * `failed_tags`: The lowercased names of the tags with posts that failed to download.
+    pub fn save(&self, failed_tags: &HashSet<String>) -> Result<(), Error> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        let mut state = self.clone();
+        if !failed_tags.is_empty() {
+            let saved = TagState::load();
+            for tag in failed_tags {
+                match saved.tags.get(tag) {
+                    Some(record) => state.tags.insert(tag.clone(), record.clone()),
+                    None => state.tags.remove(tag),
+                };
+            }
+        }
+
+        create_dir_all(state_directory())?;
+        write(
+            state_directory().join(TAG_STATE_NAME),
+            to_string_pretty(&state)?,
+        )?;
+
+        Ok(())
+    }
+}
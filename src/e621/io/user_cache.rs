@@ -0,0 +1,130 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::fs::{create_dir_all, read_to_string, write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, to_string_pretty};
+
+use crate::e621::io::state_directory;
+
+/// Name of the file the looked up user ids are cached in.
+pub const USER_CACHE_NAME: &str = "user_cache.json";
+
+/// How long a cached user id is used before it's looked up again, in seconds (a week).
+pub const USER_CACHE_TTL: u64 = 7 * 24 * 60 * 60;
+
+/// A user id looked up from the API, along with when it was looked up.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+struct CachedUser {
+    /// The id of the user.
+    id: i64,
+    /// When the id was looked up, in seconds since the unix epoch.
+    #[serde(rename = "cachedAt")]
+    cached_at: u64,
+}
+
+/// A cache of the ids of users named in the blacklist (`user:<name>`), saved between runs so they don't have to be
+/// looked up from the API every run.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct UserCache {
+    /// The cached users, by their lowercased name.
+    users: HashMap<String, CachedUser>,
+    /// Whether users were added since the cache was loaded.
+    #[serde(skip)]
+    changed: bool,
+}
+
+impl UserCache {
+    /// Loads the cache, returning an empty cache if there is none or it can't be read.
+    pub fn load() -> Self {
+        let path = state_directory().join(USER_CACHE_NAME);
+        if !path.exists() {
+            return UserCache::default();
+        }
+
+        match read_to_string(path).map(|e| from_str::<UserCache>(&e)) {
+            Ok(Ok(cache)) => cache,
+            Ok(Err(e)) => {
+                warn!("Unable to parse {USER_CACHE_NAME}: {e}");
+                UserCache::default()
+            }
+            Err(e) => {
+                warn!("Unable to read {USER_CACHE_NAME}: {e}");
+                UserCache::default()
+            }
+        }
+    }
+
+    /// Gets the id of a user, if it was cached less than [USER_CACHE_TTL] ago.
+    ///
+    /// # Arguments
+    ///
+    /// * `username`: The name of the user.
+    ///
+    /// returns: Option<i64>
+    pub fn get(&self, username: &str) -> Option<i64> {
+        self.users
+            .get(&username.to_lowercase())
+            .filter(|e| now().saturating_sub(e.cached_at) < USER_CACHE_TTL)
+            .map(|e| e.id)
+    }
+
+    /// Caches the id of a user.
+    ///
+    /// # Arguments
+    ///
+    /// * `username`: The name of the user.
+    /// * `id`: The id of the user.
+    pub fn insert(&mut self, username: &str, id: i64) {
+        self.users.insert(
+            username.to_lowercase(),
+            CachedUser {
+                id,
+                cached_at: now(),
+            },
+        );
+        self.changed = true;
+    }
+
+    /// Writes the cache to the cache file, if any users were added.
+    pub fn save(&self) -> Result<(), Error> {
+        if !self.changed {
+            return Ok(());
+        }
+
+        create_dir_all(state_directory())?;
+        write(
+            state_directory().join(USER_CACHE_NAME),
+            to_string_pretty(self)?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The current time, in seconds since the unix epoch.
+pub(crate) fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_secs())
+        .unwrap_or_default()
+}
@@ -0,0 +1,191 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt::{Display, Formatter};
+
+use console::style;
+
+use crate::e621::error::E621Error;
+use crate::e621::io::tag::{Group, Tag, TagType};
+use crate::e621::sender::RequestSender;
+
+/// A problem found with an entry in the tag file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagProblem {
+    /// The tag doesn't exist on e621.
+    UnknownTag(String),
+    /// The tag is an alias, and should be replaced with the tag it is aliased to.
+    Alias { from: String, to: String },
+    /// The search is valid, but doesn't return any posts.
+    EmptySearch,
+    /// The pool, set, or post with the id doesn't exist.
+    NotFound(String),
+    /// The search has more tags than the account can search at once.
+    TooManyTags { tags: usize, limit: usize },
+}
+
+impl Display for TagProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TagProblem::UnknownTag(tag) => write!(f, "unknown tag \"{tag}\""),
+            TagProblem::Alias { from, to } => {
+                write!(f, "\"{from}\" is an alias, use \"{to}\" instead")
+            }
+            TagProblem::EmptySearch => write!(f, "search doesn't return any posts"),
+            TagProblem::NotFound(kind) => write!(f, "{kind} doesn't exist"),
+            TagProblem::TooManyTags { tags, limit } => write!(
+                f,
+                "search has {tags} tags, but your account can only search {limit} tags at once"
+            ),
+        }
+    }
+}
+
+/// Checks every entry of the parsed tag file against the API, collecting all problems instead of exiting on the first.
+pub struct TagValidator {
+    /// The sender used for the API calls.
+    request_sender: RequestSender,
+}
+
+impl TagValidator {
+    /// Creates a new validator.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for the API calls.
+    pub fn new(request_sender: RequestSender) -> Self {
+        TagValidator { request_sender }
+    }
+
+    /// Validates all groups and prints a report of the problems found.
+    ///
+    /// # Arguments
+    ///
+    /// * `groups`: The groups parsed from the tag file.
+    ///
+    /// returns: Result<usize, E621Error> (the amount of problems found)
+    pub fn validate(&self, groups: &[Group]) -> Result<usize, E621Error> {
+        let tag_query_limit = self.request_sender.tag_query_limit()?;
+        let mut problems = 0;
+        for group in groups {
+            info!(
+                "Validating group {} ({} entries)...",
+                style(format!("[{}]", group.name())).color256(39).italic(),
+                group.tags().len()
+            );
+
+            for tag in group.tags() {
+                for problem in self.validate_tag(tag, tag_query_limit)? {
+                    warn!("[{}] {}: {problem}", group.name(), tag.name());
+                    problems += 1;
+                }
+            }
+        }
+
+        if problems == 0 {
+            info!("The tag file is valid, no problems were found!");
+        } else {
+            warn!("Found {problems} problem(s) in the tag file...");
+        }
+
+        Ok(problems)
+    }
+
+    /// Validates a single entry.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The entry to validate.
+    /// * `tag_query_limit`: The most tags the account can search at once.
+    ///
+    /// returns: Result<Vec<TagProblem, Global>, E621Error>
+    fn validate_tag(
+        &self,
+        tag: &Tag,
+        tag_query_limit: usize,
+    ) -> Result<Vec<TagProblem>, E621Error> {
+        let (url_type_key, kind) = match tag.tag_type() {
+            TagType::Pool => ("pool", "pool"),
+            TagType::Set => ("set", "set"),
+            TagType::Post => ("single", "post"),
+            _ => return self.validate_search(tag.name(), tag_query_limit),
+        };
+
+        trace!("Checking {kind} {}...", tag.name());
+        if self.request_sender.entry_exists(tag.name(), url_type_key)? {
+            Ok(vec![])
+        } else {
+            Ok(vec![TagProblem::NotFound(format!(
+                "{kind} #{}",
+                tag.name()
+            ))])
+        }
+    }
+
+    /// Checks that a search doesn't have more tags than the account can search, then validates each tag of the search
+    /// and checks that the search returns posts.
+    ///
+    /// # Arguments
+    ///
+    /// * `search`: The space separated tags of the search.
+    /// * `tag_query_limit`: The most tags the account can search at once.
+    ///
+    /// returns: Result<Vec<TagProblem, Global>, E621Error>
+    fn validate_search(
+        &self,
+        search: &str,
+        tag_query_limit: usize,
+    ) -> Result<Vec<TagProblem>, E621Error> {
+        let tags = search.split_whitespace().count();
+        if tags > tag_query_limit {
+            return Ok(vec![TagProblem::TooManyTags {
+                tags,
+                limit: tag_query_limit,
+            }]);
+        }
+
+        let mut problems = Vec::new();
+        for tag in search.split(' ').map(|e| e.trim_start_matches(['-', '~'])) {
+            // Meta tags (e.g `rating:s`) and wildcards (e.g `fox*`) aren't real tags, so they can't be looked up.
+            if tag.is_empty() || tag.contains([':', '*']) {
+                continue;
+            }
+
+            trace!("Checking tag {tag}...");
+            if !self.request_sender.get_tags_by_name(tag)?.is_empty() {
+                continue;
+            }
+
+            let alias = self
+                .request_sender
+                .query_aliases(tag)?
+                .and_then(|e| e.into_iter().next());
+            problems.push(match alias {
+                Some(entry) => TagProblem::Alias {
+                    from: tag.to_string(),
+                    to: entry.consequent_name,
+                },
+                None => TagProblem::UnknownTag(tag.to_string()),
+            });
+        }
+
+        if problems.is_empty() && self.request_sender.bulk_search(search, 1)?.posts.is_empty() {
+            problems.push(TagProblem::EmptySearch);
+        }
+
+        Ok(problems)
+    }
+}
@@ -14,33 +14,74 @@
  * limitations under the License.
  */
 
-use std::cell::RefCell;
-use std::fs::{create_dir_all, write};
-use std::path::PathBuf;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::fs::{create_dir_all, read, read_to_string, remove_file, write, File};
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
-use std::time::Duration;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::channel;
+use std::thread::{scope, sleep};
+use std::time::{Duration, Instant, SystemTime};
 
-use anyhow::Context;
+use anyhow::{bail, Context, Error};
+use console::user_attended;
 use dialoguer::Confirm;
-use indicatif::{ProgressBar, ProgressDrawTarget};
+use fs2::available_space;
+use indicatif::{HumanBytes, ProgressBar, ProgressDrawTarget};
+use serde_json::to_string_pretty;
 
-use crate::e621::blacklist::Blacklist;
-use crate::e621::grabber::{Grabber, Shorten};
+use crate::args::Args;
+use crate::e621::blacklist::{read_local_blacklist, Blacklist};
+use crate::e621::cancel::is_cancelled;
+use crate::e621::dtext::dtext_to_markdown;
+use crate::e621::error::{skip_item_error, skipped_items, skipped_items_since, E621Error};
+use crate::e621::events::ProgressEvent;
+use crate::e621::filter::PostFilter;
+use crate::e621::grabber::{GrabbedPost, Grabber, PostCollection, Shorten};
+use crate::e621::io::file_attributes::write_post_attributes;
+use crate::e621::io::file_index::FileIndex;
+use crate::e621::io::grab_file::GrabFile;
+use crate::e621::io::report::{RequestStats, RunReport};
 use crate::e621::io::tag::Group;
-use crate::e621::io::{Config, Login};
-use crate::e621::sender::entries::UserEntry;
+use crate::e621::io::user_cache::now;
+use crate::e621::io::{state_directory, Config, Login};
+use crate::e621::sanitize::sanitize_name;
+use crate::e621::sender::entries::{CommentEntry, PostEntry, UserEntry};
 use crate::e621::sender::RequestSender;
+use crate::e621::tui::preview::{preview_post, supports_graphics};
+use crate::e621::tui::review::PostReviewer;
 use crate::e621::tui::{ProgressBarBuilder, ProgressStyleBuilder};
+use crate::json_logger::set_current_post;
 
-pub(crate) mod blacklist;
-pub(crate) mod grabber;
-pub(crate) mod io;
-pub(crate) mod sender;
+pub mod blacklist;
+pub mod cancel;
+pub mod dtext;
+pub mod error;
+pub mod events;
+pub mod filter;
+pub mod grabber;
+pub mod io;
+pub mod sanitize;
+pub mod sender;
 pub(crate) mod tui;
 
+/// Name of the file the artist info is saved to in artist collections.
+pub const ARTIST_INFO_NAME: &str = "artist_info.json";
+
+/// Extension of the files the notes of posts are saved to, replacing the extension of the downloaded file.
+pub const NOTES_EXTENSION: &str = "notes.json";
+
+/// Name of the file the wiki pages of the searched tags are saved to in the collections of tag searches.
+pub const WIKI_PAGE_NAME: &str = "README.md";
+
+/// The least amount of time between two requests for comments, so archiving comments doesn't flood the API with a
+/// request for every downloaded post.
+const COMMENT_REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+
 /// A web connector that manages how the API is called (through the [RequestSender]), how posts are grabbed
 /// (through [Grabber]), and how the posts are downloaded.
-pub(crate) struct E621WebConnector {
+pub struct E621WebConnector {
     /// The sender used for all API calls.
     request_sender: RequestSender,
     /// The config which is modified when grabbing posts.
@@ -51,57 +92,232 @@ pub(crate) struct E621WebConnector {
     grabber: Grabber,
     /// The user's blacklist.
     blacklist: Rc<RefCell<Blacklist>>,
+    /// The total amount of posts downloaded this run.
+    downloaded: u64,
+    /// The total amount of posts skipped this run.
+    skipped: u64,
+    /// When comments were last requested, used to space out the requests.
+    last_comment_request: Cell<Option<Instant>>,
+    /// The amount of items skipped before the run started, so only the run's own failures are reported.
+    skipped_items_before: usize,
+    /// The name the file of each post is saved as when downloads are flattened, claimed before any post is left out.
+    file_names: HashMap<i64, String>,
 }
 
 impl E621WebConnector {
     /// Creates instance of `Self` for grabbing and downloading posts.
-    pub(crate) fn new(request_sender: &RequestSender) -> Self {
+    pub fn new(request_sender: &RequestSender) -> Self {
         E621WebConnector {
             request_sender: request_sender.clone(),
             download_directory: Config::get().download_directory().to_string(),
             progress_bar: ProgressBar::hidden(),
             grabber: Grabber::new(request_sender.clone(), false),
             blacklist: Rc::new(RefCell::new(Blacklist::new(request_sender.clone()))),
+            downloaded: 0,
+            skipped: 0,
+            last_comment_request: Cell::new(None),
+            skipped_items_before: skipped_items(),
+            file_names: HashMap::new(),
         }
     }
 
-    /// Gets input and enters safe depending on user choice.
-    pub(crate) fn should_enter_safe_mode(&mut self) {
-        trace!("Prompt for safe mode...");
-        let confirm_prompt = Confirm::new()
-            .with_prompt("Should enter safe mode?")
-            .show_default(true)
-            .default(false)
-            .interact()
-            .with_context(|| {
-                error!("Failed to setup confirmation prompt!");
-                "Terminal unable to set up confirmation prompt..."
-            })
-            .unwrap();
+    /// Adds a filter deciding which searched posts are grabbed, which runs after the built-in filters.
+    ///
+    /// # Arguments
+    ///
+    /// * `filter`: The filter to add.
+    pub fn add_filter(&mut self, filter: Box<dyn PostFilter>) {
+        self.grabber.add_filter(filter);
+    }
 
-        trace!("Safe mode decision: {confirm_prompt}");
-        if confirm_prompt {
+    /// Enters safe mode depending on the config, or on user choice if the config doesn't set it.
+    ///
+    /// Only e621 has a safe mode (e926), so other instances skip the choice, and e926 itself is always in safe mode.
+    /// When `--yes` is passed or nobody is there to answer, safe mode is left off instead of asking.
+    pub fn should_enter_safe_mode(&mut self) -> Result<(), Error> {
+        if self.request_sender.is_safe() {
+            trace!("Requesting from e926, safe mode is always on...");
+            self.grabber.set_safe_mode(true);
+            return Ok(());
+        }
+
+        if !self.request_sender.has_safe_mode() {
+            trace!("The instance has no safe mode, skipping the choice...");
+            return Ok(());
+        }
+
+        let safe_mode = match Config::get().safe_mode() {
+            Some(safe_mode) => {
+                trace!("Safe mode set by config: {safe_mode}");
+                safe_mode
+            }
+            None if Args::get().serve().is_some() => {
+                trace!("Safe mode not set by config, leaving it off while serving...");
+                false
+            }
+            None if Args::get().yes() || !user_attended() => {
+                warn!(
+                    "Safe mode is off because it can't be asked for, set \"safeMode\" in the config to choose it."
+                );
+                false
+            }
+            None => {
+                trace!("Prompt for safe mode...");
+                let confirm_prompt = Confirm::new()
+                    .with_prompt("Should enter safe mode?")
+                    .show_default(true)
+                    .default(false)
+                    .interact()
+                    .context("Terminal unable to set up confirmation prompt...")?;
+
+                trace!("Safe mode decision: {confirm_prompt}");
+                confirm_prompt
+            }
+        };
+
+        if safe_mode {
             self.request_sender.update_to_safe();
             self.grabber.set_safe_mode(true);
         }
+
+        Ok(())
     }
 
     /// Processes the blacklist and tokenizes for use when grabbing posts.
-    pub(crate) fn process_blacklist(&mut self) {
-        let username = Login::get().username();
+    ///
+    /// The blacklist of the user's account (when logged in) is merged with the local blacklist file, if it exists.
+    pub fn process_blacklist(&mut self) -> Result<(), E621Error> {
+        let mut blacklist_lines = Vec::new();
+        let login = Login::get();
+        if !login.is_empty() {
+            let user: UserEntry = self
+                .request_sender
+                .get_entry_from_appended_id(login.username(), "user")?;
+            self.grabber.set_tag_query_limit(user.max_search_tags());
+            blacklist_lines.extend(user.blacklisted_tags);
+        } else {
+            trace!("Skipping account blacklist as user is not logged in...");
+        }
+
+        blacklist_lines.extend(read_local_blacklist()?);
+        let blacklist_tags = blacklist_lines.join("\n");
+        if !blacklist_tags.trim().is_empty() {
+            let blacklist = self.blacklist.clone();
+            blacklist
+                .borrow_mut()
+                .parse_blacklist(blacklist_tags)
+                .cache_users()?;
+            self.grabber.set_blacklist(blacklist);
+        }
+
+        Ok(())
+    }
+
+    /// Writes the blacklist of the logged in user's account to a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The file to write the blacklist to.
+    pub fn export_blacklist(&self, path: &Path) -> Result<(), E621Error> {
+        let user: UserEntry = self
+            .request_sender
+            .get_entry_from_appended_id(Login::get().username(), "user")?;
+        let blacklist = user.blacklisted_tags.unwrap_or_default();
+        write(path, format!("{}\n", blacklist.trim_end()))?;
+        info!(
+            "Exported {} blacklist lines to {}.",
+            blacklist.lines().filter(|e| !e.trim().is_empty()).count(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Replaces the blacklist of the logged in user's account with the blacklist in a file, after backing up the
+    /// current blacklist to the state directory.
+    ///
+    /// The file is imported as it is, comments included. The user is asked before the blacklist is replaced, unless
+    /// `--yes` was passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The file to read the blacklist from.
+    pub fn import_blacklist(&self, path: &Path) -> Result<(), Error> {
+        let contents = read_to_string(path)
+            .with_context(|| format!("Unable to read the blacklist from {}!", path.display()))?;
+        let blacklist = contents.trim_end();
         let user: UserEntry = self
             .request_sender
-            .get_entry_from_appended_id(username, "user");
-        if let Some(blacklist_tags) = user.blacklisted_tags {
-            if !blacklist_tags.is_empty() {
-                let blacklist = self.blacklist.clone();
-                blacklist
-                    .borrow_mut()
-                    .parse_blacklist(blacklist_tags)
-                    .cache_users();
-                self.grabber.set_blacklist(blacklist);
+            .get_entry_from_appended_id(Login::get().username(), "user")?;
+
+        create_dir_all(state_directory())?;
+        let backup = state_directory().join(format!("blacklist_backup_{}.txt", now()));
+        let current = user.blacklisted_tags.unwrap_or_default();
+        write(&backup, format!("{}\n", current.trim_end())).with_context(|| {
+            format!("Unable to back up your blacklist to {}!", backup.display())
+        })?;
+        info!(
+            "Backed up the blacklist of your account to {}.",
+            backup.display()
+        );
+
+        if !Args::get().yes() {
+            if !user_attended() {
+                bail!("Pass --yes to replace the blacklist of your account without being asked.");
             }
+
+            let confirmed = Confirm::new()
+                .with_prompt(format!(
+                    "Replace the blacklist of your account ({} lines) with {} ({} lines)?",
+                    current.lines().filter(|e| !e.trim().is_empty()).count(),
+                    path.display(),
+                    blacklist.lines().filter(|e| !e.trim().is_empty()).count()
+                ))
+                .default(false)
+                .interact()?;
+            if !confirmed {
+                info!("The blacklist of your account was left as it was.");
+                return Ok(());
+            }
+        }
+
+        self.request_sender.update_blacklist(user.id, blacklist)?;
+        info!(
+            "Imported {} blacklist lines from {} into your account.",
+            blacklist.lines().filter(|e| !e.trim().is_empty()).count(),
+            path.display()
+        );
+
+        Ok(())
+    }
+
+    /// Checks a post against the processed blacklist and logs which blacklist lines flag it.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post to check.
+    pub fn test_blacklist(&self, post_id: i64) -> Result<(), E621Error> {
+        let post: PostEntry = self
+            .request_sender
+            .get_entry_from_appended_id(&post_id.to_string(), "single")?;
+        let blacklist = self.blacklist.borrow();
+        if blacklist.is_empty() {
+            info!("The blacklist is empty, so post {post_id} isn't blacklisted.");
+            return Ok(());
+        }
+
+        let reasons = blacklist.explain_post(&post);
+        if reasons.is_empty() {
+            info!("Post {post_id} isn't blacklisted.");
+            return Ok(());
         }
+
+        info!("Post {post_id} is blacklisted by:");
+        for reason in reasons {
+            info!("  {reason}");
+        }
+
+        Ok(())
     }
 
     /// Creates `Grabber` and grabs all posts before returning a tuple containing all general posts and single posts
@@ -110,92 +326,770 @@ impl E621WebConnector {
     /// # Arguments
     ///
     /// * `groups`: The groups to grab from.
-    pub(crate) fn grab_all(&mut self, groups: &[Group]) {
+    pub fn grab_all(&mut self, groups: &[Group]) {
         trace!("Grabbing posts...");
+        self.grabber.initialize_spinner();
         self.grabber.grab_favorites();
         self.grabber.grab_posts_by_tags(groups);
+        self.grabber.finish_spinner();
+        self.grabber.save_audit_log();
+    }
+
+    /// Lets the user review the grabbed posts and deselect the ones they don't want to download.
+    pub fn review_posts(&mut self) -> Result<(), Error> {
+        trace!("Reviewing grabbed posts...");
+        let preview_sender = Args::get().preview().then_some(&self.request_sender);
+        PostReviewer::review(self.grabber.posts_mut(), preview_sender)
+    }
+
+    /// Creates a grab file from the grabbed posts, so they can be downloaded by a later run.
+    pub fn grab_file(&self) -> GrabFile {
+        GrabFile::new(
+            self.grabber.safe_mode(),
+            self.grabber.posts().clone(),
+            self.grabber.tag_state().clone(),
+        )
+    }
+
+    /// Uses the posts of a grab file instead of grabbing posts, entering safe mode if they were grabbed in it.
+    ///
+    /// # Arguments
+    ///
+    /// * `grab_file`: The grab file to download the posts of.
+    pub fn load_grab_file(&mut self, grab_file: GrabFile) {
+        if grab_file.safe_mode() && self.request_sender.has_safe_mode() {
+            self.request_sender.update_to_safe();
+        }
+
+        self.grabber.set_safe_mode(grab_file.safe_mode());
+        let (collections, tag_state) = grab_file.into_parts();
+        self.grabber.set_grabbed(collections, &tag_state);
     }
 
     /// Saves image to download directory.
-    fn save_image(&self, file_path: &str, bytes: &[u8]) {
-        write(file_path, bytes)
-            .with_context(|| {
-                error!("Failed to save image!");
-                "A downloaded image was unable to be saved..."
-            })
-            .unwrap();
-        trace!("Saved {file_path}...");
+    fn save_image(file_path: &Path, bytes: &[u8]) -> Result<(), E621Error> {
+        write(file_path, bytes).inspect_err(|_| error!("Failed to save image!"))?;
+        trace!("Saved {}...", file_path.display());
+        Ok(())
+    }
+
+    /// Downloads a post and saves it to the file path (see [E621WebConnector::save_post]), moving the progress bar as
+    /// each chunk of the file is read.
+    ///
+    /// The bar moves by the size of the post in total, even when the download fails or is repeated, so it still ends
+    /// at the total once every post is done.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to download.
+    /// * `file_path`: The path to save the post to.
+    /// * `progress_bar`: The progress bar shared by every download.
+    /// * `download`: Downloads the file of the post, reporting the bytes of every chunk it reads.
+    fn download_post(
+        post: &GrabbedPost,
+        file_path: &Path,
+        progress_bar: &ProgressBar,
+        download: impl Fn(&dyn Fn(u64)) -> Result<Vec<u8>, E621Error>,
+    ) -> Result<(), E621Error> {
+        let size = post.file_size().max(0) as u64;
+        let streamed = Cell::new(0);
+        let progress = |bytes: u64| {
+            let bytes = bytes.min(size - streamed.get());
+            streamed.set(streamed.get() + bytes);
+            progress_bar.inc(bytes);
+        };
+        let result = Self::save_post(post, file_path, || download(&progress));
+        progress_bar.inc(size - streamed.get());
+        result
     }
 
-    /// Removes invalid characters from directory path.
+    /// Downloads a post and saves it to the file path.
+    ///
+    /// The saved file is checked against the md5 of the post, and downloaded again up to [Config::verify_attempts]
+    /// times if it doesn't match. Then the id and source url of the post are saved in the extended attributes of the
+    /// file with [Config::write_file_attributes] on, and the file is dated to when the post was uploaded with
+    /// [Config::set_file_dates] on.
     ///
     /// # Arguments
     ///
-    /// * `dir_name`: Directory name to remove invalid chars from.
+    /// * `post`: The post to download.
+    /// * `file_path`: The path to save the post to.
+    /// * `download`: Downloads the file of the post.
+    fn save_post(
+        post: &GrabbedPost,
+        file_path: &Path,
+        download: impl Fn() -> Result<Vec<u8>, E621Error>,
+    ) -> Result<(), E621Error> {
+        if let Some(parent_path) = file_path.parent() {
+            create_dir_all(parent_path).inspect_err(|_| {
+                error!("Could not create directories for images!");
+                trace!("Path: \"{}\"", parent_path.display());
+            })?;
+        }
+
+        let attempts = Config::get().verify_attempts();
+        let mut attempt = 1;
+        loop {
+            let bytes = download()?;
+            Self::save_image(file_path, &bytes)?;
+            match Self::verify_post(post, file_path) {
+                Err(e @ E621Error::Checksum { .. }) if attempt < attempts => {
+                    warn!(
+                        "{e}, downloading it again (attempt {} of {attempts})...",
+                        attempt + 1
+                    );
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+                Ok(()) => break,
+            }
+        }
+
+        if Config::get().write_file_attributes() {
+            if let Err(e) = write_post_attributes(file_path, post.id(), post.url()) {
+                Self::warn_attributes_failed(file_path, &e);
+            }
+        }
+
+        if let Some(created_at) = post.created_at().filter(|_| Config::get().set_file_dates()) {
+            if let Err(e) = Self::set_file_date(file_path, created_at) {
+                warn!("Unable to set the date of {}: {e}", file_path.display());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Logs that the attributes of a file couldn't be written, warning only the first time so a file system without
+    /// extended attributes doesn't fill the log.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path`: The path of the file.
+    /// * `error`: Why the attributes couldn't be written.
+    fn warn_attributes_failed(file_path: &Path, error: &std::io::Error) {
+        static WARNED: AtomicBool = AtomicBool::new(false);
+        if WARNED.swap(true, Ordering::Relaxed) {
+            trace!(
+                "Unable to write the attributes of {}: {error}",
+                file_path.display()
+            );
+        } else {
+            warn!(
+                "Unable to write the attributes of {}: {error}, the file system may not support them...",
+                file_path.display()
+            );
+        }
+    }
+
+    /// Sets the modified date of a file.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path`: The path of the file.
+    /// * `date`: The date to set.
+    ///
+    /// returns: Result<(), E621Error>
+    fn set_file_date(file_path: &Path, date: SystemTime) -> Result<(), E621Error> {
+        File::options()
+            .write(true)
+            .open(file_path)?
+            .set_modified(date)?;
+        Ok(())
+    }
+
+    /// Checks that a saved post matches the md5 the API reported for it, and deletes the file if it doesn't.
+    ///
+    /// Posts without a known md5 (e.g samples) pass, as does every post when [Config::verify_attempts] is `0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post that was saved.
+    /// * `file_path`: The path the post was saved to.
+    ///
+    /// returns: Result<(), E621Error>
+    fn verify_post(post: &GrabbedPost, file_path: &Path) -> Result<(), E621Error> {
+        let Some(expected) = post.md5().filter(|_| Config::get().verify_attempts() > 0) else {
+            return Ok(());
+        };
+
+        let actual = format!("{:x}", md5::compute(read(file_path)?));
+        if actual.eq_ignore_ascii_case(expected) {
+            return Ok(());
+        }
+
+        remove_file(file_path)?;
+        Err(E621Error::Checksum {
+            url: post.url().to_string(),
+            expected: expected.to_string(),
+            actual,
+        })
+    }
+
+    /// Downloads the posts of a collection, and calls `finish` with the result of every post once it's downloaded.
+    ///
+    /// Posts are downloaded on [Config::download_threads] threads at once when the sender can download files from
+    /// other threads (see [RequestSender::file_downloader]), and one after another otherwise. Either way, `finish` is
+    /// called on this thread.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts to download, with the paths to save them to.
+    /// * `finish`: Called with every post, the path it was saved to, and the result of the download.
+    fn download_posts_of_collection(
+        &self,
+        posts: &[(&GrabbedPost, PathBuf)],
+        mut finish: impl FnMut(&GrabbedPost, &Path, Result<(), E621Error>),
+    ) {
+        let threads = Config::get().download_threads().min(posts.len());
+        let downloader = (threads > 1)
+            .then(|| self.request_sender.file_downloader())
+            .flatten();
+        let Some(downloader) = downloader else {
+            for (post, file_path) in posts {
+                if is_cancelled() {
+                    break;
+                }
+
+                set_current_post(Some(post.id()));
+                let result = Self::download_post(post, file_path, &self.progress_bar, |progress| {
+                    self.request_sender.download_image(post.url(), progress)
+                });
+                finish(post, file_path, result);
+            }
+            return;
+        };
+
+        trace!("Downloading {} posts on {threads} threads...", posts.len());
+        let next_post = AtomicUsize::new(0);
+        let progress_bar = &self.progress_bar;
+        let (sender, receiver) = channel();
+        scope(|scope| {
+            for _ in 0..threads {
+                let sender = sender.clone();
+                let downloader = downloader.clone();
+                let next_post = &next_post;
+                scope.spawn(move || {
+                    while !is_cancelled() {
+                        let index = next_post.fetch_add(1, Ordering::Relaxed);
+                        let Some((post, file_path)) = posts.get(index) else {
+                            break;
+                        };
+
+                        set_current_post(Some(post.id()));
+                        let result =
+                            Self::download_post(post, file_path, progress_bar, |progress| {
+                                downloader.download(post.url(), progress)
+                            });
+                        if sender.send((index, result)).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+
+            // Only the workers hold senders from here on, so the results end once every worker is done.
+            drop(sender);
+            for (index, result) in receiver {
+                let (post, file_path) = &posts[index];
+                set_current_post(Some(post.id()));
+                finish(post, file_path, result);
+            }
+        });
+    }
+
+    /// Logs and reports the result of downloading a post, and once it's downloaded, favorites, upvotes, and saves the
+    /// notes and comments of the post as the config and its group ask for.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection_name`: The name of the collection the post is in.
+    /// * `post`: The post that was downloaded.
+    /// * `file_path`: The path the post was saved to.
+    /// * `result`: The result of the download.
+    /// * `logged_in`: Whether the user is logged in, which favoriting and upvoting need.
+    ///
+    /// returns: bool
+    fn finish_post(
+        &self,
+        collection_name: &str,
+        post: &GrabbedPost,
+        file_path: &Path,
+        result: Result<(), E621Error>,
+        logged_in: bool,
+    ) -> bool {
+        if let Err(e) = result {
+            self.progress_bar
+                .suspend(|| skip_item_error(&format!("post {}", post.id()), &e));
+            ProgressEvent::PostSkipped {
+                collection: collection_name,
+                file: post.name(),
+                reason: "error",
+            }
+            .emit();
+            return false;
+        }
+
+        if post.degraded() {
+            trace!("{} was downloaded as a sample (degraded)...", post.name());
+        }
+
+        self.run_post_actions(post, file_path, logged_in, false);
+        ProgressEvent::PostDownloaded {
+            collection: collection_name,
+            file: post.name(),
+            bytes: post.file_size(),
+            degraded: post.degraded(),
+        }
+        .emit();
+        true
+    }
+
+    /// Favorites, upvotes, and saves the notes and comments of a downloaded post as the config and its group ask for.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The downloaded post.
+    /// * `file_path`: The path the post was saved to.
+    /// * `logged_in`: Whether the user is logged in, which favoriting and upvoting need.
+    /// * `only_missing`: Whether notes and comments are only saved when they weren't saved before.
+    fn run_post_actions(
+        &self,
+        post: &GrabbedPost,
+        file_path: &Path,
+        logged_in: bool,
+        only_missing: bool,
+    ) {
+        if logged_in && post.favorite() {
+            if let Err(e) = self.favorite_post(post) {
+                self.progress_bar
+                    .suspend(|| skip_item_error(&format!("favoriting post {}", post.id()), &e));
+            }
+        }
+
+        if logged_in && post.upvote() {
+            if let Err(e) = self.upvote_post(post) {
+                self.progress_bar
+                    .suspend(|| skip_item_error(&format!("upvoting post {}", post.id()), &e));
+            }
+        }
+
+        let missing = |path: PathBuf| !only_missing || (file_path.exists() && !path.exists());
+        if Config::get().save_notes()
+            && post.has_notes()
+            && missing(file_path.with_extension(NOTES_EXTENSION))
+        {
+            if let Err(e) = self.save_notes(post, file_path) {
+                self.progress_bar
+                    .suspend(|| skip_item_error(&format!("notes of post {}", post.id()), &*e));
+            }
+        }
+
+        if Config::get().save_comments() != "none"
+            && post.comment_count() > 0
+            && missing(Self::comments_path(file_path))
+        {
+            if let Err(e) = self.save_comments(post, file_path) {
+                self.progress_bar
+                    .suspend(|| skip_item_error(&format!("comments of post {}", post.id()), &*e));
+            }
+        }
+    }
+
+    /// Saves the info and external links of the artists a collection was grabbed from to `artist_info.json` in the
+    /// collection's directory.
+    ///
+    /// Every plain tag of the collection's search is looked up as an artist, and tags that aren't artists are left
+    /// out (see [PostCollection::searched_tags]).
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The artist collection.
+    /// * `directory`: The directory of the collection.
+    fn save_artist_info(&self, collection: &PostCollection, directory: &Path) -> Result<(), Error> {
+        let mut artists = Vec::new();
+        for tag in collection.searched_tags() {
+            if let Some(artist) = self.request_sender.get_artist(tag)? {
+                artists.push(artist);
+            }
+        }
+
+        if artists.is_empty() {
+            trace!("No artists found for \"{}\"...", collection.name());
+            return Ok(());
+        }
+
+        create_dir_all(directory)?;
+        let path = directory.join(self.collection_file_name(collection, ARTIST_INFO_NAME));
+        write(&path, to_string_pretty(&artists)?)?;
+        trace!("Saved artist info to {}...", path.display());
+        Ok(())
+    }
+
+    /// Saves the wiki pages of the tags a collection was searched with to [WIKI_PAGE_NAME] in the collection's
+    /// directory, with a section for every tag.
+    ///
+    /// The pages are converted from the DText markup e621 writes them in to markdown (see [dtext_to_markdown]), and
+    /// each section starts with a heading for the tag.
+    ///
+    /// Tags without a wiki page (and qualifiers like `rating:s`) are left out.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection of the tag search.
+    /// * `directory`: The directory of the collection.
+    fn save_wiki_pages(&self, collection: &PostCollection, directory: &Path) -> Result<(), Error> {
+        let mut readme = String::new();
+        for tag in collection.searched_tags() {
+            if let Some(page) = self.request_sender.get_wiki_page(tag)? {
+                readme.push_str(&format!(
+                    "# {}\n\n{}\n\n",
+                    page.title.replace('_', " "),
+                    dtext_to_markdown(&page.body, Config::get().instance_url()).trim_end()
+                ));
+            }
+        }
+
+        if readme.is_empty() {
+            trace!("No wiki pages found for \"{}\"...", collection.name());
+            return Ok(());
+        }
+
+        create_dir_all(directory)?;
+        let path = directory.join(self.collection_file_name(collection, WIKI_PAGE_NAME));
+        write(&path, format!("{}\n", readme.trim_end()))?;
+        trace!("Saved wiki pages to {}...", path.display());
+        Ok(())
+    }
+
+    /// Saves the notes of a post (their regions and text) next to the downloaded file, replacing its extension with
+    /// `.notes.json`.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to save the notes of.
+    /// * `file_path`: The path the post was saved to.
+    fn save_notes(&self, post: &GrabbedPost, file_path: &Path) -> Result<(), Error> {
+        let notes = self.request_sender.get_notes(post.id())?;
+        if notes.is_empty() {
+            trace!("Post {} has no active notes...", post.id());
+            return Ok(());
+        }
+
+        let path = file_path.with_extension(NOTES_EXTENSION);
+        write(&path, to_string_pretty(&notes)?)?;
+        trace!("Saved {} notes to {}...", notes.len(), path.display());
+        Ok(())
+    }
+
+    /// Saves the comments of a post next to the downloaded file, as JSON (`.comments.json`) or markdown
+    /// (`.comments.md`) depending on the `saveComments` option, replacing the extension of the file.
+    ///
+    /// Requests are spaced at least [COMMENT_REQUEST_INTERVAL] apart.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to save the comments of.
+    /// * `file_path`: The path the post was saved to.
+    fn save_comments(&self, post: &GrabbedPost, file_path: &Path) -> Result<(), Error> {
+        if let Some(last_request) = self.last_comment_request.get() {
+            if let Some(wait) = COMMENT_REQUEST_INTERVAL.checked_sub(last_request.elapsed()) {
+                sleep(wait);
+            }
+        }
+
+        let comments = self.request_sender.get_comments(post.id());
+        self.last_comment_request.set(Some(Instant::now()));
+        let comments = comments?;
+        if comments.is_empty() {
+            trace!("Post {} has no visible comments...", post.id());
+            return Ok(());
+        }
+
+        let contents = match Config::get().save_comments() {
+            "markdown" => Self::comments_to_markdown(post.id(), &comments),
+            _ => to_string_pretty(&comments)?,
+        };
+        let path = Self::comments_path(file_path);
+        write(&path, contents)?;
+        trace!("Saved {} comments to {}...", comments.len(), path.display());
+        Ok(())
+    }
+
+    /// Gets the path the comments of a post are saved to, next to its file (`.comments.json` or `.comments.md`,
+    /// depending on the `saveComments` option).
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path`: The path the post was saved to.
+    ///
+    /// returns: PathBuf
+    fn comments_path(file_path: &Path) -> PathBuf {
+        match Config::get().save_comments() {
+            "markdown" => file_path.with_extension("comments.md"),
+            _ => file_path.with_extension("comments.json"),
+        }
+    }
+
+    /// Writes a comment thread as markdown, with a heading for the post and a section for every comment.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post the comments are on.
+    /// * `comments`: The comments, oldest first.
     ///
     /// returns: String
-    fn remove_invalid_chars(&self, dir_name: &str) -> String {
-        dir_name
-            .chars()
-            .map(|e| match e {
-                '?' | ':' | '*' | '<' | '>' | '\"' | '|' => '_',
-                _ => e,
+    fn comments_to_markdown(post_id: i64, comments: &[CommentEntry]) -> String {
+        let mut markdown = format!("# Comments on post #{post_id}\n");
+        for comment in comments {
+            markdown.push_str(&format!(
+                "\n---\n\n**{}** on {} (score: {})\n\n{}\n",
+                comment.creator_name,
+                comment.created_at,
+                comment.score,
+                comment.body.replace("\r\n", "\n").trim_end()
+            ));
+        }
+
+        markdown
+    }
+
+    /// Adds a downloaded post to the user's favorites.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to favorite.
+    fn favorite_post(&self, post: &GrabbedPost) -> Result<(), E621Error> {
+        self.request_sender.add_favorite(post.id())?;
+        trace!("Added post {} to favorites...", post.id());
+        Ok(())
+    }
+
+    /// Upvotes a downloaded post.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to upvote.
+    fn upvote_post(&self, post: &GrabbedPost) -> Result<(), E621Error> {
+        self.request_sender.upvote(post.id())?;
+        trace!("Upvoted post {}...", post.id());
+        Ok(())
+    }
+
+    /// Gets the directory the posts of a collection are downloaded to.
+    ///
+    /// On Windows, this is an extended-length path (see [extended_length_path]), so long collection names (e.g pool
+    /// titles) don't cross the path length limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection to get the directory of.
+    ///
+    /// returns: PathBuf
+    fn collection_path(&self, collection: &PostCollection) -> PathBuf {
+        if Config::get().flatten_downloads() {
+            return extended_length_path(PathBuf::from(&self.download_directory));
+        }
+
+        let static_path: PathBuf = [
+            &self.download_directory,
+            collection.category(),
+            &sanitize_name(collection.name()),
+        ]
+        .iter()
+        .collect();
+        extended_length_path(static_path)
+    }
+
+    /// Gets the name a file shared by the whole collection (e.g [ARTIST_INFO_NAME]) is saved as, which is prefixed with
+    /// the name of the collection when downloads are flattened, so collections don't overwrite each other's files.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection the file belongs to.
+    /// * `name`: The name of the file.
+    ///
+    /// returns: String
+    fn collection_file_name(&self, collection: &PostCollection, name: &str) -> String {
+        if Config::get().flatten_downloads() {
+            sanitize_name(&format!("{} {name}", collection.name()))
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Claims a file name for a post in the flattened download directory, adding the id of the post to the name if
+    /// another post already claimed it this run (e.g pages of two pools with the same name).
+    ///
+    /// # Arguments
+    ///
+    /// * `claimed`: The id of the post that claimed each file name so far.
+    /// * `name`: The name of the file.
+    /// * `id`: The id of the post.
+    ///
+    /// returns: String
+    fn claim_file_name(claimed: &mut HashMap<String, i64>, name: String, id: i64) -> String {
+        let name = match claimed.get(&name) {
+            Some(owner) if *owner != id => {
+                let path = Path::new(&name);
+                let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                match path.extension() {
+                    Some(ext) => format!("{stem} ({id}).{}", ext.to_string_lossy()),
+                    None => format!("{stem} ({id})"),
+                }
+            }
+            _ => name,
+        };
+
+        claimed.insert(name.clone(), id);
+        name
+    }
+
+    /// Claims the file name of every grabbed post when downloads are flattened, before any post is left out, so the
+    /// posts already downloaded are found under the same names the rest are downloaded as.
+    fn claim_file_names(&mut self) {
+        self.file_names.clear();
+        if !Config::get().flatten_downloads() {
+            return;
+        }
+
+        let mut claimed = HashMap::new();
+        for post in self.grabber.posts().iter().flat_map(|e| e.posts()) {
+            let name = Self::claim_file_name(&mut claimed, sanitize_name(post.name()), post.id());
+            self.file_names.insert(post.id(), name);
+        }
+    }
+
+    /// The name the file of the post is saved as, which is the name claimed for it when downloads are flattened.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to get the file name of.
+    ///
+    /// returns: String
+    fn file_name(&self, post: &GrabbedPost) -> String {
+        match self.file_names.get(&post.id()) {
+            Some(name) => name.clone(),
+            None => sanitize_name(post.name()),
+        }
+    }
+
+    /// Leaves out the posts whose files were already downloaded, so the post count and download size shown before
+    /// downloading only cover new posts.
+    ///
+    /// With [Config::scan_downloads] on, posts whose md5 matches any file under the download directory are left out
+    /// as well (see [FileIndex]).
+    ///
+    /// The posts left out are counted as skipped, and returned with the path of their file so they can still be
+    /// favorited, upvoted, and have their notes and comments saved (see [finish_left_out_posts]).
+    ///
+    /// returns: Vec<(GrabbedPost, PathBuf), Global>
+    ///
+    /// [finish_left_out_posts]: Self::finish_left_out_posts
+    fn leave_out_downloaded_posts(&mut self) -> Vec<(GrabbedPost, PathBuf)> {
+        self.claim_file_names();
+        let index = Config::get().scan_downloads().then(|| {
+            info!("Indexing the files in the download directory...");
+            let index = FileIndex::scan(Path::new(&self.download_directory));
+            info!("Indexed {} files...", index.len());
+            index
+        });
+        let is_indexed = |post: &GrabbedPost| {
+            index
+                .as_ref()
+                .zip(post.md5())
+                .is_some_and(|(index, md5)| index.contains(md5))
+        };
+        let mut left_out_posts = Vec::new();
+        let downloaded: Vec<HashSet<i64>> = self
+            .grabber
+            .posts()
+            .iter()
+            .map(|collection| {
+                let path = self.collection_path(collection);
+                collection
+                    .posts()
+                    .iter()
+                    .filter_map(|e| {
+                        let file_path = path.join(self.file_name(e));
+                        (file_path.exists() || is_indexed(e)).then(|| {
+                            left_out_posts.push((e.clone(), file_path));
+                            e.id()
+                        })
+                    })
+                    .collect()
             })
-            .collect()
+            .collect();
+        let mut left_out = 0;
+        for (collection, downloaded) in self.grabber.posts_mut().iter_mut().zip(downloaded) {
+            collection
+                .posts_mut()
+                .retain(|e| !downloaded.contains(&e.id()));
+            left_out += downloaded.len();
+        }
+
+        if left_out > 0 {
+            info!("{left_out} posts were already downloaded and are left out...");
+            self.skipped += left_out as u64;
+        }
+
+        left_out_posts
+    }
+
+    /// Favorites, upvotes, and saves the notes and comments of posts that were left out because they were already
+    /// downloaded, as the config and their group ask for.
+    ///
+    /// Notes and comments are only saved when the file is in place and they weren't saved before.
+    ///
+    /// # Arguments
+    ///
+    /// * `posts`: The posts that were left out, with the path of their file.
+    fn finish_left_out_posts(&self, posts: &[(GrabbedPost, PathBuf)]) {
+        let logged_in = self.request_sender.is_authenticated();
+        for (post, file_path) in posts {
+            if is_cancelled() {
+                break;
+            }
+
+            self.run_post_actions(post, file_path, logged_in, true);
+        }
     }
 
     /// Processes `PostSet` and downloads all posts from it.
-    fn download_collection(&mut self) {
+    ///
+    /// A post that fails to download is skipped with a warning, and the rest of the posts are still downloaded.
+    ///
+    /// returns: HashSet<String, RandomState> (the lowercased names of the collections with posts that failed to
+    /// download)
+    fn download_collection(&mut self) -> HashSet<String> {
+        let mut downloaded = 0;
+        let mut skipped = 0;
+        let mut failed_collections = HashSet::new();
+        let logged_in = self.request_sender.is_authenticated();
+        if !logged_in
+            && self
+                .grabber
+                .posts()
+                .iter()
+                .flat_map(|e| e.posts())
+                .any(|e| e.favorite() || e.upvote())
+        {
+            warn!("Posts from groups with `favorite` or `upvote` set are left alone, as you aren't logged in...");
+        }
+
         for collection in self.grabber.posts().iter() {
+            if is_cancelled() {
+                info!("Download was cancelled...");
+                break;
+            }
+
             let collection_name = collection.name();
             let collection_category = collection.category();
             let collection_posts = collection.posts();
             let collection_count = collection_posts.len();
             let short_collection_name = collection.shorten("...");
 
-            #[cfg(unix)]
-            let static_path: PathBuf = [
-                &self.download_directory,
-                collection.category(),
-                &self.remove_invalid_chars(collection_name),
-            ]
-            .iter()
-            .collect();
-
-            #[cfg(windows)]
-            let mut static_path: PathBuf = [
-                &self.download_directory,
-                collection.category(),
-                &self.remove_invalid_chars(collection_name),
-            ]
-            .iter()
-            .collect();
-
-            // This is put here to attempt to shorten the length of the path if it passes window's
-            // max path length.
-            #[cfg(windows)]
-            const MAX_PATH: usize = 260; // Defined in Windows documentation.
-
-            #[cfg(windows)]
-            let start_path_len = static_path.as_os_str().len();
-
-            #[cfg(windows)]
-            if start_path_len >= MAX_PATH {
-                static_path = [
-                    &self.download_directory,
-                    collection_category,
-                    &self.remove_invalid_chars(&collection.shorten('_')),
-                ]
-                .iter()
-                .collect();
-
-                let new_len = static_path.as_os_str().len();
-                if new_len >= MAX_PATH {
-                    error!("Path is too long and crosses the {MAX_PATH} char limit.\
-                       Please relocate the program to a directory closer to the root drive directory.");
-                    trace!("Path length: {new_len}");
-                }
-            }
+            let static_path = self.collection_path(collection);
 
             trace!("Printing Collection Info:");
             trace!("Collection Name:            \"{collection_name}\"");
@@ -203,47 +1097,72 @@ impl E621WebConnector {
             trace!("Collection Post Length:     \"{collection_count}\"");
             trace!(
                 "Static file path for this collection: \"{}\"",
-                static_path.to_str().unwrap()
+                static_path.display()
             );
 
+            if Config::get().save_artist_info() && collection.is_artist() && collection_count > 0 {
+                if let Err(e) = self.save_artist_info(collection, &static_path) {
+                    self.progress_bar.suspend(|| {
+                        skip_item_error(&format!("artist info of \"{collection_name}\""), &*e)
+                    });
+                }
+            }
+
+            if Config::get().save_wiki_pages()
+                && collection.tag_type().is_some()
+                && collection_count > 0
+            {
+                if let Err(e) = self.save_wiki_pages(collection, &static_path) {
+                    self.progress_bar.suspend(|| {
+                        skip_item_error(&format!("wiki pages of \"{collection_name}\""), &*e)
+                    });
+                }
+            }
+
+            let mut pending_posts = Vec::new();
             for post in collection_posts {
-                let file_path: PathBuf = [
-                    &static_path.to_str().unwrap().to_string(),
-                    &self.remove_invalid_chars(post.name()),
-                ]
-                .iter()
-                .collect();
+                if is_cancelled() {
+                    break;
+                }
+
+                let file_path = static_path.join(self.file_name(post));
 
                 if file_path.exists() {
                     self.progress_bar
                         .set_message("Duplicate found: skipping... ");
                     self.progress_bar.inc(post.file_size() as u64);
+                    self.run_post_actions(post, &file_path, logged_in, true);
+                    ProgressEvent::PostSkipped {
+                        collection: collection_name,
+                        file: post.name(),
+                        reason: "duplicate",
+                    }
+                    .emit();
+                    skipped += 1;
                     continue;
                 }
 
-                self.progress_bar
-                    .set_message(format!("Downloading: {short_collection_name} "));
-
-                let parent_path = file_path.parent().unwrap();
-                create_dir_all(parent_path)
-                    .with_context(|| {
-                        error!("Could not create directories for images!");
-                        format!(
-                            "Directory path unable to be created...\nPath: \"{}\"",
-                            parent_path.to_str().unwrap()
-                        )
-                    })
-                    .unwrap();
-
-                let bytes = self
-                    .request_sender
-                    .download_image(post.url(), post.file_size());
-                self.save_image(file_path.to_str().unwrap(), &bytes);
-                self.progress_bar.inc(post.file_size() as u64);
+                pending_posts.push((post, file_path));
             }
 
+            self.progress_bar
+                .set_message(format!("Downloading: {short_collection_name} "));
+            self.download_posts_of_collection(&pending_posts, |post, file_path, result| {
+                if self.finish_post(collection_name, post, file_path, result, logged_in) {
+                    downloaded += 1;
+                } else {
+                    skipped += 1;
+                    failed_collections.insert(collection_name.to_lowercase());
+                }
+            });
+
+            set_current_post(None);
             trace!("Collection {collection_name} is finished downloading...");
         }
+
+        self.downloaded += downloaded;
+        self.skipped += skipped;
+        failed_collections
     }
 
     /// Initializes the progress bar for downloading process.
@@ -265,16 +1184,193 @@ impl E621WebConnector {
     }
 
     /// Downloads tuple of general posts and single posts.
-    pub(crate) fn download_posts(&mut self) {
+    ///
+    /// returns: Result<(), Error>
+    pub fn download_posts(&mut self) -> Result<(), Error> {
+        let left_out_posts = self.leave_out_downloaded_posts();
+
         // Initializes the progress bar for downloading.
         let length = self.get_total_file_size();
-        trace!("Total file size for all images grabbed is {length}KB");
-        self.initialize_progress_bar(length);
-        self.download_collection();
-        self.progress_bar.finish_and_clear();
+        trace!(
+            "Total file size for all images grabbed is {}",
+            HumanBytes(length)
+        );
+        if is_cancelled() || !self.confirm_download(length)? {
+            info!("Download was cancelled...");
+        } else if !self.check_free_space(length) {
+            info!("Download was aborted due to insufficient disk space...");
+        } else {
+            self.initialize_progress_bar(length);
+            ProgressEvent::DownloadStarted {
+                posts: self.get_total_post_count(),
+                bytes: length,
+            }
+            .emit();
+            self.finish_left_out_posts(&left_out_posts);
+            let failed_collections = self.download_collection();
+            self.progress_bar.finish_and_clear();
+            if !is_cancelled() {
+                self.grabber.save_tag_state(&failed_collections);
+            }
+
+            self.save_report(length);
+        }
+
+        self.log_request_metrics();
+        ProgressEvent::RunFinished {
+            downloaded: self.downloaded,
+            skipped: self.skipped,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Logs how many requests were sent during the run and how long they took, with a line for every endpoint in the
+    /// log file.
+    fn log_request_metrics(&self) {
+        let metrics = self.request_sender.metrics();
+        let total = metrics.total();
+        info!(
+            "Sent {} requests ({} failed, {} retried), received {} with an average response time of {}ms.",
+            total.requests,
+            total.failures,
+            self.request_sender.retries(),
+            HumanBytes(total.bytes),
+            total.average_latency().as_millis()
+        );
+        for (endpoint, metrics) in metrics.endpoints() {
+            trace!(
+                "{endpoint}: {} requests, {} failed, {} received, {}ms average",
+                metrics.requests,
+                metrics.failures,
+                HumanBytes(metrics.bytes),
+                metrics.average_latency().as_millis()
+            );
+        }
     }
 
-    /// Gets the total size (in KB) of every post image to be downloaded.
+    /// Saves the report of this run so it can be viewed later.
+    ///
+    /// # Arguments
+    ///
+    /// * `length`: The total bytes grabbed.
+    fn save_report(&self, length: u64) {
+        let mut report = RunReport::new();
+        report.collections = self.grabber.posts().len();
+        report.downloaded = self.downloaded;
+        report.skipped = self.skipped;
+        report.total_bytes = length;
+        report.blacklist_hits = self.blacklist.borrow().hits();
+        report.requests = self
+            .request_sender
+            .metrics()
+            .endpoints()
+            .iter()
+            .map(|(endpoint, metrics)| RequestStats {
+                endpoint: endpoint.clone(),
+                requests: metrics.requests,
+                failures: metrics.failures,
+                bytes: metrics.bytes,
+                average_ms: metrics.average_latency().as_millis() as u64,
+            })
+            .collect();
+        report.retries = self.request_sender.retries();
+        report.failures = skipped_items_since(self.skipped_items_before);
+        if let Err(e) = report.save() {
+            warn!("Unable to save the run report: {e}");
+        }
+    }
+
+    /// Shows the total post count and estimated download size, then asks the user whether to continue.
+    ///
+    /// The prompt is skipped (and the download confirmed) when `--yes` or `--serve` is passed, and an error is returned
+    /// instead of asking when nobody is there to answer.
+    ///
+    /// # Arguments
+    ///
+    /// * `length`: The total bytes to download.
+    ///
+    /// returns: Result<bool, Error>
+    fn confirm_download(&self, length: u64) -> Result<bool, Error> {
+        let post_count = self.get_total_post_count();
+        info!(
+            "Grabbed {} posts with an estimated download size of {}.",
+            console::style(post_count).cyan().italic(),
+            console::style(HumanBytes(length)).cyan().italic()
+        );
+
+        if Args::get().yes() || Args::get().serve().is_some() || post_count == 0 {
+            return Ok(true);
+        }
+
+        if !user_attended() {
+            bail!("Pass --yes to start downloading without being asked.");
+        }
+
+        if post_count == 1 && Args::get().preview() && supports_graphics() {
+            if let Some(post) = self.grabber.posts().iter().flat_map(|e| e.posts()).next() {
+                preview_post(&self.request_sender, post);
+            }
+        }
+
+        trace!("Prompt for download confirmation...");
+        let confirm_prompt = Confirm::new()
+            .with_prompt("Start downloading?")
+            .show_default(true)
+            .default(true)
+            .interact()
+            .context("Terminal unable to set up confirmation prompt...")?;
+
+        trace!("Download decision: {confirm_prompt}");
+        Ok(confirm_prompt)
+    }
+
+    /// Compares the total download size against the space available on the download volume.
+    ///
+    /// If the download won't fit, a warning is logged and, depending on the configured insufficient space action, the
+    /// download is either continued (`"warn"`) or aborted (`"abort"`).
+    ///
+    /// # Arguments
+    ///
+    /// * `length`: The total bytes to download.
+    ///
+    /// returns: bool
+    fn check_free_space(&self, length: u64) -> bool {
+        // The download directory may not exist yet, so the closest existing ancestor is checked instead.
+        let directory = Path::new(&self.download_directory);
+        let existing = directory
+            .ancestors()
+            .find(|e| e.exists())
+            .unwrap_or_else(|| Path::new("."));
+        let available = match available_space(existing) {
+            Ok(available) => available,
+            Err(e) => {
+                warn!("Unable to check free disk space: {e}");
+                return true;
+            }
+        };
+
+        trace!("Available space on download volume is {available} bytes");
+        if available >= length {
+            return true;
+        }
+
+        warn!(
+            "The download needs {} but only {} is available on the download volume!",
+            console::style(HumanBytes(length)).cyan().italic(),
+            console::style(HumanBytes(available)).cyan().italic()
+        );
+
+        Config::get().insufficient_space_action() != "abort"
+    }
+
+    /// Gets the total count of posts to be downloaded.
+    fn get_total_post_count(&self) -> usize {
+        self.grabber.posts().iter().map(|e| e.posts().len()).sum()
+    }
+
+    /// Gets the total size (in bytes) of every post image to be downloaded.
     fn get_total_file_size(&self) -> u64 {
         self.grabber
             .posts()
@@ -283,3 +1379,45 @@ impl E621WebConnector {
             .sum()
     }
 }
+
+/// Converts a path into an absolute extended-length path (e.g `\\?\C:\downloads\Pools\...`), which Windows doesn't
+/// limit to 260 characters like normal paths.
+///
+/// Windows uses extended-length paths as they are, without turning `/` into `\`, so the separators are normalized
+/// here.
+///
+/// # Arguments
+///
+/// * `path`: The path to convert.
+///
+/// returns: PathBuf
+#[cfg(windows)]
+fn extended_length_path(path: PathBuf) -> PathBuf {
+    const PREFIX: &str = r"\\?\";
+    const UNC_PREFIX: &str = r"\\?\UNC\";
+
+    let Ok(path) = std::path::absolute(&path) else {
+        return path;
+    };
+
+    let path = path.to_string_lossy().replace('/', "\\");
+    if path.starts_with(PREFIX) {
+        PathBuf::from(path)
+    } else if let Some(share) = path.strip_prefix(r"\\") {
+        PathBuf::from(format!("{UNC_PREFIX}{share}"))
+    } else {
+        PathBuf::from(format!("{PREFIX}{path}"))
+    }
+}
+
+/// Other platforms don't limit the length of paths, so the path is used as it is.
+///
+/// # Arguments
+///
+/// * `path`: The path to convert.
+///
+/// returns: PathBuf
+#[cfg(not(windows))]
+fn extended_length_path(path: PathBuf) -> PathBuf {
+    path
+}
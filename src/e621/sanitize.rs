@@ -0,0 +1,170 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use crate::e621::io::Config;
+
+/// The most bytes most file systems (e.g ext4, and NTFS in UTF-16 units) allow in a single file or directory name.
+pub const MAX_FILE_NAME_BYTES: usize = 255;
+
+/// The least bytes names can be cut down to, which leaves room for md5 names and pool page numbers.
+pub const MIN_FILE_NAME_BYTES: usize = 40;
+
+/// What invalid characters are replaced with when the config doesn't set a valid replacement.
+pub const DEFAULT_REPLACEMENT: &str = "_";
+
+/// Characters that can't be used in names on Windows, along with the path separators.
+const INVALID_CHARS: [char; 9] = ['/', '\\', '?', ':', '*', '<', '>', '"', '|'];
+
+/// Names Windows reserves for devices, which can't be used as a name even with an extension (e.g `CON.png`).
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// The longest extension that's kept when a name is cut down.
+const MAX_EXTENSION_BYTES: usize = 10;
+
+/// Checks if a character can't be used in a file or directory name.
+///
+/// # Arguments
+///
+/// * `c`: The character to check.
+///
+/// returns: bool
+pub fn is_invalid_char(c: char) -> bool {
+    c.is_control() || INVALID_CHARS.contains(&c)
+}
+
+/// Makes a name safe to use as a file or directory name on every platform, with the replacement and max length set
+/// in the config (see [sanitize_name_with]).
+///
+/// # Arguments
+///
+/// * `name`: The name to sanitize.
+///
+/// returns: String
+pub fn sanitize_name(name: &str) -> String {
+    let config = Config::get();
+    sanitize_name_with(
+        name,
+        config.file_name_replacement(),
+        config.max_file_name_length(),
+    )
+}
+
+/// Makes a name safe to use as a file or directory name on every platform.
+///
+/// Invalid characters (including path separators) are replaced, the name is cut down to the max length while keeping
+/// its extension, trailing dots and spaces (which Windows drops) are removed, and names Windows reserves for devices
+/// (e.g `CON`) get the replacement added to them.
+///
+/// # Arguments
+///
+/// * `name`: The name to sanitize.
+/// * `replacement`: What invalid characters are replaced with.
+/// * `max_bytes`: The most bytes the name can take.
+///
+/// returns: String
+pub fn sanitize_name_with(name: &str, replacement: &str, max_bytes: usize) -> String {
+    let mut sanitized = String::with_capacity(name.len());
+    for c in name.chars() {
+        if is_invalid_char(c) {
+            sanitized.push_str(replacement);
+        } else {
+            sanitized.push(c);
+        }
+    }
+
+    let mut sanitized = truncate_name(&sanitized, max_bytes);
+    sanitized.truncate(sanitized.trim_end_matches(['.', ' ']).len());
+
+    let stem_len = sanitized.split('.').next().unwrap_or_default().len();
+    if RESERVED_NAMES
+        .iter()
+        .any(|e| e.eq_ignore_ascii_case(&sanitized[..stem_len]))
+    {
+        sanitized.insert_str(stem_len, replacement);
+    }
+
+    if sanitized.is_empty() {
+        sanitized.push_str(replacement);
+    }
+
+    sanitized
+}
+
+/// Cuts a name down to at most `max_bytes` bytes, keeping its extension if it has one.
+///
+/// # Arguments
+///
+/// * `name`: The name to cut down.
+/// * `max_bytes`: The most bytes to keep.
+///
+/// returns: String
+fn truncate_name(name: &str, max_bytes: usize) -> String {
+    if name.len() <= max_bytes {
+        return name.to_string();
+    }
+
+    match name.rsplit_once('.') {
+        Some((stem, extension))
+            if !stem.is_empty()
+                && extension.len() <= MAX_EXTENSION_BYTES
+                && extension.len() < max_bytes =>
+        {
+            let stem = truncate_bytes(stem, max_bytes - extension.len() - 1);
+            format!("{stem}.{extension}")
+        }
+        _ => truncate_bytes(name, max_bytes).to_string(),
+    }
+}
+
+/// Cuts text down to at most `max_chars` characters, without splitting a character.
+///
+/// # Arguments
+///
+/// * `text`: The text to cut down.
+/// * `max_chars`: The most characters to keep.
+///
+/// returns: &str
+pub fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+/// Cuts text down to at most `max_bytes` bytes, without splitting a character (e.g in Japanese pool titles, where
+/// every character takes 3 bytes).
+///
+/// # Arguments
+///
+/// * `text`: The text to cut down.
+/// * `max_bytes`: The most bytes to keep.
+///
+/// returns: &str
+pub fn truncate_bytes(text: &str, max_bytes: usize) -> &str {
+    if text.len() <= max_bytes {
+        return text;
+    }
+
+    let mut end = max_bytes;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    &text[..end]
+}
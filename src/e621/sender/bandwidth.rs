@@ -0,0 +1,69 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Mutex, PoisonError};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Limits how fast response bodies are read, so a run doesn't take up the whole connection.
+///
+/// The limit can be shared between threads, the bodies read at the same time then share the speed.
+#[derive(Debug)]
+pub struct BandwidthLimit {
+    /// The most bytes read every second.
+    bytes_per_sec: u64,
+    /// When the bytes read so far are done being paid for, reading waits until then.
+    next_read: Mutex<Instant>,
+}
+
+impl BandwidthLimit {
+    /// Creates a limit of the amount of bytes every second.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes_per_sec`: The most bytes read every second.
+    ///
+    /// returns: BandwidthLimit
+    pub fn new(bytes_per_sec: u64) -> Self {
+        BandwidthLimit {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next_read: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Waits until the bytes that were read are allowed by the limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes`: The amount of bytes that were read.
+    pub fn consume(&self, bytes: usize) {
+        let read_until = {
+            let mut next_read = self
+                .next_read
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            // Time spent not reading isn't saved up, so a pause between files isn't followed by a burst.
+            let start = (*next_read).max(Instant::now());
+            *next_read = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+            *next_read
+        };
+
+        let delay = read_until.saturating_duration_since(Instant::now());
+        if !delay.is_zero() {
+            sleep(delay);
+        }
+    }
+}
@@ -0,0 +1,537 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::error::Error;
+use std::io;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use anyhow::anyhow;
+use httpdate::parse_http_date;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{AUTHORIZATION, DATE, ETAG, IF_NONE_MATCH, USER_AGENT};
+use reqwest::{Method, Proxy, StatusCode};
+
+use crate::e621::error::E621Error;
+use crate::e621::io::Config;
+use crate::e621::sender::bandwidth::BandwidthLimit;
+use crate::e621::sender::throttle::RateLimit;
+
+/// Default user agent value.
+const USER_AGENT_VALUE: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (by ",
+    env!("CARGO_PKG_AUTHORS"),
+    " on e621)"
+);
+
+/// The amount of bytes read from the body of a downloaded file at once.
+const CHUNK_SIZE: usize = 16 * 1024;
+
+/// A fully read response from the API.
+pub struct ApiResponse {
+    /// The status code of the response.
+    pub status: StatusCode,
+    /// The body of the response.
+    pub body: Vec<u8>,
+    /// The ETag of the response, if the server sent one.
+    pub etag: Option<String>,
+    /// The request budget the server reported, if any.
+    pub rate_limit: Option<RateLimit>,
+    /// The time the server sent the response at, if it reported one.
+    pub date: Option<SystemTime>,
+}
+
+/// A client that the [RequestSender](super::RequestSender) sends all of its requests through.
+///
+/// [SenderClient] sends the requests to the server, but any other client (e.g one answering with recorded responses)
+/// can be used in its place, so the grabber, blacklist, and tag identification can run without talking to e621.
+pub trait ApiClient {
+    /// Sends a GET request and reads the full response.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login of the client is attached to the request.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<ApiResponse, E621Error>;
+
+    /// Sends a GET request that the server only answers with a body if it doesn't match the ETag, and with
+    /// [StatusCode::NOT_MODIFIED] otherwise.
+    ///
+    /// Clients that can't send conditional requests send a normal request instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login of the client is attached to the request.
+    /// * `etag`: The ETag of the cached response.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn get_if_none_match(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+        _etag: &str,
+    ) -> Result<ApiResponse, E621Error> {
+        self.get(url, query, authenticated)
+    }
+
+    /// Sends an unauthenticated GET request for a file (e.g the image or video of a post) and reads the full response,
+    /// calling `progress` with the amount of bytes of every chunk as it's read.
+    ///
+    /// Unlike API responses, files are read within the `maxDownloadSpeed` of the config. Clients that don't stream or
+    /// limit how fast they download send a normal request instead, and report the whole body at once.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url of the file.
+    /// * `progress`: Called with the amount of bytes read each time a chunk is read.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn download(&self, url: &str, progress: &dyn Fn(u64)) -> Result<ApiResponse, E621Error> {
+        let response = self.get(url, &[], false)?;
+        progress(response.body.len() as u64);
+        Ok(response)
+    }
+
+    /// Sends an authenticated PATCH request with a form body and reads the full response.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `form`: The fields of the form.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn patch(&self, url: &str, form: &[(&str, &str)]) -> Result<ApiResponse, E621Error>;
+
+    /// Sends an authenticated POST request with a form body and reads the full response.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `form`: The fields of the form.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn post(&self, url: &str, form: &[(&str, &str)]) -> Result<ApiResponse, E621Error>;
+
+    /// If the client sends login information with authenticated requests.
+    fn is_authenticated(&self) -> bool;
+
+    /// The amount of requests the client had to send again (e.g over HTTP/1.1 after HTTP/2 failed).
+    fn retries(&self) -> u64 {
+        0
+    }
+
+    /// A client sending requests the same way that can be used from other threads, so files can be downloaded in
+    /// parallel.
+    ///
+    /// Clients that return [None] (the default) download files one after another.
+    fn shared(&self) -> Option<Arc<dyn ApiClient + Send + Sync>> {
+        None
+    }
+}
+
+/// The client used for all requests to the server by the [Grabber], [Blacklist], [E621WebConnector], etc.
+///
+/// Clones of the client share their connections, the HTTP/1.1 fallback, and the retry counter.
+#[derive(Clone)]
+pub struct SenderClient {
+    /// The [Client] that sends the requests.
+    client: Client,
+    /// The [Client] that sends the requests over HTTP/1.1, used once a request over HTTP/2 failed.
+    http1_client: Client,
+    /// Whether requests are sent through the HTTP/1.1 client.
+    use_http1: Arc<AtomicBool>,
+    /// The amount of requests that were sent again.
+    retries: Arc<AtomicU64>,
+    /// Whether a request has succeeded over HTTP/2, after which failed requests are no longer blamed on HTTP/2.
+    http2_confirmed: Arc<AtomicBool>,
+    /// The limit of how fast downloaded files are read, if the config sets one.
+    bandwidth_limit: Option<Arc<BandwidthLimit>>,
+    /// The user agent sent with every request, [USER_AGENT_VALUE] followed by the suffix in the config.
+    user_agent: String,
+    /// The base64 encrypted username and password of the user. This is passed only through the [AUTHORIZATION] header
+    /// of the request and is a highly secured method of login through client.
+    auth: String,
+}
+
+impl SenderClient {
+    /// Creates root client.
+    ///
+    /// The settings come from the loaded config, or from the config file if it isn't loaded yet (e.g while the setup
+    /// wizard runs before there is a config).
+    ///
+    /// An [E621Error::Config] is returned if the client can't be set up with the settings, so a configured proxy is
+    /// never left out.
+    pub fn new(auth: String) -> Result<Self, E621Error> {
+        let config = Config::loaded_or_peek();
+        let user_agent = match config.user_agent_suffix() {
+            Some(suffix) => format!("{USER_AGENT_VALUE} {suffix}"),
+            None => String::from(USER_AGENT_VALUE),
+        };
+        trace!("SenderClient initializing with user agent \"{user_agent}\"");
+
+        Ok(SenderClient {
+            client: SenderClient::build_client(&config, true)?,
+            http1_client: SenderClient::build_client(&config, false)?,
+            use_http1: Arc::new(AtomicBool::new(false)),
+            retries: Arc::new(AtomicU64::new(0)),
+            http2_confirmed: Arc::new(AtomicBool::new(false)),
+            bandwidth_limit: config
+                .max_download_speed()
+                .map(|e| Arc::new(BandwidthLimit::new(e * 1024))),
+            user_agent,
+            auth,
+        })
+    }
+
+    /// Runs client through a builder to give it required settings.
+    /// Cookies aren't stored in the client, TCP_NODELAY is on, gzip and brotli responses are decoded, and the timeouts,
+    /// keepalive, and idle connections come from the config.
+    /// Requests go through the proxy in the config, or the proxy in the `HTTPS_PROXY` and `HTTP_PROXY` environment
+    /// variables when it's unset.
+    ///
+    /// # Arguments
+    ///
+    /// * `config`: The config the settings come from.
+    /// * `http2`: Whether the client talks HTTP/2 right away instead of HTTP/1.1.
+    ///
+    /// returns: Result<Client, E621Error>
+    fn build_client(config: &Config, http2: bool) -> Result<Client, E621Error> {
+        let mut builder = Client::builder().use_rustls_tls();
+        if http2 {
+            builder = builder.http2_prior_knowledge();
+        } else {
+            builder = builder.http1_only();
+        }
+
+        if let Some(proxy) = config.proxy() {
+            let proxy = Proxy::all(proxy).map_err(|e| {
+                E621Error::Config(anyhow!("The proxy in the config can't be used: {e}"))
+            })?;
+            builder = builder.proxy(proxy);
+        }
+
+        if config.connect_timeout_secs() > 0 {
+            builder = builder.connect_timeout(Duration::from_secs(config.connect_timeout_secs()));
+        }
+
+        if let Some(max_idle_connections) = config.max_idle_connections() {
+            builder = builder.pool_max_idle_per_host(max_idle_connections);
+        }
+
+        builder
+            .tcp_keepalive(seconds(config.keepalive_secs()))
+            .tcp_nodelay(true)
+            .gzip(true)
+            .brotli(true)
+            .timeout(seconds(config.timeout_secs()))
+            .build()
+            .map_err(|e| E621Error::Config(anyhow!("Unable to set up the HTTP client: {e}")))
+    }
+
+    /// The [Client] requests are currently sent through.
+    fn client(&self) -> &Client {
+        if self.use_http1.load(Ordering::Relaxed) {
+            &self.http1_client
+        } else {
+            &self.client
+        }
+    }
+
+    /// Sends a request built by `build` and reads the full response.
+    ///
+    /// # Arguments
+    ///
+    /// * `build`: Builds the request with the given client.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn send(&self, build: impl Fn(&Client) -> RequestBuilder) -> Result<ApiResponse, E621Error> {
+        read_response(self.send_request(build)?, |e| Ok(e.bytes()?.to_vec()))
+    }
+
+    /// Sends a request built by `build`, and if the HTTP/2 handshake fails (e.g behind a proxy that only speaks
+    /// HTTP/1.1), switches to an HTTP/1.1 client for this and every following request.
+    ///
+    /// Only GET requests are sent again after a failure that may not have been caused by HTTP/2, so requests that
+    /// change something (e.g favoriting or voting on a post) are never sent twice.
+    ///
+    /// # Arguments
+    ///
+    /// * `build`: Builds the request with the given client.
+    ///
+    /// returns: Result<Response, E621Error>
+    fn send_request(
+        &self,
+        build: impl Fn(&Client) -> RequestBuilder,
+    ) -> Result<Response, E621Error> {
+        let request = build(self.client()).build()?;
+        let retryable = request.method() == Method::GET;
+        let response = match self.client().execute(request) {
+            Ok(response) => {
+                self.http2_confirmed.store(true, Ordering::Relaxed);
+                response
+            }
+            Err(error)
+                if !self.use_http1.load(Ordering::Relaxed)
+                    && is_http2_failure(
+                        &error,
+                        self.http2_confirmed.load(Ordering::Relaxed),
+                        retryable,
+                    ) =>
+            {
+                warn!("Request over HTTP/2 failed ({error}), falling back to HTTP/1.1...");
+                self.use_http1.store(true, Ordering::Relaxed);
+                self.retries.fetch_add(1, Ordering::Relaxed);
+                build(&self.http1_client).send()?
+            }
+            Err(error) => return Err(error.into()),
+        };
+
+        Ok(response)
+    }
+}
+
+/// Converts an amount of seconds from the config into a duration, where `0` means no duration.
+///
+/// # Arguments
+///
+/// * `secs`: The amount of seconds.
+///
+/// returns: Option<Duration>
+fn seconds(secs: u64) -> Option<Duration> {
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Checks if a request failed because the server (or a proxy in front of it) doesn't speak HTTP/2.
+///
+/// Until a request has succeeded over HTTP/2, any failure of a retryable request after connecting is blamed on it,
+/// since servers that only speak HTTP/1.1 tend to answer the HTTP/2 handshake by closing the connection. Requests that
+/// aren't retryable only count as HTTP/2 failures when the error is about the HTTP/2 protocol itself.
+///
+/// # Arguments
+///
+/// * `error`: The error the request failed with.
+/// * `http2_confirmed`: Whether a request has already succeeded over HTTP/2.
+/// * `retryable`: Whether the request can safely be sent twice (e.g a GET request).
+///
+/// returns: bool
+fn is_http2_failure(error: &reqwest::Error, http2_confirmed: bool, retryable: bool) -> bool {
+    if error.is_timeout() || error.is_connect() {
+        return false;
+    }
+
+    if !http2_confirmed && retryable && error.is_request() {
+        return true;
+    }
+
+    let mut source: Option<&dyn Error> = Some(error);
+    while let Some(error) = source {
+        let message = error.to_string().to_lowercase();
+        if message.contains("http2")
+            || message.contains("http/2")
+            || message.contains("h2 protocol")
+        {
+            return true;
+        }
+
+        source = error.source();
+    }
+
+    false
+}
+
+/// Reads the full response.
+///
+/// # Arguments
+///
+/// * `response`: The response to read.
+/// * `read_body`: Reads the body of the response.
+///
+/// returns: Result<ApiResponse, E621Error>
+fn read_response(
+    response: Response,
+    read_body: impl FnOnce(Response) -> Result<Vec<u8>, E621Error>,
+) -> Result<ApiResponse, E621Error> {
+    let etag = response
+        .headers()
+        .get(ETAG)
+        .and_then(|e| e.to_str().ok())
+        .map(String::from);
+    let rate_limit = RateLimit::from_headers(response.headers());
+    let date = response
+        .headers()
+        .get(DATE)
+        .and_then(|e| e.to_str().ok())
+        .and_then(|e| parse_http_date(e).ok());
+    let status = response.status();
+    let body = read_body(response)?;
+    Ok(ApiResponse {
+        status,
+        body,
+        etag,
+        rate_limit,
+        date,
+    })
+}
+
+/// Reads the full body of a downloaded file a chunk at a time, reporting each chunk as it's read and waiting between
+/// chunks so the speed stays under the limit, if there is one.
+///
+/// # Arguments
+///
+/// * `body`: The body to read.
+/// * `bandwidth_limit`: The limit of how fast the body is read, if any.
+/// * `progress`: Called with the amount of bytes read each time a chunk is read.
+///
+/// returns: Result<Vec<u8, Global>, Error>
+fn read_file(
+    mut body: Response,
+    bandwidth_limit: Option<&BandwidthLimit>,
+    progress: &dyn Fn(u64),
+) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(body.content_length().unwrap_or_default() as usize);
+    let mut chunk = vec![0; CHUNK_SIZE];
+    loop {
+        let read = body.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+
+        bytes.extend_from_slice(&chunk[..read]);
+        progress(read as u64);
+        if let Some(bandwidth_limit) = bandwidth_limit {
+            bandwidth_limit.consume(read);
+        }
+    }
+}
+
+/// Converts an error from reading a response body back into the request error it wraps, so failed downloads aren't
+/// reported as file system errors.
+///
+/// # Arguments
+///
+/// * `error`: The error reading the body failed with.
+///
+/// returns: E621Error
+fn body_error(error: io::Error) -> E621Error {
+    let kind = error.kind();
+    match error.into_inner() {
+        Some(inner) => match inner.downcast::<reqwest::Error>() {
+            Ok(error) => E621Error::Network(*error),
+            Err(inner) => E621Error::Io(io::Error::new(kind, inner)),
+        },
+        None => E621Error::Io(kind.into()),
+    }
+}
+
+impl ApiClient for SenderClient {
+    /// Sends the request with the user agent header attached, ensuring that all requests sent have the proper user
+    /// agent info. The authorization header is only attached when the request is authenticated and a login exists.
+    fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<ApiResponse, E621Error> {
+        self.send(|client| {
+            let request = client
+                .get(url)
+                .header(USER_AGENT, self.user_agent.as_str())
+                .query(query);
+            if authenticated && !self.auth.is_empty() {
+                request.header(AUTHORIZATION, self.auth.as_str())
+            } else {
+                request
+            }
+        })
+    }
+
+    fn get_if_none_match(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+        etag: &str,
+    ) -> Result<ApiResponse, E621Error> {
+        self.send(|client| {
+            let request = client
+                .get(url)
+                .header(USER_AGENT, self.user_agent.as_str())
+                .header(IF_NONE_MATCH, etag)
+                .query(query);
+            if authenticated && !self.auth.is_empty() {
+                request.header(AUTHORIZATION, self.auth.as_str())
+            } else {
+                request
+            }
+        })
+    }
+
+    fn download(&self, url: &str, progress: &dyn Fn(u64)) -> Result<ApiResponse, E621Error> {
+        let response = self
+            .send_request(|client| client.get(url).header(USER_AGENT, self.user_agent.as_str()))?;
+        read_response(response, |e| {
+            read_file(e, self.bandwidth_limit.as_deref(), progress).map_err(body_error)
+        })
+    }
+
+    fn patch(&self, url: &str, form: &[(&str, &str)]) -> Result<ApiResponse, E621Error> {
+        self.send(|client| {
+            client
+                .patch(url)
+                .header(USER_AGENT, self.user_agent.as_str())
+                .header(AUTHORIZATION, self.auth.as_str())
+                .form(form)
+        })
+    }
+
+    fn post(&self, url: &str, form: &[(&str, &str)]) -> Result<ApiResponse, E621Error> {
+        self.send(|client| {
+            client
+                .post(url)
+                .header(USER_AGENT, self.user_agent.as_str())
+                .header(AUTHORIZATION, self.auth.as_str())
+                .form(form)
+        })
+    }
+
+    fn is_authenticated(&self) -> bool {
+        !self.auth.is_empty()
+    }
+
+    fn retries(&self) -> u64 {
+        self.retries.load(Ordering::Relaxed)
+    }
+
+    fn shared(&self) -> Option<Arc<dyn ApiClient + Send + Sync>> {
+        Some(Arc::new(self.clone()))
+    }
+}
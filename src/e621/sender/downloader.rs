@@ -0,0 +1,75 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Arc, Mutex};
+
+use crate::e621::error::E621Error;
+use crate::e621::sender::client::ApiClient;
+use crate::e621::sender::metrics::RequestMetrics;
+use crate::e621::sender::throttle::Throttle;
+use crate::e621::sender::{metered, RequestSender};
+
+/// Downloads files from other threads, so several posts can be downloaded at once.
+///
+/// Every downloader created from a [RequestSender] (see [RequestSender::file_downloader]) waits on the same throttle
+/// and counts its requests in the same metrics as the sender.
+#[derive(Clone)]
+pub struct FileDownloader {
+    /// The client the files are requested through.
+    client: Arc<dyn ApiClient + Send + Sync>,
+    /// The counters of every request sent, shared with the sender.
+    metrics: Arc<Mutex<RequestMetrics>>,
+    /// Holds requests back when the server reports that the request budget is nearly used up, shared with the sender.
+    throttle: Arc<Throttle>,
+}
+
+impl FileDownloader {
+    /// Creates a downloader sending its requests through the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client the files are requested through.
+    /// * `metrics`: The counters the requests are counted in.
+    /// * `throttle`: The throttle holding requests back.
+    ///
+    /// returns: FileDownloader
+    pub(super) fn new(
+        client: Arc<dyn ApiClient + Send + Sync>,
+        metrics: Arc<Mutex<RequestMetrics>>,
+        throttle: Arc<Throttle>,
+    ) -> Self {
+        FileDownloader {
+            client,
+            metrics,
+            throttle,
+        }
+    }
+
+    /// Downloads a file, the same way as [RequestSender::download_image].
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to the file to download.
+    /// * `progress`: Called with the amount of bytes read each time a chunk of the file is read.
+    ///
+    /// returns: Result<Vec<u8, Global>, E621Error>
+    pub fn download(&self, url: &str, progress: &dyn Fn(u64)) -> Result<Vec<u8>, E621Error> {
+        let response = metered(&self.throttle, &self.metrics, url, || {
+            self.client.download(url, progress)
+        })?;
+        Ok(RequestSender::check_status(url, response)?.body)
+    }
+}
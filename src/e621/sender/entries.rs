@@ -18,27 +18,31 @@ use serde::{Deserialize, Serialize};
 
 use crate::e621::io::tag::TagType;
 
+/// The most tags a search can have when not logged in, or when the account doesn't report its own limit.
+pub const DEFAULT_TAG_QUERY_LIMIT: usize = 40;
+
 /// GET return of alias entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct AliasEntry {
+#[serde(default)]
+pub struct AliasEntry {
     /// Alias ID.
-    pub(crate) id: i64,
+    pub id: i64,
     /// Alias name.
-    pub(crate) antecedent_name: String,
+    pub antecedent_name: String,
     /// Reason for the alias.
-    pub(crate) reason: String,
+    pub reason: String,
     /// ID of the creator of the alias.
-    pub(crate) creator_id: i64,
+    pub creator_id: i64,
     /// The date the alias was created.
-    pub(crate) created_at: Option<String>,
+    pub created_at: Option<String>,
     /// Forum post id tied to the request for the alias to be approved.
-    pub(crate) forum_post_id: Option<i64>,
+    pub forum_post_id: Option<i64>,
     /// The date for when the alias was updated.
-    pub(crate) updated_at: Option<String>,
+    pub updated_at: Option<String>,
     /// Forum topic ID for the thread where the request for alias approval was created.
-    pub(crate) forum_topic_id: Option<i64>,
+    pub forum_topic_id: Option<i64>,
     /// Original tag name.
-    pub(crate) consequent_name: String,
+    pub consequent_name: String,
     /// Current status of the alias.
     /// Can be `approved`, `active`, `pending`, `deleted`, `retired`, `processing`, and `queued`.
     ///
@@ -48,26 +52,41 @@ pub(crate) struct AliasEntry {
     /// ## Reason for Error
     /// This is probably an internal error with the server, and while it is exceptionally rare,
     /// there is still a probability.
-    pub(crate) status: String,
+    pub status: String,
     /// The amount of post the aliased tag is tied to.
-    pub(crate) post_count: i64,
+    pub post_count: i64,
     /// ID of the user that approved the alias.
-    pub(crate) approver_id: Option<i64>,
+    pub approver_id: Option<i64>,
+}
+
+/// GET return of tag implication entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImplicationEntry {
+    /// Implication ID.
+    pub id: i64,
+    /// The tag that implies the other tag (e.g `wolf`).
+    pub antecedent_name: String,
+    /// The tag that is implied (e.g `canine`).
+    pub consequent_name: String,
+    /// Current status of the implication (e.g `active`, `pending`, `deleted`).
+    pub status: String,
 }
 
 /// GET return of tag entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct TagEntry {
+#[serde(default)]
+pub struct TagEntry {
     /// Id of the tag.
-    pub(crate) id: i64,
+    pub id: i64,
     /// Name of the tag.
-    pub(crate) name: String,
+    pub name: String,
     /// Amount of posts that uses the tag.
-    pub(crate) post_count: i64,
+    pub post_count: i64,
     /// Related tags that this tag is commonly paired with.
-    pub(crate) related_tags: String,
+    pub related_tags: String,
     /// Most recent date the `related_tags` was updated.
-    pub(crate) related_tags_updated_at: String,
+    pub related_tags_updated_at: String,
     /// The type of tag it is.
     ///
     /// This tag can be the following types:
@@ -77,59 +96,93 @@ pub(crate) struct TagEntry {
     /// - `3`: Copyright;
     /// - `4`: Character;
     /// - `5`: Species;
-    pub(crate) category: u8,
+    pub category: u8,
     /// Whether or not the tag is locked.
-    pub(crate) is_locked: bool,
+    pub is_locked: bool,
     /// The date the tag was created.
-    pub(crate) created_at: String,
+    pub created_at: String,
     /// The date the tag was updated.
-    pub(crate) updated_at: String,
+    pub updated_at: String,
 }
 
 impl TagEntry {
+    /// The names of the tags related to this tag, from most to least related.
+    ///
+    /// `related_tags` holds pairs of a tag name and how related it is, separated by spaces (e.g `wolf 300 canine 250`).
+    pub fn related_tag_names(&self) -> Vec<&str> {
+        self.related_tags
+            .split_whitespace()
+            .step_by(2)
+            .filter(|e| *e != self.name)
+            .collect()
+    }
+
+    /// The name of the tag's category (e.g `artist` or `species`).
+    pub fn category_name(&self) -> &'static str {
+        match self.category {
+            0 => "general",
+            1 => "artist",
+            3 => "copyright",
+            4 => "character",
+            5 => "species",
+            6 => "invalid",
+            7 => "meta",
+            8 => "lore",
+            _ => "unknown",
+        }
+    }
+
     /// Constrains the `TagType` enum to a tags type specifically.
     ///
-    /// This can only be `TagType::General` or `TagType::Artist`.
-    pub(crate) fn to_tag_type(&self) -> TagType {
+    /// This can only be `TagType::General` or `TagType::Artist`, categories e621 adds later are treated as general.
+    pub fn to_tag_type(&self) -> TagType {
         match self.category {
             // `0`: General; `3`: Copyright; `5`: Species; `4`: Character; `6`: Invalid;
             // `7`: Meta; `8`: Lore;
             0 | 3..=8 => TagType::General,
             // `1`: Artist;
             1 => TagType::Artist,
-            _ => unreachable!(),
+            category => {
+                trace!(
+                    "Tag {} has the unknown category {category}, treating it as general...",
+                    self.name
+                );
+                TagType::General
+            }
         }
     }
 }
 
 /// Wrapper struct that holds the return of bulk searches.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct BulkPostEntry {
+#[serde(default)]
+pub struct BulkPostEntry {
     /// All posts in the bulk.
-    pub(crate) posts: Vec<PostEntry>,
+    pub posts: Vec<PostEntry>,
 }
 
 /// GET return of post entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct PostEntry {
+#[serde(default)]
+pub struct PostEntry {
     /// The ID number of the post.
-    pub(crate) id: i64,
+    pub id: i64,
     /// The time the post was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) created_at: String,
+    pub created_at: String,
     ///  The time the post was last updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) updated_at: Option<String>,
+    pub updated_at: Option<String>,
     /// The main image of the post.
-    pub(crate) file: File,
+    pub file: File,
     /// The preview image of the post.
-    pub(crate) preview: Preview,
+    pub preview: Preview,
     /// The sample image of the post.
-    pub(crate) sample: Sample,
+    pub sample: Sample,
     /// The score of the post.
-    pub(crate) score: Score,
+    pub score: Score,
     /// The tags tied to the post.
-    pub(crate) tags: Tags,
+    pub tags: Tags,
     /// An array of tags that are locked on the post.
-    pub(crate) locked_tags: Vec<String>,
+    pub locked_tags: Vec<String>,
     /// An ID that increases for every post alteration on E6 (explained below)
     ///
     /// `change_seq` is a number that is increased every time a post is changed on the site.
@@ -149,103 +202,123 @@ pub(crate) struct PostEntry {
     /// - `is_pending`
     /// - `is_flagged`
     /// - `is_rating_locked`
-    pub(crate) change_seq: i64,
+    pub change_seq: i64,
     /// All the flags that could be raised on the post.
-    pub(crate) flags: Flags,
+    pub flags: Flags,
     /// The post’s rating. Either `s`, `q` or `e`.
-    pub(crate) rating: String,
+    pub rating: String,
     /// How many people have favorited the post.
-    pub(crate) fav_count: i64,
+    pub fav_count: i64,
     /// The source field of the post.
-    pub(crate) sources: Vec<String>,
+    pub sources: Vec<String>,
     /// An array of Pool IDs that the post is a part of.
-    pub(crate) pools: Vec<i64>,
+    pub pools: Vec<i64>,
     /// The relationships of the post.
-    pub(crate) relationships: Relationships,
+    pub relationships: Relationships,
     /// The ID of the user that approved the post, if available.
-    pub(crate) approver_id: Option<i64>,
+    pub approver_id: Option<i64>,
     /// The ID of the user that uploaded the post.
-    pub(crate) uploader_id: i64,
+    pub uploader_id: i64,
     /// The post’s description.
-    pub(crate) description: String,
+    pub description: String,
     /// The count of comments on the post.
-    pub(crate) comment_count: i64,
+    pub comment_count: i64,
     /// If provided auth credentials, will return if the authenticated user has favorited the post or not.
     /// HTTP Basic Auth is recommended over `login` and `api_key` parameters in the URL.
-    pub(crate) is_favorited: bool,
+    pub is_favorited: bool,
+    /// If the post has notes (e.g translations of the text in the image).
+    pub has_notes: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct File {
+#[serde(default)]
+pub struct File {
     /// The width of the post.
-    pub(crate) width: i64,
+    pub width: i64,
     /// The height of the post.
-    pub(crate) height: i64,
+    pub height: i64,
     /// The file’s extension.
-    pub(crate) ext: String,
+    pub ext: String,
     /// The size of the file in bytes.
-    pub(crate) size: i64,
+    pub size: i64,
     /// The md5 of the file.
-    pub(crate) md5: String,
+    pub md5: String,
     /// The URL where the file is hosted on E6
-    pub(crate) url: Option<String>,
+    pub url: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Preview {
+#[serde(default)]
+pub struct Preview {
     /// The width of the post preview.
-    pub(crate) width: i64,
+    pub width: i64,
     /// The height of the post preview.
-    pub(crate) height: i64,
+    pub height: i64,
     /// The URL where the preview file is hosted on E6
-    pub(crate) url: Option<String>,
+    pub url: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Sample {
+#[serde(default)]
+pub struct Sample {
     ///  If the post has a sample/thumbnail or not.
-    pub(crate) has: Option<bool>,
+    pub has: Option<bool>,
     /// The width of the post sample.
-    pub(crate) height: i64,
+    pub height: i64,
     /// The height of the post sample.
-    pub(crate) width: i64,
+    pub width: i64,
     /// The URL where the sample file is hosted on E6.
-    pub(crate) url: Option<String>,
+    pub url: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Score {
+#[serde(default)]
+pub struct Score {
     /// The number of times voted up.
-    pub(crate) up: i64,
+    pub up: i64,
     /// A negative number representing the number of times voted down.
-    pub(crate) down: i64,
+    pub down: i64,
     /// The total score (up + down).
-    pub(crate) total: i64,
+    pub total: i64,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Tags {
+#[serde(default)]
+pub struct Tags {
     /// An array of all the `general` tags on the post.
-    pub(crate) general: Vec<String>,
+    pub general: Vec<String>,
     /// An array of all the `species` tags on the post.
-    pub(crate) species: Vec<String>,
+    pub species: Vec<String>,
     /// An array of all the `character` tags on the post.
-    pub(crate) character: Vec<String>,
+    pub character: Vec<String>,
     /// An array of all the `copyright` tags on the post.
-    pub(crate) copyright: Vec<String>,
+    pub copyright: Vec<String>,
     /// An array of all the `artist` tags on the post.
-    pub(crate) artist: Vec<String>,
+    pub artist: Vec<String>,
     /// An array of all the `invalid` tags on the post.
-    pub(crate) invalid: Vec<String>,
+    pub invalid: Vec<String>,
     /// An array of all the `lore` tags on the post.
-    pub(crate) lore: Vec<String>,
+    pub lore: Vec<String>,
     /// An array of all the `meta` tags on the post.
-    pub(crate) meta: Vec<String>,
+    pub meta: Vec<String>,
 }
 
 impl Tags {
+    /// Iterates over all of the tags without consuming them.
+    pub fn iter(&self) -> impl Iterator<Item = &String> {
+        self.general
+            .iter()
+            .chain(&self.species)
+            .chain(&self.character)
+            .chain(&self.copyright)
+            .chain(&self.artist)
+            .chain(&self.invalid)
+            .chain(&self.lore)
+            .chain(&self.meta)
+    }
+
     /// Consumes and combines all of the tags into a single array.
-    pub(crate) fn combine_tags(self) -> Vec<String> {
+    pub fn combine_tags(self) -> Vec<String> {
         vec![
             self.general,
             self.species,
@@ -263,215 +336,389 @@ impl Tags {
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Flags {
+#[serde(default)]
+pub struct Flags {
     /// If the post is pending approval.
-    pub(crate) pending: bool,
+    pub pending: bool,
     /// If the post is flagged for deletion.
-    pub(crate) flagged: bool,
+    pub flagged: bool,
     /// If the post has it’s notes locked.
-    pub(crate) note_locked: bool,
+    pub note_locked: bool,
     /// If the post’s status has been locked.
-    pub(crate) status_locked: Option<bool>,
+    pub status_locked: Option<bool>,
     /// If the post’s rating has been locked.
-    pub(crate) rating_locked: bool,
+    pub rating_locked: bool,
     /// If the post has been deleted.
-    pub(crate) deleted: bool,
+    pub deleted: bool,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct Relationships {
+#[serde(default)]
+pub struct Relationships {
     /// The ID of the post’s parent, if it has one.
-    pub(crate) parent_id: Option<i64>,
+    pub parent_id: Option<i64>,
     /// If the post has child posts.
-    pub(crate) has_children: bool,
-    pub(crate) has_active_children: bool,
+    pub has_children: bool,
+    pub has_active_children: bool,
     /// A list of child post IDs that are linked to the post, if it has any.
-    pub(crate) children: Vec<i64>,
+    pub children: Vec<i64>,
 }
 
 /// GET return of set entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct SetEntry {
+#[serde(default)]
+pub struct SetEntry {
     /// The ID of the set.
-    pub(crate) id: i64,
+    pub id: i64,
     /// The time the pool was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) created_at: String,
+    pub created_at: String,
     /// The time the pool was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) updated_at: String,
+    pub updated_at: String,
     /// The ID of the user that created the set.
-    pub(crate) creator_id: i64,
+    pub creator_id: i64,
     /// If the set is public and visible.
-    pub(crate) is_public: bool,
+    pub is_public: bool,
     /// The name of the set.
-    pub(crate) name: String,
+    pub name: String,
     /// The short name of the set.
-    pub(crate) shortname: String,
+    pub shortname: String,
     /// The description of the set.
-    pub(crate) description: String,
+    pub description: String,
     /// The amount of posts in the set.
-    pub(crate) post_count: i64,
+    pub post_count: i64,
     /// If the set will transfer its post on delete.
-    pub(crate) transfer_on_delete: bool,
+    pub transfer_on_delete: bool,
     /// An array group of posts in the pool.
-    pub(crate) post_ids: Vec<i64>,
+    pub post_ids: Vec<i64>,
 }
 
 /// GET return of pool entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct PoolEntry {
+#[serde(default)]
+pub struct PoolEntry {
     /// The ID of the pool.
-    pub(crate) id: i64,
+    pub id: i64,
     /// The name of the pool.
-    pub(crate) name: String,
+    pub name: String,
     /// The time the pool was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) created_at: String,
+    pub created_at: String,
     /// The time the pool was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) updated_at: String,
+    pub updated_at: String,
     /// The ID of the user that created the pool.
-    pub(crate) creator_id: i64,
+    pub creator_id: i64,
     /// The description of the pool.
-    pub(crate) description: String,
+    pub description: String,
     /// If the pool is active and still getting posts added.
-    pub(crate) is_active: bool,
+    pub is_active: bool,
     /// Can be `series` or `collection`.
-    pub(crate) category: String,
+    pub category: String,
     /// An array group of posts in the pool.
-    pub(crate) post_ids: Vec<i64>,
+    pub post_ids: Vec<i64>,
     /// The name of the user that created the pool.
-    pub(crate) creator_name: String,
+    pub creator_name: String,
     /// The amount of posts in the pool.
-    pub(crate) post_count: i64,
+    pub post_count: i64,
+}
+
+/// GET return of artist entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistEntry {
+    /// The ID of the artist.
+    pub id: i64,
+    /// The name of the artist (their artist tag).
+    pub name: String,
+    /// Other names the artist goes by.
+    pub other_names: Vec<String>,
+    /// The group the artist belongs to.
+    pub group_name: String,
+    /// The ID of the e621 account that belongs to the artist.
+    pub linked_user_id: Option<i64>,
+    /// Notes about the artist.
+    pub notes: Option<String>,
+    /// If the artist is active.
+    pub is_active: bool,
+    /// If the artist entry is locked from being edited.
+    pub is_locked: bool,
+    /// The ID of the user that created the artist entry.
+    pub creator_id: i64,
+    /// The time the artist was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the artist was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
+    /// The external links of the artist (e.g their gallery or social media).
+    pub urls: Vec<ArtistUrl>,
+}
+
+/// An external link of an [ArtistEntry].
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArtistUrl {
+    /// The ID of the link.
+    pub id: i64,
+    /// The ID of the artist the link belongs to.
+    pub artist_id: i64,
+    /// The link as it was entered.
+    pub url: String,
+    /// The link after being normalized by e621.
+    pub normalized_url: String,
+    /// If the link is still active.
+    pub is_active: bool,
+    /// The time the link was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the link was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
+}
+
+/// GET return of note entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NoteEntry {
+    /// The ID of the note.
+    pub id: i64,
+    /// The ID of the post the note is on.
+    pub post_id: i64,
+    /// The distance of the note's region from the left of the image, in pixels.
+    pub x: i64,
+    /// The distance of the note's region from the top of the image, in pixels.
+    pub y: i64,
+    /// The width of the note's region, in pixels.
+    pub width: i64,
+    /// The height of the note's region, in pixels.
+    pub height: i64,
+    /// The text of the note.
+    pub body: String,
+    /// If the note is active (notes that were removed aren't).
+    pub is_active: bool,
+    /// The version of the note, which increases every time it's edited.
+    pub version: i64,
+    /// The ID of the user that created the note.
+    pub creator_id: i64,
+    /// The name of the user that created the note.
+    pub creator_name: String,
+    /// The time the note was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the note was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
+}
+
+/// GET return of comment entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CommentEntry {
+    /// The ID of the comment.
+    pub id: i64,
+    /// The ID of the post the comment is on.
+    pub post_id: i64,
+    /// The text of the comment (in e621's DText markup).
+    pub body: String,
+    /// The score of the comment.
+    pub score: i64,
+    /// The ID of the user that wrote the comment.
+    pub creator_id: i64,
+    /// The name of the user that wrote the comment.
+    pub creator_name: String,
+    /// The ID of the user that last edited the comment.
+    pub updater_id: Option<i64>,
+    /// The name of the user that last edited the comment.
+    pub updater_name: Option<String>,
+    /// If the comment is hidden.
+    pub is_hidden: bool,
+    /// If the comment is stickied to the top of the thread.
+    pub is_sticky: bool,
+    /// The type of warning a moderator attached to the comment, if any.
+    pub warning_type: Option<String>,
+    /// The time the comment was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the comment was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
+}
+
+/// GET return of wiki page entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WikiPageEntry {
+    /// The ID of the wiki page.
+    pub id: i64,
+    /// The title of the wiki page (the tag it's about).
+    pub title: String,
+    /// The text of the wiki page (in e621's DText markup).
+    pub body: String,
+    /// Other names of the tag.
+    pub other_names: Vec<String>,
+    /// If the wiki page is locked from being edited.
+    pub is_locked: bool,
+    /// If the wiki page was deleted.
+    pub is_deleted: bool,
+    /// The ID of the user that created the wiki page.
+    pub creator_id: i64,
+    /// The name of the user that created the wiki page.
+    pub creator_name: String,
+    /// The ID of the user that last edited the wiki page.
+    pub updater_id: Option<i64>,
+    /// The time the wiki page was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the wiki page was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
+}
+
+/// GET return of saved search entry for e621/e926.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedSearchEntry {
+    /// The ID of the saved search.
+    pub id: i64,
+    /// The ID of the user the saved search belongs to.
+    pub user_id: i64,
+    /// The searched tags.
+    pub query: String,
+    /// The labels the user sorted the saved search under.
+    pub labels: Vec<String>,
+    /// The time the saved search was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub created_at: String,
+    /// The time the saved search was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
+    pub updated_at: String,
 }
 
 /// GET return of user entry for e621/e926.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-pub(crate) struct UserEntry {
+#[serde(default)]
+pub struct UserEntry {
     /// The amount of wiki changes made by the user.
-    pub(crate) wiki_page_version_count: i64,
+    pub wiki_page_version_count: i64,
     /// The amount of artist changes made by the user.
-    pub(crate) artist_version_count: i64,
+    pub artist_version_count: i64,
     /// The amount of pool changes made by the user.
-    pub(crate) pool_version_count: i64,
+    pub pool_version_count: i64,
     /// The amount of post changes made by the user.
-    pub(crate) forum_post_count: i64,
+    pub forum_post_count: i64,
     /// Count of comments posted by the user.
-    pub(crate) comment_count: i64,
+    pub comment_count: i64,
     /// Count of flags done by the user.
-    pub(crate) flag_count: i64,
+    pub flag_count: i64,
     /// The amount of positive feedback given by the user.
-    pub(crate) positive_feedback_count: i64,
+    pub positive_feedback_count: i64,
     /// The amount of neutral feedback given by the user.
-    pub(crate) neutral_feedback_count: i64,
+    pub neutral_feedback_count: i64,
     /// The amount of negative feedback given by the user.
-    pub(crate) negative_feedback_count: i64,
+    pub negative_feedback_count: i64,
     /// Upload limit of the user.
-    pub(crate) upload_limit: i64,
+    pub upload_limit: i64,
     /// ID of the user.
-    pub(crate) id: i64,
+    pub id: i64,
     /// The time the pool was created in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) created_at: String,
+    pub created_at: String,
     /// Name of the user.
-    pub(crate) name: String,
+    pub name: String,
     /// Level of the user.
-    pub(crate) level: i64,
+    pub level: i64,
     /// Base upload limit of the user.
-    pub(crate) base_upload_limit: i64,
+    pub base_upload_limit: i64,
     /// Count of posts uploaded by the user.
-    pub(crate) post_upload_count: i64,
+    pub post_upload_count: i64,
     /// Count of posts updated by the user.
-    pub(crate) post_update_count: i64,
+    pub post_update_count: i64,
     /// Count of notes updated by the user.
-    pub(crate) note_update_count: i64,
+    pub note_update_count: i64,
     /// If user is banned or not.
-    pub(crate) is_banned: bool,
+    pub is_banned: bool,
     /// Whether or not the user can approve posts.
-    pub(crate) can_approve_posts: bool,
+    pub can_approve_posts: bool,
     /// Whether or not uploading posts affect the post limit.
-    pub(crate) can_upload_free: bool,
+    pub can_upload_free: bool,
     /// The string of the user's current level.
-    pub(crate) level_string: String,
+    pub level_string: String,
     /// Whether or not avatars should be shown.
-    pub(crate) show_avatars: Option<bool>,
+    pub show_avatars: Option<bool>,
     /// Whether or not the blacklist should block avatars.
-    pub(crate) blacklist_avatars: Option<bool>,
+    pub blacklist_avatars: Option<bool>,
     /// Whether or not the blacklist should block users.
-    pub(crate) blacklist_users: Option<bool>,
+    pub blacklist_users: Option<bool>,
     /// Whether or not a post's description should be collapsed initially.
-    pub(crate) description_collapsed_initially: Option<bool>,
+    pub description_collapsed_initially: Option<bool>,
     /// Whether or not comments should be hidden.
-    pub(crate) hide_comments: Option<bool>,
+    pub hide_comments: Option<bool>,
     /// Whether or not hidden comments should be shown.
-    pub(crate) show_hidden_comments: Option<bool>,
+    pub show_hidden_comments: Option<bool>,
     /// Whether or not to show post statistics.
-    pub(crate) show_post_statistics: Option<bool>,
+    pub show_post_statistics: Option<bool>,
     /// Whether or not the user has mail.
-    pub(crate) has_mail: Option<bool>,
+    pub has_mail: Option<bool>,
     /// Whether or not the user will receive email notifications.
-    pub(crate) receive_email_notifications: Option<bool>,
+    pub receive_email_notifications: Option<bool>,
     /// Whether or not keyboard navigation is on/off.
-    pub(crate) enable_keyboard_navigation: Option<bool>,
+    pub enable_keyboard_navigation: Option<bool>,
     /// Whether or not privacy mode is enabled.
-    pub(crate) enable_privacy_mode: Option<bool>,
+    pub enable_privacy_mode: Option<bool>,
     /// Whether or not usernames should be styled.
-    pub(crate) style_usernames: Option<bool>,
+    pub style_usernames: Option<bool>,
     /// Whether auto complete should be on or off.
-    pub(crate) enable_auto_complete: Option<bool>,
+    pub enable_auto_complete: Option<bool>,
     /// Whether or not searches should be saved.
-    pub(crate) has_saved_searches: Option<bool>,
+    pub has_saved_searches: Option<bool>,
     /// Whether or not thumbnails should be cropped.  
-    pub(crate) disable_cropped_thumbnails: Option<bool>,
+    pub disable_cropped_thumbnails: Option<bool>,
     /// Whether or not mobile gestures should be on or off.
-    pub(crate) disable_mobile_gestures: Option<bool>,
+    pub disable_mobile_gestures: Option<bool>,
     /// Whether or not safe mode is on/off.
-    pub(crate) enable_safe_mode: Option<bool>,
+    pub enable_safe_mode: Option<bool>,
     /// Whether or not responsive mode is disabled.
-    pub(crate) disable_responsive_mode: Option<bool>,
+    pub disable_responsive_mode: Option<bool>,
     /// Whether or not post tooltips is disabled.
-    pub(crate) disable_post_tooltips: Option<bool>,
+    pub disable_post_tooltips: Option<bool>,
     /// Whether or not the user can't flag.
-    pub(crate) no_flagging: Option<bool>,
+    pub no_flagging: Option<bool>,
     /// Whether or not the user can't give feedback.
-    pub(crate) no_feedback: Option<bool>,
+    pub no_feedback: Option<bool>,
     /// Whether or not dmail is disabled.
-    pub(crate) disable_user_dmails: Option<bool>,
+    pub disable_user_dmails: Option<bool>,
     /// Whether or not compact uploader is enabled.
-    pub(crate) enable_compact_uploader: Option<bool>,
+    pub enable_compact_uploader: Option<bool>,
     /// The time the pool was updated in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) updated_at: Option<String>,
+    pub updated_at: Option<String>,
     /// The user's email.
-    pub(crate) email: Option<String>,
+    pub email: Option<String>,
     /// The time the user was last logged in in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) last_logged_in_at: Option<String>,
+    pub last_logged_in_at: Option<String>,
     /// The time the last forum the user read in the format of `YYYY-MM-DDTHH:MM:SS.MS+00:00`.
-    pub(crate) last_forum_read_at: Option<String>,
+    pub last_forum_read_at: Option<String>,
     /// Recent tags searched by the user.
-    pub(crate) recent_tags: Option<String>,
+    pub recent_tags: Option<String>,
     /// Comment threshold of the user.
-    pub(crate) comment_threshold: Option<i64>,
+    pub comment_threshold: Option<i64>,
     /// Default image size of the user.
-    pub(crate) default_image_size: Option<String>,
+    pub default_image_size: Option<String>,
     /// Favorite tags the user has.
-    pub(crate) favorite_tags: Option<String>,
+    pub favorite_tags: Option<String>,
     /// The user's blacklist tags.
-    pub(crate) blacklisted_tags: Option<String>,
+    pub blacklisted_tags: Option<String>,
     /// The time zone of the user.
-    pub(crate) time_zone: Option<String>,
+    pub time_zone: Option<String>,
     /// The post count per page.
-    pub(crate) per_page: Option<i64>,
+    pub per_page: Option<i64>,
     /// Custom style/theme of E6.
-    pub(crate) custom_style: Option<String>,
+    pub custom_style: Option<String>,
     /// Count of all the user's favorites.
-    pub(crate) favorite_count: Option<i64>,
+    pub favorite_count: Option<i64>,
     /// The API regen multiplier.
-    pub(crate) api_regen_multiplier: Option<i64>,
+    pub api_regen_multiplier: Option<i64>,
     /// The API burst limit.
-    pub(crate) api_burst_limit: Option<i64>,
+    pub api_burst_limit: Option<i64>,
     /// The remaining API limit.
-    pub(crate) remaining_api_limit: Option<i64>,
+    pub remaining_api_limit: Option<i64>,
     /// The statement given while being in timeout.
-    pub(crate) statement_timeout: Option<i64>,
+    pub statement_timeout: Option<i64>,
     /// The limit for how many times a user can favorite.
-    pub(crate) favorite_limit: Option<i64>,
+    pub favorite_limit: Option<i64>,
     /// The maximum tag query limit, the amount amount of tags a user can search.
-    pub(crate) tag_query_limit: Option<i64>,
+    pub tag_query_limit: Option<i64>,
+}
+
+impl UserEntry {
+    /// The most tags the user can search at once, or [DEFAULT_TAG_QUERY_LIMIT] if the account doesn't report it.
+    pub fn max_search_tags(&self) -> usize {
+        self.tag_query_limit
+            .filter(|e| *e > 0)
+            .map_or(DEFAULT_TAG_QUERY_LIMIT, |e| e as usize)
+    }
 }
@@ -0,0 +1,145 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use reqwest::Url;
+
+use crate::e621::error::E621Error;
+use crate::e621::sender::client::ApiResponse;
+
+/// The name files (downloads and previews) are counted under, instead of their own paths.
+const FILES_ENDPOINT: &str = "files";
+
+/// Counters of the requests sent to one endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointMetrics {
+    /// The amount of requests sent.
+    pub requests: u64,
+    /// The amount of requests that failed or were answered with an error status.
+    pub failures: u64,
+    /// The amount of bytes received.
+    pub bytes: u64,
+    /// The time spent waiting for all the responses.
+    pub total_time: Duration,
+}
+
+impl EndpointMetrics {
+    /// The average time spent waiting for a response.
+    pub fn average_latency(&self) -> Duration {
+        match self.requests {
+            0 => Duration::ZERO,
+            requests => self.total_time / requests as u32,
+        }
+    }
+
+    /// Adds the counters of another endpoint to this one.
+    ///
+    /// # Arguments
+    ///
+    /// * `other`: The counters to add.
+    fn add(&mut self, other: &EndpointMetrics) {
+        self.requests += other.requests;
+        self.failures += other.failures;
+        self.bytes += other.bytes;
+        self.total_time += other.total_time;
+    }
+}
+
+/// Counters of every request the [RequestSender](super::RequestSender) sent, by endpoint, used to tell slow runs apart
+/// from rate limited ones.
+#[derive(Debug, Clone, Default)]
+pub struct RequestMetrics {
+    /// The counters of every endpoint, by the name of the endpoint (see [endpoint_name]).
+    endpoints: BTreeMap<String, EndpointMetrics>,
+}
+
+impl RequestMetrics {
+    /// Counts a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url that was requested.
+    /// * `elapsed`: The time spent waiting for the response.
+    /// * `result`: The response, or the error the request failed with.
+    pub fn record(
+        &mut self,
+        url: &str,
+        elapsed: Duration,
+        result: &Result<ApiResponse, E621Error>,
+    ) {
+        let endpoint = self.endpoints.entry(endpoint_name(url)).or_default();
+        endpoint.requests += 1;
+        endpoint.total_time += elapsed;
+        match result {
+            Ok(response) => {
+                endpoint.bytes += response.body.len() as u64;
+                if !response.status.is_success() && !response.status.is_redirection() {
+                    endpoint.failures += 1;
+                }
+            }
+            Err(_) => endpoint.failures += 1,
+        }
+    }
+
+    /// The counters of every endpoint, by the name of the endpoint.
+    pub fn endpoints(&self) -> &BTreeMap<String, EndpointMetrics> {
+        &self.endpoints
+    }
+
+    /// The counters of all endpoints combined.
+    pub fn total(&self) -> EndpointMetrics {
+        let mut total = EndpointMetrics::default();
+        for endpoint in self.endpoints.values() {
+            total.add(endpoint);
+        }
+
+        total
+    }
+}
+
+/// Gets the name a request is counted under, which is the path of the url with the id or name of the entry replaced
+/// (e.g `/pools/{id}.json`), or [FILES_ENDPOINT] for files.
+///
+/// # Arguments
+///
+/// * `url`: The url that was requested.
+///
+/// returns: String
+pub fn endpoint_name(url: &str) -> String {
+    let Ok(url) = Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let path = url.path();
+    if path.starts_with("/data/") {
+        return String::from(FILES_ENDPOINT);
+    }
+
+    let segments: Vec<&str> = path.split('/').collect();
+    match segments.as_slice() {
+        ["", resource, entry, rest @ ..] if !entry.is_empty() => {
+            let entry = if rest.is_empty() && entry.ends_with(".json") {
+                "{id}.json"
+            } else {
+                "{id}"
+            };
+            [&["", resource, entry], rest].concat().join("/")
+        }
+        _ => path.to_string(),
+    }
+}
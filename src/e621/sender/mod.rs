@@ -17,19 +17,38 @@
 use std::any::type_name;
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs::{create_dir_all, write};
+use std::io;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
-use anyhow::{Context, Result};
-use reqwest::blocking::{Client, RequestBuilder, Response};
-use reqwest::header::{AUTHORIZATION, USER_AGENT};
+use reqwest::{StatusCode, Url};
 use serde::de::DeserializeOwned;
-use serde_json::{from_value, Value};
+use serde_json::{from_slice, from_str, Deserializer, Value};
 
-use crate::e621::io::{emergency_exit, Login};
-use crate::e621::sender::entries::{AliasEntry, BulkPostEntry, PostEntry, TagEntry};
+use crate::e621::error::E621Error;
+use crate::e621::io::response_cache::ResponseCache;
+use crate::e621::io::{state_directory, Config, Login, DEBUG_DIRECTORY};
+use crate::e621::sender::client::{ApiClient, ApiResponse, SenderClient};
+use crate::e621::sender::downloader::FileDownloader;
+use crate::e621::sender::entries::{
+    AliasEntry, ArtistEntry, BulkPostEntry, CommentEntry, ImplicationEntry, NoteEntry, PoolEntry,
+    SavedSearchEntry, SetEntry, TagEntry, UserEntry, WikiPageEntry, DEFAULT_TAG_QUERY_LIMIT,
+};
+use crate::e621::sender::metrics::RequestMetrics;
+use crate::e621::sender::throttle::Throttle;
 
-pub(crate) mod entries;
+pub mod bandwidth;
+pub mod client;
+pub mod downloader;
+pub mod entries;
+pub mod metrics;
+pub mod throttle;
+
+/// The most entries the API returns in a page.
+const PAGE_LIMIT: usize = 320;
 
 /// Creates a hashmap through similar syntax of the `vec` macro.
 ///
@@ -39,7 +58,8 @@ pub(crate) mod entries;
 /// # Example
 ///
 /// ```rust
-/// # use std::collections::hashmap;
+/// # use std::collections::HashMap;
+/// # use e621_downloader::hashmap;
 ///
 /// let hashmap = hashmap![("Testing", "testing"), ("Example", "example")];
 ///
@@ -60,234 +80,442 @@ macro_rules! hashmap {
     };
 }
 
-/// Default user agent value.
-const USER_AGENT_VALUE: &str = concat!(
-    env!("CARGO_PKG_NAME"),
-    "/",
-    env!("CARGO_PKG_VERSION"),
-    " (by ",
-    env!("CARGO_PKG_AUTHORS"),
-    " on e621)"
-);
-
-/// A reference counted client used for all searches by the [Grabber], [Blacklist], [E621WebConnector], etc.
-struct SenderClient {
-    /// [Client] wrapped in a [Rc] so only one instance of the client exists. This will prevent an overabundance of
-    /// clients in the code.
-    client: Rc<Client>,
-    /// The base64 encrypted username and password of the user. This is passed only through the [AUTHORIZATION] header
-    /// of the request and is a highly secured method of login through client.
-    auth: Rc<String>,
+/// A sender that handles direct calls to the API.
+///
+/// This acts as a safety layer to ensure calls to the API are less error prone.
+pub struct RequestSender {
+    /// The client that will be used to send all requests.
+    ///
+    /// The client is wrapped in a [Rc], this will ensure that all request are only sent through one client.
+    client: Rc<dyn ApiClient>,
+    /// All available urls to use with the sender.
+    urls: Rc<RefCell<HashMap<String, String>>>,
+    /// The cache of metadata responses (e.g tags, aliases, pools, and sets).
+    cache: Rc<ResponseCache>,
+    /// The tags already looked up this run by name, so a tag used on several lines is only looked up once.
+    tag_lookups: Rc<RefCell<HashMap<String, Vec<TagEntry>>>>,
+    /// The aliases already looked up this run by name.
+    alias_lookups: Rc<RefCell<HashMap<String, Option<Vec<AliasEntry>>>>>,
+    /// The counters of every request sent, shared between all clones of the sender and its [FileDownloader]s.
+    metrics: Arc<Mutex<RequestMetrics>>,
+    /// Holds requests back when the server reports that the request budget is nearly used up.
+    throttle: Arc<Throttle>,
 }
 
-impl SenderClient {
-    /// Creates root client.
-    fn new(auth: String) -> Self {
-        trace!("SenderClient initializing with USER_AGENT_VALUE \"{USER_AGENT_VALUE}\"");
-
-        SenderClient {
-            client: Rc::new(SenderClient::build_client()),
-            auth: Rc::new(auth),
-        }
+impl RequestSender {
+    /// Creates a sender that doesn't send any login information, useful before a login exists.
+    ///
+    /// returns: Result<RequestSender, E621Error> (an error if the client can't be set up with the config)
+    pub fn anonymous() -> Result<Self, E621Error> {
+        Ok(RequestSender::with_client(
+            Rc::new(SenderClient::new(String::new())?),
+            Config::loaded_or_peek().instance_url(),
+        ))
     }
 
-    /// Runs client through a builder to give it required settings.
-    /// Cookies aren't stored in the client, TCP_NODELAY is on, and timeout is changed from 30 seconds to 60.
-    fn build_client() -> Client {
-        Client::builder()
-            .use_rustls_tls()
-            .http2_prior_knowledge()
-            .tcp_keepalive(Duration::from_secs(30))
-            .tcp_nodelay(true)
-            .timeout(Duration::from_secs(60))
-            .build()
-            .unwrap_or_else(|_| Client::new())
+    /// Creates a sender that sends the login information with authenticated requests.
+    ///
+    /// returns: Result<RequestSender, E621Error> (an error if the client can't be set up with the config)
+    pub fn new() -> Result<Self, E621Error> {
+        let login = Login::get();
+        let auth = if login.is_empty() {
+            String::new()
+        } else {
+            base64_url::encode(format!("{}:{}", login.username(), login.api_key()).as_str())
+        };
+
+        Ok(RequestSender::with_client(
+            Rc::new(SenderClient::new(auth)?),
+            Config::loaded_or_peek().instance_url(),
+        ))
     }
 
-    /// A wrapping function that acts the exact same as `self.client.get` but will instead attach the user agent header
-    /// before returning the [RequestBuilder]. This will ensure that all requests sent have the proper user agent info.
+    /// Creates a sender that sends all requests through the client, to the API at the base url.
     ///
     /// # Arguments
     ///
-    /// * `url`: The url to request.
+    /// * `client`: The client to send requests through.
+    /// * `base_url`: The url of the API (e.g `https://e621.net`).
     ///
-    /// returns: RequestBuilder
-    pub(crate) fn get(&self, url: &str) -> RequestBuilder {
-        self.client.get(url).header(USER_AGENT, USER_AGENT_VALUE)
+    /// returns: RequestSender
+    pub fn with_client(client: Rc<dyn ApiClient>, base_url: &str) -> Self {
+        RequestSender {
+            client,
+            urls: Rc::new(RefCell::new(RequestSender::initialize_url_map(base_url))),
+            cache: Rc::new(ResponseCache::new()),
+            tag_lookups: Rc::new(RefCell::new(HashMap::new())),
+            alias_lookups: Rc::new(RefCell::new(HashMap::new())),
+            metrics: Arc::new(Mutex::new(RequestMetrics::default())),
+            throttle: Arc::new(Throttle::default()),
+        }
     }
 
-    /// This is the same as `self.get(url)` but will attach the authorization header with username and API hash.
+    /// Initializes all the urls that will be used by the sender.
     ///
     /// # Arguments
     ///
-    /// * `url`: The url to request.
+    /// * `base_url`: The url of the API.
     ///
-    /// returns: RequestBuilder
-    pub(crate) fn get_with_auth(&self, url: &str) -> RequestBuilder {
-        if self.auth.is_empty() {
-            self.get(url)
-        } else {
-            self.get(url).header(AUTHORIZATION, self.auth.as_str())
+    /// returns: HashMap<String, String, RandomState>
+    fn initialize_url_map(base_url: &str) -> HashMap<String, String> {
+        hashmap![
+            ("posts", format!("{base_url}/posts.json")),
+            ("pool", format!("{base_url}/pools/")),
+            ("pool_bulk", format!("{base_url}/pools.json")),
+            ("set", format!("{base_url}/post_sets/")),
+            ("set_bulk", format!("{base_url}/post_sets.json")),
+            ("single", format!("{base_url}/posts/")),
+            ("blacklist", format!("{base_url}/users/")),
+            ("tag", format!("{base_url}/tags/")),
+            ("tag_bulk", format!("{base_url}/tags.json")),
+            ("alias", format!("{base_url}/tag_aliases.json")),
+            ("implication", format!("{base_url}/tag_implications.json")),
+            ("artist", format!("{base_url}/artists.json")),
+            ("note", format!("{base_url}/notes.json")),
+            ("comment", format!("{base_url}/comments.json")),
+            ("wiki", format!("{base_url}/wiki_pages.json")),
+            ("favorite", format!("{base_url}/favorites.json")),
+            ("saved_search", format!("{base_url}/saved_searches.json")),
+            ("user", format!("{base_url}/users/"))
+        ]
+    }
+
+    /// Gets the most tags the logged in account can search at once, or [DEFAULT_TAG_QUERY_LIMIT] when not logged in.
+    ///
+    /// returns: Result<usize, E621Error>
+    pub fn tag_query_limit(&self) -> Result<usize, E621Error> {
+        let login = Login::get();
+        if login.is_empty() {
+            return Ok(DEFAULT_TAG_QUERY_LIMIT);
         }
+
+        let user: UserEntry = self.get_entry_from_appended_id(login.username(), "user")?;
+        Ok(user.max_search_tags())
     }
-}
 
-impl Clone for SenderClient {
-    /// Creates a new instance of SenderClient, but clones the [Rc] of the root client, ensuring that all requests are
-    /// going to the same client.
-    fn clone(&self) -> Self {
-        SenderClient {
-            client: Rc::clone(&self.client),
-            auth: Rc::clone(&self.auth),
+    /// If the client authenticated or not.
+    pub fn is_authenticated(&self) -> bool {
+        self.client.is_authenticated()
+    }
+
+    /// Sends an authenticated request for the user's own page, checking that the login credentials are accepted.
+    ///
+    /// Only [StatusCode::UNAUTHORIZED] and [StatusCode::FORBIDDEN] mean the credentials were rejected
+    /// ([E621Error::Auth]), any other failed response (e.g rate limits or maintenance) is returned as a status error.
+    ///
+    /// # Arguments
+    ///
+    /// * `username`: The username of the logged in user.
+    ///
+    /// returns: Result<(), E621Error>
+    pub fn validate_credentials(&self, username: &str) -> Result<(), E621Error> {
+        let url = self.append_url(&self.urls.borrow()["user"], username);
+        let response = self.send(&url, &[], true)?;
+
+        let status = response.status;
+        trace!("Credential check returned status {status}");
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => Err(E621Error::Auth(status)),
+            _ => Self::check_status(&url, response).map(|_| ()),
         }
     }
-}
 
-/// A sender that handles direct calls to the API.
-///
-/// This acts as a safety layer to ensure calls to the API are less error prone.
-pub(crate) struct RequestSender {
-    /// The client that will be used to send all requests.
+    /// Sends a small request without the login, checking that the API can be reached, and returns the time the server
+    /// reported in its response.
     ///
-    /// Even though the [SenderClient] isn't wrapped in a [Rc], the main client inside of it is, this will ensure that
-    /// all request are only sent through one client.
-    client: SenderClient,
-    /// All available urls to use with the sender.
-    urls: Rc<RefCell<HashMap<String, String>>>,
-}
+    /// returns: Result<Option<SystemTime>, E621Error>
+    pub fn ping(&self) -> Result<Option<SystemTime>, E621Error> {
+        let url = self.urls.borrow()["posts"].clone();
+        Ok(self.check_response(&url, &[("limit", "1")], false)?.date)
+    }
 
-impl RequestSender {
-    pub(crate) fn new() -> Self {
-        let login = Login::get();
-        let auth = if login.is_empty() {
-            String::new()
-        } else {
-            base64_url::encode(format!("{}:{}", login.username(), login.api_key()).as_str())
-        };
+    /// Replaces the blacklist of the logged in user's account.
+    ///
+    /// # Arguments
+    ///
+    /// * `user_id`: The id of the logged in user.
+    /// * `blacklist`: The new blacklist, with one entry per line.
+    ///
+    /// returns: Result<(), E621Error>
+    pub fn update_blacklist(&self, user_id: i64, blacklist: &str) -> Result<(), E621Error> {
+        let url = self.append_url(&self.urls.borrow()["user"], &user_id.to_string());
+        let response = self
+            .metered(&url, |client| {
+                client.patch(&url, &[("user[blacklisted_tags]", blacklist)])
+            })
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))?;
+        Self::check_status(&url, response)?;
+        Ok(())
+    }
 
-        RequestSender {
-            client: SenderClient::new(auth),
-            urls: Rc::new(RefCell::new(RequestSender::initialize_url_map())),
+    /// Adds a post to the logged in user's favorites.
+    ///
+    /// Posts that are already favorited are left as they are.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post to favorite.
+    ///
+    /// returns: Result<(), E621Error>
+    pub fn add_favorite(&self, post_id: i64) -> Result<(), E621Error> {
+        let url = &self.urls.borrow()["favorite"];
+        let response = self
+            .metered(url, |client| {
+                client.post(url, &[("post_id", &post_id.to_string())])
+            })
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))?;
+
+        // e621 answers with 422 when the post is already favorited.
+        if response.status == StatusCode::UNPROCESSABLE_ENTITY {
+            trace!("Post {post_id} is already favorited...");
+            return Ok(());
         }
+
+        Self::check_status(url, response)?;
+        Ok(())
     }
 
-    /// Initializes all the urls that will be used by the sender.
-    fn initialize_url_map() -> HashMap<String, String> {
-        hashmap![
-            ("posts", "https://e621.net/posts.json"),
-            ("pool", "https://e621.net/pools/"),
-            ("set", "https://e621.net/post_sets/"),
-            ("single", "https://e621.net/posts/"),
-            ("blacklist", "https://e621.net/users/"),
-            ("tag", "https://e621.net/tags/"),
-            ("tag_bulk", "https://e621.net/tags.json"),
-            ("alias", "https://e621.net/tag_aliases.json"),
-            ("user", "https://e621.net/users/")
-        ]
+    /// Upvotes a post as the logged in user.
+    ///
+    /// Posts that are already upvoted stay upvoted, instead of the vote being removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post to upvote.
+    ///
+    /// returns: Result<(), E621Error>
+    pub fn upvote(&self, post_id: i64) -> Result<(), E621Error> {
+        let url = self.append_url(&self.urls.borrow()["single"], &format!("{post_id}/votes"));
+        let response = self
+            .metered(&url, |client| {
+                client.post(&url, &[("score", "1"), ("no_unvote", "true")])
+            })
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))?;
+        Self::check_status(&url, response)?;
+        Ok(())
     }
 
-    /// If the client authenticated or not.
-    pub(crate) fn is_authenticated(&self) -> bool {
-        !self.client.auth.is_empty()
+    /// Whether the API is requested from e621, which has e926 as its safe counterpart.
+    pub fn has_safe_mode(&self) -> bool {
+        self.is_instance("e621.net")
+    }
+
+    /// Whether the API is requested from e926, where every post is safe.
+    pub fn is_safe(&self) -> bool {
+        self.is_instance("e926.net")
+    }
+
+    /// Checks if the API is requested from the host.
+    ///
+    /// # Arguments
+    ///
+    /// * `host`: The host of the instance (e.g `e621.net`).
+    ///
+    /// returns: bool
+    fn is_instance(&self, host: &str) -> bool {
+        Url::parse(&self.urls.borrow()["posts"])
+            .ok()
+            .and_then(|e| e.host_str().map(|e| e.trim_start_matches("www.") == host))
+            .unwrap_or(false)
     }
 
     /// Updates all the urls from e621 to e926.
-    pub(crate) fn update_to_safe(&mut self) {
+    pub fn update_to_safe(&mut self) {
         self.urls
             .borrow_mut()
             .iter_mut()
             .for_each(|(_, value)| *value = value.replace("e621", "e926"));
     }
 
-    /// If a request failed, this will output what type of error it is before exiting.
+    /// If a request was unsuccessful, this will output what type of error it is.
     ///
     /// # Arguments
     ///
-    /// * `error`: The type of error thrown.
-    fn output_error(&self, error: &reqwest::Error) {
-        error!(
-            "Error occurred from sent request. \
-             Error: {error}",
-        );
-        trace!("Url where error occurred: {:#?}", error.url());
-
-        if let Some(status) = error.status() {
-            let code = status.as_u16();
-            trace!("The response code from the server was: {code}");
-
-            const SERVER_INTERNAL: u16 = 500;
-            const SERVER_RATE_LIMIT: u16 = 503;
-            const CLIENT_FORBIDDEN: u16 = 403;
-            const CLIENT_THROTTLED: u16 = 421;
-            match code {
-                SERVER_INTERNAL => {
-                    error!(
-                        "There was an error that happened internally in the servers, \
-                         please try using the downloader later until the issue is solved."
-                    );
-                }
-                SERVER_RATE_LIMIT => {
-                    error!(
-                        "Server could not handle the request, or the downloader has \
-                         exceeded the rate-limit. Contact the developer immediately about this \
-                         issue."
-                    );
-                }
-                CLIENT_FORBIDDEN => {
-                    error!(
-                        "The client was forbidden from accessing the api, contact the \
-                         developer immediately if this error occurs."
-                    );
-                }
-                CLIENT_THROTTLED => {
-                    error!(
-                        "The user is throttled, thus the request is unsuccessful. \
-                         Contact the developer immediately if this error occurs."
-                    );
-                }
-                _ => {
-                    error!("Response code couldn't be posted...");
-                }
+    /// * `url`: The url where the error occurred.
+    /// * `status`: The status the server responded with.
+    fn output_error(url: &str, status: StatusCode) {
+        error!("Error occurred from sent request. Status: {status}");
+        trace!("Url where error occurred: {url}");
+
+        let code = status.as_u16();
+        trace!("The response code from the server was: {code}");
+
+        const SERVER_INTERNAL: u16 = 500;
+        const SERVER_RATE_LIMIT: u16 = 503;
+        const CLIENT_FORBIDDEN: u16 = 403;
+        const CLIENT_THROTTLED: u16 = 421;
+        match code {
+            SERVER_INTERNAL => {
+                error!(
+                    "There was an error that happened internally in the servers, \
+                     please try using the downloader later until the issue is solved."
+                );
+            }
+            SERVER_RATE_LIMIT => {
+                error!(
+                    "Server could not handle the request, or the downloader has \
+                     exceeded the rate-limit. Contact the developer immediately about this \
+                     issue."
+                );
+            }
+            CLIENT_FORBIDDEN => {
+                error!(
+                    "The client was forbidden from accessing the api, contact the \
+                     developer immediately if this error occurs."
+                );
+            }
+            CLIENT_THROTTLED => {
+                error!(
+                    "The user is throttled, thus the request is unsuccessful. \
+                     Contact the developer immediately if this error occurs."
+                );
+            }
+            _ => {
+                error!("Response code couldn't be posted...");
             }
         }
+    }
 
-        emergency_exit("To prevent the program from crashing, it will do an emergency exit.");
+    /// Sends a request through the client, counting it in the [RequestMetrics].
+    ///
+    /// The request waits first if the last response reported that the request budget is nearly used up.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url the request is sent to.
+    /// * `send`: Sends the request with the client.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn metered(
+        &self,
+        url: &str,
+        send: impl FnOnce(&dyn ApiClient) -> Result<ApiResponse, E621Error>,
+    ) -> Result<ApiResponse, E621Error> {
+        metered(&self.throttle, &self.metrics, url, || {
+            send(self.client.as_ref())
+        })
+    }
+
+    /// The counters of every request sent so far.
+    pub fn metrics(&self) -> RequestMetrics {
+        self.metrics
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
     }
 
-    /// Gets the response from a sent request and checks to ensure it was successful.
+    /// Creates a downloader that downloads files from other threads, sharing the throttle and request counters of the
+    /// sender.
+    ///
+    /// Returns [None] if the client can't be used from other threads (see [ApiClient::shared]).
+    pub fn file_downloader(&self) -> Option<FileDownloader> {
+        let client = self.client.shared()?;
+        Some(FileDownloader::new(
+            client,
+            Arc::clone(&self.metrics),
+            Arc::clone(&self.throttle),
+        ))
+    }
+
+    /// The amount of requests the client had to send again (e.g over HTTP/1.1 after HTTP/2 failed).
+    pub fn retries(&self) -> u64 {
+        self.client.retries()
+    }
+
+    /// Sends a request, without checking the status of the response.
     ///
     /// # Arguments
     ///
-    /// * `result`: The result to check.
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login is attached to the request.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn send(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<ApiResponse, E621Error> {
+        self.metered(url, |client| client.get(url, query, authenticated))
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))
+    }
+
+    /// Sends a request and checks to ensure its response was successful.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login is attached to the request.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn check_response(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<ApiResponse, E621Error> {
+        let response = self.send(url, query, authenticated)?;
+        Self::check_status(url, response)
+    }
+
+    /// Checks that a response was successful.
+    ///
+    /// # Arguments
     ///
-    /// returns: Response
-    fn check_response(&self, result: Result<Response, reqwest::Error>) -> Response {
-        match result {
-            Ok(response) => response,
-            Err(ref error) => {
-                self.output_error(error);
-                unreachable!()
+    /// * `url`: The url that was requested.
+    /// * `response`: The response to check.
+    ///
+    /// returns: Result<ApiResponse, E621Error>
+    fn check_status(url: &str, response: ApiResponse) -> Result<ApiResponse, E621Error> {
+        match response.status {
+            status if status.is_success() => Ok(response),
+            StatusCode::UNAUTHORIZED => Err(E621Error::Auth(response.status)),
+            status => {
+                Self::output_error(url, status);
+                Err(E621Error::Status {
+                    url: url.to_string(),
+                    status,
+                })
             }
         }
     }
 
-    /// Sends request to download image.
+    /// Sends request to download image, within the `maxDownloadSpeed` of the config.
     ///
     /// # Arguments
     ///
     /// * `url`: The url to the file to download.
-    /// * `file_size`: The file size of the file.
+    /// * `progress`: Called with the amount of bytes read each time a chunk of the file is read.
     ///
-    /// returns: Vec<u8, Global>
-    pub(crate) fn download_image(&self, url: &str, file_size: i64) -> Vec<u8> {
-        let mut image_response = self.check_response(self.client.get(url).send());
-        let mut image_bytes: Vec<u8> = Vec::with_capacity(file_size as usize);
-        image_response
-            .copy_to(&mut image_bytes)
-            .with_context(|| "Failed to download image!".to_string())
-            .unwrap();
+    /// returns: Result<Vec<u8, Global>, E621Error>
+    pub fn download_image(&self, url: &str, progress: &dyn Fn(u64)) -> Result<Vec<u8>, E621Error> {
+        let response = self
+            .metered(url, |client| client.download(url, progress))
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))?;
+        Ok(Self::check_status(url, response)?.body)
+    }
 
-        image_bytes
+    /// Fetches the preview (thumbnail) of a post.
+    ///
+    /// Unlike [RequestSender::download_image], failures aren't logged, since a missing preview is only traced.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to the preview.
+    ///
+    /// returns: Result<Vec<u8, Global>, E621Error>
+    pub fn fetch_preview(&self, url: &str) -> Result<Vec<u8>, E621Error> {
+        let response = self.metered(url, |client| client.get(url, &[], false))?;
+        if !response.status.is_success() {
+            return Err(E621Error::Status {
+                url: url.to_string(),
+                status: response.status,
+            });
+        }
+
+        Ok(response.body)
     }
 
     /// Appends base url with id/name before ending with `.json`.
@@ -298,7 +526,7 @@ impl RequestSender {
     /// * `append`: The id/name.
     ///
     /// returns: String
-    pub(crate) fn append_url(&self, url: &str, append: &str) -> String {
+    pub fn append_url(&self, url: &str, append: &str) -> String {
         format!("{url}{append}.json")
     }
 
@@ -309,52 +537,206 @@ impl RequestSender {
     /// * `id`: The id to search for.
     /// * `url_type_key`: The type of url to use.
     ///
-    /// returns: T
-    pub(crate) fn get_entry_from_appended_id<T>(&self, id: &str, url_type_key: &str) -> T
+    /// returns: Result<T, E621Error>
+    pub fn get_entry_from_appended_id<T>(
+        &self,
+        id: &str,
+        url_type_key: &str,
+    ) -> Result<T, E621Error>
     where
         T: DeserializeOwned,
     {
-        let value: Value = self
-            .check_response(
-                self.client
-                    .get_with_auth(&self.append_url(&self.urls.borrow()[url_type_key], id))
-                    .send(),
-            )
-            .json()
-            .with_context(|| {
-                format!(
-                    "Json was unable to deserialize to \"{}\"!\n\
-                     url_type_key: {}\n\
-                     id: {}",
-                    type_name::<Value>(),
-                    url_type_key,
-                    id
-                )
-            })
-            .unwrap();
+        let url = self.append_url(&self.urls.borrow()[url_type_key], id);
+        let body = match url_type_key {
+            "pool" | "set" => self.cached_response_text(&url, &[], true)?,
+            _ => self.response_text(&url, &[], true)?,
+        };
+        let value: Value = Self::parse_body(&body, url_type_key)?;
 
         let value = match url_type_key {
-            "single" => value
-                .get("post")
-                .unwrap_or_else(|| {
-                    emergency_exit(&format!(
-                        "Post was not found! Post ID ({}) is invalid or post was deleted.",
-                        id
-                    ));
-                    unreachable!()
-                })
-                .to_owned(),
+            "single" => value.get("post").cloned().ok_or_else(|| {
+                E621Error::ApiSchema(format!(
+                    "Post was not found! Post ID ({id}) is invalid or post was deleted."
+                ))
+            })?,
             _ => value,
         };
 
-        from_value(value)
-            .with_context(|| {
-                error!("Could not convert entry to type \"{}\"!", type_name::<T>());
-                "Unexpected error occurred when trying to perform conversion from value to entry \
-                type above."
-                    .to_string()
+        Self::parse_body(&value.to_string(), url_type_key)
+    }
+
+    /// Sends a request and reads the body of its response.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login is attached to the request.
+    ///
+    /// returns: Result<String, E621Error>
+    fn response_text(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<String, E621Error> {
+        let response = self.check_response(url, query, authenticated)?;
+        Ok(String::from_utf8_lossy(&response.body).into_owned())
+    }
+
+    /// Sends a request and returns the body as text, going through the response cache.
+    ///
+    /// A cached body younger than the TTL in the config is used without asking the server, and an older one is used
+    /// when the server confirms (through its ETag) that the response hasn't changed. Nothing is cached when the TTL is
+    /// `0`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url`: The url to request.
+    /// * `query`: The query parameters of the request.
+    /// * `authenticated`: Whether the login is attached to the request.
+    ///
+    /// returns: Result<String, E621Error>
+    fn cached_response_text(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        authenticated: bool,
+    ) -> Result<String, E621Error> {
+        let ttl = Config::get().metadata_cache_ttl_hours() * 60 * 60;
+        let login = if authenticated && self.client.is_authenticated() {
+            Login::get().username()
+        } else {
+            "anon"
+        };
+        let key = ResponseCache::key(url, query, login);
+        let cached = if ttl > 0 { self.cache.get(&key) } else { None };
+        if let Some(cached) = cached.as_ref().filter(|e| e.is_fresh(ttl)) {
+            trace!("Using the cached response of {key}...");
+            return Ok(cached.body().to_string());
+        }
+
+        let response = self
+            .metered(url, |client| match cached.as_ref().and_then(|e| e.etag()) {
+                Some(etag) => client.get_if_none_match(url, query, authenticated, etag),
+                None => client.get(url, query, authenticated),
             })
-            .unwrap()
+            .inspect_err(|e| error!("Error occurred from sent request. Error: {e}"))?;
+        let (etag, body) = match (response.status, cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => {
+                trace!("{key} didn't change, using the cached response...");
+                (cached.etag().map(String::from), cached.body().to_string())
+            }
+            _ => {
+                let response = Self::check_status(url, response)?;
+                let body = String::from_utf8_lossy(&response.body).into_owned();
+                (response.etag, body)
+            }
+        };
+
+        if ttl > 0 {
+            if let Err(e) = self.cache.insert(&key, etag.as_deref(), &body) {
+                warn!("Unable to cache the response of {key}: {e}");
+            }
+        }
+
+        Ok(body)
+    }
+
+    /// Deserializes a response body, saving the raw body to the debug directory if it fails.
+    ///
+    /// The entries default any missing field, and fields the entries don't know about are logged once, so changes to
+    /// the API's schema don't stop the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `body`: The response body.
+    /// * `name`: What the response is for (used in the name of the debug file).
+    ///
+    /// returns: Result<T, E621Error>
+    fn parse_body<T>(body: &str, name: &str) -> Result<T, E621Error>
+    where
+        T: DeserializeOwned,
+    {
+        let mut deserializer = Deserializer::from_str(body);
+        serde_ignored::deserialize(&mut deserializer, |path| {
+            report_unknown_field(name, &path.to_string())
+        })
+        .map_err(|e| Self::parse_error::<T>(body, name, e))
+    }
+
+    /// Deserializes a response body that is a list of entries.
+    ///
+    /// e621 returns an object instead of an empty array when nothing matches, which is returned as an empty list.
+    ///
+    /// # Arguments
+    ///
+    /// * `body`: The response body.
+    /// * `name`: What the response is for (used in the name of the debug file).
+    ///
+    /// returns: Result<Vec<T, Global>, E621Error>
+    fn parse_list<T>(body: &str, name: &str) -> Result<Vec<T>, E621Error>
+    where
+        T: DeserializeOwned,
+    {
+        let result: Value = Self::parse_body(body, name)?;
+        if result.is_object() {
+            return Ok(vec![]);
+        }
+
+        serde_ignored::deserialize(result, |path| report_unknown_field(name, &path.to_string()))
+            .map_err(|e| Self::parse_error::<Vec<T>>(body, name, e))
+    }
+
+    /// Logs a response body that couldn't be deserialized and saves it to the debug directory.
+    ///
+    /// # Arguments
+    ///
+    /// * `body`: The response body.
+    /// * `name`: What the response is for (used in the name of the debug file).
+    /// * `error`: Why the body couldn't be deserialized.
+    ///
+    /// returns: E621Error
+    fn parse_error<T>(body: &str, name: &str, error: serde_json::Error) -> E621Error {
+        error!(
+            "Unable to deserialize the {name} response to \"{}\": {error}",
+            type_name::<T>()
+        );
+        match dump_response(name, body) {
+            Ok(path) => info!(
+                "The raw response was saved to {}, please include it when reporting this issue.",
+                path.display()
+            ),
+            Err(e) => warn!("Unable to save the raw response: {e}"),
+        }
+
+        E621Error::Parse {
+            name: name.to_string(),
+            error,
+        }
+    }
+
+    /// Checks whether the entry with the id exists.
+    ///
+    /// # Arguments
+    ///
+    /// * `id`: The id of the entry.
+    /// * `url_type_key`: The type of entry (`single`, `set`, or `pool`).
+    ///
+    /// returns: Result<bool, E621Error>
+    pub fn entry_exists(&self, id: &str, url_type_key: &str) -> Result<bool, E621Error> {
+        let url = self.append_url(&self.urls.borrow()[url_type_key], id);
+        let response = self.send(&url, &[], true)?;
+        if !response.status.is_success() {
+            return Ok(false);
+        }
+
+        Ok(match url_type_key {
+            "single" => from_slice::<Value>(&response.body)
+                .map(|e| e.get("post").is_some())
+                .unwrap_or(false),
+            _ => true,
+        })
     }
 
     /// Performs a bulk search for posts using tags to filter the response.
@@ -364,116 +746,452 @@ impl RequestSender {
     /// * `searching_tag`: The tags for filtering.
     /// * `page`: The page to search for.
     ///
-    /// returns: BulkPostEntry
-    pub(crate) fn bulk_search(&self, searching_tag: &str, page: u16) -> BulkPostEntry {
+    /// returns: Result<BulkPostEntry, E621Error>
+    pub fn bulk_search(&self, searching_tag: &str, page: u16) -> Result<BulkPostEntry, E621Error> {
         debug!("Downloading page {page} of tag {searching_tag}");
 
-        self.check_response(
-            self.client
-                .get_with_auth(&self.urls.borrow()["posts"])
-                .query(&[
-                    ("tags", searching_tag),
-                    ("page", &format!("{page}")),
-                    ("limit", &320.to_string()),
-                ])
-                .send(),
-        )
-        .json()
-        .with_context(|| {
-            error!(
-                "Unable to deserialize json to \"{}\"!",
-                type_name::<Vec<PostEntry>>()
-            );
-            "Failed to perform bulk search...".to_string()
-        })
-        .unwrap()
+        let body = self.response_text(
+            &self.urls.borrow()["posts"],
+            &[
+                ("tags", searching_tag),
+                ("page", &format!("{page}")),
+                ("limit", &320.to_string()),
+            ],
+            true,
+        )?;
+        Self::parse_body(&body, "posts")
     }
 
-    /// Gets tags by their name.
+    /// Searches for pools by their name and/or the name of their creator, newest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name pattern to match, where `*` matches any amount of characters (e.g `*dragon*`).
+    /// * `creator`: The name of the user who created the pools.
+    /// * `page`: The page to search for.
+    ///
+    /// returns: Result<Vec<PoolEntry, Global>, E621Error>
+    pub fn pools_search(
+        &self,
+        name: Option<&str>,
+        creator: Option<&str>,
+        page: u16,
+    ) -> Result<Vec<PoolEntry>, E621Error> {
+        let page = page.to_string();
+        let mut query = vec![("page", page.as_str()), ("limit", "320")];
+        if let Some(name) = name {
+            query.push(("search[name_matches]", name));
+        }
+        if let Some(creator) = creator {
+            query.push(("search[creator_name]", creator));
+        }
+
+        let body = self.response_text(&self.urls.borrow()["pool_bulk"], &query, false)?;
+        Self::parse_list(&body, "pool_bulk")
+    }
+
+    /// Gets an artist by their name, returning [None] if there is no artist with the name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name`: The name of the artist (their artist tag).
+    ///
+    /// returns: Result<Option<ArtistEntry>, E621Error>
+    pub fn get_artist(&self, name: &str) -> Result<Option<ArtistEntry>, E621Error> {
+        let body = self.response_text(
+            &self.urls.borrow()["artist"],
+            &[("search[name]", name)],
+            false,
+        )?;
+        let artists: Vec<ArtistEntry> = Self::parse_list(&body, "artist")?;
+        Ok(artists.into_iter().find(|e| e.name == name))
+    }
+
+    /// Gets the active notes of a post.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post.
+    ///
+    /// returns: Result<Vec<NoteEntry, Global>, E621Error>
+    pub fn get_notes(&self, post_id: i64) -> Result<Vec<NoteEntry>, E621Error> {
+        let body = self.response_text(
+            &self.urls.borrow()["note"],
+            &[
+                ("search[post_id]", &post_id.to_string()),
+                ("search[is_active]", "true"),
+                ("limit", "320"),
+            ],
+            true,
+        )?;
+        Self::parse_list(&body, "note")
+    }
+
+    /// Gets the comments of a post, oldest first.
+    ///
+    /// Every page of comments is requested, until a page has less than [PAGE_LIMIT] comments.
+    ///
+    /// # Arguments
+    ///
+    /// * `post_id`: The id of the post.
+    ///
+    /// returns: Result<Vec<CommentEntry, Global>, E621Error>
+    pub fn get_comments(&self, post_id: i64) -> Result<Vec<CommentEntry>, E621Error> {
+        let mut comments: Vec<CommentEntry> = Vec::new();
+        for page in 1.. {
+            let body = self.response_text(
+                &self.urls.borrow()["comment"],
+                &[
+                    ("group_by", "comment"),
+                    ("search[post_id]", &post_id.to_string()),
+                    ("limit", &PAGE_LIMIT.to_string()),
+                    ("page", &page.to_string()),
+                ],
+                true,
+            )?;
+            let page: Vec<CommentEntry> = Self::parse_list(&body, "comment")?;
+            let last_page = page.len() < PAGE_LIMIT;
+            comments.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        comments.sort_by_key(|e| e.id);
+        Ok(comments)
+    }
+
+    /// Gets the wiki page of a tag, returning [None] if the tag has no wiki page.
+    ///
+    /// # Arguments
+    ///
+    /// * `title`: The title of the wiki page (the name of the tag).
+    ///
+    /// returns: Result<Option<WikiPageEntry>, E621Error>
+    pub fn get_wiki_page(&self, title: &str) -> Result<Option<WikiPageEntry>, E621Error> {
+        let body = self.response_text(
+            &self.urls.borrow()["wiki"],
+            &[("search[title]", title)],
+            false,
+        )?;
+        let pages: Vec<WikiPageEntry> = Self::parse_list(&body, "wiki")?;
+        Ok(pages
+            .into_iter()
+            .find(|e| e.title == title && !e.is_deleted))
+    }
+
+    /// Gets the sets a user created or maintains, including their private sets when the user is the one logged in.
+    ///
+    /// # Arguments
+    ///
+    /// * `username`: The name of the user.
+    ///
+    /// returns: Result<Vec<SetEntry, Global>, E621Error>
+    pub fn sets_of_user(&self, username: &str) -> Result<Vec<SetEntry>, E621Error> {
+        let mut sets: Vec<SetEntry> =
+            self.all_pages("set_bulk", &[("search[creator_name]", username)])?;
+        let user: UserEntry = self.get_entry_from_appended_id(username, "user")?;
+        let maintained: Vec<SetEntry> = self.all_pages(
+            "set_bulk",
+            &[("search[maintainer_id]", &user.id.to_string())],
+        )?;
+        for set in maintained {
+            if !sets.iter().any(|e| e.id == set.id) {
+                sets.push(set);
+            }
+        }
+
+        Ok(sets)
+    }
+
+    /// Gets every saved search of the logged in user.
+    ///
+    /// returns: Result<Vec<SavedSearchEntry, Global>, E621Error>
+    pub fn get_saved_searches(&self) -> Result<Vec<SavedSearchEntry>, E621Error> {
+        self.all_pages("saved_search", &[])
+    }
+
+    /// Sends an authenticated request for every page of a list, until a page has less than [PAGE_LIMIT] entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `url_type_key`: The key of the url to request.
+    /// * `query`: The query parameters of the request, without the limit and page.
+    ///
+    /// returns: Result<Vec<T, Global>, E621Error>
+    fn all_pages<T>(&self, url_type_key: &str, query: &[(&str, &str)]) -> Result<Vec<T>, E621Error>
+    where
+        T: DeserializeOwned,
+    {
+        let limit = PAGE_LIMIT.to_string();
+        let mut entries: Vec<T> = Vec::new();
+        for page in 1.. {
+            let page = page.to_string();
+            let query: Vec<(&str, &str)> = query
+                .iter()
+                .copied()
+                .chain([("limit", limit.as_str()), ("page", page.as_str())])
+                .collect();
+            let body = self.response_text(&self.urls.borrow()[url_type_key], &query, true)?;
+            let page: Vec<T> = Self::parse_list(&body, url_type_key)?;
+            let last_page = page.len() < PAGE_LIMIT;
+            entries.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Gets tags by their name, reusing the result when the tag was already looked up this run.
     ///
     /// # Arguments
     ///
     /// * `tag`: The name of the tag.
     ///
-    /// returns: Vec<TagEntry, Global>
-    pub(crate) fn get_tags_by_name(&self, tag: &str) -> Vec<TagEntry> {
-        let result: Value = self
-            .check_response(
-                self.client
-                    .get(&self.urls.borrow()["tag_bulk"])
-                    .query(&[("search[name]", tag)])
-                    .send(),
+    /// returns: Result<Vec<TagEntry, Global>, E621Error>
+    pub fn get_tags_by_name(&self, tag: &str) -> Result<Vec<TagEntry>, E621Error> {
+        if let Some(entries) = self.tag_lookups.borrow().get(tag) {
+            trace!("Tag {tag} was already looked up this run...");
+            return Ok(entries.clone());
+        }
+
+        let entries = self.fetch_tags_by_name(tag)?;
+        self.tag_lookups
+            .borrow_mut()
+            .insert(tag.to_string(), entries.clone());
+        Ok(entries)
+    }
+
+    /// Asks the API (or the response cache) for tags by their name.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The name of the tag.
+    ///
+    /// returns: Result<Vec<TagEntry, Global>, E621Error>
+    fn fetch_tags_by_name(&self, tag: &str) -> Result<Vec<TagEntry>, E621Error> {
+        let body = self.cached_response_text(
+            &self.urls.borrow()["tag_bulk"],
+            &[("search[name]", tag)],
+            false,
+        )?;
+        Self::parse_list(&body, "tag_bulk")
+    }
+
+    /// Searches for tags starting with the prefix, ordered from most to least used.
+    ///
+    /// Unlike the other searches, failed requests aren't logged, since this is used while the user is typing. Responses
+    /// that can't be parsed are still reported and saved like every other list (see [RequestSender::parse_list]).
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix`: The start of the tag name.
+    /// * `limit`: The maximum amount of tags to return.
+    ///
+    /// returns: Result<Vec<TagEntry, Global>, E621Error>
+    pub fn search_tags_by_prefix(
+        &self,
+        prefix: &str,
+        limit: u8,
+    ) -> Result<Vec<TagEntry>, E621Error> {
+        let url = &self.urls.borrow()["tag_bulk"];
+        let response = self.metered(url, |client| {
+            client.get(
+                url,
+                &[
+                    ("search[name_matches]", format!("{prefix}*").as_str()),
+                    ("search[order]", "count"),
+                    ("limit", limit.to_string().as_str()),
+                ],
+                false,
             )
-            .json()
-            .with_context(|| {
-                format!(
-                    "Json was unable to deserialize to \"{}\"!\n\
-                     url_type_key: tag_bulk\n\
-                     tag: {}",
-                    type_name::<Value>(),
-                    tag
-                )
-            })
-            .unwrap();
-        if result.is_object() {
-            vec![]
-        } else {
-            from_value::<Vec<TagEntry>>(result)
-                .with_context(|| {
-                    error!(
-                        "Unable to deserialize Value to \"{}\"!",
-                        type_name::<Vec<TagEntry>>()
-                    );
-                    "Failed to perform bulk search...".to_string()
-                })
-                .unwrap()
+        })?;
+        if !response.status.is_success() {
+            return Err(E621Error::Status {
+                url: url.to_string(),
+                status: response.status,
+            });
         }
+
+        Self::parse_list(&String::from_utf8_lossy(&response.body), "tag_bulk")
     }
 
-    /// Queries aliases and returns response.
+    /// Gets the active implications that imply the tag, which are the tags that are a kind of it (e.g `wolf` and `fox`
+    /// for `canine`).
+    ///
+    /// Every page of implications is requested, until a page has less than [PAGE_LIMIT] implications.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The implied tag.
+    ///
+    /// returns: Result<Vec<ImplicationEntry, Global>, E621Error>
+    pub fn get_implications_of(&self, tag: &str) -> Result<Vec<ImplicationEntry>, E621Error> {
+        let mut implications: Vec<ImplicationEntry> = Vec::new();
+        for page in 1.. {
+            let body = self.cached_response_text(
+                &self.urls.borrow()["implication"],
+                &[
+                    ("search[consequent_name]", tag),
+                    ("search[status]", "active"),
+                    ("limit", &PAGE_LIMIT.to_string()),
+                    ("page", &page.to_string()),
+                ],
+                false,
+            )?;
+            let page: Vec<ImplicationEntry> = Self::parse_list(&body, "implication")?;
+            let last_page = page.len() < PAGE_LIMIT;
+            implications.extend(page);
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(implications)
+    }
+
+    /// Queries aliases and returns response, reusing the result when the alias was already looked up this run.
     ///
     /// # Arguments
     ///
     /// * `tag`: The alias to search for.
     ///
-    /// returns: Option<Vec<AliasEntry, Global>>
+    /// returns: Result<Option<Vec<AliasEntry, Global>>, E621Error>
     ///
     /// # Examples
     ///
     /// ```
     ///
     /// ```
-    pub(crate) fn query_aliases(&self, tag: &str) -> Option<Vec<AliasEntry>> {
-        let result = self
-            .check_response(
-                self.client
-                    .get(&self.urls.borrow()["alias"])
-                    .query(&[
-                        ("commit", "Search"),
-                        ("search[name_matches]", tag),
-                        ("search[order]", "status"),
-                    ])
-                    .send(),
-            )
-            .json::<Vec<AliasEntry>>();
+    pub fn query_aliases(&self, tag: &str) -> Result<Option<Vec<AliasEntry>>, E621Error> {
+        if let Some(aliases) = self.alias_lookups.borrow().get(tag) {
+            trace!("Alias {tag} was already looked up this run...");
+            return Ok(aliases.clone());
+        }
 
-        match result {
-            Ok(e) => Some(e),
+        let aliases = self.fetch_aliases(tag)?;
+        self.alias_lookups
+            .borrow_mut()
+            .insert(tag.to_string(), aliases.clone());
+        Ok(aliases)
+    }
+
+    /// Asks the API (or the response cache) for aliases of a tag.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag`: The alias to search for.
+    ///
+    /// returns: Result<Option<Vec<AliasEntry, Global>>, E621Error>
+    fn fetch_aliases(&self, tag: &str) -> Result<Option<Vec<AliasEntry>>, E621Error> {
+        let body = self.cached_response_text(
+            &self.urls.borrow()["alias"],
+            &[
+                ("commit", "Search"),
+                ("search[name_matches]", tag),
+                ("search[order]", "status"),
+            ],
+            false,
+        )?;
+
+        match from_str::<Vec<AliasEntry>>(&body) {
+            Ok(e) => Ok(Some(e)),
             Err(e) => {
                 trace!("No alias was found for {tag}...");
                 trace!("Printing trace message for why None was returned...");
                 trace!("{}", e.to_string());
-                None
+                Ok(None)
             }
         }
     }
 }
 
+/// Logs a field the entries don't know about, only the first time it's seen.
+///
+/// # Arguments
+///
+/// * `name`: What the response is for.
+/// * `path`: The path of the field (e.g `posts.3.new_field`).
+fn report_unknown_field(name: &str, path: &str) {
+    static REPORTED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    // Indexes are removed so the same field is only reported once for every post in a response.
+    let field = path
+        .split('.')
+        .filter(|e| e.parse::<usize>().is_err())
+        .collect::<Vec<&str>>()
+        .join(".");
+    let key = format!("{name}: {field}");
+    if let Ok(mut reported) = REPORTED.lock() {
+        if !reported.contains(&key) {
+            trace!("The {name} response has an unknown field \"{field}\", it will be ignored...");
+            reported.push(key);
+        }
+    }
+}
+
+/// Saves a raw response body to a timestamped file in the debug directory, returning the path of the file.
+///
+/// # Arguments
+///
+/// * `name`: What the response is for.
+/// * `body`: The response body.
+///
+/// returns: Result<PathBuf, Error>
+fn dump_response(name: &str, body: &str) -> Result<PathBuf, io::Error> {
+    let directory = state_directory().join(DEBUG_DIRECTORY);
+    create_dir_all(&directory)?;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_millis())
+        .unwrap_or_default();
+    let path = directory.join(format!("{timestamp}_{name}.json"));
+    write(&path, body)?;
+
+    Ok(path)
+}
+
+/// Sends a request, counting it in the [RequestMetrics], after waiting if the last response reported that the request
+/// budget is nearly used up.
+///
+/// # Arguments
+///
+/// * `throttle`: The throttle holding requests back.
+/// * `metrics`: The counters the request is counted in.
+/// * `url`: The url the request is sent to.
+/// * `send`: Sends the request.
+///
+/// returns: Result<ApiResponse, E621Error>
+fn metered(
+    throttle: &Throttle,
+    metrics: &Mutex<RequestMetrics>,
+    url: &str,
+    send: impl FnOnce() -> Result<ApiResponse, E621Error>,
+) -> Result<ApiResponse, E621Error> {
+    throttle.wait();
+    let start = Instant::now();
+    let result = send();
+    throttle.update(result.as_ref().ok().and_then(|e| e.rate_limit.as_ref()));
+    metrics
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .record(url, start.elapsed(), &result);
+    result
+}
+
 impl Clone for RequestSender {
     fn clone(&self) -> Self {
         RequestSender {
-            client: self.client.clone(),
+            client: Rc::clone(&self.client),
             urls: Rc::clone(&self.urls),
+            cache: Rc::clone(&self.cache),
+            tag_lookups: Rc::clone(&self.tag_lookups),
+            alias_lookups: Rc::clone(&self.alias_lookups),
+            metrics: Arc::clone(&self.metrics),
+            throttle: Arc::clone(&self.throttle),
         }
     }
 }
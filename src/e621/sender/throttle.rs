@@ -0,0 +1,173 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::{Mutex, PoisonError};
+use std::thread::sleep;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Headers holding the amount of requests that can still be sent before the budget resets.
+const REMAINING_HEADERS: [&str; 3] = [
+    "x-ratelimit-remaining",
+    "ratelimit-remaining",
+    "x-rate-limit-remaining",
+];
+
+/// Headers holding the amount of requests the budget allows.
+const LIMIT_HEADERS: [&str; 3] = ["x-ratelimit-limit", "ratelimit-limit", "x-rate-limit-limit"];
+
+/// Headers holding when the budget resets, either in seconds from now or as a unix timestamp.
+const RESET_HEADERS: [&str; 3] = ["x-ratelimit-reset", "ratelimit-reset", "x-rate-limit-reset"];
+
+/// The reset window assumed when the server doesn't say when the budget resets.
+const DEFAULT_RESET: Duration = Duration::from_secs(1);
+
+/// The longest a request is held back, so a far away reset doesn't stall the run.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Reset values above this are unix timestamps instead of seconds from now.
+const TIMESTAMP_THRESHOLD: u64 = 1_000_000_000;
+
+/// The request budget the server reported in the headers of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// The amount of requests the budget allows, if reported.
+    pub limit: Option<u64>,
+    /// The amount of requests that can still be sent before the budget resets.
+    pub remaining: u64,
+    /// How long until the budget resets, if reported.
+    pub reset: Option<Duration>,
+}
+
+impl RateLimit {
+    /// Reads the budget from the headers of a response, returning [None] if the server didn't report one.
+    ///
+    /// A `Retry-After` header (sent with 429 and 503 responses) counts as an exhausted budget.
+    ///
+    /// # Arguments
+    ///
+    /// * `headers`: The headers of the response.
+    ///
+    /// returns: Option<RateLimit>
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let number = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|e| headers.get(*e))
+                .and_then(|e| e.to_str().ok())
+                .and_then(|e| e.trim().parse::<u64>().ok())
+        };
+
+        if let Some(retry_after) = number(&[RETRY_AFTER.as_str()]) {
+            return Some(RateLimit {
+                limit: number(&LIMIT_HEADERS),
+                remaining: 0,
+                reset: Some(Duration::from_secs(retry_after)),
+            });
+        }
+
+        Some(RateLimit {
+            limit: number(&LIMIT_HEADERS),
+            remaining: number(&REMAINING_HEADERS)?,
+            reset: number(&RESET_HEADERS).map(seconds_until),
+        })
+    }
+
+    /// Whether the budget is nearly used up, which is a tenth of the limit (or 5 requests when the limit is unknown).
+    pub fn is_nearly_exhausted(&self) -> bool {
+        let threshold = self.limit.map_or(5, |e| (e / 10).max(1));
+        self.remaining <= threshold
+    }
+
+    /// The time to wait before the next request, spreading the remaining requests over the time until the reset (at
+    /// most [MAX_DELAY]).
+    pub fn delay(&self) -> Duration {
+        let reset = self.reset.unwrap_or(DEFAULT_RESET);
+        let delay = match self.remaining {
+            0 => reset,
+            remaining => reset / (remaining as u32).saturating_add(1),
+        };
+        delay.min(MAX_DELAY)
+    }
+}
+
+/// Converts a reset header into the time until the reset.
+///
+/// # Arguments
+///
+/// * `reset`: The seconds until the reset, or the unix timestamp of the reset.
+///
+/// returns: Duration
+fn seconds_until(reset: u64) -> Duration {
+    if reset < TIMESTAMP_THRESHOLD {
+        return Duration::from_secs(reset);
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|e| e.as_secs())
+        .unwrap_or_default();
+    Duration::from_secs(reset.saturating_sub(now))
+}
+
+/// Slows requests down when the server reports that the request budget is nearly used up, instead of waiting for
+/// the server to answer with 421 or 503.
+///
+/// The throttle can be shared between threads, requests sent at the same time are then spaced out one after another.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    /// When the next request can be sent and the time between requests, if requests have to wait.
+    schedule: Mutex<Option<(Instant, Duration)>>,
+}
+
+impl Throttle {
+    /// Waits until the next request can be sent, and holds the request after it back by the time between requests.
+    pub fn wait(&self) {
+        let next_request = {
+            let mut schedule = self.schedule.lock().unwrap_or_else(PoisonError::into_inner);
+            schedule.as_mut().map(|(next_request, spacing)| {
+                let start = (*next_request).max(Instant::now());
+                *next_request = start + *spacing;
+                start
+            })
+        };
+
+        if let Some(next_request) = next_request {
+            let delay = next_request.saturating_duration_since(Instant::now());
+            if !delay.is_zero() {
+                trace!(
+                    "Request budget is nearly used up, waiting {}ms...",
+                    delay.as_millis()
+                );
+                sleep(delay);
+            }
+        }
+    }
+
+    /// Schedules the next request from the budget reported in a response, or stops holding requests back if the budget
+    /// isn't nearly used up.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate_limit`: The budget reported in the response, if any.
+    pub fn update(&self, rate_limit: Option<&RateLimit>) {
+        let schedule = rate_limit
+            .filter(|e| e.is_nearly_exhausted())
+            .map(|e| (Instant::now() + e.delay(), e.delay()));
+        *self.schedule.lock().unwrap_or_else(PoisonError::into_inner) = schedule;
+    }
+}
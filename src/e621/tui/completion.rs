@@ -0,0 +1,85 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use dialoguer::Completion;
+
+use crate::e621::sender::RequestSender;
+
+/// The minimum amount of characters typed before tags are searched.
+const MIN_PREFIX_LENGTH: usize = 2;
+
+/// The amount of tag suggestions listed when tab is pressed.
+const SUGGESTION_COUNT: u8 = 5;
+
+/// Completes the tag currently being typed with the most used tag on e621 that starts with it.
+///
+/// The tag being typed is everything after the last comma or space, so this works for both single tags and lists.
+pub(crate) struct TagCompletion {
+    /// The sender used to search for tags.
+    request_sender: RequestSender,
+}
+
+impl TagCompletion {
+    /// Creates a new tag completion using the request sender.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used to search for tags.
+    pub(crate) fn new(request_sender: RequestSender) -> Self {
+        TagCompletion { request_sender }
+    }
+}
+
+impl Completion for TagCompletion {
+    fn get(&self, input: &str) -> Option<String> {
+        let split = input.rfind([',', ' ']).map(|i| i + 1).unwrap_or(0);
+        let (head, prefix) = input.split_at(split);
+        let prefix = prefix.trim_start_matches('-').to_lowercase();
+        if prefix.len() < MIN_PREFIX_LENGTH {
+            return None;
+        }
+
+        let tags = match self
+            .request_sender
+            .search_tags_by_prefix(&prefix, SUGGESTION_COUNT)
+        {
+            Ok(tags) => tags,
+            Err(e) => {
+                trace!("Unable to search tags for completion: {e}");
+                return None;
+            }
+        };
+
+        let best = tags.first()?;
+        if tags.len() > 1 {
+            let suggestions: Vec<String> = tags
+                .iter()
+                .map(|e| format!("{} ({})", e.name, e.post_count))
+                .collect();
+            trace!(
+                "Tag suggestions for \"{prefix}\": {}",
+                suggestions.join(", ")
+            );
+        }
+
+        let negation = if input[split..].starts_with('-') {
+            "-"
+        } else {
+            ""
+        };
+        Some(format!("{head}{negation}{}", best.name))
+    }
+}
@@ -0,0 +1,288 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::{read_to_string, write};
+
+use anyhow::Error;
+use dialoguer::{Editor, Input, MultiSelect, Select};
+use indicatif::HumanBytes;
+
+use crate::e621::io::report::{RunReport, REPORT_NAME};
+use crate::e621::io::tag::{is_toml_tag_file, tag_file_path, Group};
+use crate::e621::io::{config_path, is_toml_config, Config};
+use crate::e621::sender::entries::SetEntry;
+use crate::e621::sender::RequestSender;
+use crate::e621::tui::completion::TagCompletion;
+
+/// What the user chose to do from the [MainMenu].
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum MenuChoice {
+    /// Start downloading.
+    Download,
+    /// Exit the program.
+    Exit,
+}
+
+/// Shows a checkbox menu of the groups from the tag file, returning only the groups the user selected.
+///
+/// # Arguments
+///
+/// * `groups`: The groups to choose from.
+///
+/// returns: Result<Vec<Group, Global>, Error>
+pub(crate) fn select_groups(groups: Vec<Group>) -> Result<Vec<Group>, Error> {
+    let items: Vec<String> = groups
+        .iter()
+        .map(|e| format!("[{}] ({} entries)", e.name(), e.tags().len()))
+        .collect();
+    let defaults = vec![true; items.len()];
+    let selected = MultiSelect::new()
+        .with_prompt("Which groups should be downloaded? (space to toggle, enter to confirm)")
+        .items(&items)
+        .defaults(&defaults)
+        .interact()?;
+
+    trace!("Selected groups: {selected:?}");
+    Ok(groups
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, e)| e)
+        .collect())
+}
+
+/// Adds entries to the first group with the name in the (legacy) tag file, adding the group to the end of the file if
+/// it doesn't have one.
+///
+/// # Arguments
+///
+/// * `group`: The name of the group (e.g `artists`).
+/// * `entries`: The entries to add.
+///
+/// returns: Result<(), Error>
+pub(crate) fn add_to_tag_file(group: &str, entries: &[&str]) -> Result<(), Error> {
+    let tag_file = tag_file_path();
+    let contents = read_to_string(&tag_file).unwrap_or_default();
+    let mut lines: Vec<String> = contents.lines().map(String::from).collect();
+    let header = format!("[{group}]");
+    let position = match lines.iter().position(|e| e.trim() == header) {
+        Some(i) => i + 1,
+        None => {
+            lines.push(header);
+            lines.len()
+        }
+    };
+    for (i, entry) in entries.iter().enumerate() {
+        lines.insert(position + i, entry.to_string());
+    }
+
+    write(tag_file, lines.join("\n") + "\n")?;
+    Ok(())
+}
+
+/// Shows a checkbox menu of sets, returning only the sets the user selected.
+///
+/// # Arguments
+///
+/// * `sets`: The sets to choose from.
+///
+/// returns: Result<Vec<SetEntry, Global>, Error>
+pub(crate) fn select_sets(sets: Vec<SetEntry>) -> Result<Vec<SetEntry>, Error> {
+    let items: Vec<String> = sets
+        .iter()
+        .map(|e| {
+            let visibility = if e.is_public { "" } else { ", private" };
+            format!("{} ({} posts{visibility})", e.name, e.post_count)
+        })
+        .collect();
+    let selected = MultiSelect::new()
+        .with_prompt("Which sets should be downloaded? (space to toggle, enter to confirm)")
+        .items(&items)
+        .interact()?;
+
+    trace!("Selected sets: {selected:?}");
+    Ok(sets
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| selected.contains(i))
+        .map(|(_, e)| e)
+        .collect())
+}
+
+/// The top-level menu which lets the user download, edit their tags and settings, and view the last report.
+pub(crate) struct MainMenu;
+
+impl MainMenu {
+    /// The items shown in the menu.
+    const ITEMS: [&'static str; 6] = [
+        "Download now",
+        "Add tag",
+        "Edit tags",
+        "Settings",
+        "View last report",
+        "Exit",
+    ];
+
+    /// Shows the menu until the user chooses to either download or exit.
+    pub(crate) fn run() -> Result<MenuChoice, Error> {
+        loop {
+            let selection = Select::new()
+                .with_prompt("What would you like to do?")
+                .items(&Self::ITEMS)
+                .default(0)
+                .interact()?;
+
+            trace!("Main menu selection: {}", Self::ITEMS[selection]);
+            match selection {
+                0 => return Ok(MenuChoice::Download),
+                1 => Self::add_tag()?,
+                2 => Self::edit_tags()?,
+                3 => Self::edit_settings()?,
+                4 => Self::view_last_report(),
+                _ => return Ok(MenuChoice::Exit),
+            }
+        }
+    }
+
+    /// Asks for a tag, with tab completion, and adds it to a group in the tag file.
+    fn add_tag() -> Result<(), Error> {
+        let tag_file = tag_file_path();
+        if is_toml_tag_file(&tag_file) {
+            info!("Tags can't be added to a structured tag file, use \"Edit tags\" instead...");
+            return Ok(());
+        }
+
+        let contents = read_to_string(&tag_file).unwrap_or_default();
+        let groups: Vec<String> = contents
+            .lines()
+            .map(|e| e.trim())
+            .filter(|e| e.starts_with('[') && e.ends_with(']'))
+            .map(|e| e[1..e.len() - 1].to_string())
+            .collect();
+        if groups.is_empty() {
+            info!("The tag file has no groups, use \"Edit tags\" to add one first...");
+            return Ok(());
+        }
+
+        let group = Select::new()
+            .with_prompt("Which group should the tag be added to?")
+            .items(&groups)
+            .default(0)
+            .interact()?;
+        let completion = TagCompletion::new(RequestSender::anonymous()?);
+        let tag: String = Input::new()
+            .with_prompt("Tag (tab to complete)")
+            .completion_with(&completion)
+            .interact_text()?;
+
+        add_to_tag_file(&groups[group], &[tag.trim()])?;
+        info!("Added \"{}\" to [{}]...", tag.trim(), groups[group]);
+
+        Ok(())
+    }
+
+    /// Opens the tag file in the user's editor and saves the changes.
+    fn edit_tags() -> Result<(), Error> {
+        let tag_file = tag_file_path();
+        let extension = if is_toml_tag_file(&tag_file) {
+            ".toml"
+        } else {
+            ".txt"
+        };
+        let tags = read_to_string(&tag_file).unwrap_or_default();
+        match Editor::new().extension(extension).edit(&tags)? {
+            Some(edited) => {
+                write(tag_file, edited)?;
+                info!("Tag file saved...");
+            }
+            None => info!("Tag file was not changed..."),
+        }
+
+        Ok(())
+    }
+
+    /// Opens the config file in the user's editor, only saving the changes if they are valid.
+    fn edit_settings() -> Result<(), Error> {
+        let path = config_path();
+        let extension = if is_toml_config(&path) {
+            ".toml"
+        } else {
+            ".json"
+        };
+        let config = read_to_string(&path).unwrap_or_default();
+        let edited = match Editor::new().extension(extension).edit(&config)? {
+            Some(edited) => edited,
+            None => {
+                info!("Config file was not changed...");
+                return Ok(());
+            }
+        };
+
+        match Config::parse(&edited, &path) {
+            Ok(_) => {
+                write(&path, edited)?;
+                info!("Config file saved...");
+            }
+            Err(e) => {
+                error!("The edited config is invalid and was not saved: {e}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Prints the report of the last run.
+    fn view_last_report() {
+        match RunReport::load() {
+            Some(report) => {
+                info!("Last run report ({REPORT_NAME}):");
+                info!("Finished at:     {} (unix time)", report.finished_at);
+                info!("Collections:     {}", report.collections);
+                info!("Downloaded:      {}", report.downloaded);
+                info!("Skipped:         {}", report.skipped);
+                info!("Total size:      {}", HumanBytes(report.total_bytes));
+                if !report.blacklist_hits.is_empty() {
+                    info!("Blacklist lines that filtered posts:");
+                    for hit in &report.blacklist_hits {
+                        info!("  {:>6}  {}", hit.posts, hit.line);
+                    }
+                }
+
+                if !report.requests.is_empty() {
+                    info!("Requests ({} retried):", report.retries);
+                    for stats in &report.requests {
+                        info!(
+                            "  {:>6}  {:>4} failed  {:>10}  {:>6}ms avg  {}",
+                            stats.requests,
+                            stats.failures,
+                            HumanBytes(stats.bytes).to_string(),
+                            stats.average_ms,
+                            stats.endpoint
+                        );
+                    }
+                }
+
+                if !report.failures.is_empty() {
+                    info!("Skipped because of errors:");
+                    for failure in &report.failures {
+                        info!("  {}: {}", failure.item, failure.reason);
+                    }
+                }
+            }
+            None => info!("There is no report from a previous run yet..."),
+        }
+    }
+}
@@ -14,11 +14,17 @@
  * limitations under the License.
  */
 
-
 use std::time::Duration;
 
 use indicatif::{ProgressBar, ProgressDrawTarget, ProgressStyle};
 
+pub(crate) mod completion;
+pub(crate) mod menu;
+pub(crate) mod preview;
+pub(crate) mod review;
+pub(crate) mod suggest;
+pub(crate) mod wizard;
+
 /// A builder that helps in making a new [ProgressStyle] for use.
 pub(crate) struct ProgressStyleBuilder {
     /// The [ProgressStyle] being built.
@@ -84,6 +90,13 @@ impl ProgressBarBuilder {
         }
     }
 
+    /// Creates new instance of the builder with a spinner that has no known length.
+    pub(crate) fn spinner() -> Self {
+        Self {
+            progress_bar: ProgressBar::new_spinner(),
+        }
+    }
+
     /// Sets the style of the progress bar to the style given.
     ///
     /// # Arguments
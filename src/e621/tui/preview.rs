@@ -0,0 +1,82 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use anyhow::Error;
+use image::load_from_memory;
+use viuer::{get_kitty_support, is_iterm_supported, print, Config as ViuerConfig, KittySupport};
+
+use crate::e621::grabber::GrabbedPost;
+use crate::e621::sender::RequestSender;
+
+/// The width (in terminal columns) previews are drawn at.
+const PREVIEW_WIDTH: u32 = 40;
+
+/// Checks if the terminal supports the kitty, iTerm, or sixel graphics protocol, the ones previews are drawn with.
+///
+/// Previews are left out when this returns false.
+pub(crate) fn supports_graphics() -> bool {
+    get_kitty_support() != KittySupport::None || is_iterm_supported() || supports_sixel()
+}
+
+/// Checks if the terminal supports sixel graphics.
+#[cfg(all(unix, feature = "sixel"))]
+fn supports_sixel() -> bool {
+    viuer::is_sixel_supported()
+}
+
+/// Sixel graphics are only built on unix with the `sixel` feature, so they're never supported otherwise.
+#[cfg(not(all(unix, feature = "sixel")))]
+fn supports_sixel() -> bool {
+    false
+}
+
+/// Fetches the preview of a post and draws it in the terminal.
+///
+/// Posts without a preview are skipped, and failures are only traced, since a missing preview shouldn't stop the
+/// prompt it's shown with.
+///
+/// # Arguments
+///
+/// * `request_sender`: The sender used to fetch the preview.
+/// * `post`: The post to preview.
+pub(crate) fn preview_post(request_sender: &RequestSender, post: &GrabbedPost) {
+    if let Some(url) = post.preview_url() {
+        if let Err(e) = request_sender
+            .fetch_preview(url)
+            .map_err(Error::from)
+            .and_then(|bytes| show_preview(&bytes))
+        {
+            trace!("Unable to show preview for post {}: {e}", post.id());
+        }
+    }
+}
+
+/// Draws an image in the terminal.
+///
+/// # Arguments
+///
+/// * `bytes`: The encoded image (e.g jpg, png) to draw.
+pub(crate) fn show_preview(bytes: &[u8]) -> Result<(), Error> {
+    let image = load_from_memory(bytes)?;
+    let config = ViuerConfig {
+        width: Some(PREVIEW_WIDTH),
+        absolute_offset: false,
+        ..Default::default()
+    };
+    print(&image, &config)?;
+
+    Ok(())
+}
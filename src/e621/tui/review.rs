@@ -0,0 +1,164 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashSet;
+
+use anyhow::Error;
+use dialoguer::{MultiSelect, Select};
+use indicatif::HumanBytes;
+
+use crate::e621::grabber::{GrabbedPost, PostCollection};
+use crate::e621::sender::RequestSender;
+use crate::e621::tui::preview::{preview_post, supports_graphics};
+
+/// The amount of tags shown for each post while reviewing.
+const REVIEW_TAG_COUNT: usize = 8;
+
+/// A curation step between grabbing and downloading, where the user can deselect posts they don't want.
+pub(crate) struct PostReviewer;
+
+impl PostReviewer {
+    /// Steps through each collection, letting the user deselect posts before they are downloaded.
+    ///
+    /// When a [RequestSender] is given for previews and the terminal supports graphics, each post is shown one at a
+    /// time with its thumbnail instead of as a single checklist.
+    ///
+    /// # Arguments
+    ///
+    /// * `collections`: The grabbed collections to review.
+    /// * `preview_sender`: The sender used to fetch previews, [None] to review without previews.
+    pub(crate) fn review(
+        collections: &mut [PostCollection],
+        preview_sender: Option<&RequestSender>,
+    ) -> Result<(), Error> {
+        let preview_sender = preview_sender.filter(|_| {
+            let supported = supports_graphics();
+            if !supported {
+                warn!("The terminal doesn't support kitty, iTerm, or sixel graphics, previews are disabled...");
+            }
+
+            supported
+        });
+
+        for collection in collections.iter_mut() {
+            if collection.posts().is_empty() {
+                continue;
+            }
+
+            if let Some(request_sender) = preview_sender {
+                Self::review_with_previews(collection, request_sender)?;
+                continue;
+            }
+
+            let items: Vec<String> = collection.posts().iter().map(Self::describe).collect();
+            let defaults = vec![true; items.len()];
+            let selected = MultiSelect::new()
+                .with_prompt(format!(
+                    "Reviewing \"{}\" (space to toggle, enter to confirm)",
+                    collection.name()
+                ))
+                .items(&items)
+                .defaults(&defaults)
+                .max_length(20)
+                .interact()?;
+
+            let total = items.len();
+            let selected: HashSet<usize> = selected.into_iter().collect();
+            let mut index = 0;
+            collection.posts_mut().retain(|_| {
+                let keep = selected.contains(&index);
+                index += 1;
+                keep
+            });
+
+            trace!(
+                "Kept {} of {total} posts in \"{}\" after review...",
+                selected.len(),
+                collection.name()
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Steps through a collection one post at a time, showing each post's preview before asking to keep it.
+    ///
+    /// # Arguments
+    ///
+    /// * `collection`: The collection to review.
+    /// * `request_sender`: The sender used to fetch previews.
+    fn review_with_previews(
+        collection: &mut PostCollection,
+        request_sender: &RequestSender,
+    ) -> Result<(), Error> {
+        const CHOICES: [&str; 3] = ["Keep", "Discard", "Keep all remaining"];
+
+        info!("Reviewing \"{}\"...", collection.name());
+        let mut keep = Vec::with_capacity(collection.posts().len());
+        let mut keep_remaining = false;
+        for post in collection.posts() {
+            if keep_remaining {
+                keep.push(true);
+                continue;
+            }
+
+            preview_post(request_sender, post);
+
+            let choice = Select::new()
+                .with_prompt(Self::describe(post))
+                .items(&CHOICES)
+                .default(0)
+                .interact()?;
+            keep_remaining = choice == 2;
+            keep.push(choice != 1);
+        }
+
+        let mut keep = keep.into_iter();
+        collection
+            .posts_mut()
+            .retain(|_| keep.next().unwrap_or(true));
+
+        Ok(())
+    }
+
+    /// Describes a post with its id, score, size, and first few tags.
+    ///
+    /// # Arguments
+    ///
+    /// * `post`: The post to describe.
+    ///
+    /// returns: String
+    fn describe(post: &GrabbedPost) -> String {
+        let mut tags = post
+            .tags()
+            .iter()
+            .take(REVIEW_TAG_COUNT)
+            .cloned()
+            .collect::<Vec<String>>()
+            .join(" ");
+        if post.tags().len() > REVIEW_TAG_COUNT {
+            tags.push_str(" ...");
+        }
+
+        format!(
+            "#{} | score {} | {} | {}",
+            post.id(),
+            post.score(),
+            HumanBytes(post.file_size() as u64),
+            tags
+        )
+    }
+}
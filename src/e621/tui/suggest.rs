@@ -0,0 +1,135 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Error};
+use console::{style, user_attended};
+use dialoguer::MultiSelect;
+
+use crate::e621::io::tag::{is_toml_tag_file, parse_tag_file_unchecked, tag_file_path};
+use crate::e621::sender::entries::TagEntry;
+use crate::e621::sender::RequestSender;
+use crate::e621::tui::menu::add_to_tag_file;
+
+/// Prints the tags related to a tag with their post counts, then lets the user pick the ones to add to the tag file.
+///
+/// Artists are added to the `artists` group and every other tag to the `general` group. Tags already in the tag file
+/// are listed, but can't be picked again.
+///
+/// # Arguments
+///
+/// * `tag`: The tag to find related tags for.
+/// * `request_sender`: The sender used for the API calls.
+///
+/// returns: Result<(), Error>
+pub(crate) fn suggest_tags(tag: &str, request_sender: &RequestSender) -> Result<(), Error> {
+    let tag = tag.trim().to_lowercase();
+    let Some(entry) = request_sender.get_tags_by_name(&tag)?.into_iter().next() else {
+        bail!("The tag \"{tag}\" doesn't exist on e621!");
+    };
+
+    let names = entry.related_tag_names();
+    if names.is_empty() {
+        info!("e621 has no related tags for \"{tag}\" yet...");
+        return Ok(());
+    }
+
+    // e621 takes a comma separated list of names, which looks every related tag up at once.
+    let entries: HashMap<String, TagEntry> = request_sender
+        .get_tags_by_name(&names.join(","))?
+        .into_iter()
+        .map(|e| (e.name.clone(), e))
+        .collect();
+    let related: Vec<&TagEntry> = names.iter().filter_map(|e| entries.get(*e)).collect();
+    let existing: Vec<String> = parse_tag_file_unchecked(request_sender)
+        .map(|groups| {
+            groups
+                .iter()
+                .flat_map(|e| e.tags())
+                .map(|e| e.name().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    info!("Tags related to \"{tag}\" ({} posts):", entry.post_count);
+    for related_entry in &related {
+        let note = if existing.contains(&related_entry.name) {
+            ", already in the tag file"
+        } else {
+            ""
+        };
+        info!(
+            "  {} {}",
+            related_entry.name,
+            style(format!(
+                "({} posts, {}{note})",
+                related_entry.post_count,
+                related_entry.category_name()
+            ))
+            .dim()
+        );
+    }
+
+    let tag_file = tag_file_path();
+    if !user_attended() {
+        return Ok(());
+    }
+
+    if is_toml_tag_file(&tag_file) {
+        info!(
+            "Tags can't be added to a structured tag file, add the ones you want to it by hand..."
+        );
+        return Ok(());
+    }
+
+    let choices: Vec<&TagEntry> = related
+        .into_iter()
+        .filter(|e| !existing.contains(&e.name))
+        .collect();
+    if choices.is_empty() {
+        return Ok(());
+    }
+
+    let items: Vec<String> = choices
+        .iter()
+        .map(|e| format!("{} ({} posts)", e.name, e.post_count))
+        .collect();
+    let selected = MultiSelect::new()
+        .with_prompt(format!(
+            "Which tags should be added to {}? (space to toggle, enter to confirm)",
+            tag_file.display()
+        ))
+        .items(&items)
+        .interact()?;
+    trace!("Selected tags: {selected:?}");
+
+    let (artists, general): (Vec<&TagEntry>, Vec<&TagEntry>) = selected
+        .into_iter()
+        .map(|i| choices[i])
+        .partition(|e| e.category_name() == "artist");
+    for (group, picked) in [("artists", artists), ("general", general)] {
+        if picked.is_empty() {
+            continue;
+        }
+
+        let names: Vec<&str> = picked.iter().map(|e| e.name.as_str()).collect();
+        add_to_tag_file(group, &names)?;
+        info!("Added {} to [{group}]...", names.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,269 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fs::write;
+
+use anyhow::Error;
+use dialoguer::{Confirm, Input, Password, Select};
+
+use crate::e621::io::tag::tag_file_path;
+use crate::e621::io::{Config, Login, NAMING_CONVENTIONS};
+use crate::e621::sender::entries::PoolEntry;
+use crate::e621::sender::RequestSender;
+use crate::e621::tui::completion::TagCompletion;
+
+/// A guided setup that asks the user for their login, download settings, and starter tags, before writing the config,
+/// login, and tag files.
+pub(crate) struct SetupWizard;
+
+impl SetupWizard {
+    /// Runs the wizard and writes all three files.
+    pub(crate) fn run() -> Result<(), Error> {
+        info!("Welcome to the e621 downloader! Let's set things up...");
+
+        let login = Self::ask_login()?;
+        let config = Self::ask_config()?;
+        let tags = Self::ask_tags()?;
+
+        config.save()?;
+        trace!("Config file created by setup wizard...");
+        login.save()?;
+        trace!("Login file created by setup wizard...");
+        write(tag_file_path(), tags)?;
+        trace!("Tag file created by setup wizard...");
+
+        info!("Setup is finished! You can change these settings later in the config, login, and tag files.");
+        Ok(())
+    }
+
+    /// Asks for artists and pools to download when the tag file is missing, checking each one with e621, and writes the
+    /// tag file with the ones that were found.
+    ///
+    /// returns: Result<bool, Error> (whether a tag file was written)
+    pub(crate) fn create_tag_file() -> Result<bool, Error> {
+        let confirmed = Confirm::new()
+            .with_prompt("There is no tag file yet, do you want to add some artists and pools now?")
+            .default(true)
+            .interact()?;
+        if !confirmed {
+            return Ok(false);
+        }
+
+        let request_sender = RequestSender::anonymous()?;
+        let completion = TagCompletion::new(request_sender.clone());
+        let artists: String = Input::new()
+            .with_prompt("Artists to download (comma separated, tab to complete, can be empty)")
+            .allow_empty(true)
+            .completion_with(&completion)
+            .interact_text()?;
+        let artists = Self::check_artists(&request_sender, &artists);
+        let pools: String = Input::new()
+            .with_prompt("Pool IDs to download (comma separated, can be empty)")
+            .allow_empty(true)
+            .interact_text()?;
+        let pools = Self::check_pools(&request_sender, &pools);
+        if artists.is_empty() && pools.is_empty() {
+            warn!("Nothing was added...");
+            return Ok(false);
+        }
+
+        let mut tags = Self::tag_file_header();
+        Self::push_group(&mut tags, "artists", &artists.join(","));
+        Self::push_group(&mut tags, "pools", &pools.join(","));
+        write(tag_file_path(), tags)?;
+        info!(
+            "Tag file \"{}\" created with {} artist(s) and {} pool(s)...",
+            tag_file_path().display(),
+            artists.len(),
+            pools.len()
+        );
+
+        Ok(true)
+    }
+
+    /// Checks each of the comma separated artists with e621, replacing aliases with the artist they are aliased to and
+    /// leaving out the ones that don't exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used to look up the artists.
+    /// * `artists`: The comma separated artists.
+    ///
+    /// returns: Vec<String>
+    fn check_artists(request_sender: &RequestSender, artists: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        for artist in Self::split_entries(artists) {
+            let artist = artist.to_lowercase().replace(' ', "_");
+            match request_sender.get_tags_by_name(&artist) {
+                Ok(tags) if !tags.is_empty() => found.push(artist),
+                Ok(_) => match request_sender
+                    .query_aliases(&artist)
+                    .ok()
+                    .flatten()
+                    .and_then(|e| e.into_iter().next())
+                {
+                    Some(alias) => {
+                        info!(
+                            "\"{artist}\" is an alias, adding \"{}\" instead...",
+                            alias.consequent_name
+                        );
+                        found.push(alias.consequent_name);
+                    }
+                    None => warn!("Leaving out \"{artist}\", it doesn't exist on e621..."),
+                },
+                Err(e) => warn!("Leaving out \"{artist}\", it couldn't be checked: {e}"),
+            }
+        }
+
+        found
+    }
+
+    /// Checks each of the comma separated pool ids with e621, leaving out the ones that don't exist.
+    ///
+    /// The pools are returned as tag file entries, with the name of the pool as a comment.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used to look up the pools.
+    /// * `pools`: The comma separated pool ids.
+    ///
+    /// returns: Vec<String>
+    fn check_pools(request_sender: &RequestSender, pools: &str) -> Vec<String> {
+        let mut found = Vec::new();
+        for pool in Self::split_entries(pools) {
+            if !pool.chars().all(|e| e.is_ascii_digit()) {
+                warn!("Leaving out \"{pool}\", pool IDs are numbers...");
+                continue;
+            }
+
+            match request_sender.get_entry_from_appended_id::<PoolEntry>(pool, "pool") {
+                Ok(entry) => {
+                    found.push(format!("{pool} # Title: {}", entry.name.replace('_', " ")))
+                }
+                Err(e) => warn!("Leaving out pool {pool}, it couldn't be found: {e}"),
+            }
+        }
+
+        found
+    }
+
+    /// Asks for the username, API key, and whether to download favorites.
+    fn ask_login() -> Result<Login, Error> {
+        let username: String = Input::new()
+            .with_prompt("e621 username (leave empty to skip logging in)")
+            .allow_empty(true)
+            .interact_text()?;
+        if username.trim().is_empty() {
+            return Ok(Login::new("", "", false));
+        }
+
+        let api_key = Password::new()
+            .with_prompt("API key (found in your account settings)")
+            .interact()?;
+        let download_favorites = Confirm::new()
+            .with_prompt("Download your favorites?")
+            .default(true)
+            .interact()?;
+
+        Ok(Login::new(
+            username.trim(),
+            api_key.trim(),
+            download_favorites,
+        ))
+    }
+
+    /// Asks for the download directory and naming convention.
+    fn ask_config() -> Result<Config, Error> {
+        let download_directory: String = Input::new()
+            .with_prompt("Download directory")
+            .default(String::from("downloads/"))
+            .interact_text()?;
+        let naming_convention = Select::new()
+            .with_prompt("File naming convention")
+            .items(&NAMING_CONVENTIONS)
+            .default(0)
+            .interact()?;
+
+        Ok(Config::with_download_settings(
+            download_directory.trim(),
+            NAMING_CONVENTIONS[naming_convention],
+        ))
+    }
+
+    /// Asks for a starter list of artists and tags, returning the contents of the new tag file.
+    ///
+    /// Pressing tab while typing completes the current tag, so typos don't make the tag file invalid.
+    fn ask_tags() -> Result<String, Error> {
+        let completion = TagCompletion::new(RequestSender::anonymous()?);
+        let artists: String = Input::new()
+            .with_prompt("Artists to download (comma separated, tab to complete, can be empty)")
+            .allow_empty(true)
+            .completion_with(&completion)
+            .interact_text()?;
+        let general: String = Input::new()
+            .with_prompt(
+                "General tag searches to download (comma separated, tab to complete, can be empty)",
+            )
+            .allow_empty(true)
+            .completion_with(&completion)
+            .interact_text()?;
+
+        let mut tags = Self::tag_file_header();
+        Self::push_group(&mut tags, "artists", &artists);
+        Self::push_group(&mut tags, "general", &general);
+
+        Ok(tags)
+    }
+
+    /// The comments at the top of a tag file written by the wizard.
+    fn tag_file_header() -> String {
+        String::from(
+            "# This is the tag file that you will use so the program can know what tags to search.\n\
+             # If you wish to comment in this file, simply put `#` at the beginning or end of line.\n",
+        )
+    }
+
+    /// Splits comma separated entries, leaving out empty ones.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries`: The comma separated entries.
+    ///
+    /// returns: impl Iterator<Item = &str>
+    fn split_entries(entries: &str) -> impl Iterator<Item = &str> {
+        entries.split(',').map(str::trim).filter(|e| !e.is_empty())
+    }
+
+    /// Adds a group with the comma separated entries to the tag file contents, skipping it if there are no entries.
+    ///
+    /// # Arguments
+    ///
+    /// * `tags`: The tag file contents.
+    /// * `group`: The name of the group.
+    /// * `entries`: The comma separated entries.
+    fn push_group(tags: &mut String, group: &str, entries: &str) {
+        let entries: Vec<&str> = Self::split_entries(entries).collect();
+        if entries.is_empty() {
+            return;
+        }
+
+        tags.push_str(&format!("\n[{group}]\n"));
+        for entry in entries {
+            tags.push_str(entry);
+            tags.push('\n');
+        }
+    }
+}
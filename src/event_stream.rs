@@ -0,0 +1,186 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use serde_json::to_string;
+
+use crate::e621::events::{add_listener, ProgressEvent};
+
+/// The path events are streamed from.
+const EVENTS_PATH: &str = "/events";
+
+/// How long writing an event to a client can take before the client is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How long a new client can take to send its request before it's dropped.
+const READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends events to the writer thread, so emitting an event never waits on a client.
+static WRITER: Mutex<Option<Sender<StreamMessage>>> = Mutex::new(None);
+
+/// A message for the writer thread, which owns the connected clients.
+enum StreamMessage {
+    /// A client subscribed to the events.
+    Client(TcpStream),
+    /// An event, serialized as JSON, to send to every client.
+    Event(String),
+}
+
+/// Streams every [ProgressEvent] to connected clients as server-sent events, so dashboards (or `curl`) can follow a
+/// run live.
+///
+/// Each event is sent as a `data:` line holding the same JSON as `--progress-json` prints. Every new client is read on
+/// its own thread, and events are written to the clients by a single writer thread, so a slow client never holds up
+/// the run or the other clients.
+pub struct EventStream {
+    /// The listener accepting clients.
+    listener: TcpListener,
+}
+
+impl EventStream {
+    /// Binds the event stream to the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to stream events on.
+    ///
+    /// returns: Result<EventStream, Error>
+    pub fn bind(address: SocketAddr) -> Result<Self, Error> {
+        let listener = TcpListener::bind(address)
+            .with_context(|| format!("Unable to stream events on {address}"))?;
+        Ok(EventStream { listener })
+    }
+
+    /// Starts accepting clients on a background thread, and sends them every event emitted from now on.
+    pub fn spawn(self) {
+        let (sender, receiver) = channel();
+        *WRITER.lock().unwrap_or_else(PoisonError::into_inner) = Some(sender.clone());
+        thread::spawn(move || write_events(receiver));
+        add_listener(broadcast_event);
+        if let Ok(address) = self.listener.local_addr() {
+            info!("Streaming progress events on http://{address}{EVENTS_PATH}...");
+        }
+
+        thread::spawn(move || {
+            for stream in self.listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        trace!("Unable to accept event stream client: {e}");
+                        continue;
+                    }
+                };
+
+                let sender = sender.clone();
+                thread::spawn(move || {
+                    if let Err(e) = accept_client(stream, &sender) {
+                        trace!("Unable to accept event stream client: {e}");
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// Reads the request of a new client, and either subscribes it to the events or rejects it.
+///
+/// # Arguments
+///
+/// * `stream`: The connection to the client.
+/// * `writer`: Sends the client to the writer thread once it's subscribed.
+///
+/// returns: Result<(), Error>
+fn accept_client(mut stream: TcpStream, writer: &Sender<StreamMessage>) -> Result<(), Error> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+
+    // The headers aren't needed, but are read so the client doesn't see the connection reset.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, path) = (parts.next(), parts.next());
+    if method != Some("GET") || path != Some(EVENTS_PATH) {
+        write!(
+            stream,
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+        )?;
+        return Ok(());
+    }
+
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: keep-alive\r\n\r\n"
+    )?;
+    stream.flush()?;
+
+    trace!("Event stream client {} connected...", stream.peer_addr()?);
+    writer.send(StreamMessage::Client(stream))?;
+    Ok(())
+}
+
+/// Writes every event to the connected clients, dropping the clients that can't be written to.
+///
+/// # Arguments
+///
+/// * `receiver`: The new clients and the events to send.
+fn write_events(receiver: Receiver<StreamMessage>) {
+    let mut clients: Vec<TcpStream> = Vec::new();
+    for message in receiver {
+        match message {
+            StreamMessage::Client(client) => clients.push(client),
+            StreamMessage::Event(json) => {
+                clients.retain_mut(|client| write!(client, "data: {json}\n\n").is_ok())
+            }
+        }
+    }
+}
+
+/// Hands an event to the writer thread, which sends it to every client.
+///
+/// # Arguments
+///
+/// * `event`: The event to send.
+fn broadcast_event(event: &ProgressEvent) {
+    let json = match to_string(event) {
+        Ok(json) => json,
+        Err(e) => {
+            trace!("Unable to serialize progress event {event:?}: {e}");
+            return;
+        }
+    };
+
+    if let Some(writer) = WRITER
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .as_ref()
+    {
+        // The writer thread only stops with the program, so a failed send can be ignored.
+        writer.send(StreamMessage::Event(json)).unwrap_or_default();
+    }
+}
@@ -0,0 +1,126 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::cell::Cell;
+use std::fs::File;
+use std::io::{LineWriter, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use console::strip_ansi_codes;
+use log::{LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use simplelog::{Config, SharedLogger};
+
+thread_local! {
+    /// The id of the post currently being worked on, attached to every event logged while it's set.
+    static CURRENT_POST: Cell<Option<i64>> = const { Cell::new(None) };
+}
+
+/// Sets the post currently being worked on, or clears it with [None].
+///
+/// # Arguments
+///
+/// * `id`: The id of the post.
+pub fn set_current_post(id: Option<i64>) {
+    CURRENT_POST.with(|e| e.set(id));
+}
+
+/// A single log event, written as one line of json.
+#[derive(Serialize)]
+struct LogEvent<'a> {
+    /// When the event happened, in milliseconds since the unix epoch.
+    time: u128,
+    /// The level of the event (e.g "info", "error").
+    level: &'a str,
+    /// The module the event was logged from.
+    module: &'a str,
+    /// The message of the event, without any terminal styling.
+    message: String,
+    /// The id of the post being worked on when the event was logged, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    post_id: Option<i64>,
+}
+
+/// A log sink that writes every event as a line of json, for log aggregation tools and GUI wrappers.
+pub struct JsonLogger {
+    /// The most detailed level written.
+    level: LevelFilter,
+    /// The file the events are written to.
+    writer: Mutex<LineWriter<File>>,
+}
+
+impl JsonLogger {
+    /// Creates a new json logger, boxed to be used with [simplelog::CombinedLogger].
+    ///
+    /// # Arguments
+    ///
+    /// * `level`: The most detailed level written.
+    /// * `file`: The file the events are written to.
+    pub fn new(level: LevelFilter, file: File) -> Box<Self> {
+        Box::new(JsonLogger {
+            level,
+            writer: Mutex::new(LineWriter::new(file)),
+        })
+    }
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level && metadata.target().starts_with("e621_downloader")
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let event = LogEvent {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|e| e.as_millis())
+                .unwrap_or_default(),
+            level: &record.level().as_str().to_lowercase(),
+            module: record.module_path().unwrap_or_else(|| record.target()),
+            message: strip_ansi_codes(&record.args().to_string()).to_string(),
+            post_id: CURRENT_POST.with(Cell::get),
+        };
+
+        if let (Ok(line), Ok(mut writer)) = (serde_json::to_string(&event), self.writer.lock()) {
+            writeln!(writer, "{line}").unwrap_or_default();
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut writer) = self.writer.lock() {
+            writer.flush().unwrap_or_default();
+        }
+    }
+}
+
+impl SharedLogger for JsonLogger {
+    fn level(&self) -> LevelFilter {
+        self.level
+    }
+
+    fn config(&self) -> Option<&Config> {
+        None
+    }
+
+    fn as_log(self: Box<Self>) -> Box<dyn Log> {
+        Box::new(*self)
+    }
+}
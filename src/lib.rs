@@ -0,0 +1,31 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! The grabbing and downloading engine of the e621 downloader.
+//!
+//! The binary is a thin front-end over [program::Program], other programs (GUIs, bots, etc.) can use the
+//! [e621::sender::RequestSender], [e621::grabber::Grabber], and [e621::E621WebConnector] directly.
+
+#[macro_use]
+extern crate log;
+
+pub mod args;
+pub mod e621;
+pub mod event_stream;
+pub mod json_logger;
+pub mod program;
+pub mod server;
+pub mod update;
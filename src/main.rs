@@ -20,46 +20,112 @@ extern crate log;
 use std::env::consts::{
     ARCH, DLL_EXTENSION, DLL_PREFIX, DLL_SUFFIX, EXE_EXTENSION, EXE_SUFFIX, FAMILY, OS,
 };
-use std::fs::File;
+use std::fs::{create_dir_all, metadata, remove_file, rename, File, OpenOptions};
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-use anyhow::Error;
 use log::LevelFilter;
 use simplelog::{
-    ColorChoice, CombinedLogger, Config, ConfigBuilder, TermLogger, TerminalMode, WriteLogger,
+    ColorChoice, CombinedLogger, Config, ConfigBuilder, SharedLogger, TermLogger, TerminalMode,
+    WriteLogger,
 };
 
-use crate::program::Program;
+use e621_downloader::args::Args;
+use e621_downloader::e621::io::{config_directory, Config as DownloaderConfig, LOG_NAME};
+use e621_downloader::json_logger::JsonLogger;
+use e621_downloader::program::{ExitStatus, Program};
 
-mod e621;
-mod program;
+fn main() -> ExitCode {
+    Args::get();
+    if let Err(status) = initialize_logger() {
+        return ExitCode::from(status.code() as u8);
+    }
 
-fn main() -> Result<(), Error> {
-    initialize_logger();
     log_system_information();
 
     let program = Program::new();
-    program.run()
+    ExitCode::from(program.run().code() as u8)
 }
 
 /// Initializes the logger with preset filtering.
-fn initialize_logger() {
+///
+/// When `--progress-json` is passed, terminal logging is moved to stderr so stdout only carries progress events.
+/// When `--json-log` is passed, every event is also written to that file as a line of json. If that file can't be
+/// created, the error is printed and [ExitStatus::ConfigError] is returned, since there is no logger to report it yet.
+fn initialize_logger() -> Result<(), ExitStatus> {
     let mut config = ConfigBuilder::new();
     config.add_filter_allow_str("e621_downloader");
 
-    CombinedLogger::init(vec![
+    let terminal_mode = if Args::get().progress_json() {
+        TerminalMode::Stderr
+    } else {
+        TerminalMode::Mixed
+    };
+
+    let mut loggers: Vec<Box<dyn SharedLogger>> = vec![
         TermLogger::new(
             LevelFilter::Info,
             Config::default(),
-            TerminalMode::Mixed,
+            terminal_mode,
             ColorChoice::Auto,
         ),
-        WriteLogger::new(
-            LevelFilter::max(),
-            config.build(),
-            File::create("e621_downloader.log").unwrap(),
-        ),
-    ])
-    .unwrap();
+        WriteLogger::new(LevelFilter::max(), config.build(), open_log_file()),
+    ];
+    if let Some(path) = Args::get().json_log() {
+        let file = File::create(path).map_err(|e| {
+            eprintln!("Unable to create the json log {}: {e}", path.display());
+            ExitStatus::ConfigError
+        })?;
+        loggers.push(JsonLogger::new(LevelFilter::max(), file));
+    }
+
+    CombinedLogger::init(loggers).unwrap();
+    Ok(())
+}
+
+/// Opens the log file, rotating the old logs first when needed.
+///
+/// The log is appended to until it passes `logMaxSizeMb` in the config (or every run when it's `0`), then it's moved
+/// to `e621_downloader.1.log`, with older logs shifted up and any past `logHistory` deleted.
+fn open_log_file() -> File {
+    let config = DownloaderConfig::peek();
+    let path = config_directory().join(LOG_NAME);
+    let max_size = config.log_max_size_mb() * 1024 * 1024;
+    let size = metadata(&path).map(|e| e.len()).unwrap_or(0);
+    if size > 0 && size >= max_size {
+        rotate_logs(config.log_history());
+    }
+
+    // The config directory is created here on the first run, since the log is the first file written to it.
+    create_dir_all(config_directory()).unwrap_or_default();
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap()
+}
+
+/// Shifts every old log up by one, deleting the logs past the history limit, then moves the log to the first slot.
+///
+/// # Arguments
+///
+/// * `history`: The amount of old logs to keep.
+fn rotate_logs(history: usize) {
+    let rotated =
+        |n: usize| -> PathBuf { config_directory().join(format!("e621_downloader.{n}.log")) };
+    let path = config_directory().join(LOG_NAME);
+
+    // Errors are ignored, since the logger isn't running yet and a failed rotation shouldn't stop the program.
+    remove_file(rotated(history)).unwrap_or_default();
+    for n in (1..history).rev() {
+        rename(rotated(n), rotated(n + 1)).unwrap_or_default();
+    }
+
+    if history == 0 {
+        remove_file(path).unwrap_or_default();
+    } else {
+        rename(path, rotated(1)).unwrap_or_default();
+    }
 }
 
 /// Logs important information about the system being used.
@@ -18,13 +18,35 @@ use std::env::current_dir;
 use std::fs::write;
 use std::path::Path;
 
-use console::Term;
-use anyhow::Error;
+use anyhow::{bail, Error};
+use console::{user_attended, Term};
+use dialoguer::Confirm;
 
-use crate::e621::E621WebConnector;
-use crate::e621::io::{Config, emergency_exit, Login};
-use crate::e621::io::tag::{parse_tag_file, TAG_FILE_EXAMPLE, TAG_NAME};
+use crate::args::{Args, Command};
+use crate::e621::error::{set_run_stage, skipped_items, skipped_items_since, E621Error, RunStage};
+use crate::e621::io::diagnostics::write_diagnostics;
+use crate::e621::io::grab_file::GrabFile;
+use crate::e621::io::preflight::{CheckStatus, Preflight};
+use crate::e621::io::report::{ErrorReport, Failure};
+use crate::e621::io::response_cache::ResponseCache;
+use crate::e621::io::saved_search::{add_saved_searches, saved_search_groups};
+use crate::e621::io::tag::{
+    is_toml_tag_file, parse_tag_file, parse_tag_file_unchecked, tag_file_exists, tag_file_path,
+    Group, Tag, TagSearchType, TagType, TAG_FILE_EXAMPLE,
+};
+use crate::e621::io::validate::TagValidator;
+use crate::e621::io::{
+    config_directory, enter_portable_directory, enter_profile, move_legacy_state, state_directory,
+    Config, Login,
+};
 use crate::e621::sender::RequestSender;
+use crate::e621::tui::menu::{select_groups, select_sets, MainMenu, MenuChoice};
+use crate::e621::tui::suggest::suggest_tags;
+use crate::e621::tui::wizard::SetupWizard;
+use crate::e621::E621WebConnector;
+use crate::event_stream::EventStream;
+use crate::server::Server;
+use crate::update::{check_for_updates, self_update};
 
 /// The name of the cargo package.
 const NAME: &str = env!("CARGO_PKG_NAME");
@@ -35,19 +57,117 @@ const VERSION: &str = env!("CARGO_PKG_VERSION");
 /// The authors who created the package.
 const AUTHORS: &str = env!("CARGO_PKG_AUTHORS");
 
+/// The status the program exits with, so wrapper scripts can react to how the run ended without reading the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitStatus {
+    /// Everything was downloaded.
+    Success = 0,
+    /// The run failed for any reason not covered by the other statuses.
+    Failure = 1,
+    /// The config, login, or tag file is missing or invalid.
+    ConfigError = 2,
+    /// The login credentials were rejected by e621.
+    AuthFailure = 3,
+    /// e621 couldn't be reached, or responded with an error.
+    NetworkFailure = 4,
+    /// The run finished, but at least one item (tag, pool, set, post, or tag file line) was skipped.
+    PartialFailure = 5,
+}
+
+impl ExitStatus {
+    /// The process exit code of the status.
+    pub fn code(self) -> i32 {
+        self as i32
+    }
+}
+
+impl From<&Error> for ExitStatus {
+    /// Picks the status for an error that ended the run.
+    ///
+    /// # Arguments
+    ///
+    /// * `error`: The error that ended the run.
+    ///
+    /// returns: ExitStatus
+    fn from(error: &Error) -> Self {
+        match error.downcast_ref::<E621Error>() {
+            Some(E621Error::Config(_) | E621Error::TagFile(_)) => ExitStatus::ConfigError,
+            Some(E621Error::Auth(_)) => ExitStatus::AuthFailure,
+            Some(E621Error::Network(_) | E621Error::Resolve { .. } | E621Error::Status { .. }) => {
+                ExitStatus::NetworkFailure
+            }
+            _ => ExitStatus::Failure,
+        }
+    }
+}
+
+/// The most skipped items listed at the end of a run, the rest are only in the log and the run report.
+const LISTED_SKIPPED_ITEMS: usize = 20;
+
 /// A program class that handles the flow of the downloader user experience and steps of execution.
-pub(crate) struct Program;
+#[derive(Default)]
+pub struct Program;
 
 impl Program {
     /// Creates a new instance of the program.
-    pub(crate) fn new() -> Self {
+    pub fn new() -> Self {
         Self
     }
 
-    /// Runs the downloader program.
-    pub(crate) fn run(&self) -> Result<(), Error> {
+    /// Runs the downloader program, returning the status the program should exit with.
+    pub fn run(&self) -> ExitStatus {
+        let mut fatal = None;
+        let status = match self.run_steps() {
+            Ok(()) => {
+                let skipped = skipped_items();
+                if skipped > 0 {
+                    warn!("{skipped} item(s) were skipped because of errors:");
+                    for skipped_item in skipped_items_since(0).iter().take(LISTED_SKIPPED_ITEMS) {
+                        warn!("  {}: {}", skipped_item.item, skipped_item.reason);
+                    }
+
+                    if skipped > LISTED_SKIPPED_ITEMS {
+                        warn!(
+                            "  ...and {} more, check the log for details.",
+                            skipped - LISTED_SKIPPED_ITEMS
+                        );
+                    }
+
+                    ExitStatus::PartialFailure
+                } else {
+                    ExitStatus::Success
+                }
+            }
+            Err(e) => {
+                error!("{e:#}");
+                let status = ExitStatus::from(&e);
+                fatal = Some(Failure::from_error(&e));
+                status
+            }
+        };
+
+        // The error report of the run being diagnosed is kept, instead of being replaced by the diagnose command's own.
+        if !matches!(Args::get().command(), Some(Command::Diagnose { .. })) {
+            let failures = skipped_items_since(0).into_iter().map(Failure::from);
+            save_error_report(status, failures.chain(fatal).collect());
+        }
+        trace!("Exiting with status {status:?} ({})...", status.code());
+        status
+    }
+
+    /// Runs each step of the downloader program.
+    fn run_steps(&self) -> Result<(), Error> {
         Term::stdout().set_title("e621 downloader");
         trace!("Starting e621 downloader...");
+
+        // The args are resolved before the working directory changes, so relative paths passed on the command line
+        // still point to the files the user meant, even when the program is embedded and never called `Args::get`.
+        let args = Args::get();
+        enter_portable_directory()?;
+        if let Some(profile) = args.profile() {
+            enter_profile(profile)?;
+        }
+
         trace!("Program Name: {}", NAME);
         trace!("Program Version: {}", VERSION);
         trace!("Program Authors: {}", AUTHORS);
@@ -58,6 +178,40 @@ impl Program {
                 .to_str()
                 .unwrap()
         );
+        trace!("Program Config Directory: {}", config_directory().display());
+
+        if let Some(address) = Args::get().event_stream() {
+            EventStream::bind(address)?.spawn();
+        }
+
+        // Bundles the files needed to look into a problem, even when the config can't be loaded.
+        if let Some(Command::Diagnose { path }) = Args::get().command() {
+            write_diagnostics(path)?;
+            info!(
+                "Diagnostics saved to {}, attach it when reporting an issue.",
+                path.display()
+            );
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Updates the program and exits without downloading anything.
+        if Args::get().command() == Some(&Command::SelfUpdate) {
+            self_update()?;
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Runs the setup wizard on first launch, as long as someone is there to answer it.
+        if !Config::config_exists()
+            && !Login::login_exists()
+            && !tag_file_exists()
+            && !is_toml_tag_file(&tag_file_path())
+            && user_attended()
+        {
+            trace!("First launch detected, running setup wizard...");
+            SetupWizard::run()?;
+        }
 
         // Check the config file and ensures that it is created.
         trace!("Checking if config file exists...");
@@ -67,17 +221,37 @@ impl Program {
             Config::create_config()?;
         }
 
+        Config::load()?;
+        trace!("Program State Directory: {}", state_directory().display());
+        move_legacy_state();
+
+        if Config::get().check_for_updates() {
+            check_for_updates();
+        }
+
         // Create tag if it doesn't exist.
         trace!("Checking if tag file exists...");
-        if !Path::new(TAG_NAME).exists() {
-            info!("Tag file does not exist, creating tag file...");
-            write(TAG_NAME, TAG_FILE_EXAMPLE)?;
-            trace!("Tag file \"{}\" created...", TAG_NAME);
+        if !tag_file_exists() {
+            let tag_file = tag_file_path();
+            if is_toml_tag_file(&tag_file) {
+                return Err(E621Error::TagFile(format!(
+                    "The tag file {} does not exist!",
+                    tag_file.display()
+                ))
+                .into());
+            }
 
-            emergency_exit(
-                "The tag file is created, the application will close so you can include \
-             the artists, sets, pools, and individual posts you wish to download.",
-            );
+            if user_attended() && SetupWizard::create_tag_file()? {
+                info!("Continuing with the new tag file...");
+            } else {
+                return Err(Self::create_example_tag_file(&tag_file)?.into());
+            }
+        }
+
+        // Shows the main menu, letting the user edit their files before downloading.
+        if Args::get().menu() && MainMenu::run()? == MenuChoice::Exit {
+            info!("Exiting...");
+            return Ok(());
         }
 
         // Creates connector and requester to prepare for downloading posts.
@@ -87,28 +261,356 @@ impl Program {
         trace!("Login API Key: {}", "*".repeat(login.api_key().len()));
         trace!("Login Download Favorites: {}", login.download_favorites());
 
-        let request_sender = RequestSender::new();
-        let mut connector = E621WebConnector::new(&request_sender);
-        connector.should_enter_safe_mode();
+        let request_sender = RequestSender::new()?;
+        ResponseCache::new().prune(Config::get().metadata_cache_ttl_hours() * 60 * 60);
+        if Config::get().preflight_checks() {
+            Self::run_preflight_checks(&request_sender)?;
+        } else if !login.is_empty() {
+            trace!("Validating login credentials...");
+            if let Err(e) = request_sender.validate_credentials(login.username()) {
+                if matches!(e, E621Error::Auth(_)) {
+                    Self::explain_invalid_login();
+                }
 
-        // Parses tag file.
-        trace!("Parsing tag file...");
-        let groups = parse_tag_file(&request_sender)?;
+                return Err(e.into());
+            }
 
-        // Collects all grabbed posts and moves it to connector to start downloading.
-        if !login.is_empty() {
-            trace!("Parsing user blacklist...");
-            connector.process_blacklist();
-        } else {
-            trace!("Skipping blacklist as user is not logged in...");
+            trace!("Login credentials are valid...");
         }
 
-        connector.grab_all(&groups);
-        connector.download_posts();
+        // Validates the tag file and exits without downloading anything.
+        if Args::get().command() == Some(&Command::Validate) {
+            trace!("Validating tag file...");
+            let groups = parse_tag_file_unchecked(&request_sender)?;
+            TagValidator::new(request_sender).validate(&groups)?;
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Exports or imports the account blacklist and exits without downloading anything.
+        if let Some(
+            command @ (Command::BlacklistExport { path } | Command::BlacklistImport { path }),
+        ) = Args::get().command()
+        {
+            if login.is_empty() {
+                bail!(
+                    "You need to be logged in to export or import the blacklist of your account."
+                );
+            }
+
+            let connector = E621WebConnector::new(&request_sender);
+            if matches!(command, Command::BlacklistExport { .. }) {
+                connector.export_blacklist(path)?;
+            } else {
+                connector.import_blacklist(path)?;
+            }
+
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Explains whether a post is blacklisted and exits without downloading anything.
+        if let Some(&Command::BlacklistTest { post_id }) = Args::get().command() {
+            trace!("Testing post {post_id} against the blacklist...");
+            let mut connector = E621WebConnector::new(&request_sender);
+            connector.process_blacklist()?;
+            connector.test_blacklist(post_id)?;
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Suggests tags related to a tag and exits without downloading anything.
+        if let Some(Command::Suggest { tag }) = Args::get().command() {
+            suggest_tags(tag, &request_sender)?;
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Grabs the posts of the tag file and saves them, so they can be downloaded later.
+        if let Some(Command::Grab { output }) = Args::get().command() {
+            let connector = Self::grab_groups(&request_sender, Self::load_tag_file_groups)?;
+            let grab_file = connector.grab_file();
+            grab_file.save(output)?;
+            info!(
+                "Saved {} posts in {} collections to {}...",
+                grab_file.post_count(),
+                grab_file.collections().len(),
+                output.display()
+            );
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Downloads the posts saved by an earlier grab, without grabbing anything.
+        if let Some(Command::Download { input }) = Args::get().command() {
+            let grab_file = GrabFile::load(input)?;
+            info!(
+                "Loaded {} posts in {} collections from {}...",
+                grab_file.post_count(),
+                grab_file.collections().len(),
+                input.display()
+            );
 
+            let mut connector = E621WebConnector::new(&request_sender);
+            connector.load_grab_file(grab_file);
+            if Args::get().review() {
+                connector.review_posts()?;
+            }
+
+            set_run_stage(RunStage::Downloading);
+            connector.download_posts()?;
+            info!("Finished downloading posts!");
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Lets the user pick which of their own sets to download.
+        if Args::get().command() == Some(&Command::MySets) {
+            if login.is_empty() {
+                bail!("You need to be logged in to list the sets of your account.");
+            }
+
+            if !user_attended() {
+                bail!("Picking sets needs an interactive terminal.");
+            }
+
+            let sets = request_sender.sets_of_user(login.username())?;
+            if sets.is_empty() {
+                info!("Your account has no sets...");
+                return Ok(());
+            }
+
+            let sets = select_sets(sets)?;
+            if sets.is_empty() {
+                info!("No sets were selected...");
+                return Ok(());
+            }
+
+            let tags = sets
+                .iter()
+                .map(|e| Tag::new(&e.id.to_string(), TagSearchType::Special, TagType::Set))
+                .collect();
+            Self::download_groups(&request_sender, |_| {
+                Ok(vec![Group::with_tags(String::from("sets"), tags)])
+            })?;
+            info!("Finished downloading posts!");
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Adds the saved searches of the account to the tag file, or downloads them right away.
+        if let Some(&Command::SavedSearches { run }) = Args::get().command() {
+            if login.is_empty() {
+                bail!("You need to be logged in to use the saved searches of your account.");
+            }
+
+            let searches = request_sender.get_saved_searches()?;
+            if searches.is_empty() {
+                info!("Your account has no saved searches...");
+                return Ok(());
+            }
+
+            info!("Your account has {} saved searches:", searches.len());
+            for search in &searches {
+                info!(
+                    "  {} {}",
+                    search.query,
+                    console::style(search.labels.join(", ")).dim()
+                );
+            }
+
+            if run {
+                Self::download_groups(&request_sender, |e| Ok(saved_search_groups(&searches, e)))?;
+                info!("Finished downloading posts!");
+            } else {
+                let tag_file = tag_file_path();
+                if !Args::get().yes() && !user_attended() {
+                    bail!(
+                        "Pass --yes to add the saved searches to the tag file without being asked."
+                    );
+                }
+
+                let confirmed = Args::get().yes()
+                    || Confirm::new()
+                        .with_prompt(format!("Add them to {}?", tag_file.display()))
+                        .default(true)
+                        .interact()?;
+                if confirmed {
+                    let added = add_saved_searches(&searches, &request_sender)?;
+                    info!(
+                        "Added {added} saved searches to {} (the rest are already in it).",
+                        tag_file.display()
+                    );
+                }
+            }
+
+            info!("Exiting...");
+            return Ok(());
+        }
+
+        // Serves the JSON API, which downloads when a run is requested instead of right away.
+        if let Some(address) = Args::get().serve() {
+            return Server::bind(address)?.run();
+        }
+
+        Self::download(&request_sender)?;
         info!("Finished downloading posts!");
         info!("Exiting...");
 
         Ok(())
     }
+
+    /// Runs every preflight check, then stops the run with the error of the first check that failed, if any did.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for all API calls.
+    ///
+    /// returns: Result<(), Error>
+    fn run_preflight_checks(request_sender: &RequestSender) -> Result<(), Error> {
+        let results = Preflight::new(request_sender).run();
+        for result in results {
+            if result.status != CheckStatus::Fail {
+                continue;
+            }
+
+            let error = result
+                .error
+                .unwrap_or_else(|| Error::msg(result.detail.clone()));
+            if matches!(error.downcast_ref(), Some(E621Error::Auth(_))) {
+                Self::explain_invalid_login();
+            }
+
+            return Err(error.context(format!("Preflight check \"{}\" failed", result.check)));
+        }
+
+        Ok(())
+    }
+
+    /// Writes the example tag file, returning the error that stops the run so the user can fill it in.
+    ///
+    /// # Arguments
+    ///
+    /// * `tag_file`: The path of the tag file.
+    ///
+    /// returns: Result<E621Error, Error>
+    fn create_example_tag_file(tag_file: &Path) -> Result<E621Error, Error> {
+        info!("Tag file does not exist, creating tag file...");
+        write(tag_file, TAG_FILE_EXAMPLE)?;
+        info!("Tag file \"{}\" created...", tag_file.display());
+
+        Ok(E621Error::TagFile(String::from(
+            "The tag file is created, the application will close so you can include \
+             the artists, sets, pools, and individual posts you wish to download.",
+        )))
+    }
+
+    /// Explains how to fix login credentials that were rejected.
+    fn explain_invalid_login() {
+        error!("The username or API key in the login file was rejected by e621!");
+        info!(
+            "Be sure that the username is correct and that the API key was copied from your account \
+             settings without any extra spaces."
+        );
+    }
+
+    /// Grabs the posts of every group in the tag file and downloads them.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for all API calls.
+    ///
+    /// returns: Result<(), Error>
+    pub fn download(request_sender: &RequestSender) -> Result<(), Error> {
+        Self::download_groups(request_sender, Self::load_tag_file_groups)
+    }
+
+    /// Parses the groups of the tag file, letting the user pick which to download when `--select-groups` is passed.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used to identify the tags.
+    ///
+    /// returns: Result<Vec<Group>, Error>
+    fn load_tag_file_groups(request_sender: &RequestSender) -> Result<Vec<Group>, Error> {
+        trace!("Parsing tag file...");
+        let groups = parse_tag_file(request_sender)?;
+        if Args::get().select_groups() {
+            return select_groups(groups);
+        }
+
+        Ok(groups)
+    }
+
+    /// Grabs the posts of the groups loaded by `load_groups` and downloads them.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for all API calls.
+    /// * `load_groups`: Loads the groups to download.
+    ///
+    /// returns: Result<(), Error>
+    pub fn download_groups(
+        request_sender: &RequestSender,
+        load_groups: impl FnOnce(&RequestSender) -> Result<Vec<Group>, Error>,
+    ) -> Result<(), Error> {
+        let mut connector = Self::grab_groups(request_sender, load_groups)?;
+        set_run_stage(RunStage::Downloading);
+        connector.download_posts()
+    }
+
+    /// Grabs the posts of the groups loaded by `load_groups`, and lets the user review them when `--review` is passed.
+    ///
+    /// The groups are loaded after safe mode is decided, so any tag identification goes through the right site.
+    ///
+    /// # Arguments
+    ///
+    /// * `request_sender`: The sender used for all API calls.
+    /// * `load_groups`: Loads the groups to grab.
+    ///
+    /// returns: Result<E621WebConnector, Error>
+    fn grab_groups(
+        request_sender: &RequestSender,
+        load_groups: impl FnOnce(&RequestSender) -> Result<Vec<Group>, Error>,
+    ) -> Result<E621WebConnector, Error> {
+        let mut connector = E621WebConnector::new(request_sender);
+        connector.should_enter_safe_mode()?;
+        set_run_stage(RunStage::Parsing);
+        let groups = load_groups(request_sender)?;
+
+        // Collects all grabbed posts and moves it to connector to start downloading.
+        trace!("Parsing blacklist...");
+        set_run_stage(RunStage::Grabbing);
+        connector.process_blacklist()?;
+
+        connector.grab_all(&groups);
+        if Args::get().review() {
+            connector.review_posts()?;
+        }
+
+        Ok(connector)
+    }
+}
+
+/// Saves the failures of a run that didn't succeed to the error report, or removes the error report of an earlier run
+/// when it did.
+///
+/// # Arguments
+///
+/// * `status`: The status the run ended with.
+/// * `failures`: Every failure of the run.
+pub(crate) fn save_error_report(status: ExitStatus, failures: Vec<Failure>) {
+    if status == ExitStatus::Success {
+        ErrorReport::remove();
+        return;
+    }
+
+    let report = ErrorReport::new(status.code(), failures);
+    match report.save() {
+        Ok(()) => info!(
+            "A summary of the errors was saved to {}, attach it when reporting an issue.",
+            ErrorReport::path().display()
+        ),
+        Err(e) => warn!("Unable to save the error report: {e}"),
+    }
 }
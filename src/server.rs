@@ -0,0 +1,319 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use anyhow::{Context, Error};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_str, json, to_string, Value};
+
+use crate::e621::cancel::{cancel, is_cancelled, reset_cancel};
+use crate::e621::error::{run_stage, skipped_items, skipped_items_since};
+use crate::e621::events::{add_listener, ProgressEvent};
+use crate::e621::io::report::{Failure, RunReport};
+use crate::e621::sender::RequestSender;
+use crate::program::{save_error_report, ExitStatus, Program};
+
+/// The progress of the current (or last) run started through the server.
+static PROGRESS: Lazy<Mutex<RunProgress>> = Lazy::new(|| Mutex::new(RunProgress::default()));
+
+/// The state a run started through the server is in.
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunState {
+    /// No run has been started yet.
+    #[default]
+    Idle,
+    /// The tags are being grabbed.
+    Grabbing,
+    /// The grabbed posts are being downloaded.
+    Downloading,
+    /// The run finished.
+    Finished,
+    /// The run was cancelled before it finished.
+    Cancelled,
+    /// The run ended with an error.
+    Failed,
+}
+
+impl RunState {
+    /// Whether a run in this state is still going.
+    pub fn is_active(self) -> bool {
+        matches!(self, RunState::Grabbing | RunState::Downloading)
+    }
+}
+
+/// A snapshot of the progress of a run, as returned by the `progress` command.
+#[derive(Serialize, Debug, Clone, Default)]
+pub struct RunProgress {
+    /// The state the run is in.
+    pub state: RunState,
+    /// The tag (or id) currently being grabbed, if any.
+    pub tag: Option<String>,
+    /// The amount of tags grabbing has started for.
+    pub tags: usize,
+    /// The amount of posts to download, known once downloading starts.
+    pub posts: usize,
+    /// The total size of the posts to download in bytes, known once downloading starts.
+    pub bytes: u64,
+    /// The amount of posts downloaded so far.
+    pub downloaded: u64,
+    /// The size of the posts downloaded so far in bytes.
+    pub downloaded_bytes: u64,
+    /// The amount of posts skipped so far.
+    pub skipped: u64,
+    /// The exit code the run would have had on the command line, once it has ended.
+    pub exit_code: Option<i32>,
+    /// The error the run failed with, if any.
+    pub error: Option<String>,
+}
+
+/// A command sent to the server, as a line of JSON tagged with a `command` field (e.g `{"command": "start"}`).
+#[derive(Deserialize, Debug)]
+#[serde(tag = "command", rename_all = "snake_case")]
+enum ServerRequest {
+    /// Starts a run with the current tag file.
+    Start,
+    /// Gets the progress of the current (or last) run.
+    Progress,
+    /// Cancels the current run.
+    Cancel,
+    /// Gets the report of the last finished run.
+    Report,
+}
+
+/// A server exposing a small JSON API on a local socket, so GUI front-ends can control the downloader.
+///
+/// Each request is one line of JSON, and is answered with one line of JSON containing an `ok` field, along with
+/// either the requested data or an `error` message.
+pub struct Server {
+    /// The listener accepting connections from front-ends.
+    listener: TcpListener,
+}
+
+impl Server {
+    /// Binds the server to the given address.
+    ///
+    /// # Arguments
+    ///
+    /// * `address`: The address to serve on.
+    ///
+    /// returns: Result<Server, Error>
+    pub fn bind(address: SocketAddr) -> Result<Self, Error> {
+        let listener =
+            TcpListener::bind(address).with_context(|| format!("Unable to serve on {address}"))?;
+        Ok(Server { listener })
+    }
+
+    /// Accepts connections until the program is closed, handling each on its own thread.
+    pub fn run(&self) -> Result<(), Error> {
+        add_listener(record_event);
+        info!("Serving on {}...", self.listener.local_addr()?);
+
+        for stream in self.listener.incoming() {
+            match stream {
+                Ok(stream) => {
+                    thread::spawn(move || {
+                        if let Err(e) = handle_connection(stream) {
+                            trace!("Connection closed: {e}");
+                        }
+                    });
+                }
+                Err(e) => warn!("Unable to accept connection: {e}"),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Answers every request sent over the connection until it's closed.
+///
+/// # Arguments
+///
+/// * `stream`: The connection to a front-end.
+///
+/// returns: Result<(), Error>
+fn handle_connection(stream: TcpStream) -> Result<(), Error> {
+    trace!("Accepted connection from {}...", stream.peer_addr()?);
+    let reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match from_str::<ServerRequest>(&line) {
+            Ok(request) => {
+                trace!("Received request {request:?}...");
+                respond(request)
+            }
+            Err(e) => error_response(&format!("Invalid request: {e}")),
+        };
+
+        writeln!(writer, "{}", to_string(&response)?)?;
+    }
+
+    Ok(())
+}
+
+/// Carries out a request and builds the response to it.
+///
+/// # Arguments
+///
+/// * `request`: The request to carry out.
+///
+/// returns: Value
+fn respond(request: ServerRequest) -> Value {
+    match request {
+        ServerRequest::Start => match start_run() {
+            Ok(()) => json!({ "ok": true, "progress": progress().clone() }),
+            Err(e) => error_response(&e),
+        },
+        ServerRequest::Progress => json!({ "ok": true, "progress": progress().clone() }),
+        ServerRequest::Cancel => {
+            if !progress().state.is_active() {
+                return error_response("No run is in progress.");
+            }
+
+            info!("Cancelling run...");
+            cancel();
+            json!({ "ok": true, "progress": progress().clone() })
+        }
+        ServerRequest::Report => json!({ "ok": true, "report": RunReport::load() }),
+    }
+}
+
+/// Builds the response for a request that failed.
+///
+/// # Arguments
+///
+/// * `error`: Why the request failed.
+///
+/// returns: Value
+fn error_response(error: &str) -> Value {
+    json!({ "ok": false, "error": error })
+}
+
+/// Locks the progress of the current run.
+fn progress() -> MutexGuard<'static, RunProgress> {
+    PROGRESS.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Starts a run on a new thread, unless one is already in progress.
+///
+/// returns: Result<(), String>
+fn start_run() -> Result<(), String> {
+    {
+        let mut progress = progress();
+        if progress.state.is_active() {
+            return Err(String::from("A run is already in progress."));
+        }
+
+        *progress = RunProgress {
+            state: RunState::Grabbing,
+            ..Default::default()
+        };
+    }
+
+    info!("Starting run...");
+    reset_cancel();
+    let skipped_before = skipped_items();
+    thread::spawn(move || {
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            Program::download(&RequestSender::new()?)
+        }));
+
+        let mut progress = progress();
+        progress.tag = None;
+        let mut fatal = None;
+        let status = match result {
+            Ok(Ok(())) => {
+                progress.state = if is_cancelled() {
+                    RunState::Cancelled
+                } else {
+                    RunState::Finished
+                };
+                if skipped_items() > skipped_before {
+                    ExitStatus::PartialFailure
+                } else {
+                    ExitStatus::Success
+                }
+            }
+            Ok(Err(e)) => {
+                error!("{e:#}");
+                progress.state = RunState::Failed;
+                progress.error = Some(format!("{e:#}"));
+                fatal = Some(Failure::from_error(&e));
+                ExitStatus::from(&e)
+            }
+            Err(_) => {
+                let message = String::from("The run stopped unexpectedly.");
+                progress.state = RunState::Failed;
+                progress.error = Some(message.clone());
+                fatal = Some(Failure {
+                    stage: run_stage(),
+                    item: String::from("run"),
+                    chain: vec![message],
+                });
+                ExitStatus::Failure
+            }
+        };
+
+        progress.exit_code = Some(status.code());
+        let failures = skipped_items_since(skipped_before)
+            .into_iter()
+            .map(Failure::from);
+        save_error_report(status, failures.chain(fatal).collect());
+
+        info!("Run ended ({:?}).", progress.state);
+    });
+
+    Ok(())
+}
+
+/// Updates the progress of the current run with an emitted event.
+///
+/// # Arguments
+///
+/// * `event`: The event that was emitted.
+fn record_event(event: &ProgressEvent) {
+    let mut progress = progress();
+    match event {
+        ProgressEvent::GrabStarted { tag } => {
+            progress.tag = Some(tag.to_string());
+            progress.tags += 1;
+        }
+        ProgressEvent::DownloadStarted { posts, bytes } => {
+            progress.state = RunState::Downloading;
+            progress.tag = None;
+            progress.posts = *posts;
+            progress.bytes = *bytes;
+        }
+        ProgressEvent::PostDownloaded { bytes, .. } => {
+            progress.downloaded += 1;
+            progress.downloaded_bytes += (*bytes).max(0) as u64;
+        }
+        ProgressEvent::PostSkipped { .. } => progress.skipped += 1,
+        ProgressEvent::RunFinished { .. } => {}
+    }
+}
@@ -0,0 +1,349 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::env::consts::{ARCH, OS};
+use std::env::current_exe;
+use std::fs::{metadata, remove_file, rename, set_permissions, write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use anyhow::{bail, Context, Error};
+use console::user_attended;
+use dialoguer::Confirm;
+use reqwest::blocking::Client;
+use reqwest::header::ACCEPT;
+use reqwest::Proxy;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::args::Args;
+use crate::e621::io::Config;
+
+/// The GitHub API url of the newest release.
+const LATEST_RELEASE_URL: &str =
+    "https://api.github.com/repos/McSib/e621_downloader/releases/latest";
+
+/// The version of the running program.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// How long asking GitHub for the newest release can take, so a slow connection doesn't hold up the run.
+const CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long downloading a new release can take.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Extensions of release assets that are archives, which can't be installed without unpacking them.
+const ARCHIVE_EXTENSIONS: [&str; 6] = [".zip", ".tar.gz", ".tgz", ".tar.xz", ".7z", ".rar"];
+
+/// Parts of the names of release assets that hold checksums.
+const CHECKSUM_NAMES: [&str; 2] = ["sha256", "checksum"];
+
+/// Parts of the names of release assets that hold signatures.
+const SIGNATURE_NAMES: [&str; 1] = [".sig"];
+
+/// Names architectures go by in the names of release assets, by the name Rust gives them.
+const ARCH_NAMES: [(&str, &[&str]); 4] = [
+    ("x86_64", &["x86_64", "x86-64", "amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("x86", &["i686", "i386", "x86_32", "win32"]),
+    ("arm", &["armv7", "armhf"]),
+];
+
+/// A release of the downloader on GitHub.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct Release {
+    /// The tag of the release, which is its version (e.g `1.7.2`).
+    pub tag_name: String,
+    /// The page of the release.
+    pub html_url: String,
+    /// The files attached to the release.
+    pub assets: Vec<ReleaseAsset>,
+}
+
+/// A file attached to a release.
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ReleaseAsset {
+    /// The name of the file.
+    pub name: String,
+    /// The url the file is downloaded from.
+    pub browser_download_url: String,
+}
+
+impl ReleaseAsset {
+    /// Whether the asset is a program, instead of an archive or a list of checksums.
+    fn is_program(&self) -> bool {
+        let name = self.name.to_lowercase();
+        !ARCHIVE_EXTENSIONS.iter().any(|e| name.ends_with(e))
+            && !CHECKSUM_NAMES
+                .iter()
+                .chain(SIGNATURE_NAMES.iter())
+                .any(|e| name.contains(e))
+    }
+
+    /// Whether the asset is a list of checksums.
+    fn is_checksum(&self) -> bool {
+        let name = self.name.to_lowercase();
+        CHECKSUM_NAMES.iter().any(|e| name.contains(e))
+    }
+}
+
+impl Release {
+    /// Whether the release is newer than the running program.
+    pub fn is_newer(&self) -> bool {
+        match (parse_version(&self.tag_name), parse_version(VERSION)) {
+            (Some(latest), Some(current)) => latest > current,
+            _ => false,
+        }
+    }
+
+    /// The program built for the running platform, if the release has one.
+    ///
+    /// Programs named after the architecture of the running platform are preferred over programs that don't name an
+    /// architecture.
+    pub fn platform_asset(&self) -> Option<&ReleaseAsset> {
+        let os_names: &[&str] = match OS {
+            "windows" => &["windows", ".exe"],
+            "macos" => &["macos", "darwin", "osx", "apple"],
+            os => &[os],
+        };
+        let arch_names = |name: &str| {
+            ARCH_NAMES
+                .iter()
+                .find(|(_, aliases)| aliases.iter().any(|e| name.contains(e)))
+                .map(|(arch, _)| *arch)
+        };
+
+        let candidates: Vec<(&ReleaseAsset, Option<&str>)> = self
+            .assets
+            .iter()
+            .filter(|e| e.is_program())
+            .filter(|e| os_names.iter().any(|os| e.name.to_lowercase().contains(os)))
+            .map(|e| (e, arch_names(&e.name.to_lowercase())))
+            .collect();
+        candidates
+            .iter()
+            .find(|(_, arch)| *arch == Some(ARCH))
+            .or_else(|| candidates.iter().find(|(_, arch)| arch.is_none()))
+            .map(|(asset, _)| *asset)
+    }
+
+    /// Gets the sha256 of an asset from the checksums attached to the release, either a `<name>.sha256` file or a list
+    /// of checksums (e.g `SHA256SUMS`) with a `<sha256>  <name>` line for every asset.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client the checksums are downloaded with.
+    /// * `asset`: The asset to get the checksum of.
+    ///
+    /// returns: Result<String, Error>
+    fn checksum_of(&self, client: &Client, asset: &ReleaseAsset) -> Result<String, Error> {
+        for checksums in self.assets.iter().filter(|e| e.is_checksum()) {
+            let own_file = checksums.name.starts_with(&asset.name);
+            let text = client
+                .get(&checksums.browser_download_url)
+                .send()?
+                .error_for_status()?
+                .text()?;
+            for line in text.lines() {
+                let mut parts = line.split_whitespace();
+                let (Some(checksum), name) = (parts.next(), parts.next()) else {
+                    continue;
+                };
+
+                let name = name.map(|e| e.trim_start_matches('*'));
+                let matches = name.map_or(own_file, |e| e == asset.name);
+                if matches
+                    && checksum.len() == 64
+                    && checksum.chars().all(|e| e.is_ascii_hexdigit())
+                {
+                    return Ok(checksum.to_lowercase());
+                }
+            }
+        }
+
+        bail!(
+            "Release {} has no checksum for {}, so it can't be verified",
+            self.tag_name,
+            asset.name
+        )
+    }
+}
+
+/// Asks GitHub for the newest release and tells the user when it's newer than the running program.
+///
+/// Failures are only traced, as the check shouldn't stop the run.
+pub fn check_for_updates() {
+    trace!("Checking for a newer release...");
+    match latest_release() {
+        Ok(release) if release.is_newer() => {
+            info!(
+                "Version {} is available (you are running {VERSION}), download it from {}",
+                release.tag_name.trim_start_matches('v'),
+                release.html_url
+            );
+        }
+        Ok(_) => trace!("Running the newest release..."),
+        Err(e) => trace!("Unable to check for a newer release: {e}"),
+    }
+}
+
+/// Replaces the running program with the newest release for the running platform, after checking its sha256 against
+/// the checksums attached to the release.
+///
+/// The user is asked before updating, unless `--yes` was passed. When nobody is there to answer, the update is only
+/// installed with `--yes`.
+///
+/// returns: Result<(), Error>
+pub fn self_update() -> Result<(), Error> {
+    let release = latest_release()?;
+    let version = release.tag_name.trim_start_matches('v');
+    if !release.is_newer() {
+        info!("You are running the newest version ({VERSION})...");
+        return Ok(());
+    }
+
+    let Some(asset) = release.platform_asset() else {
+        bail!(
+            "Version {version} has no program for {OS} ({ARCH}), download it from {}",
+            release.html_url
+        );
+    };
+
+    if !Args::get().yes() && !user_attended() {
+        bail!("Version {version} is available, pass --yes to update without being asked");
+    }
+
+    let confirmed = Args::get().yes()
+        || Confirm::new()
+            .with_prompt(format!("Update from {VERSION} to {version}?"))
+            .default(true)
+            .interact()?;
+    if !confirmed {
+        return Ok(());
+    }
+
+    let client = github_client(DOWNLOAD_TIMEOUT)?;
+    let expected = release.checksum_of(&client, asset)?;
+    info!("Downloading {}...", asset.name);
+    let bytes = client
+        .get(&asset.browser_download_url)
+        .send()?
+        .error_for_status()?
+        .bytes()?;
+    let actual = format!("{:x}", Sha256::digest(&bytes));
+    if actual != expected {
+        bail!(
+            "The downloaded {} has the sha256 {actual}, but {expected} was expected, so it wasn't installed",
+            asset.name
+        );
+    }
+
+    let executable = current_exe().context("Unable to find the running program")?;
+    replace_executable(&executable, &bytes)?;
+    info!("Updated to version {version}, the new version is used from the next run...");
+    Ok(())
+}
+
+/// Replaces a program with a new one, moving the old program aside first so it can be put back if the new one can't
+/// be moved into place.
+///
+/// The old program is deleted afterward, except on Windows, where a running program can't be deleted, and it's
+/// deleted by the next update instead.
+///
+/// # Arguments
+///
+/// * `executable`: The path of the program to replace.
+/// * `bytes`: The new program.
+///
+/// returns: Result<(), Error>
+fn replace_executable(executable: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let with_suffix = |suffix: &str| {
+        let mut path = executable.as_os_str().to_owned();
+        path.push(suffix);
+        PathBuf::from(path)
+    };
+    let new = with_suffix(".new");
+    let old = with_suffix(".old");
+    remove_file(&old).unwrap_or_default();
+
+    write(&new, bytes).with_context(|| format!("Unable to write {}", new.display()))?;
+    set_permissions(&new, metadata(executable)?.permissions())?;
+    rename(executable, &old)
+        .with_context(|| format!("Unable to move {} aside", executable.display()))?;
+    if let Err(e) = rename(&new, executable) {
+        rename(&old, executable)?;
+        return Err(Error::from(e).context(format!("Unable to replace {}", executable.display())));
+    }
+
+    remove_file(&old).unwrap_or_default();
+    Ok(())
+}
+
+/// Gets the newest release from GitHub.
+///
+/// returns: Result<Release, Error>
+pub fn latest_release() -> Result<Release, Error> {
+    let release = github_client(CHECK_TIMEOUT)?
+        .get(LATEST_RELEASE_URL)
+        .header(ACCEPT, "application/vnd.github+json")
+        .send()?
+        .error_for_status()?
+        .json::<Release>()?;
+    Ok(release)
+}
+
+/// Creates a client for requests to GitHub, which go through the proxy in the config like requests to e621.
+///
+/// # Arguments
+///
+/// * `timeout`: How long a request can take.
+///
+/// returns: Result<Client, Error>
+fn github_client(timeout: Duration) -> Result<Client, Error> {
+    let mut builder = Client::builder()
+        .use_rustls_tls()
+        .timeout(timeout)
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ));
+    if let Some(proxy) = Config::loaded_or_peek().proxy() {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Parses a version (e.g `1.7.2` or `v1.7.2`) into its numbers, leaving out any pre-release part (e.g `-beta`).
+///
+/// # Arguments
+///
+/// * `version`: The version to parse.
+///
+/// returns: Option<Vec<u64, Global>>
+fn parse_version(version: &str) -> Option<Vec<u64>> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()?
+        .split('.')
+        .map(|e| e.parse::<u64>().ok())
+        .collect()
+}
@@ -0,0 +1,172 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for filtering posts with the blacklist.
+
+use std::rc::Rc;
+
+use e621_downloader::e621::blacklist::Blacklist;
+use e621_downloader::e621::sender::entries::{BulkPostEntry, PostEntry};
+
+use crate::common::{fixture, FixtureClient};
+
+mod common;
+
+/// Filters the posts in `posts_blacklist.json` with the blacklist, returning the ids of the posts left.
+///
+/// # Arguments
+///
+/// * `blacklist`: The blacklist to filter with.
+/// * `whitelist`: Tags that keep posts even if they are blacklisted.
+///
+/// returns: Vec<i64, Global>
+fn filter(blacklist: &str, whitelist: &[&str]) -> Vec<i64> {
+    let client = Rc::new(FixtureClient::new());
+    let mut posts: Vec<PostEntry> =
+        serde_json::from_slice::<BulkPostEntry>(&fixture("posts_blacklist.json"))
+            .unwrap()
+            .posts;
+    let whitelist: Vec<String> = whitelist.iter().map(|e| e.to_string()).collect();
+
+    let mut filter = Blacklist::new(FixtureClient::sender(&client));
+    let filtered = filter
+        .parse_blacklist(blacklist.to_string())
+        .filter_posts_with_whitelist(&mut posts, &whitelist);
+    assert_eq!(filtered as usize, 5 - posts.len());
+
+    posts.iter().map(|e| e.id).collect()
+}
+
+#[test]
+fn posts_with_a_blacklisted_tag_are_filtered() {
+    assert_eq!(filter("gore", &[]), vec![402, 403, 404]);
+}
+
+#[test]
+fn every_tag_of_a_line_has_to_match() {
+    assert_eq!(filter("fox wolf", &[]), vec![401, 402, 403, 405]);
+}
+
+#[test]
+fn negated_tags_keep_posts() {
+    assert_eq!(filter("gore -comic", &[]), vec![402, 403, 404, 405]);
+}
+
+#[test]
+fn lines_are_checked_separately() {
+    assert_eq!(filter("rating:e\nscore:<0", &[]), vec![401, 404, 405]);
+}
+
+#[test]
+fn whitelisted_tags_keep_posts() {
+    assert_eq!(filter("gore", &["comic"]), vec![402, 403, 404, 405]);
+}
+
+#[test]
+fn empty_blacklist_keeps_every_post() {
+    let client = Rc::new(FixtureClient::new());
+    let mut blacklist = Blacklist::new(FixtureClient::sender(&client));
+    assert!(blacklist.parse_blacklist(String::new()).is_empty());
+    assert_eq!(filter("", &[]), vec![401, 402, 403, 404, 405]);
+}
+
+#[test]
+fn lines_with_an_invalid_special_tag_are_left_out() {
+    let client = Rc::new(FixtureClient::new());
+    let mut blacklist = Blacklist::new(FixtureClient::sender(&client));
+    assert!(blacklist
+        .parse_blacklist("gore score:abc\nfox pool:x\nfoo:bar".to_string())
+        .is_empty());
+    assert_eq!(
+        filter("gore score:abc\nwolf", &[]),
+        vec![401, 402, 403, 405]
+    );
+}
+
+#[test]
+fn scores_can_be_negative() {
+    assert_eq!(filter("score:<-5", &[]), vec![401, 402, 404, 405]);
+}
+
+#[test]
+fn score_ranges_are_inclusive() {
+    assert_eq!(filter("score:0..50", &[]), vec![403, 405]);
+}
+
+#[test]
+fn score_ranges_can_leave_out_a_side() {
+    assert_eq!(filter("score:..10", &[]), vec![402, 405]);
+    assert_eq!(filter("score:50..", &[]), vec![401, 403, 404]);
+}
+
+#[test]
+fn scores_are_compared_with_each_operator() {
+    assert_eq!(filter("score:<=0", &[]), vec![401, 402, 405]);
+    assert_eq!(filter("score:>50", &[]), vec![401, 402, 403, 404]);
+    assert_eq!(filter("score:>=50", &[]), vec![401, 403, 404]);
+    assert_eq!(filter("score:=50", &[]), vec![401, 403, 404, 405]);
+    assert_eq!(filter("score:50", &[]), vec![401, 403, 404, 405]);
+}
+
+#[test]
+fn lines_with_an_invalid_score_comparison_are_left_out() {
+    assert_eq!(filter("fox score:<>5", &[]), vec![401, 402, 403, 404, 405]);
+    assert_eq!(filter("fox score:..", &[]), vec![401, 402, 403, 404, 405]);
+}
+
+#[test]
+fn wildcards_match_the_start_of_a_tag() {
+    assert_eq!(filter("*_fluids", &[]), vec![401, 403, 404, 405]);
+}
+
+#[test]
+fn wildcards_match_the_end_of_a_tag() {
+    assert_eq!(filter("wolf*", &[]), vec![401, 402, 403]);
+    assert_eq!(filter("fox* wolf", &[]), vec![401, 402, 403, 405]);
+}
+
+#[test]
+fn wildcards_can_be_anywhere_in_a_tag() {
+    assert_eq!(filter("*a*b", &[]), vec![401, 402, 404, 405]);
+}
+
+#[test]
+fn negated_wildcards_keep_posts() {
+    assert_eq!(filter("fox -wolf*", &[]), vec![404, 405]);
+    assert_eq!(filter("fox* -gore", &[]), vec![401, 405]);
+}
+
+#[test]
+fn file_types_are_filtered() {
+    assert_eq!(filter("type:webm", &[]), vec![402, 403, 404, 405]);
+}
+
+#[test]
+fn dimensions_are_filtered() {
+    assert_eq!(filter("width:>1000", &[]), vec![402, 403, 405]);
+    assert_eq!(filter("height:<500", &[]), vec![401, 403, 405]);
+}
+
+#[test]
+fn favorite_counts_are_filtered() {
+    assert_eq!(filter("favcount:<10", &[]), vec![401, 403, 405]);
+}
+
+#[test]
+fn pools_are_filtered() {
+    assert_eq!(filter("pool:12", &[]), vec![401, 403, 405]);
+    assert_eq!(filter("fox -pool:30", &[]), vec![404]);
+}
@@ -0,0 +1,274 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Shared helpers for the integration tests, most importantly [FixtureClient], an [ApiClient] answering requests with
+//! the recorded responses in `tests/fixtures` instead of talking to e621.
+
+#![allow(dead_code)]
+
+use std::cell::RefCell;
+use std::env::temp_dir;
+use std::fs::read;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Once;
+
+use clap::Parser;
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+
+use e621_downloader::args::Args;
+use e621_downloader::e621::error::E621Error;
+use e621_downloader::e621::grabber::{Grabber, PostCollection};
+use e621_downloader::e621::io::tag::{Group, Tag};
+use e621_downloader::e621::io::{Config, Login};
+use e621_downloader::e621::sender::client::{ApiClient, ApiResponse};
+use e621_downloader::e621::sender::RequestSender;
+
+/// The url the fixture client pretends to be.
+pub const BASE_URL: &str = "https://e621.test";
+
+/// A response the [FixtureClient] answers a request with.
+struct Route {
+    /// The path of the url (e.g `/posts.json`).
+    path: String,
+    /// The query parameters the request has to have, other parameters are ignored.
+    query: Vec<(String, String)>,
+    /// The fixture the response body is read from.
+    fixture: String,
+}
+
+/// An [ApiClient] answering every request with a fixture from `tests/fixtures`, and with [StatusCode::NOT_FOUND] when
+/// no fixture was routed for the request.
+///
+/// Every request is recorded, so tests can check which requests were sent (e.g how many pages were searched).
+#[derive(Default)]
+pub struct FixtureClient {
+    /// The routed responses, the first matching route answers the request.
+    routes: Vec<Route>,
+    /// Every request sent, as `path?key=value&...`.
+    requests: RefCell<Vec<String>>,
+}
+
+impl FixtureClient {
+    /// Creates a client without any routes.
+    pub fn new() -> Self {
+        FixtureClient::default()
+    }
+
+    /// Answers requests for the path with the given query parameters with the fixture.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the url (e.g `/posts.json`).
+    /// * `query`: The query parameters the request has to have.
+    /// * `fixture`: The name of the file in `tests/fixtures`.
+    ///
+    /// returns: FixtureClient
+    pub fn route(mut self, path: &str, query: &[(&str, &str)], fixture: &str) -> Self {
+        self.routes.push(Route {
+            path: path.to_string(),
+            query: query
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            fixture: fixture.to_string(),
+        });
+        self
+    }
+
+    /// Every request sent so far, as `path?key=value&...`.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.borrow().clone()
+    }
+
+    /// The requests sent so far for the path.
+    ///
+    /// # Arguments
+    ///
+    /// * `path`: The path of the url (e.g `/posts.json`).
+    ///
+    /// returns: usize
+    pub fn request_count(&self, path: &str) -> usize {
+        self.requests
+            .borrow()
+            .iter()
+            .filter(|e| e.split('?').next() == Some(path))
+            .count()
+    }
+
+    /// Creates a sender that sends every request through the client.
+    ///
+    /// # Arguments
+    ///
+    /// * `client`: The client to send requests through.
+    ///
+    /// returns: RequestSender
+    pub fn sender(client: &Rc<FixtureClient>) -> RequestSender {
+        init();
+        RequestSender::with_client(client.clone(), BASE_URL)
+    }
+
+    /// Creates a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `status`: The status of the response.
+    /// * `body`: The body of the response.
+    ///
+    /// returns: ApiResponse
+    fn response(status: StatusCode, body: Vec<u8>) -> ApiResponse {
+        ApiResponse {
+            status,
+            body,
+            etag: None,
+            rate_limit: None,
+            date: None,
+        }
+    }
+}
+
+impl ApiClient for FixtureClient {
+    fn get(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        _authenticated: bool,
+    ) -> Result<ApiResponse, E621Error> {
+        let path = url.strip_prefix(BASE_URL).unwrap_or(url);
+        let request: Vec<String> = query.iter().map(|(k, v)| format!("{k}={v}")).collect();
+        self.requests
+            .borrow_mut()
+            .push(format!("{path}?{}", request.join("&")));
+
+        let route = self.routes.iter().find(|route| {
+            route.path == path
+                && route
+                    .query
+                    .iter()
+                    .all(|(k, v)| query.contains(&(k.as_str(), v.as_str())))
+        });
+        Ok(match route {
+            Some(route) => FixtureClient::response(StatusCode::OK, fixture(&route.fixture)),
+            None => FixtureClient::response(StatusCode::NOT_FOUND, b"{}".to_vec()),
+        })
+    }
+
+    fn patch(&self, url: &str, _form: &[(&str, &str)]) -> Result<ApiResponse, E621Error> {
+        self.get(url, &[], true)
+    }
+
+    fn post(&self, url: &str, _form: &[(&str, &str)]) -> Result<ApiResponse, E621Error> {
+        self.get(url, &[], true)
+    }
+
+    fn is_authenticated(&self) -> bool {
+        false
+    }
+}
+
+/// Grabs the tags through a grabber sending its requests through the client.
+///
+/// # Arguments
+///
+/// * `client`: The client to send requests through.
+/// * `tags`: The tags to grab.
+///
+/// returns: Grabber
+pub fn grab(client: &Rc<FixtureClient>, tags: Vec<Tag>) -> Grabber {
+    let mut grabber = Grabber::new(FixtureClient::sender(client), false);
+    grabber.grab_posts_by_tags(&[Group::with_tags(String::from("test"), tags)]);
+    grabber
+}
+
+/// Finds the collection with the name.
+///
+/// # Arguments
+///
+/// * `grabber`: The grabber that grabbed the collection.
+/// * `name`: The name of the collection.
+///
+/// returns: &PostCollection
+pub fn collection<'a>(grabber: &'a Grabber, name: &str) -> &'a PostCollection {
+    grabber
+        .posts()
+        .iter()
+        .find(|e| e.name() == name)
+        .unwrap_or_else(|| panic!("No collection named {name} was grabbed!"))
+}
+
+/// The ids of the posts in the collection, sorted.
+///
+/// # Arguments
+///
+/// * `collection`: The collection to get the ids of.
+///
+/// returns: Vec<i64, Global>
+pub fn sorted_ids(collection: &PostCollection) -> Vec<i64> {
+    let mut ids: Vec<i64> = collection.posts().iter().map(|e| e.id()).collect();
+    ids.sort();
+    ids
+}
+
+/// Reads a fixture from `tests/fixtures`.
+///
+/// # Arguments
+///
+/// * `name`: The name of the fixture.
+///
+/// returns: Vec<u8, Global>
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "fixtures", name]
+        .iter()
+        .collect();
+    read(&path).unwrap_or_else(|e| panic!("Unable to read {}: {e}", path.display()))
+}
+
+/// Sets the args, config, and login the tests run with, instead of reading them from the command line and the config
+/// directory.
+///
+/// Generated state (e.g the tag state) is kept in a temporary directory, and the response cache is turned off.
+pub fn init() {
+    init_with(json!({}));
+}
+
+/// Like [init], but with extra config options (e.g `{"skipDuplicatePosts": true}`).
+///
+/// The config can only be set once per test binary, so every test of a binary that needs extra options has to call
+/// this with the same options before anything else.
+///
+/// # Arguments
+///
+/// * `options`: The config options to set, as a JSON object.
+pub fn init_with(options: Value) {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let state_directory = temp_dir().join("e621_downloader_tests");
+        let mut config = json!({
+            "stateDirectory": state_directory,
+            "metadataCacheTtlHours": 0,
+        });
+        if let (Some(config), Some(options)) = (config.as_object_mut(), options.as_object()) {
+            config.extend(options.clone());
+        }
+
+        let config: Config = serde_json::from_value(config).unwrap();
+
+        Args::set(Args::parse_from(["e621_downloader"]));
+        Config::set(config);
+        Login::set(Login::default());
+    });
+}
@@ -0,0 +1,80 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for converting wiki pages from DText to markdown.
+
+use e621_downloader::e621::dtext::dtext_to_markdown;
+
+/// Converts the DText with links relative to `https://e621.test`.
+///
+/// # Arguments
+///
+/// * `dtext`: The DText to convert.
+///
+/// returns: String
+fn convert(dtext: &str) -> String {
+    dtext_to_markdown(dtext, "https://e621.test")
+}
+
+#[test]
+fn headings_are_converted() {
+    assert_eq!(
+        convert("h4. Appearance\r\nh2#history. History"),
+        "#### Appearance\n## History"
+    );
+}
+
+#[test]
+fn formatting_is_converted() {
+    assert_eq!(
+        convert("[b]bold[/b], [I]italic[/I], [s]gone[/s], and [u]underlined[/u]"),
+        "**bold**, *italic*, ~~gone~~, and underlined"
+    );
+}
+
+#[test]
+fn links_are_converted() {
+    assert_eq!(
+        convert("\"e621\":https://e621.net and \"help\":/help"),
+        "[e621](https://e621.net) and [help](https://e621.test/help)"
+    );
+    assert_eq!(
+        convert("See [[Red Fox]], [[arctic_fox|foxes]], and {{fox rating:s}}."),
+        "See [red fox](https://e621.test/wiki_pages/show_or_new?title=red_fox), \
+         [foxes](https://e621.test/wiki_pages/show_or_new?title=arctic_fox), and \
+         [fox rating:s](https://e621.test/posts?tags=fox+rating%3As)."
+    );
+    assert_eq!(
+        convert("Drawn in post #12 of pool #3."),
+        "Drawn in [post #12](https://e621.test/posts/12) of [pool #3](https://e621.test/pools/3)."
+    );
+}
+
+#[test]
+fn lists_are_converted() {
+    assert_eq!(
+        convert("* fox\n** red fox\n* wolf"),
+        "- fox\n  - red fox\n- wolf"
+    );
+}
+
+#[test]
+fn quotes_and_code_are_converted() {
+    assert_eq!(
+        convert("[quote]\nquoted [b]text[/b]\n[/quote]\n[code]\n[b]kept[/b]\n[/code]"),
+        ">\n> quoted **text**\n>\n```\n[b]kept[/b]\n```"
+    );
+}
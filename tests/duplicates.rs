@@ -0,0 +1,102 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for leaving out posts grabbed by several collections, with `skipDuplicatePosts` enabled.
+
+use std::rc::Rc;
+
+use serde_json::json;
+
+use e621_downloader::e621::io::tag::{Tag, TagSearchType, TagType};
+
+use crate::common::{collection, grab, init_with, sorted_ids, FixtureClient};
+
+mod common;
+
+/// A client answering the `fox` search with posts 101 to 103, the `canine` search with posts 102 and 103, the `lake`
+/// search with the posts of pool 12, and pool 12 itself.
+fn client() -> Rc<FixtureClient> {
+    init_with(json!({ "skipDuplicatePosts": true }));
+    let search = |client: FixtureClient, tags: &str, fixtures: &[&str]| {
+        let mut client = client;
+        for (i, fixture) in fixtures.iter().enumerate() {
+            let page = (i + 1).to_string();
+            client = client.route(
+                "/posts.json",
+                &[("tags", tags), ("page", page.as_str())],
+                fixture,
+            );
+        }
+
+        client.route("/posts.json", &[("tags", tags)], "posts_empty.json")
+    };
+
+    let client = FixtureClient::new().route("/pools/12.json", &[], "pool_12.json");
+    let client = search(
+        client,
+        "fox",
+        &["posts_fox_page1.json", "posts_fox_page2.json"],
+    );
+    let client = search(client, "canine", &["posts_fox_page1.json"]);
+    let client = search(client, "lake", &["posts_pool_12.json"]);
+    Rc::new(search(client, "pool:12", &["posts_pool_12.json"]))
+}
+
+/// A general tag search.
+///
+/// # Arguments
+///
+/// * `name`: The tags of the search.
+///
+/// returns: Tag
+fn general(name: &str) -> Tag {
+    Tag::new(name, TagSearchType::General, TagType::General)
+}
+
+/// Pool 12.
+fn pool() -> Tag {
+    Tag::new("12", TagSearchType::Special, TagType::Pool)
+}
+
+#[test]
+fn posts_grabbed_by_an_earlier_tag_are_left_out() {
+    let grabber = grab(&client(), vec![general("fox"), general("canine")]);
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![101, 102, 103]);
+    assert!(collection(&grabber, "canine").posts().is_empty());
+}
+
+#[test]
+fn pools_keep_posts_grabbed_by_an_earlier_tag() {
+    let grabber = grab(&client(), vec![general("lake"), pool()]);
+    assert_eq!(
+        sorted_ids(collection(&grabber, "lake")),
+        vec![201, 202, 203]
+    );
+    assert_eq!(
+        sorted_ids(collection(&grabber, "Test_Pool")),
+        vec![201, 202, 203]
+    );
+}
+
+#[test]
+fn posts_of_pools_are_left_out_of_later_tags() {
+    let grabber = grab(&client(), vec![pool(), general("lake")]);
+    assert_eq!(
+        sorted_ids(collection(&grabber, "Test_Pool")),
+        vec![201, 202, 203]
+    );
+    assert!(collection(&grabber, "lake").posts().is_empty());
+}
@@ -0,0 +1,291 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for the grabber, searching through the fixture client instead of e621.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use anyhow::{bail, Error};
+use serde_json::{json, Value};
+
+use e621_downloader::e621::blacklist::Blacklist;
+use e621_downloader::e621::filter::PostFilter;
+use e621_downloader::e621::grabber::Grabber;
+use e621_downloader::e621::io::tag::{Group, GroupOptions, Tag, TagSearchType, TagType};
+use e621_downloader::e621::sender::entries::PostEntry;
+
+use crate::common::{collection, grab, sorted_ids, FixtureClient};
+
+mod common;
+
+/// Routes the pages of the `fox` search to the fixtures, with every page after them empty.
+///
+/// # Arguments
+///
+/// * `pages`: The fixtures of the pages, in order.
+///
+/// returns: FixtureClient
+fn fox_pages(pages: &[&str]) -> FixtureClient {
+    let mut client = FixtureClient::new();
+    for (i, fixture) in pages.iter().enumerate() {
+        let page = (i + 1).to_string();
+        client = client.route(
+            "/posts.json",
+            &[("tags", "fox"), ("page", page.as_str())],
+            fixture,
+        );
+    }
+
+    client.route("/posts.json", &[("tags", "fox")], "posts_empty.json")
+}
+
+#[test]
+fn general_search_stops_at_the_first_empty_page() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json", "posts_fox_page2.json"]));
+    let grabber = grab(
+        &client,
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+    );
+
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![101, 102, 103]);
+    assert_eq!(client.request_count("/posts.json"), 3);
+}
+
+#[test]
+fn general_search_stops_at_the_page_limit() {
+    let client = Rc::new(FixtureClient::new().route(
+        "/posts.json",
+        &[("tags", "fox")],
+        "posts_fox_page2.json",
+    ));
+    grab(
+        &client,
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+    );
+
+    assert_eq!(client.request_count("/posts.json"), 4);
+}
+
+#[test]
+fn special_search_grabs_every_page() {
+    let client = Rc::new(fox_pages(&[
+        "posts_fox_page1.json",
+        "posts_fox_page2.json",
+        "posts_fox_page1.json",
+    ]));
+    let grabber = grab(
+        &client,
+        vec![Tag::new("fox", TagSearchType::Special, TagType::General)],
+    );
+
+    assert_eq!(collection(&grabber, "fox").posts().len(), 5);
+    assert_eq!(client.request_count("/posts.json"), 4);
+}
+
+#[test]
+fn pool_posts_are_kept_in_pool_order() {
+    let client = Rc::new(
+        FixtureClient::new()
+            .route("/pools/12.json", &[], "pool_12.json")
+            .route(
+                "/posts.json",
+                &[("tags", "pool:12"), ("page", "1")],
+                "posts_pool_12.json",
+            )
+            .route("/posts.json", &[("tags", "pool:12")], "posts_empty.json"),
+    );
+    let grabber = grab(
+        &client,
+        vec![Tag::new("12", TagSearchType::Special, TagType::Pool)],
+    );
+
+    let ids: Vec<i64> = collection(&grabber, "Test_Pool")
+        .posts()
+        .iter()
+        .map(|e| e.id())
+        .collect();
+    assert_eq!(ids, vec![201, 203, 202]);
+}
+
+#[test]
+fn set_posts_are_searched_by_shortname() {
+    let client = Rc::new(
+        FixtureClient::new()
+            .route("/post_sets/7.json", &[], "set_7.json")
+            .route(
+                "/posts.json",
+                &[("tags", "set:test_set"), ("page", "1")],
+                "posts_set_7.json",
+            )
+            .route(
+                "/posts.json",
+                &[("tags", "set:test_set")],
+                "posts_empty.json",
+            ),
+    );
+    let grabber = grab(
+        &client,
+        vec![Tag::new("7", TagSearchType::Special, TagType::Set)],
+    );
+
+    assert_eq!(sorted_ids(collection(&grabber, "Test Set")), vec![301, 302]);
+}
+
+#[test]
+fn deleted_posts_and_posts_without_a_url_are_left_out() {
+    let client = Rc::new(fox_pages(&["posts_invalid.json"]));
+    let grabber = grab(
+        &client,
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+    );
+
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![111]);
+}
+
+#[test]
+fn blacklisted_posts_are_left_out() {
+    let client = Rc::new(fox_pages(&["posts_blacklist.json"]));
+    let mut blacklist = Blacklist::new(FixtureClient::sender(&client));
+    blacklist.parse_blacklist(String::from("gore"));
+
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    grabber.set_blacklist(Rc::new(RefCell::new(blacklist)));
+    grabber.grab_posts_by_tags(&[Group::with_tags(
+        String::from("test"),
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+    )]);
+
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![402, 403, 404]);
+}
+
+/// A filter that fails on the post with the id, and accepts every other post.
+struct FailingFilter(i64);
+
+impl PostFilter for FailingFilter {
+    fn name(&self) -> &str {
+        "failing"
+    }
+
+    fn accepts(&mut self, post: &PostEntry, _options: &GroupOptions) -> Result<bool, Error> {
+        if post.id == self.0 {
+            bail!("unable to check post {}", post.id);
+        }
+
+        Ok(true)
+    }
+}
+
+#[test]
+fn searches_are_skipped_when_a_filter_fails() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json", "posts_fox_page2.json"]));
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    grabber.add_filter(Box::new(FailingFilter(101)));
+    grabber.grab_posts_by_tags(&[Group::with_tags(
+        String::from("test"),
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+    )]);
+
+    assert!(grabber.posts().iter().all(|e| e.name() != "fox"));
+}
+
+#[test]
+fn failing_tags_are_skipped() {
+    let client = Rc::new(fox_pages(&["posts_fox_page2.json"]));
+    let grabber = grab(
+        &client,
+        vec![
+            Tag::new("missing", TagSearchType::General, TagType::General),
+            Tag::new("fox", TagSearchType::General, TagType::General),
+        ],
+    );
+
+    assert!(grabber.posts().iter().all(|e| e.name() != "missing"));
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![101]);
+}
+
+/// A group grabbing `fox` with the options.
+///
+/// # Arguments
+///
+/// * `options`: The options of the group, as a JSON object (e.g `{"incremental": true}`).
+///
+/// returns: Group
+fn fox_group(options: Value) -> Group {
+    Group::with_options(
+        String::from("test"),
+        vec![Tag::new("fox", TagSearchType::General, TagType::General)],
+        serde_json::from_value(options).unwrap(),
+    )
+}
+
+#[test]
+fn incremental_groups_only_search_posts_newer_than_the_last_run() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json", "posts_fox_page2.json"]));
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    let groups = [fox_group(json!({ "incremental": true }))];
+
+    grabber.grab_posts_by_tags(&groups);
+    assert_eq!(grabber.tag_state().last_post_id("fox"), Some(103));
+    assert!(!client.requests().iter().any(|e| e.contains("id:>")));
+
+    grabber.grab_posts_by_tags(&groups);
+    assert!(client
+        .requests()
+        .iter()
+        .any(|e| e.contains("tags=fox id:>103")));
+}
+
+#[test]
+fn other_groups_search_every_post() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json", "posts_fox_page2.json"]));
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    let groups = [fox_group(json!({}))];
+
+    grabber.grab_posts_by_tags(&groups);
+    grabber.grab_posts_by_tags(&groups);
+    assert_eq!(grabber.tag_state().last_post_id("fox"), None);
+    assert!(!client.requests().iter().any(|e| e.contains("id:>")));
+}
+
+#[test]
+fn searches_over_the_tag_limit_are_skipped() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json"]));
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    grabber.set_tag_query_limit(1);
+    grabber.grab_posts_by_tags(&[Group::with_tags(
+        String::from("test"),
+        vec![
+            Tag::new("fox wolf", TagSearchType::General, TagType::General),
+            Tag::new("fox", TagSearchType::General, TagType::General),
+        ],
+    )]);
+
+    assert!(grabber.posts().iter().all(|e| e.name() != "fox wolf"));
+    assert_eq!(sorted_ids(collection(&grabber, "fox")), vec![102, 103]);
+    assert!(!client.requests().iter().any(|e| e.contains("fox wolf")));
+}
+
+#[test]
+fn tags_added_to_the_search_count_towards_the_tag_limit() {
+    let client = Rc::new(fox_pages(&["posts_fox_page1.json"]));
+    let mut grabber = Grabber::new(FixtureClient::sender(&client), false);
+    grabber.set_tag_query_limit(1);
+    grabber.grab_posts_by_tags(&[fox_group(json!({ "safe": true }))]);
+
+    assert!(grabber.posts().iter().all(|e| e.name() != "fox"));
+    assert_eq!(client.request_count("/posts.json"), 0);
+}
@@ -0,0 +1,204 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for identifying the tags and aliases of the tag file.
+
+use std::env::temp_dir;
+use std::fs::{create_dir_all, write};
+use std::rc::Rc;
+
+use e621_downloader::e621::io::tag::{
+    parse_tag_file_at, Group, Tag, TagIdentifier, TagSearchType, TagType,
+};
+
+use crate::common::FixtureClient;
+
+mod common;
+
+/// A client that knows the tags and aliases in the fixtures, answering every other lookup with nothing found.
+fn client() -> Rc<FixtureClient> {
+    let tag = |name: &str, fixture: &str| (name.to_string(), fixture.to_string());
+    let tags = [
+        tag("some_artist", "tags_artist.json"),
+        tag("small_character", "tags_character_small.json"),
+        tag("large_character", "tags_character_large.json"),
+        tag("fox", "tags_general.json"),
+        tag("new_name", "tags_new_name.json"),
+    ];
+
+    let mut client = FixtureClient::new();
+    for (name, fixture) in &tags {
+        client = client.route("/tags.json", &[("search[name]", name)], fixture);
+    }
+
+    Rc::new(
+        client
+            .route("/tags.json", &[], "tags_none.json")
+            .route(
+                "/tag_aliases.json",
+                &[("search[name_matches]", "old_name")],
+                "aliases_old_name.json",
+            )
+            .route("/tag_aliases.json", &[], "aliases_none.json"),
+    )
+}
+
+/// Identifies the tags through the fixture client.
+///
+/// # Arguments
+///
+/// * `tags`: The tags to identify.
+///
+/// returns: Option<Tag>
+fn id_tag(tags: &str) -> Option<Tag> {
+    TagIdentifier::id_tag(tags, FixtureClient::sender(&client()))
+}
+
+/// Writes a tag file to a temporary directory and parses it through the fixture client.
+///
+/// # Arguments
+///
+/// * `name`: The name of the tag file.
+/// * `contents`: The contents of the tag file.
+///
+/// returns: Vec<Group, Global>
+fn parse(name: &str, contents: &str) -> Vec<Group> {
+    let directory = temp_dir().join("e621_downloader_tests");
+    create_dir_all(&directory).unwrap();
+    let path = directory.join(name);
+    write(&path, contents).unwrap();
+    parse_tag_file_at(&path, &FixtureClient::sender(&client())).unwrap()
+}
+
+#[test]
+fn artists_are_searched_completely() {
+    let tag = id_tag("some_artist").unwrap();
+    assert_eq!(tag.tag_type(), &TagType::Artist);
+    assert_eq!(tag.search_type(), &TagSearchType::Special);
+}
+
+#[test]
+fn small_characters_are_searched_completely() {
+    let tag = id_tag("small_character").unwrap();
+    assert_eq!(tag.tag_type(), &TagType::General);
+    assert_eq!(tag.search_type(), &TagSearchType::Special);
+}
+
+#[test]
+fn large_characters_and_general_tags_are_searched_a_few_pages() {
+    for name in ["large_character", "fox"] {
+        let tag = id_tag(name).unwrap();
+        assert_eq!(tag.tag_type(), &TagType::General);
+        assert_eq!(tag.search_type(), &TagSearchType::General);
+    }
+}
+
+#[test]
+fn special_tags_win_in_searches_with_several_tags() {
+    let tag = id_tag("fox some_artist").unwrap();
+    assert_eq!(tag.name(), "fox some_artist");
+    assert_eq!(tag.tag_type(), &TagType::Artist);
+}
+
+#[test]
+fn meta_tags_are_searched_as_general_tags() {
+    let tag = id_tag("rating:s").unwrap();
+    assert_eq!(tag.search_type(), &TagSearchType::General);
+}
+
+#[test]
+fn aliases_are_identified_by_their_tag() {
+    let (tag, aliases) =
+        TagIdentifier::id_tag_with_aliases("old_name", FixtureClient::sender(&client()));
+    let tag = tag.unwrap();
+    assert_eq!(tag.name(), "old_name");
+    assert_eq!(tag.tag_type(), &TagType::Artist);
+    assert_eq!(
+        aliases,
+        vec![(String::from("old_name"), String::from("new_name"))]
+    );
+}
+
+#[test]
+fn unknown_tags_are_skipped() {
+    assert!(id_tag("no_such_tag").is_none());
+}
+
+#[test]
+fn tags_are_only_looked_up_once() {
+    let client = client();
+    let sender = FixtureClient::sender(&client);
+    TagIdentifier::id_tag("fox", sender.clone());
+    TagIdentifier::id_tag("fox some_artist", sender);
+    assert_eq!(
+        client
+            .requests()
+            .iter()
+            .filter(|e| e.contains("search[name]=fox"))
+            .count(),
+        1
+    );
+}
+
+#[test]
+fn optional_tags_are_identified_without_the_tilde() {
+    let (tag, aliases) =
+        TagIdentifier::id_tag_with_aliases("~fox ~old_name", FixtureClient::sender(&client()));
+    assert_eq!(tag.unwrap().name(), "~fox ~old_name");
+    assert_eq!(
+        aliases,
+        vec![(String::from("old_name"), String::from("new_name"))]
+    );
+}
+
+#[test]
+fn tag_prefixes_without_matches_are_an_empty_list() {
+    let client = Rc::new(
+        FixtureClient::new()
+            .route(
+                "/tags.json",
+                &[("search[name_matches]", "fo*")],
+                "tags_general.json",
+            )
+            .route("/tags.json", &[], "tags_none.json"),
+    );
+    let sender = FixtureClient::sender(&client);
+    let tags = sender.search_tags_by_prefix("fo", 10).unwrap();
+    assert_eq!(tags.len(), 1);
+    assert_eq!(tags[0].name, "fox");
+    assert!(sender.search_tags_by_prefix("zz", 10).unwrap().is_empty());
+}
+
+#[test]
+fn groups_with_invalid_names_are_skipped() {
+    let groups = parse(
+        "invalid_group.txt",
+        "[Single Post]\n123\n456\n[general]\nfox\n[artists",
+    );
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].name(), "general");
+    assert_eq!(groups[0].tags().len(), 1);
+}
+
+#[test]
+fn tags_with_invalid_characters_are_skipped() {
+    let groups = parse(
+        "invalid_tag.txt",
+        "[general]\no\u{2019}neill\n\u{2019}s\nfox\n",
+    );
+    let names: Vec<&str> = groups[0].tags().iter().map(|e| e.name()).collect();
+    assert_eq!(names, vec!["fox"]);
+}
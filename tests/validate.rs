@@ -0,0 +1,76 @@
+/*
+ * Copyright (c) 2022 McSib
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Tests for validating the tag file.
+
+use std::rc::Rc;
+
+use e621_downloader::e621::io::tag::{Group, Tag, TagSearchType, TagType};
+use e621_downloader::e621::io::validate::TagValidator;
+
+use crate::common::FixtureClient;
+
+mod common;
+
+/// Validates a search through a client that only knows the tag `fox`, returning the amount of problems found.
+///
+/// # Arguments
+///
+/// * `search`: The search to validate.
+///
+/// returns: usize
+fn validate(search: &str) -> usize {
+    let client = Rc::new(
+        FixtureClient::new()
+            .route(
+                "/tags.json",
+                &[("search[name]", "fox")],
+                "tags_general.json",
+            )
+            .route("/tags.json", &[], "tags_none.json")
+            .route("/tag_aliases.json", &[], "aliases_none.json")
+            .route("/posts.json", &[], "posts_fox_page1.json"),
+    );
+
+    TagValidator::new(FixtureClient::sender(&client))
+        .validate(&[Group::with_tags(
+            String::from("test"),
+            vec![Tag::new(search, TagSearchType::General, TagType::General)],
+        )])
+        .unwrap()
+}
+
+#[test]
+fn known_tags_are_valid() {
+    assert_eq!(validate("fox -fox rating:s"), 0);
+}
+
+#[test]
+fn unknown_tags_are_reported() {
+    assert_eq!(validate("fox wolf"), 1);
+}
+
+#[test]
+fn optional_tags_are_looked_up_without_the_tilde() {
+    assert_eq!(validate("~fox ~fox"), 0);
+    assert_eq!(validate("~fox ~wolf"), 1);
+}
+
+#[test]
+fn wildcards_are_not_looked_up() {
+    assert_eq!(validate("fox*"), 0);
+    assert_eq!(validate("-*_fox"), 0);
+}